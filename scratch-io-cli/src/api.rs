@@ -1,4 +1,4 @@
-use crate::eprintln_exit;
+use crate::{OutputFormat, eprintln_exit, print_output};
 
 use clap::Subcommand;
 use scratch_io::itch_api::types::{BuildID, CollectionID, GameID, UploadID, UserID};
@@ -12,12 +12,24 @@ pub enum ApiCommand {
     /// The ID of the user to retrieve information about
     user_id: UserID,
   },
+  /// List the public games of a user given their username
+  UserGames {
+    /// The username of the user, like the one in their itch.io page URL
+    username: String,
+    /// Only list the games in this page, starting at 1
+    #[arg(long)]
+    page: Option<u64>,
+  },
   /// Retrieve information about the profile of the current user
   ProfileInfo,
   /// List the games that the user created or that the user is an admin of
   CreatedGames,
   /// List the game keys owned by the user
-  OwnedKeys,
+  OwnedKeys {
+    /// Only list the keys in this page, starting at 1
+    #[arg(long)]
+    page: Option<u64>,
+  },
   /// List the profile's collections
   ProfileCollections,
   /// Retrieve information about a collection
@@ -29,11 +41,19 @@ pub enum ApiCommand {
   CollectionGames {
     /// The ID of the collection where the games are located
     collection_id: CollectionID,
+    /// Only list the games in this page, starting at 1
+    #[arg(long)]
+    page: Option<u64>,
   },
   /// Retrieve information about a game given its ID
   GameInfo {
-    /// The ID of the game to retrieve information about
-    game_id: GameID,
+    /// The ID of the game to retrieve information about, or the URL of its itch.io page
+    game: String,
+  },
+  /// Retrieve information about a game given its ID, including its description, tags and rating
+  GameDetails {
+    /// The ID of the game to retrieve information about, or the URL of its itch.io page
+    game: String,
   },
   /// Request a scoped API subkey for a specific game from the itch.io server,
   /// with permissions scoped to `profile:me`
@@ -80,112 +100,150 @@ pub enum ApiCommand {
   },
 }
 
+// Get the pages to fetch from an optional page number
+fn page_range(page: Option<u64>) -> endpoints::PageRange {
+  page.map_or_else(endpoints::PageRange::default, endpoints::PageRange::single)
+}
+
 impl ApiCommand {
-  pub fn handle_command(self, client: &ItchClient) {
+  pub fn handle_command(self, client: &ItchClient, format: OutputFormat) {
     match self {
       Self::UserInfo { user_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_user_info(client, user_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_user_info(client, user_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
+        );
+      }
+      Self::UserGames { username, page } => {
+        print_output(
+          format,
+          &endpoints::get_user_games_range(client, &username, page_range(page))
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::ProfileInfo => {
-        println!(
-          "{:#?}",
-          endpoints::get_profile(client).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_profile(client).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::CreatedGames => {
-        println!(
-          "{:#?}",
-          endpoints::get_created_games(client).unwrap_or_else(|e| eprintln_exit!("{e}"))
-        )
+        print_output(
+          format,
+          &endpoints::get_created_games(client).unwrap_or_else(|e| eprintln_exit!("{e}")),
+        );
       }
-      Self::OwnedKeys => {
-        println!(
-          "{:#?}",
-          endpoints::get_owned_keys(client).unwrap_or_else(|e| eprintln_exit!("{e}"))
+      Self::OwnedKeys { page } => {
+        print_output(
+          format,
+          &endpoints::get_owned_keys_range(client, page_range(page))
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::ProfileCollections => {
-        println!(
-          "{:#?}",
-          endpoints::get_profile_collections(client).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_profile_collections(client).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::CollectionInfo { collection_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_collection_info(client, collection_id)
-            .unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_collection_info(client, collection_id)
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
-      Self::CollectionGames { collection_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_collection_games(client, collection_id)
-            .unwrap_or_else(|e| eprintln_exit!("{e}"))
-        )
+      Self::CollectionGames {
+        collection_id,
+        page,
+      } => {
+        print_output(
+          format,
+          &endpoints::get_collection_games_range(client, collection_id, page_range(page))
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
+        );
       }
-      Self::GameInfo { game_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_game_info(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+      Self::GameInfo { game } => {
+        let game_id = crate::resolve_game(client, &game);
+        print_output(
+          format,
+          &endpoints::get_game_info(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
+        );
+      }
+      Self::GameDetails { game } => {
+        let game_id = crate::resolve_game(client, &game);
+        print_output(
+          format,
+          &endpoints::get_game_details(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::GameApiSubkey { game_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_game_subkey(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_game_subkey(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::GameUploads { game_id } => {
         let uploads =
           endpoints::get_game_uploads(client, game_id).unwrap_or_else(|e| eprintln_exit!("{e}"));
-        println!("{uploads:#?}");
+        print_output(format, &uploads);
+
+        // The platforms are only printed for humans, so the JSON output is a single value
+        if matches!(format, OutputFormat::Json) {
+          return;
+        }
 
-        println!("{:#?}", scratch_io::get_game_platforms(&uploads));
+        match scratch_io::get_game_platforms(&uploads) {
+          scratch_io::GamePlatformsResult::NoUploads => println!(
+            "This game has no downloadable files (maybe it's web-only or not yet released)"
+          ),
+          scratch_io::GamePlatformsResult::NoRecognizedPlatforms => {
+            println!("None of the game's uploads is available in a recognized platform");
+          }
+          scratch_io::GamePlatformsResult::Platforms(platforms) => println!("{platforms:#?}"),
+        }
       }
       Self::UploadInfo { upload_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_upload_info(client, upload_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_upload_info(client, upload_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::UploadBuilds { upload_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_upload_builds(client, upload_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_upload_builds(client, upload_id)
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::BuildInfo { build_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_build_info(client, build_id).unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_build_info(client, build_id).unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::UpgradePath {
         current_build_id,
         target_build_id,
       } => {
-        println!(
-          "{:#?}",
-          endpoints::get_upgrade_path(client, current_build_id, target_build_id)
-            .unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_upgrade_path(client, current_build_id, target_build_id)
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::UploadScannedArchive { upload_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_upload_scanned_archive(client, upload_id)
-            .unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_upload_scanned_archive(client, upload_id)
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
       Self::BuildScannedArchive { build_id } => {
-        println!(
-          "{:#?}",
-          endpoints::get_build_scanned_archive(client, build_id)
-            .unwrap_or_else(|e| eprintln_exit!("{e}"))
+        print_output(
+          format,
+          &endpoints::get_build_scanned_archive(client, build_id)
+            .unwrap_or_else(|e| eprintln_exit!("{e}")),
         );
       }
     }
@@ -1,10 +1,11 @@
+use crate::credentials::{self, CredentialStore};
 use crate::eprintln_exit;
 use directories::ProjectDirs;
-use scratch_io::{InstalledUpload, itch_api::types::UploadID};
+use scratch_io::{GamePlatform, InstalledUpload, WrapperTemplate, itch_api::types::UploadID};
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const APP_CONFIGURATION_NAME: &str = "scratch-io";
 const APP_CONFIGURATION_FILE: &str = "config.toml";
@@ -28,6 +29,155 @@ fn get_config_file(custom_config_folder: Option<PathBuf>) -> Result<PathBuf, Str
   get_config_folder(custom_config_folder).map(|d| d.config_dir().join(APP_CONFIGURATION_FILE))
 }
 
+/// Lock the config of this application, waiting if another process has it locked
+///
+/// The lock is only held while the config file is read or replaced, so a long command doesn't
+/// block the other processes. It is a separate file, so the config file itself can be replaced
+/// while it is held. It is released when the returned file is closed, even if the process crashes
+fn lock_config(config_file_path: &Path) -> Result<std::fs::File, String> {
+  let lock_file_path: PathBuf = config_file_path.with_extension("lock");
+
+  if let Some(parent) = lock_file_path.parent() {
+    std::fs::create_dir_all(parent).map_err(|e| {
+      format!(
+        "Couldn't create config directory: \"{}\"\n{e}",
+        parent.display()
+      )
+    })?;
+  }
+
+  let lock_file = std::fs::OpenOptions::new()
+    .create(true)
+    .truncate(false)
+    .write(true)
+    .open(&lock_file_path)
+    .map_err(|e| {
+      format!(
+        "Couldn't open the config lock file: \"{}\"\n{e}",
+        lock_file_path.display()
+      )
+    })?;
+
+  let lock_error = |e: std::io::Error| {
+    format!(
+      "Couldn't lock the config file: \"{}\"\n{e}",
+      lock_file_path.display()
+    )
+  };
+
+  match lock_file.try_lock() {
+    Ok(()) => {}
+    Err(std::fs::TryLockError::WouldBlock) => {
+      eprintln!("Waiting for another scratch-io process to finish saving the config...");
+      lock_file.lock().map_err(lock_error)?;
+    }
+    Err(std::fs::TryLockError::Error(e)) => return Err(lock_error(e)),
+  }
+
+  Ok(lock_file)
+}
+
+/// Move a config file which couldn't be parsed to a backup, and start with an empty config
+fn backup_corrupted_config(config_file_path: &Path, error: &str) -> Result<Config, String> {
+  let backup_path: PathBuf = config_file_path.with_extension("toml.corrupted");
+
+  std::fs::rename(config_file_path, &backup_path).map_err(|e| {
+    format!(
+      "Invalid configuration file: \"{}\"\n{error}\nCouldn't move it to a backup: \"{}\"\n{e}",
+      config_file_path.display(),
+      backup_path.display()
+    )
+  })?;
+
+  eprintln!(
+    "Invalid configuration file: \"{}\"\n{error}\nIt was moved to \"{}\", and an empty config will be used",
+    config_file_path.display(),
+    backup_path.display()
+  );
+
+  Ok(Config::default())
+}
+
+/// Read the config file as a TOML table, to merge the changes of this process into it
+///
+/// Returns None if the file doesn't exist or it can't be parsed, so it is replaced as a whole
+fn read_table(config_file_path: &Path) -> Result<Option<toml::Table>, String> {
+  let config_text: String = match std::fs::read_to_string(config_file_path) {
+    Ok(text) => text,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(e) => {
+      return Err(format!(
+        "Couldn't read the config file data: \"{}\"\n{e}",
+        config_file_path.display()
+      ));
+    }
+  };
+
+  Ok(toml::from_str::<toml::Table>(&config_text).ok())
+}
+
+/// Apply the changes made to a config since it was loaded onto the table read from the file
+///
+/// The values which are the same in `loaded` and `current` are left as they are in `file`, so
+/// the changes saved by other processes in the meantime are kept. Tables are merged key by key,
+/// so two processes changing different installed uploads don't overwrite each other
+fn merge_changes(loaded: &toml::Table, current: &toml::Table, file: &mut toml::Table) {
+  let keys: std::collections::HashSet<&String> = loaded.keys().chain(current.keys()).collect();
+
+  for key in keys {
+    match (loaded.get(key), current.get(key)) {
+      (loaded_value, current_value) if loaded_value == current_value => (),
+      (Some(toml::Value::Table(loaded_table)), Some(toml::Value::Table(current_table))) => {
+        match file.get_mut(key) {
+          Some(toml::Value::Table(file_table)) => {
+            merge_changes(loaded_table, current_table, file_table);
+          }
+          _ => {
+            file.insert(key.clone(), toml::Value::Table(current_table.clone()));
+          }
+        }
+      }
+      (_, Some(current_value)) => {
+        file.insert(key.clone(), current_value.clone());
+      }
+      (_, None) => {
+        file.remove(key);
+      }
+    }
+  }
+}
+
+/// Write a file and wait until its data reaches the disk
+fn write_synced(path: &Path, data: &[u8]) -> std::io::Result<()> {
+  use std::io::Write;
+
+  let mut file = std::fs::File::create(path)?;
+  file.write_all(data)?;
+  file.sync_all()
+}
+
+const LAST_REGISTRY_EXPORT_VERSION: u64 = 0;
+
+/// A portable copy of the installed uploads, used to move the library to another machine
+///
+/// The game folders are stored relative to the library root they were exported from
+#[derive(Serialize, Deserialize)]
+struct RegistryExport {
+  export_version: u64,
+  installed_uploads: Vec<InstalledUpload>,
+}
+
+/// The result of importing an exported registry
+#[derive(Debug, Default)]
+pub struct RegistryImport {
+  /// The uploads that were added to the config
+  pub imported: Vec<UploadID>,
+  /// The uploads whose folder doesn't exist under the new library root
+  pub missing: Vec<UploadID>,
+  /// The uploads that were already installed, which were left untouched
+  pub already_installed: Vec<UploadID>,
+}
+
 /// A struct for deserializing the config version
 ///
 /// After the config file is parsed into this struct, it will be parsed into
@@ -42,9 +192,25 @@ struct ConfigVersion {
 #[derive(Serialize, Deserialize)]
 pub struct Config {
   pub config_version: u64,
+  /// The API key, read from the config file or from the OS keyring
   pub api_key: Option<String>,
+  /// If true, the API key is saved in the OS keyring instead of in the config file
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  api_key_in_keyring: bool,
+  /// The folder where the game folders are placed when no install path is provided
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub games_dir: Option<PathBuf>,
+  /// The wrappers used to launch the games of each platform when no wrapper is provided
+  #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+  pub wrapper_templates: HashMap<GamePlatform, WrapperTemplate>,
   #[serde_as(as = "HashMap<DisplayFromStr, _>")]
   pub installed_uploads: HashMap<UploadID, InstalledUpload>,
+  /// The config as it was loaded, to save only the changes made by this process
+  #[serde(skip)]
+  loaded: toml::Table,
+  /// Where the API key is saved, see [`Config::use_credential_store`]
+  #[serde(skip)]
+  credential_store: CredentialStore,
 }
 
 impl std::default::Default for Config {
@@ -52,7 +218,12 @@ impl std::default::Default for Config {
     Self {
       config_version: LAST_CONFIGURATION_VERSION,
       api_key: None,
+      api_key_in_keyring: false,
+      games_dir: None,
+      wrapper_templates: HashMap::new(),
       installed_uploads: HashMap::new(),
+      loaded: toml::Table::new(),
+      credential_store: CredentialStore::default(),
     }
   }
 }
@@ -60,10 +231,25 @@ impl std::default::Default for Config {
 impl Config {
   /// Load the application's config from a file
   ///
+  /// The loaded config is remembered, so [`Config::save`] only writes the changes made by this process.
+  /// If the config file can't be parsed, it is moved to a backup and an empty config is used
+  ///
   /// If `custom_config_folder` is provided, then use that as the config folder path instead of the system's default
   pub fn load(custom_config_folder: Option<PathBuf>) -> Result<Self, String> {
     // Get the config path
     let config_file_path: PathBuf = get_config_file(custom_config_folder)?;
+
+    // Lock the config while reading it, so a corrupted file isn't moved while another process saves it
+    let _lock = lock_config(&config_file_path)?;
+
+    let mut config = Self::read(&config_file_path)?;
+    config.loaded = toml::Table::try_from(&config)
+      .map_err(|e| format!("Couldn't serialize config into TOML!: {e}"))?;
+    Ok(config)
+  }
+
+  /// Read and parse the config file, or create a default config if it doesn't exist
+  fn read(config_file_path: &Path) -> Result<Self, String> {
     // If the config doesn't exist, create one with Config::default()
     if !config_file_path.try_exists().map_err(|e| {
       format!(
@@ -75,7 +261,7 @@ impl Config {
     }
 
     // Get the config text
-    let config_text: String = std::fs::read_to_string(&config_file_path).map_err(|e| {
+    let config_text: String = std::fs::read_to_string(config_file_path).map_err(|e| {
       format!(
         "Couldn't read the config file data: \"{}\"\n{e}",
         config_file_path.display()
@@ -83,34 +269,25 @@ impl Config {
     })?;
 
     // Get the config version
-    let ver = toml::from_str::<ConfigVersion>(&config_text)
-      .map_err(|e| {
-        format!(
-          "Couldn't get the config version: \"{}\"\n{e}",
-          config_file_path.display()
-        )
-      })?
-      .config_version;
+    let ver = match toml::from_str::<ConfigVersion>(&config_text) {
+      Ok(v) => v.config_version,
+      Err(e) => return backup_corrupted_config(config_file_path, &e.to_string()),
+    };
 
     // Parse the config depending on the version
     match ver {
-      LAST_CONFIGURATION_VERSION => toml::from_str::<Config>(&config_text),
-      _ => {
-        return Err(format!(
-          r#"The config version of "{}" is not compatible with this scratch-io version!
+      LAST_CONFIGURATION_VERSION => match toml::from_str::<Config>(&config_text) {
+        Ok(config) => Ok(config),
+        Err(e) => backup_corrupted_config(config_file_path, &e.to_string()),
+      },
+      _ => Err(format!(
+        r#"The config version of "{}" is not compatible with this scratch-io version!
 Update to a newer scratch-io version to be able to load the given config.
   Config version: {ver}
   Supported version: {LAST_CONFIGURATION_VERSION}"#,
-          config_file_path.display()
-        ));
-      }
-    }
-    .map_err(|e| {
-      format!(
-        "Invalid configuration file: \"{}\"\n{e}",
         config_file_path.display()
-      )
-    })
+      )),
+    }
   }
 
   /// Load the application's config from a file and panic on error
@@ -121,15 +298,112 @@ Update to a newer scratch-io version to be able to load the given config.
       .unwrap_or_else(|e| eprintln_exit!("Error while reading configuration file!\n{}", e))
   }
 
+  /// Choose where the API key is saved, moving it there if it was saved in the other place
+  ///
+  /// If the OS keyring isn't available, like on headless servers, the key is kept in the config
+  /// file with a warning
+  ///
+  /// Returns true if the key was moved, so the config must be saved
+  pub fn use_credential_store(&mut self, store: CredentialStore) -> bool {
+    match store {
+      CredentialStore::Keyring => {
+        let result = if self.api_key_in_keyring {
+          credentials::read_keyring().map(|key| {
+            self.api_key = key;
+            false
+          })
+        } else if let Some(key) = &self.api_key {
+          credentials::write_keyring(Some(key)).map(|()| {
+            eprintln!("The API key was moved from the config file to the OS keyring");
+            true
+          })
+        } else {
+          Ok(false)
+        };
+
+        match result {
+          Ok(migrated) => {
+            self.api_key_in_keyring = true;
+            self.credential_store = CredentialStore::Keyring;
+            migrated
+          }
+          Err(e) => {
+            eprintln!("Warning! {e}\nThe API key will be saved in the config file as plain text");
+            self.credential_store = CredentialStore::Plaintext;
+            false
+          }
+        }
+      }
+      CredentialStore::Plaintext => {
+        self.credential_store = CredentialStore::Plaintext;
+
+        if !self.api_key_in_keyring {
+          return false;
+        }
+
+        match credentials::read_keyring() {
+          Ok(key) => {
+            self.api_key = key;
+            self.api_key_in_keyring = false;
+            eprintln!("The API key was moved from the OS keyring to the config file");
+            if let Err(e) = credentials::write_keyring(None) {
+              eprintln!("Warning! {e}");
+            }
+            true
+          }
+          Err(e) => {
+            eprintln!(
+              "Warning! The API key is saved in the OS keyring, but it couldn't be read\n{e}"
+            );
+            false
+          }
+        }
+      }
+    }
+  }
+
   /// Save the application's config to a file
   ///
+  /// The config file is read again and the changes made since [`Config::load`] are merged into it,
+  /// so the changes saved by other scratch-io processes in the meantime aren't overwritten.
+  /// The config is only locked while it is merged and replaced
+  ///
   /// If `custom_config_folder` is provided, then use that as the config folder path instead of the system's default
   pub fn save(&self, custom_config_folder: Option<PathBuf>) -> Result<(), String> {
     // Get the config path
     let config_file_path: PathBuf = get_config_file(custom_config_folder)?;
 
+    let current_table = toml::Table::try_from(self)
+      .map_err(|e| format!("Couldn't serialize config into TOML!: {e}"))?;
+
+    // Hold the lock until the file is replaced, so no other process saves in between
+    let _lock = lock_config(&config_file_path)?;
+
+    // Merge the changes of this process into the config saved by the others since it was loaded
+    let mut config_table: toml::Table = match read_table(&config_file_path)? {
+      Some(mut file_table) => {
+        merge_changes(&self.loaded, &current_table, &mut file_table);
+        file_table
+      }
+      None => current_table,
+    };
+
+    // Only keep a reference to the API key if it is saved in the OS keyring
+    match self.credential_store {
+      CredentialStore::Keyring => {
+        credentials::write_keyring(self.api_key.as_deref())?;
+        config_table.remove("api_key");
+        config_table.insert("api_key_in_keyring".to_string(), true.into());
+      }
+      // A key saved as plain text replaces the one in the keyring
+      CredentialStore::Plaintext if self.api_key.is_some() => {
+        config_table.remove("api_key_in_keyring");
+      }
+      CredentialStore::Plaintext => (),
+    }
+
     // Get the config text
-    let config_text = toml::to_string_pretty::<Config>(self)
+    let config_text = toml::to_string_pretty(&config_table)
       .map_err(|e| format!("Couldn't serialize config into TOML!: {e}"))?;
 
     // Ensure config directory exists
@@ -142,15 +416,122 @@ Update to a newer scratch-io version to be able to load the given config.
       })?;
     }
 
-    // Write the config to a file
-    std::fs::write(&config_file_path, &config_text).map_err(|e| {
+    // Write the config to a temporary file and replace the old one with it,
+    // so the config is never left half written if the process is interrupted
+    let temp_file_path: PathBuf = config_file_path.with_extension("toml.tmp");
+    write_synced(&temp_file_path, config_text.as_bytes()).map_err(|e| {
       format!(
         "Couldn't write config to a file: \"{}\"\n{e}",
+        temp_file_path.display()
+      )
+    })?;
+
+    std::fs::rename(&temp_file_path, &config_file_path).map_err(|e| {
+      format!(
+        "Couldn't replace the config file: \"{}\"\n{e}",
         config_file_path.display()
       )
     })
   }
 
+  /// Export the installed uploads placed inside `library_root` to a portable JSON
+  ///
+  /// The game folders are made relative to `library_root`, so the library can be
+  /// imported on another machine where it is placed in a different folder
+  ///
+  /// Returns the JSON text and the uploads that were skipped because they aren't inside `library_root`
+  pub fn export_registry(&self, library_root: &Path) -> Result<(String, Vec<UploadID>), String> {
+    let library_root: PathBuf = library_root.canonicalize().map_err(|e| {
+      format!(
+        "Couldn't get the canonical form of the library root: \"{}\"\n{e}",
+        library_root.display()
+      )
+    })?;
+
+    let mut installed_uploads: Vec<InstalledUpload> = Vec::new();
+    let mut skipped: Vec<UploadID> = Vec::new();
+
+    for iu in self.installed_uploads.values() {
+      match iu.game_folder.strip_prefix(&library_root) {
+        Ok(relative) => installed_uploads.push(InstalledUpload {
+          game_folder: relative.to_path_buf(),
+          ..iu.clone()
+        }),
+        Err(_) => skipped.push(iu.upload_id),
+      }
+    }
+
+    let export = RegistryExport {
+      export_version: LAST_REGISTRY_EXPORT_VERSION,
+      installed_uploads,
+    };
+
+    let text = serde_json::to_string_pretty(&export)
+      .map_err(|e| format!("Couldn't serialize the registry into JSON!: {e}"))?;
+
+    Ok((text, skipped))
+  }
+
+  /// Import the installed uploads of a JSON exported with [`Config::export_registry`]
+  ///
+  /// The game folders are placed inside `library_root` and made absolute again.
+  /// Uploads whose folder doesn't exist under `library_root` aren't imported
+  pub fn import_registry(
+    &mut self,
+    text: &str,
+    library_root: &Path,
+  ) -> Result<RegistryImport, String> {
+    let export: RegistryExport = serde_json::from_str(text)
+      .map_err(|e| format!("Couldn't parse the exported registry!: {e}"))?;
+
+    if export.export_version != LAST_REGISTRY_EXPORT_VERSION {
+      return Err(format!(
+        r#"The exported registry version is not compatible with this scratch-io version!
+  Registry version: {}
+  Supported version: {LAST_REGISTRY_EXPORT_VERSION}"#,
+        export.export_version
+      ));
+    }
+
+    let mut result = RegistryImport::default();
+
+    for iu in export.installed_uploads {
+      if self.installed_uploads.contains_key(&iu.upload_id) {
+        result.already_installed.push(iu.upload_id);
+        continue;
+      }
+
+      // Place the game folder inside the new library root, and check that the upload is really there
+      let imported = InstalledUpload {
+        game_folder: library_root.join(&iu.game_folder),
+        ..iu
+      };
+
+      if !imported.upload_folder().is_dir() {
+        result.missing.push(imported.upload_id);
+        continue;
+      }
+
+      let game_folder = imported.game_folder.canonicalize().map_err(|e| {
+        format!(
+          "Couldn't get the canonical form of the game folder: \"{}\"\n{e}",
+          imported.game_folder.display()
+        )
+      })?;
+
+      result.imported.push(imported.upload_id);
+      self.installed_uploads.insert(
+        imported.upload_id,
+        InstalledUpload {
+          game_folder,
+          ..imported
+        },
+      );
+    }
+
+    Ok(result)
+  }
+
   /// Save the application's config to a file and panic on error
   ///
   /// If `custom_config_folder` is provided, then use that as the config folder path instead of the system's default
@@ -160,3 +541,59 @@ Update to a newer scratch-io version to be able to load the given config.
       .unwrap_or_else(|e| eprintln_exit!("Error while saving to the configuration file!\n{}", e))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::Config;
+  use scratch_io::InstalledUpload;
+  use std::path::PathBuf;
+
+  fn installed_upload(upload_id: u64) -> InstalledUpload {
+    toml::from_str(&format!(
+      r#"
+upload_id = {upload_id}
+game_folder = "/games/1"
+game_id = 1
+game_title = "Game"
+"#
+    ))
+    .unwrap()
+  }
+
+  #[test]
+  fn concurrent_saves_keep_each_other_changes() {
+    let folder =
+      std::env::temp_dir().join(format!("scratch-io-test-config-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+
+    // Both processes load the config before any of them saves
+    let mut first = Config::load(Some(folder.clone())).unwrap();
+    let mut second = Config::load(Some(folder.clone())).unwrap();
+
+    first.installed_uploads.insert(10, installed_upload(10));
+    first.save(Some(folder.clone())).unwrap();
+
+    second.installed_uploads.insert(20, installed_upload(20));
+    second.games_dir = Some(PathBuf::from("/games"));
+    second.save(Some(folder.clone())).unwrap();
+
+    let saved = Config::load(Some(folder.clone())).unwrap();
+    assert_eq!(saved.installed_uploads.len(), 2);
+    assert_eq!(saved.installed_uploads[&10], installed_upload(10));
+    assert_eq!(saved.installed_uploads[&20], installed_upload(20));
+    assert_eq!(saved.games_dir, Some(PathBuf::from("/games")));
+
+    // Removing an upload in a process doesn't bring back the ones removed by the others
+    let mut first = Config::load(Some(folder.clone())).unwrap();
+    let mut second = Config::load(Some(folder.clone())).unwrap();
+    first.installed_uploads.remove(&10);
+    first.save(Some(folder.clone())).unwrap();
+    second.installed_uploads.remove(&20);
+    second.save(Some(folder.clone())).unwrap();
+
+    let saved = Config::load(Some(folder.clone())).unwrap();
+    assert!(saved.installed_uploads.is_empty());
+
+    std::fs::remove_dir_all(&folder).unwrap();
+  }
+}
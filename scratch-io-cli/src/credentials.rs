@@ -0,0 +1,63 @@
+/// The service and account names of the API key in the OS keyring
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "scratch-io";
+#[cfg(feature = "keyring")]
+const KEYRING_ACCOUNT: &str = "api-key";
+
+/// Where the API key is saved
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CredentialStore {
+  /// In the config file, as plain text
+  #[default]
+  Plaintext,
+  /// In the secret store of the OS, keeping only a reference in the config file
+  Keyring,
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry() -> Result<keyring::Entry, String> {
+  keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT)
+    .map_err(|e| format!("Couldn't access the OS keyring!\n{e}"))
+}
+
+/// Read the API key saved in the OS keyring
+///
+/// Returns None if there isn't a saved key
+#[cfg(feature = "keyring")]
+pub fn read_keyring() -> Result<Option<String>, String> {
+  match keyring_entry()?.get_password() {
+    Ok(key) => Ok(Some(key)),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(format!(
+      "Couldn't read the API key from the OS keyring!\n{e}"
+    )),
+  }
+}
+
+/// Save the API key in the OS keyring, or remove it if it is None
+#[cfg(feature = "keyring")]
+pub fn write_keyring(api_key: Option<&str>) -> Result<(), String> {
+  let entry = keyring_entry()?;
+
+  match api_key {
+    Some(key) => entry
+      .set_password(key)
+      .map_err(|e| format!("Couldn't save the API key in the OS keyring!\n{e}")),
+    None => match entry.delete_credential() {
+      Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+      Err(e) => Err(format!(
+        "Couldn't remove the API key from the OS keyring!\n{e}"
+      )),
+    },
+  }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read_keyring() -> Result<Option<String>, String> {
+  Err("This scratch-io build doesn't support the OS keyring".to_string())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn write_keyring(_api_key: Option<&str>) -> Result<(), String> {
+  Err("This scratch-io build doesn't support the OS keyring".to_string())
+}
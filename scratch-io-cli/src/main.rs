@@ -1,16 +1,20 @@
 mod api;
 mod config;
+mod credentials;
 mod session;
+mod settings;
 mod wharf;
 
 use api::ApiCommand;
 use config::Config;
+use credentials::CredentialStore;
 use session::SessionCommand;
+use settings::ConfigCommand;
 use wharf::WharfCommand;
 
 use clap::{Parser, Subcommand};
 use scratch_io::itch_api::ItchClient;
-use scratch_io::itch_api::types::{GameID, UploadID};
+use scratch_io::itch_api::types::{BuildID, CollectionID, GameID, OwnedKeyID, UploadID};
 use scratch_io::{DownloadStatus, InstalledUpload};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -34,10 +38,70 @@ struct Cli {
   #[arg(short, long, env = "SCRATCH_CONFIG_FILE")]
   config_file: Option<PathBuf>,
 
+  /// Where the API key is saved
+  ///
+  /// A key saved in the other place is moved on the first use
+  #[arg(long, env = "SCRATCH_CREDENTIAL_STORE", value_enum, default_value_t)]
+  credential_store: CredentialStore,
+
+  /// How many times the requests are retried if they fail because of network errors
+  #[arg(long, env = "SCRATCH_RETRIES", default_value_t = 0)]
+  retries: u32,
+
+  /// The maximum number of API requests sent per second
+  ///
+  /// If not provided, the requests are only slowed down when the server asks for it
+  #[arg(long, env = "SCRATCH_MAX_REQUESTS_PER_SEC")]
+  max_requests_per_sec: Option<std::num::NonZeroU32>,
+
+  /// The URL of a proxy used for all the requests, like http://proxy.example.com:8080
+  ///
+  /// If not provided, the system proxy is used, if there is one
+  #[arg(long, env = "SCRATCH_PROXY")]
+  proxy: Option<String>,
+
+  /// The folder where the game folders are placed when no install path is provided
+  ///
+  /// Overrides the folder saved with `config set-games-dir`, which defaults to ~/Games
+  #[arg(long, env = "SCRATCH_GAMES_DIR")]
+  games_dir: Option<PathBuf>,
+
+  /// The format used to print the returned info
+  #[arg(
+    long,
+    env = "SCRATCH_OUTPUT_FORMAT",
+    value_enum,
+    default_value_t,
+    global = true
+  )]
+  format: OutputFormat,
+
   #[command(subcommand)]
   command: Commands,
 }
 
+// The format used to print the info returned by the commands
+#[derive(clap::ValueEnum, Clone, Copy, Default)]
+pub enum OutputFormat {
+  /// Rust debug formatting, meant to be read by humans
+  #[default]
+  Human,
+  /// JSON, meant to be read by scripts
+  Json,
+}
+
+// Print a value in the given output format
+pub fn print_output<T: std::fmt::Debug + serde::Serialize>(format: OutputFormat, value: &T) {
+  match format {
+    OutputFormat::Human => println!("{value:#?}"),
+    OutputFormat::Json => println!(
+      "{}",
+      serde_json::to_string_pretty(value)
+        .unwrap_or_else(|e| eprintln_exit!("Couldn't serialize the output to JSON!\n{e}"))
+    ),
+  }
+}
+
 // This enum is a copy of scratch_io::GamePlatform that derives clap::ValueEnum
 #[derive(clap::ValueEnum, Clone)]
 enum GamePlatform {
@@ -66,12 +130,72 @@ impl From<GamePlatform> for scratch_io::GamePlatform {
   }
 }
 
+// The download options shared by the commands that download uploads
+#[derive(clap::Args)]
+struct NetworkArgs {
+  /// The number of connections used to download the upload in parallel
+  ///
+  /// Interrupted parallel downloads start again from the beginning
+  #[arg(long, env = "SCRATCH_CONNECTIONS", default_value = "1")]
+  connections: std::num::NonZeroUsize,
+  /// The maximum download speed in bytes per second, like 500K or 2M
+  ///
+  /// The K, M and G suffixes are powers of 1024. If not provided, the speed isn't limited
+  #[arg(long, env = "SCRATCH_MAX_SPEED", value_parser = parse_speed)]
+  max_speed: Option<u64>,
+  /// A folder where the upload is downloaded and extracted before moving it to the install path
+  ///
+  /// It can be on another filesystem, like a fast local disk
+  #[arg(long, env = "SCRATCH_TEMP_DIR")]
+  temp_dir: Option<PathBuf>,
+  /// Another base URL of the itch.io API, tried if the download fails on the previous one
+  ///
+  /// It can be repeated, and the mirrors are tried in order. The API key is sent to them, so
+  /// they must be HTTPS addresses of itch.io or itch.zone, or one of their subdomains
+  #[arg(long = "mirror", env = "SCRATCH_MIRRORS", value_delimiter = ',')]
+  mirrors: Vec<String>,
+  /// Stop the download if its speed stays below these bytes per second, like 10K
+  ///
+  /// The download switches to the next mirror, if there is one. If not provided, a slow
+  /// download is never stopped
+  #[arg(long, env = "SCRATCH_MIN_SPEED", value_parser = parse_speed)]
+  min_speed: Option<u64>,
+  /// The seconds the speed has to stay below the minimum speed to stop the download
+  #[arg(
+    long,
+    env = "SCRATCH_STALL_TIMEOUT",
+    default_value = "60",
+    requires = "min_speed"
+  )]
+  stall_timeout: u64,
+}
+
+impl NetworkArgs {
+  // Set these options in the default download options
+  fn download_options(self) -> scratch_io::DownloadOptions {
+    scratch_io::DownloadOptions {
+      download_connections: self.connections,
+      max_bytes_per_sec: self.max_speed,
+      temp_dir: self.temp_dir,
+      mirrors: self.mirrors,
+      min_speed: self
+        .min_speed
+        .map(|speed| (speed, std::time::Duration::from_secs(self.stall_timeout))),
+      ..Default::default()
+    }
+  }
+}
+
 #[derive(Subcommand)]
 enum Commands {
   /// Manage the current session (login, logout)
   #[clap(subcommand)]
   Session(SessionCommand),
 
+  /// Manage the settings saved in the config file
+  #[clap(subcommand)]
+  Config(ConfigCommand),
+
   /// Make an API call to the itch.io servers
   #[clap(subcommand)]
   Api(ApiCommand),
@@ -95,14 +219,101 @@ enum Commands {
 enum WithApiCommands {
   /// Download the upload with the given ID
   Download {
-    /// The ID of the upload to download
-    upload_id: UploadID,
+    /// The ID of the upload to download, or the itch.io URL of a game with a single upload
+    upload: String,
     /// The path where the download folder will be placed
+    ///
+    /// Defaults to a folder named like the game inside the games folder
     #[arg(long, env = "SCRATCH_INSTALL_PATH")]
-    install_path: PathBuf,
+    install_path: Option<PathBuf>,
+    /// The ID of the owned key which grants access to the upload
+    ///
+    /// If not provided and the game isn't free, it is searched in the user's owned keys
+    #[arg(long, env = "SCRATCH_DOWNLOAD_KEY_ID")]
+    download_key_id: Option<OwnedKeyID>,
+    /// The UUID of a download key, like the one in the download page URL of a purchase
+    ///
+    /// If provided, it is used instead of the download key ID
+    #[arg(long, env = "SCRATCH_DOWNLOAD_KEY_UUID")]
+    download_key_uuid: Option<String>,
+    /// Skip the hash verification and allow installing modified files (unsafe)
+    #[arg(long, env = "SCRATCH_SKIP_HASH_VERIFICATION")]
+    skip_hash_verification: bool,
+    /// Keep extracting the archive if some of its entries are corrupted, and list them at the end
+    #[arg(long, env = "SCRATCH_LENIENT_EXTRACTION")]
+    lenient_extraction: bool,
+    /// The password used to extract the upload archive, if it is encrypted
+    #[arg(long, env = "SCRATCH_ARCHIVE_PASSWORD")]
+    archive_password: Option<String>,
+    /// Extract tar archives while downloading them, without storing the archive on disk
+    ///
+    /// Interrupted streamed downloads start again from the beginning
+    #[arg(long, env = "SCRATCH_STREAM_EXTRACT")]
+    stream_extract: bool,
+    #[clap(flatten)]
+    network: NetworkArgs,
+    /// Print where the upload would be downloaded and its size, without downloading it
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Download all the uploads of a game, like the builds for each platform
+  ///
+  /// The uploads that are already installed are skipped
+  DownloadGame {
+    /// The ID of the game whose uploads will be downloaded, or its itch.io URL
+    game: String,
+    /// The path where the upload folders will be placed
+    ///
+    /// Defaults to a folder named like the game inside the games folder
+    #[arg(long, env = "SCRATCH_INSTALL_PATH")]
+    install_path: Option<PathBuf>,
+    /// Only download the uploads available in these platforms. Can be repeated
+    #[arg(long)]
+    platform: Vec<GamePlatform>,
     /// Skip the hash verification and allow installing modified files (unsafe)
     #[arg(long, env = "SCRATCH_SKIP_HASH_VERIFICATION")]
     skip_hash_verification: bool,
+    /// Keep extracting the archives if some of their entries are corrupted
+    #[arg(long, env = "SCRATCH_LENIENT_EXTRACTION")]
+    lenient_extraction: bool,
+  },
+  /// Print the URL where the upload with the given ID is stored, without downloading it
+  ///
+  /// The URL can be passed to an external download manager. It may only work for a
+  /// limited time, and it shouldn't be shared because it doesn't require authentication
+  DownloadUrl {
+    /// The ID of the upload whose download URL will be printed
+    upload_id: UploadID,
+    /// The ID of the owned key which grants access to the upload, required for paid games
+    #[arg(long, env = "SCRATCH_DOWNLOAD_KEY_ID")]
+    download_key_id: Option<OwnedKeyID>,
+  },
+  /// List the installed uploads with a newer build available
+  Updates,
+  /// Print how many games the user owns and how many of them are installed
+  OwnedSummary,
+  /// Search games by their title
+  Search {
+    /// The text to search for
+    query: String,
+  },
+  /// Download the games of a collection that aren't installed yet
+  SyncCollection {
+    /// The ID of the collection to sync
+    collection_id: CollectionID,
+    /// The path where the game folders will be placed
+    ///
+    /// Defaults to the games folder
+    #[arg(long, env = "SCRATCH_INSTALL_PATH")]
+    install_path: Option<PathBuf>,
+    /// The platform the downloaded uploads must be available in
+    #[arg(long, env = "SCRATCH_PLATFORM")]
+    platform: GamePlatform,
+    /// Remove the installed games inside the install path that aren't in the collection anymore
+    #[arg(long, env = "SCRATCH_PRUNE")]
+    prune: bool,
+    #[clap(flatten)]
+    network: NetworkArgs,
   },
   /// Download a game cover gives its game ID
   DownloadCover {
@@ -125,8 +336,15 @@ enum WithApiCommands {
     /// The ID of the upload which has been partially downloaded
     upload_id: UploadID,
     /// The path where the download folder has been placed
+    ///
+    /// Defaults to the same folder as the download command, named like the game inside the games folder
     #[arg(long, env = "SCRATCH_INSTALL_PATH")]
-    install_path: PathBuf,
+    install_path: Option<PathBuf>,
+    /// The ID of the game the upload belongs to
+    ///
+    /// If provided, check that the upload really belongs to this game before removing anything
+    #[arg(long, env = "SCRATCH_GAME_ID")]
+    game_id: Option<GameID>,
   },
   /// Imports an already installed game given its upload ID and the game folder
   Import {
@@ -134,6 +352,71 @@ enum WithApiCommands {
     upload_id: UploadID,
     /// The path where the game folder is located
     install_path: PathBuf,
+    /// The ID of the game the upload belongs to
+    ///
+    /// If provided, check that the upload really belongs to this game before importing it
+    #[arg(long, env = "SCRATCH_GAME_ID")]
+    game_id: Option<GameID>,
+  },
+  /// Import all the untracked upload folders found inside a folder, like `~/Games/*/123456/`
+  ScanImport {
+    /// The folder to scan
+    ///
+    /// Defaults to the games folder
+    base_folder: Option<PathBuf>,
+  },
+  /// Verify an installed upload against the MD5 hash provided by itch.io
+  ///
+  /// Only uploads which aren't archives can be verified, because extracted archives are removed
+  Verify {
+    /// The ID of the upload to verify
+    upload_id: UploadID,
+    /// The path where the game folder is located
+    ///
+    /// Defaults to the game folder of the installed upload
+    #[arg(long, env = "SCRATCH_INSTALL_PATH")]
+    install_path: Option<PathBuf>,
+  },
+  /// List everything that can be launched from an installed upload
+  ///
+  /// The targets come from the itch manifest, the itch.io server scan and the launch heuristics
+  LaunchTargets {
+    /// The ID of the installed upload
+    upload_id: UploadID,
+  },
+  /// Upgrade an installed upload to another build by applying the wharf patches
+  ///
+  /// If the installed build is unknown, the upload is downloaded again fully
+  Upgrade {
+    /// The ID of the installed upload to upgrade
+    upload_id: UploadID,
+    /// The ID of the build to upgrade to
+    build_id: BuildID,
+    #[clap(flatten)]
+    network: NetworkArgs,
+  },
+  /// Show the files that change between two builds, without applying the patches
+  BuildDiff {
+    /// The ID of the build to compare from, usually the installed one
+    current: BuildID,
+    /// The ID of the build to compare to
+    target: BuildID,
+  },
+  /// Verify an installed upload against its build signature, and fetch again only the broken files
+  ///
+  /// If the server doesn't allow fetching single files, the upload is downloaded again fully
+  Heal {
+    /// The ID of the installed upload to heal
+    upload_id: UploadID,
+    #[clap(flatten)]
+    network: NetworkArgs,
+  },
+  /// Remove the files of an installed upload and download it again into the same folder
+  Reinstall {
+    /// The ID of the installed upload to reinstall
+    upload_id: UploadID,
+    #[clap(flatten)]
+    network: NetworkArgs,
   },
 }
 
@@ -141,7 +424,13 @@ enum WithApiCommands {
 #[derive(Subcommand)]
 enum WithoutApiCommands {
   /// List the installed games
-  Installed,
+  Installed {
+    /// Refresh the games info and report changes, like a free game becoming paid or being delisted
+    ///
+    /// Requires an itch.io API key
+    #[arg(long)]
+    refresh: bool,
+  },
   /// Get the installed information about an upload given its ID
   InstalledUpload {
     /// The ID of the upload to retrieve information about
@@ -152,6 +441,46 @@ enum WithoutApiCommands {
     /// The ID of the upload to remove
     upload_id: UploadID,
   },
+  /// Open the folder of an installed upload in the file manager
+  Open {
+    /// The ID of the upload whose folder will be opened
+    upload_id: UploadID,
+  },
+  /// Export the installed uploads inside a library folder to a portable JSON file
+  ExportRegistry {
+    /// The file where the exported registry will be written
+    file: PathBuf,
+    /// The folder containing the game folders, which will be stored as relative paths
+    #[arg(long, env = "SCRATCH_LIBRARY_ROOT")]
+    library_root: PathBuf,
+  },
+  /// Import the installed uploads from a JSON file created with export-registry
+  ImportRegistry {
+    /// The exported registry file
+    file: PathBuf,
+    /// The folder where the game folders are placed on this machine
+    #[arg(long, env = "SCRATCH_LIBRARY_ROOT")]
+    library_root: PathBuf,
+  },
+  /// Remove the upload folders inside a library folder which aren't installed uploads anymore
+  ///
+  /// Only the folders named after an upload ID, directly inside the library folder or inside
+  /// its game folders, are removed
+  Prune {
+    /// The folder containing the game folders
+    #[arg(env = "SCRATCH_LIBRARY_ROOT")]
+    library_root: PathBuf,
+    /// Only list the folders which would be removed
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Move the game folders inside a library folder to another library folder
+  MoveLibrary {
+    /// The folder where the game folders are currently placed
+    library_root: PathBuf,
+    /// The folder where the game folders will be placed
+    new_library_root: PathBuf,
+  },
   /// Move a installed upload to another game folder
   Move {
     /// The ID of the upload to import
@@ -159,8 +488,46 @@ enum WithoutApiCommands {
     /// The path where the game folder will be placed
     game_path_dst: PathBuf,
   },
+  /// Save the options used to launch an installed upload when no launch method is provided
+  SetLaunchOption {
+    /// The ID of the upload whose launch options will be saved
+    upload_id: UploadID,
+    /// The executable to launch, absolute or relative to the upload folder
+    #[arg(long)]
+    executable_path: Option<PathBuf>,
+    /// A wrapper command to launch the game with
+    #[arg(long)]
+    wrapper: Option<String>,
+    /// The arguments the game will be called with
+    #[arg(long)]
+    game_arguments: Option<String>,
+    /// The environment variables that will be added to the game process's environment
+    #[arg(long)]
+    environment_variables: Option<String>,
+    /// Remove the saved launch options instead
+    #[arg(long, conflicts_with_all = ["executable_path", "wrapper", "game_arguments", "environment_variables"])]
+    clear: bool,
+  },
+  /// List the best candidates to be the game executable of an installed upload, with their ratings
+  ///
+  /// These are the files the launch heuristics choose from
+  Executables {
+    /// The ID of the upload whose files will be rated
+    upload_id: UploadID,
+    /// The platform for which the game binary will be searched
+    #[arg(long, env = "SCRATCH_PLATFORM")]
+    platform: GamePlatform,
+    /// Prefer the executables whose name contains this pattern. Can be repeated
+    #[arg(long)]
+    prefer_executable: Vec<String>,
+    /// The number of candidates to print
+    #[arg(long, default_value_t = 5)]
+    limit: usize,
+  },
   /// Launchs an installed game given its upload ID and the platform or executable path
-  #[command(group(clap::ArgGroup::new("launch_method").required(true).multiple(true)))]
+  ///
+  /// If an executable path is provided, it is saved and used the next times no launch method is provided
+  #[command(group(clap::ArgGroup::new("launch_method").multiple(true)))]
   Launch {
     /// The ID of the upload to launch
     upload_id: UploadID,
@@ -176,10 +543,15 @@ enum WithoutApiCommands {
     /// The heuristics need to know which platform is the executable they are searching.
     #[arg(long, env = "SCRATCH_PLATFORM", group = "launch_method")]
     platform: Option<GamePlatform>,
+    /// Prefer the executables whose name contains this pattern when using the heuristics. Can be repeated
+    #[arg(long)]
+    prefer_executable: Vec<String>,
     /// Instead of the platform (or in addition to), a executable path can be provided
     #[arg(long, env = "SCRATCH_UPLOAD_EXECUTABLE_PATH", group = "launch_method")]
     upload_executable_path: Option<PathBuf>,
     /// A wrapper command to launch the game with
+    ///
+    /// Without it, the wrapper template of the game's platform is used, see "config set-wrapper-template"
     #[arg(long, env = "SCRATCH_WRAPPER")]
     wrapper: Option<String>,
     /// The arguments the game will be called with
@@ -187,16 +559,33 @@ enum WithoutApiCommands {
     /// The arguments will be split into a vector according to parsing rules of UNIX shell
     #[arg(long, env = "SCRATCH_GAME_ARGUMENTS")]
     game_arguments: Option<String>,
+    /// Append the game arguments to the manifest action's arguments instead of replacing them
+    #[arg(long, env = "SCRATCH_APPEND_GAME_ARGUMENTS")]
+    append_game_arguments: bool,
     /// The environment variables that will be added to the game process's environment
     ///
     /// The arguments will be split into key-value pairs using the "=" separator
     #[arg(long, env = "SCRATCH_ENVIRONMENT_VARIABLES")]
     environment_variables: Option<String>,
+    /// Capture the game's error output, and show it again if the game exits with an error
+    #[arg(long)]
+    capture_output: bool,
+    /// Check that the installed files haven't been modified since the upload was installed before launching it
+    #[arg(long, env = "SCRATCH_VERIFY_BEFORE_LAUNCH")]
+    verify: bool,
+    /// Start the game and exit without waiting for it, so its playtime isn't recorded
+    #[arg(long, conflicts_with = "capture_output")]
+    detach: bool,
   },
 }
 
 /// Returns a Itch client with the first API key of the vector that is not None
-fn get_itch_client(keys: Vec<Option<String>>) -> Result<ItchClient, String> {
+fn get_itch_client(
+  keys: Vec<Option<String>>,
+  retries: u32,
+  max_requests_per_sec: Option<std::num::NonZeroU32>,
+  proxy: Option<String>,
+) -> Result<ItchClient, String> {
   let api_key = keys.into_iter().find_map(|key| key);
 
   match api_key {
@@ -204,22 +593,21 @@ fn get_itch_client(keys: Vec<Option<String>>) -> Result<ItchClient, String> {
       "Error: an itch.io API key is required, either via --api-key, auth, or the login command."
         .to_string(),
     ),
-    Some(api_key) => Ok(ItchClient::new(api_key)),
+    Some(api_key) => Ok(
+      ItchClient::with_client_config(
+        api_key,
+        scratch_io::itch_api::ClientConfig {
+          proxy,
+          ..Default::default()
+        },
+      )
+      .map_err(|e| format!("Couldn't create the HTTP client!\n{e}"))?
+      .with_retry_policy(scratch_io::itch_api::RetryPolicy::exponential(retries + 1))
+      .with_rate_limit(max_requests_per_sec),
+    ),
   }
 }
 
-fn get_installed_upload_info(
-  upload_id: UploadID,
-  mut installed_uploads: HashMap<UploadID, InstalledUpload>,
-) -> InstalledUpload {
-  installed_uploads.remove(&upload_id).unwrap_or_else(|| {
-    eprintln_exit!(
-      "The given upload id is not installed!: {}",
-      upload_id.to_string()
-    )
-  })
-}
-
 fn get_installed_upload_info_ref(
   upload_id: UploadID,
   installed_uploads: &HashMap<UploadID, InstalledUpload>,
@@ -256,12 +644,67 @@ fn exit_if_already_installed(
   }
 }
 
+// Parse a speed in bytes per second, with an optional K, M or G suffix
+fn parse_speed(speed: &str) -> Result<u64, String> {
+  let speed = speed.trim();
+  let (number, multiplier) = match speed.chars().last().map(|c| c.to_ascii_uppercase()) {
+    Some('K') => (&speed[..speed.len() - 1], 1 << 10),
+    Some('M') => (&speed[..speed.len() - 1], 1 << 20),
+    Some('G') => (&speed[..speed.len() - 1], 1 << 30),
+    _ => (speed, 1),
+  };
+
+  let number: f64 = number
+    .trim()
+    .parse()
+    .map_err(|e| format!("Invalid speed \"{speed}\": {e}"))?;
+
+  if !number.is_finite() || number <= 0.0 {
+    return Err(format!("The speed must be greater than 0: \"{speed}\""));
+  }
+
+  Ok((number * multiplier as f64) as u64)
+}
+
+// Print where an upload would be downloaded and its size
+fn print_download_plan(
+  client: &ItchClient,
+  upload_id: UploadID,
+  dest: &Path,
+  download_key_id: Option<OwnedKeyID>,
+) {
+  let plan = scratch_io::plan_download(client, upload_id, dest, download_key_id)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't plan the download!\n{e}"));
+
+  println!("Game folder: \"{}\"", plan.game_folder.display());
+  println!("Download file: \"{}\"", plan.upload_archive.display());
+  println!("Upload folder: \"{}\"", plan.upload_folder.display());
+  match plan.content_length {
+    Some(size) => println!("Download size: {size} bytes"),
+    None => println!("Download size: unknown"),
+  }
+  if let Some(size) = plan.partial_download_size {
+    println!("Already downloaded: {size} bytes");
+  }
+  if !plan.has_hash {
+    println!("Warning! The upload doesn't have a hash, so its integrity can't be verified");
+  }
+}
+
+// The style of the progress bars of the upgrade, heal and reinstall operations
+fn byte_progress_style() -> indicatif::ProgressStyle {
+  indicatif::ProgressStyle::default_bar()
+    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) {msg}")
+    .unwrap()
+    .progress_chars("#>-")
+}
+
 // Download a game's upload
 fn download(
   client: &ItchClient,
   upload_id: UploadID,
   dest: &Path,
-  skip_hash_verification: bool,
+  options: &scratch_io::DownloadOptions,
   installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
 ) {
   exit_if_already_installed(upload_id, installed_uploads);
@@ -273,11 +716,11 @@ fn download(
       .progress_chars("#>-")
   );
 
-  let iu = scratch_io::download_upload(
+  let iu = scratch_io::download_upload_with_options(
     client,
     upload_id,
     dest,
-    skip_hash_verification,
+    options,
     |u, g| println!("{g:#?}\n{u:#?}"),
     |download_status| {
       match download_status {
@@ -287,13 +730,47 @@ fn download(
           progress_bar.set_length(bytes_to_download);
           progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
         }
-        DownloadStatus::DownloadProgress { downloaded_bytes } => {
-          progress_bar.set_position(downloaded_bytes)
+        DownloadStatus::DownloadProgress {
+          downloaded_bytes, ..
+        } => progress_bar.set_position(downloaded_bytes),
+        DownloadStatus::Extract => {
+          progress_bar.finish();
+          println!("Extracting archive...");
+          progress_bar.reset();
+          progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+        }
+        DownloadStatus::ExtractProgress {
+          extracted_bytes,
+          total_bytes,
+        } => {
+          progress_bar.set_length(total_bytes);
+          progress_bar.set_position(extracted_bytes);
+        }
+        DownloadStatus::ExtractionFailedEntries(entries) => {
+          eprintln!(
+            "Warning! {} archive entries couldn't be extracted:",
+            entries.len()
+          );
+          for e in entries {
+            eprintln!("  \"{}\": {}", e.entry, e.error);
+          }
+        }
+        DownloadStatus::Cancelled => {
+          progress_bar.abandon();
+          println!("Download cancelled. It can be resumed by downloading the upload again");
+        }
+        DownloadStatus::Finished {
+          bytes_transferred,
+          mirror,
+        } => {
+          progress_bar.finish();
+          println!("Transferred {}", indicatif::HumanBytes(bytes_transferred));
+          if let Some(mirror) = mirror {
+            println!("Downloaded from the mirror: {mirror}");
+          }
         }
-        DownloadStatus::Extract => println!("Extracting archive..."),
       };
     },
-    std::time::Duration::from_millis(100),
   )
   .unwrap_or_else(|e| eprintln_exit!("Error while downloading file!\n{}", e));
 
@@ -304,6 +781,196 @@ fn download(
   installed_uploads.insert(upload_id, iu);
 }
 
+// Download all the uploads of a game
+fn download_game(
+  client: &ItchClient,
+  game_id: GameID,
+  dest: &Path,
+  platforms: Vec<GamePlatform>,
+  options: &scratch_io::DownloadOptions,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let platforms: Vec<scratch_io::GamePlatform> = platforms.into_iter().map(Into::into).collect();
+
+  let actions = scratch_io::download_all_uploads(
+    client,
+    game_id,
+    dest,
+    (!platforms.is_empty()).then_some(platforms.as_slice()),
+    options,
+    installed_uploads,
+    |upload_id, status| match status {
+      scratch_io::UploadBatchStatus::Download(DownloadStatus::Warning(w)) => {
+        println!("Upload {upload_id}: {w}");
+      }
+      scratch_io::UploadBatchStatus::Download(DownloadStatus::StartingDownload { .. }) => {
+        println!("Upload {upload_id}: downloading...");
+      }
+      scratch_io::UploadBatchStatus::Download(DownloadStatus::Extract) => {
+        println!("Upload {upload_id}: extracting archive...");
+      }
+      scratch_io::UploadBatchStatus::Download(_) => (),
+      scratch_io::UploadBatchStatus::Done(action) => match action {
+        scratch_io::SyncAction::Installed(_) => println!("Upload {upload_id}: installed"),
+        scratch_io::SyncAction::AlreadyInstalled => {
+          println!("Upload {upload_id}: already installed");
+        }
+        scratch_io::SyncAction::Updated(_)
+        | scratch_io::SyncAction::UpToDate
+        | scratch_io::SyncAction::Pruned(_) => (),
+        scratch_io::SyncAction::Failed(e) => eprintln!("Upload {upload_id}: failed!\n{e}"),
+      },
+    },
+  )
+  .unwrap_or_else(|e| eprintln_exit!("Couldn't get the game uploads!\n{e}"));
+
+  let failed = actions
+    .iter()
+    .filter(|(_, a)| matches!(a, scratch_io::SyncAction::Failed(_)))
+    .count();
+  println!("{} uploads processed, {failed} failed", actions.len());
+}
+
+// Print the URL where a game's upload is stored
+fn print_download_url(
+  client: &ItchClient,
+  upload_id: UploadID,
+  download_key_id: Option<OwnedKeyID>,
+) {
+  let url = scratch_io::get_download_url(client, upload_id, download_key_id)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't get the download URL!\n{e}"));
+
+  println!("{url}");
+}
+
+// Get a game ID from either the ID itself or the itch.io URL of the game
+fn resolve_game(client: &ItchClient, game: &str) -> GameID {
+  game.parse().unwrap_or_else(|_| {
+    scratch_io::resolve_game_url(client, game)
+      .unwrap_or_else(|e| eprintln_exit!("Couldn't resolve the game URL!\n{e}"))
+  })
+}
+
+// Get an upload ID from either the ID itself or the itch.io URL of a game with a single upload
+fn resolve_upload(client: &ItchClient, upload: &str) -> UploadID {
+  if let Ok(upload_id) = upload.parse() {
+    return upload_id;
+  }
+
+  let game_id = resolve_game(client, upload);
+  let uploads = scratch_io::itch_api::endpoints::get_game_uploads(client, game_id)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't get the game uploads!\n{e}"));
+
+  match uploads.as_slice() {
+    [upload] => upload.id,
+    [] => eprintln_exit!("The game {game_id} doesn't have any uploads"),
+    _ => {
+      eprintln!("The game {game_id} has multiple uploads, choose one of them by its ID:");
+      for u in &uploads {
+        eprintln!("  {}\t{}", u.id, u.get_name());
+      }
+      std::process::exit(1);
+    }
+  }
+}
+
+// Search games by their title and print them
+fn search_games(client: &ItchClient, query: &str) {
+  let games = scratch_io::itch_api::endpoints::search_games(client, query)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't search the games!\n{e}"));
+
+  if games.is_empty() {
+    println!("No games found");
+  }
+
+  for game in games {
+    println!("{}\t{}\t{}", game.id, game.title, game.url);
+  }
+}
+
+// Print the installed uploads with a newer build available
+fn print_updates(
+  client: &ItchClient,
+  format: OutputFormat,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let updates = scratch_io::check_updates(client, installed_uploads.values())
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't check the updates!\n{e}"));
+
+  if let OutputFormat::Json = format {
+    print_output(format, &updates);
+    return;
+  }
+
+  if updates.is_empty() {
+    println!("All the installed uploads are up to date");
+    return;
+  }
+
+  println!("Upload\tInstalled build\tLatest build\tVersion\tGame");
+  for u in updates {
+    println!(
+      "{}\t{}\t{}\t{}\t{}",
+      u.upload_id,
+      u.installed_build_id,
+      u.latest_build_id,
+      u.latest_user_version.as_deref().unwrap_or("unknown"),
+      u.game_title
+    );
+  }
+}
+
+// Print a summary of the user's library
+fn print_owned_summary(
+  client: &ItchClient,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let summary = scratch_io::get_owned_summary(client, installed_uploads.values())
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't get the owned games summary!\n{e}"));
+
+  println!(
+    "You own {} games, {} installed ({} downloads)",
+    summary.owned_games, summary.installed_games, summary.total_downloads
+  );
+}
+
+// Sync a collection into a folder
+fn sync_collection(
+  client: &ItchClient,
+  collection_id: CollectionID,
+  install_path: &Path,
+  platform: GamePlatform,
+  prune: bool,
+  options: &scratch_io::DownloadOptions,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  scratch_io::sync_collection(
+    client,
+    collection_id,
+    install_path,
+    platform.into(),
+    prune,
+    options,
+    installed_uploads,
+    |game_id, action| match action {
+      scratch_io::SyncAction::Installed(upload_id) => {
+        println!("Game {game_id}: installed upload {upload_id}");
+      }
+      scratch_io::SyncAction::Updated(upload_id) => {
+        println!("Game {game_id}: updated upload {upload_id}");
+      }
+      scratch_io::SyncAction::UpToDate | scratch_io::SyncAction::AlreadyInstalled => {
+        println!("Game {game_id}: up to date");
+      }
+      scratch_io::SyncAction::Pruned(upload_id) => {
+        println!("Game {game_id}: removed upload {upload_id}");
+      }
+      scratch_io::SyncAction::Failed(e) => eprintln!("Game {game_id}: failed!\n{e}"),
+    },
+  )
+  .unwrap_or_else(|e| eprintln_exit!("Couldn't sync the collection: {collection_id}\n{e}"));
+}
+
 // Download a game's cover image
 fn download_cover(
   client: &ItchClient,
@@ -312,18 +979,42 @@ fn download_cover(
   filename: Option<&str>,
   force_download: bool,
 ) {
-  let cover_path =
-    scratch_io::download_game_cover(client, game_id, folder, filename, force_download)
-      .unwrap_or_else(|e| eprintln_exit!("{e}"));
+  let cover = scratch_io::download_game_cover(client, game_id, folder, filename, force_download)
+    .unwrap_or_else(|e| eprintln_exit!("{e}"));
+
+  match cover {
+    scratch_io::CoverDownload::NoCover => {
+      eprintln_exit!("The provided game with id: \"{game_id}\" doesn't have a cover image!")
+    }
+    scratch_io::CoverDownload::Downloaded(p) => {
+      println!("Game cover image downloaded to: \"{}\"", p.display())
+    }
+    scratch_io::CoverDownload::NotModified(p) => {
+      println!(
+        "Game cover image is already up to date: \"{}\"",
+        p.display()
+      )
+    }
+  }
+}
 
-  match cover_path {
-    None => eprintln_exit!("The provided game with id: \"{game_id}\" doesn't have a cover image!"),
-    Some(p) => println!("Game cover image downloaded to: \"{}\"", p.display()),
+// Exit with an error if the upload doesn't belong to the provided game
+fn exit_if_upload_not_in_game(client: &ItchClient, upload_id: UploadID, game_id: Option<GameID>) {
+  if let Some(game_id) = game_id {
+    scratch_io::assert_upload_in_game(client, upload_id, game_id)
+      .unwrap_or_else(|e| eprintln_exit!("{e}"));
   }
 }
 
 // Remove partially downloaded game files
-fn remove_partial_download(client: &ItchClient, upload_id: UploadID, game_folder: &Path) {
+fn remove_partial_download(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  game_id: Option<GameID>,
+) {
+  exit_if_upload_not_in_game(client, upload_id, game_id);
+
   let was_something_deleted = scratch_io::remove_partial_download(client, upload_id, game_folder)
     .unwrap_or_else(|e| eprintln_exit!("Couldn't remove partial download: {e}"));
 
@@ -335,21 +1026,102 @@ fn remove_partial_download(client: &ItchClient, upload_id: UploadID, game_folder
 }
 
 // Print a list of the currently installed games
-fn print_installed_games(installed_uploads: &mut HashMap<UploadID, InstalledUpload>) {
+fn print_installed_games(
+  format: OutputFormat,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  match format {
+    OutputFormat::Human => {
+      for iu in installed_uploads.values() {
+        println!("{iu:#?}");
+      }
+    }
+    OutputFormat::Json => print_output(format, &installed_uploads.values().collect::<Vec<_>>()),
+  }
+}
+
+// Refresh the games info of the installed uploads and print what has changed
+fn refresh_installed_games(
+  client: &ItchClient,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
   for iu in installed_uploads.values_mut() {
-    println!("{iu:#?}");
+    let changes = scratch_io::refresh_game_info(client, iu).unwrap_or_else(|e| {
+      eprintln_exit!(
+        "Couldn't refresh the game info of the upload: {}\n{e}",
+        iu.upload_id
+      )
+    });
+
+    for change in changes {
+      match change {
+        scratch_io::StatusChange::BecamePaid { min_price } => println!(
+          "Warning! \"{}\" is not free anymore (minimum price: {min_price}). Downloading it again may require owning it",
+          iu.game_title
+        ),
+        scratch_io::StatusChange::BecameFree => println!("\"{}\" is now free", iu.game_title),
+        scratch_io::StatusChange::TitleChanged {
+          old_title,
+          new_title,
+        } => println!("\"{old_title}\" has been renamed to \"{new_title}\""),
+        scratch_io::StatusChange::Delisted => println!(
+          "Warning! \"{}\" has been delisted or isn't accessible anymore",
+          iu.game_title
+        ),
+      }
+    }
   }
 }
 
 // Print the installed info of an upload
 fn print_installed_upload(
+  client: Option<&ItchClient>,
+  format: OutputFormat,
   upload_id: UploadID,
   installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
 ) {
   let iu = get_installed_upload_info_mut(upload_id, installed_uploads);
 
+  if let OutputFormat::Json = format {
+    let manifest = scratch_io::get_upload_manifest(upload_id, &iu.game_folder)
+      .unwrap_or_else(|e| eprintln_exit!("Couldn't get the itch manifest of the upload!: {e}"));
+    let disk_usage = scratch_io::get_upload_disk_usage(upload_id, &iu.game_folder).ok();
+
+    print_output(
+      format,
+      &serde_json::json!({
+        "installed_upload": iu,
+        "disk_usage": disk_usage,
+        "manifest": manifest,
+      }),
+    );
+    return;
+  }
+
   println!("{iu:#?}");
 
+  match scratch_io::get_upload_disk_usage(upload_id, &iu.game_folder) {
+    Ok(bytes) => println!("Disk usage: {:.2} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    Err(e) => eprintln!("Couldn't get the disk usage of the upload: {e}"),
+  }
+
+  // If there is an API key, check if a newer build is available
+  if let Some(client) = client {
+    match scratch_io::get_newer_build(client, iu) {
+      Ok(Some(build)) => println!(
+        "A newer build is available: {} (version {})",
+        build.build_info.id,
+        build
+          .build_info
+          .user_version
+          .as_deref()
+          .unwrap_or("unknown")
+      ),
+      Ok(None) => (),
+      Err(e) => eprintln!("Couldn't check if a newer build is available: {e}"),
+    }
+  }
+
   let manifest = scratch_io::get_upload_manifest(upload_id, &iu.game_folder)
     .unwrap_or_else(|e| eprintln_exit!("Couldn't get the itch manifest of the upload!: {e}"));
 
@@ -358,20 +1130,75 @@ fn print_installed_upload(
   }
 }
 
-// Import an already installed upload from a folder
-fn import(
-  client: &ItchClient,
+// Open the folder of an installed upload
+fn open_install_folder(
   upload_id: UploadID,
-  game_folder: &Path,
-  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
 ) {
-  exit_if_already_installed(upload_id, installed_uploads);
+  let iu = get_installed_upload_info_ref(upload_id, installed_uploads);
 
-  let iu = scratch_io::import(client, upload_id, game_folder)
-    .inspect(|ui| {
-      println!(
-        "Game imported from: \"{}\"",
-        ui.game_folder.join(ui.upload_id.to_string()).display()
+  scratch_io::open_install_folder(iu)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't open the upload folder: {upload_id}\n{e}"));
+}
+
+// Export the installed uploads to a portable file
+fn export_registry(config: &Config, file: &Path, library_root: &Path) {
+  let (text, skipped) = config
+    .export_registry(library_root)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't export the registry!\n{e}"));
+
+  std::fs::write(file, text).unwrap_or_else(|e| {
+    eprintln_exit!(
+      "Couldn't write the exported registry: \"{}\"\n{e}",
+      file.display()
+    )
+  });
+
+  for upload_id in skipped {
+    eprintln!("Skipped upload {upload_id}: it isn't inside the library root");
+  }
+}
+
+// Import the installed uploads from an exported file
+fn import_registry(config: &mut Config, file: &Path, library_root: &Path) {
+  let text = std::fs::read_to_string(file).unwrap_or_else(|e| {
+    eprintln_exit!(
+      "Couldn't read the exported registry: \"{}\"\n{e}",
+      file.display()
+    )
+  });
+
+  let result = config
+    .import_registry(&text, library_root)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't import the registry!\n{e}"));
+
+  for upload_id in result.imported {
+    println!("Imported upload {upload_id}");
+  }
+  for upload_id in result.already_installed {
+    println!("Skipped upload {upload_id}: it is already installed");
+  }
+  for upload_id in result.missing {
+    eprintln!("Skipped upload {upload_id}: its folder doesn't exist in the library root");
+  }
+}
+
+// Import an already installed upload from a folder
+fn import(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  game_id: Option<GameID>,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  exit_if_already_installed(upload_id, installed_uploads);
+  exit_if_upload_not_in_game(client, upload_id, game_id);
+
+  let iu = scratch_io::import(client, upload_id, game_folder)
+    .inspect(|ui| {
+      println!(
+        "Game imported from: \"{}\"",
+        ui.game_folder.join(ui.upload_id.to_string()).display()
       )
     })
     .unwrap_or_else(|e| eprintln_exit!("Error while importing game!\n{}", e));
@@ -379,6 +1206,302 @@ fn import(
   installed_uploads.insert(upload_id, iu);
 }
 
+// Import all the untracked upload folders found inside a folder
+fn scan_and_import(
+  client: &ItchClient,
+  base_folder: &Path,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let result = scratch_io::scan_and_import(client, installed_uploads.values(), base_folder)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't scan the folder!\n{e}"));
+
+  for (folder, reason) in result.unmatched {
+    eprintln!("Skipped \"{}\": {reason}", folder.display());
+  }
+
+  if result.imported.is_empty() {
+    println!("No upload folders were imported.");
+  }
+  for iu in result.imported {
+    println!(
+      "Imported upload {} ({}) from: \"{}\"",
+      iu.upload_id,
+      iu.game_title,
+      iu.upload_folder().display()
+    );
+    installed_uploads.insert(iu.upload_id, iu);
+  }
+}
+
+// Print everything that can be launched from an installed upload
+fn print_launch_targets(
+  client: &ItchClient,
+  upload_id: UploadID,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let upload_info = get_installed_upload_info_ref(upload_id, installed_uploads);
+
+  let targets = scratch_io::get_launch_targets(client, upload_id, &upload_info.game_folder)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't get the launch targets!\n{e}"));
+
+  if targets.is_empty() {
+    println!("No launch targets were found");
+  }
+
+  for target in targets {
+    let platform: String = target
+      .platform
+      .map_or_else(|| "-".to_string(), |p| format!("{p:?}"));
+    println!(
+      "{:?}\t{platform}\t{}\t{}",
+      target.source,
+      target.name,
+      target.path.display()
+    );
+  }
+}
+
+// Verify an installed upload against the hash provided by itch.io
+fn verify_installed(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: Option<&Path>,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let game_folder: &Path = game_folder
+    .unwrap_or_else(|| &get_installed_upload_info_ref(upload_id, installed_uploads).game_folder);
+
+  let valid = scratch_io::verify_installed(client, upload_id, game_folder, |status| {
+    if let DownloadStatus::Warning(w) = status {
+      println!("{w}");
+    }
+  })
+  .unwrap_or_else(|e| eprintln_exit!("Error while verifying the upload!\n{}", e));
+
+  if valid {
+    println!("Verification passed: the upload files match the itch.io hash");
+  } else {
+    eprintln_exit!(
+      "Verification failed: the upload files couldn't be verified against the itch.io hash"
+    );
+  }
+}
+
+// Print the files that change between two builds
+fn print_build_diff(client: &ItchClient, current: BuildID, target: BuildID) {
+  let folder: PathBuf =
+    std::env::temp_dir().join(format!("scratch-io-build-diff-{current}-{target}"));
+
+  let diff = scratch_io::diff_builds(
+    client,
+    current,
+    target,
+    &folder,
+    &scratch_io::DownloadOptions::default(),
+  )
+  .unwrap_or_else(|e| eprintln_exit!("Couldn't compare the builds!\n{e}"));
+
+  // The folder only held the downloaded patches
+  let _ = std::fs::remove_dir(&folder);
+
+  let method = |m: scratch_io::ChangeMethod| match m {
+    scratch_io::ChangeMethod::Rsync => "rsync",
+    scratch_io::ChangeMethod::Bsdiff => "bsdiff",
+    scratch_io::ChangeMethod::Rewritten => "rewritten",
+  };
+
+  for f in &diff.added {
+    println!(
+      "+ {} ({}, {})",
+      f.path.display(),
+      indicatif::HumanBytes(f.size),
+      method(f.method)
+    );
+  }
+  for f in &diff.modified {
+    println!(
+      "~ {} ({} changed, {})",
+      f.path.display(),
+      indicatif::HumanBytes(f.changed_bytes),
+      method(f.method)
+    );
+  }
+  for path in &diff.removed {
+    println!("- {}", path.display());
+  }
+
+  println!(
+    "\n{} added, {} modified, {} removed. About {} change",
+    diff.added.len(),
+    diff.modified.len(),
+    diff.removed.len(),
+    indicatif::HumanBytes(diff.changed_bytes())
+  );
+}
+
+// Upgrade an installed upload to another build
+// On error, the installed upload still holds the patches applied so far and must be saved
+fn upgrade_upload(
+  client: &ItchClient,
+  upload_id: UploadID,
+  build_id: BuildID,
+  options: &scratch_io::DownloadOptions,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) -> Result<(), String> {
+  let upload_info = get_installed_upload_info_mut(upload_id, installed_uploads);
+
+  // Set up the progress bar of the patches
+  let progress_bar = indicatif::ProgressBar::hidden();
+  progress_bar.set_style(byte_progress_style());
+  progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+
+  let result =
+    scratch_io::upgrade_upload(
+      client,
+      upload_info,
+      build_id,
+      options,
+      |status| match status {
+        scratch_io::UpgradeStatus::FullReinstall => {
+          println!("The installed build is unknown, downloading the upload again...")
+        }
+        scratch_io::UpgradeStatus::ApplyingPatch {
+          build_id,
+          step,
+          total_steps,
+        } => {
+          progress_bar.reset();
+          progress_bar.println(format!(
+            "Applying the patch to build {build_id} ({step}/{total_steps})..."
+          ));
+        }
+        scratch_io::UpgradeStatus::PatchProgress(p) => {
+          progress_bar.set_length(p.total_bytes);
+          progress_bar.set_position(p.bytes_written);
+          progress_bar.set_message(format!("File {}/{}", p.file_index + 1, p.total_files));
+        }
+        scratch_io::UpgradeStatus::Download(DownloadStatus::Warning(w)) => println!("{w}"),
+        scratch_io::UpgradeStatus::Download(_) => (),
+      },
+    );
+
+  progress_bar.finish_and_clear();
+  result.map_err(|e| format!("Error while upgrading the upload!\n{e}"))?;
+
+  println!("Upload {upload_id} upgraded to build {build_id}");
+  Ok(())
+}
+
+// Verify an installed upload and fetch again its broken files
+fn heal_upload(
+  client: &ItchClient,
+  upload_id: UploadID,
+  options: &scratch_io::DownloadOptions,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let upload_info = get_installed_upload_info_mut(upload_id, installed_uploads);
+
+  let progress_bar = indicatif::ProgressBar::hidden();
+  progress_bar.set_style(byte_progress_style());
+  progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+
+  let result = scratch_io::heal_upload(client, upload_info, options, |status| match status {
+    scratch_io::HealStatus::Verify {
+      bytes_hashed,
+      total_bytes,
+    } => {
+      progress_bar.set_message("Verifying");
+      progress_bar.set_length(total_bytes);
+      progress_bar.set_position(bytes_hashed);
+    }
+    scratch_io::HealStatus::Healing {
+      broken_files,
+      bytes_to_fix,
+    } => {
+      progress_bar.reset();
+      progress_bar.println(format!("Fetching {broken_files} broken files..."));
+      progress_bar.set_message("Healing");
+      progress_bar.set_length(bytes_to_fix);
+    }
+    scratch_io::HealStatus::HealProgress { bytes_written } => {
+      progress_bar.set_position(bytes_written);
+    }
+    scratch_io::HealStatus::FullReinstall => progress_bar
+      .println("The broken files can't be fetched alone, downloading the upload again..."),
+    scratch_io::HealStatus::Download(DownloadStatus::Warning(w)) => progress_bar.println(w),
+    scratch_io::HealStatus::Download(_) => (),
+  })
+  .unwrap_or_else(|e| eprintln_exit!("Error while healing the upload!\n{}", e));
+
+  progress_bar.finish_and_clear();
+
+  match result {
+    scratch_io::HealResult::Intact => {
+      println!("All the files of the upload {upload_id} are intact.")
+    }
+    scratch_io::HealResult::Healed(report) => println!(
+      "Healed {} broken files of the upload {upload_id}.",
+      report.broken_file_count()
+    ),
+    scratch_io::HealResult::Reinstalled(report) => println!(
+      "{} files of the upload {upload_id} were broken, so it was downloaded again.",
+      report.broken_file_count()
+    ),
+  }
+}
+
+// Download an installed upload again into the same folder
+fn reinstall_upload(
+  client: &ItchClient,
+  upload_id: UploadID,
+  options: &scratch_io::DownloadOptions,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let upload_info = get_installed_upload_info_mut(upload_id, installed_uploads);
+
+  let progress_bar = indicatif::ProgressBar::hidden();
+  progress_bar.set_style(byte_progress_style());
+
+  let reinstalled = scratch_io::reinstall_upload(
+    client,
+    upload_info,
+    options,
+    |_, _| (),
+    |status| match status {
+      DownloadStatus::Warning(w) => progress_bar.println(w),
+      DownloadStatus::StartingDownload { bytes_to_download } => {
+        progress_bar.set_message("Downloading");
+        progress_bar.set_length(bytes_to_download);
+        progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+      }
+      DownloadStatus::DownloadProgress {
+        downloaded_bytes, ..
+      } => progress_bar.set_position(downloaded_bytes),
+      DownloadStatus::Extract => {
+        progress_bar.reset();
+        progress_bar.set_message("Extracting");
+      }
+      DownloadStatus::ExtractProgress {
+        extracted_bytes,
+        total_bytes,
+      } => {
+        progress_bar.set_length(total_bytes);
+        progress_bar.set_position(extracted_bytes);
+      }
+      _ => (),
+    },
+  )
+  .unwrap_or_else(|e| eprintln_exit!("Error while reinstalling the upload!\n{}", e));
+
+  progress_bar.finish_and_clear();
+
+  println!(
+    "Reinstalled upload {upload_id} in: \"{}\"",
+    reinstalled.upload_folder().display()
+  );
+  *upload_info = reinstalled;
+}
+
 // Remove an installed upload from the system
 fn remove_upload(upload_id: UploadID, installed_uploads: &mut HashMap<UploadID, InstalledUpload>) {
   let upload_info = get_installed_upload_info_ref(upload_id, installed_uploads);
@@ -416,47 +1539,238 @@ fn move_upload(
   );
 }
 
+// Print the best candidates to be the game executable of an installed upload
+fn print_executables(
+  upload_id: UploadID,
+  platform: GamePlatform,
+  prefer_executable: Vec<String>,
+  limit: usize,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let upload_info = get_installed_upload_info_ref(upload_id, installed_uploads);
+
+  let executables = scratch_io::rank_executables(
+    upload_id,
+    &upload_info.game_folder,
+    platform.into(),
+    upload_info.game_title.to_string(),
+    &scratch_io::HeuristicsConfig {
+      preferred_names: prefer_executable,
+      ..Default::default()
+    },
+  )
+  .unwrap_or_else(|e| eprintln_exit!("Couldn't rate the upload files!\n{e}"));
+
+  for executable in executables.into_iter().take(limit) {
+    let subsystem: String = executable
+      .subsystem
+      .map_or_else(|| "-".to_string(), |s| s.to_string());
+    println!(
+      "{}\t{subsystem}\t{}",
+      executable.rating,
+      executable.path.display()
+    );
+  }
+}
+
+// Split a string of arguments according to the parsing rules of UNIX shell
+fn split_arguments(arguments: &str, name: &str) -> Vec<String> {
+  shell_words::split(arguments)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't split the {name}: {arguments}\n{e}"))
+}
+
+// Split a string of environment variables into key-value pairs
+fn split_environment_variables(variables: &str) -> Vec<(String, String)> {
+  split_arguments(variables, "environment variables")
+    .into_iter()
+    .map(|variable| {
+      variable
+        .split_once("=")
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .unwrap_or_else(|| {
+          eprintln_exit!(
+            "Couldn't split the environment variable because it doesn't contain a \"=\": {variable}"
+          )
+        })
+    })
+    .collect()
+}
+
+// Set or clear the launch options saved for an installed upload
+fn set_launch_option(
+  upload_id: UploadID,
+  executable_path: Option<PathBuf>,
+  wrapper: Option<&str>,
+  game_arguments: Option<&str>,
+  environment_variables: Option<&str>,
+  clear: bool,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let iu = get_installed_upload_info_mut(upload_id, installed_uploads);
+
+  let launch_override = if clear {
+    None
+  } else {
+    // Only replace the options that have been provided
+    let current = iu.launch_override.clone().unwrap_or_default();
+    Some(scratch_io::LaunchOverride {
+      executable_path: executable_path.or(current.executable_path),
+      wrapper: wrapper.map_or(current.wrapper, |w| split_arguments(w, "wrapper arguments")),
+      game_arguments: game_arguments.map_or(current.game_arguments, |a| {
+        split_arguments(a, "game arguments")
+      }),
+      environment_variables: environment_variables
+        .map_or(current.environment_variables, split_environment_variables),
+    })
+  };
+
+  iu.set_launch_override(launch_override)
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't set the launch options!\n{e}"));
+
+  match &iu.launch_override {
+    Some(o) => println!("Saved the launch options of the upload {upload_id}:\n{o:#?}"),
+    None => println!("Cleared the launch options of the upload {upload_id}"),
+  }
+}
+
+// Remove the untracked upload folders inside a library folder
+fn prune_library(
+  library_root: &Path,
+  dry_run: bool,
+  installed_uploads: &HashMap<UploadID, InstalledUpload>,
+) {
+  let folders = if dry_run {
+    scratch_io::find_orphaned_uploads(installed_uploads.values(), library_root)
+  } else {
+    scratch_io::prune_orphaned_uploads(installed_uploads.values(), library_root)
+  }
+  .unwrap_or_else(|e| eprintln_exit!("Couldn't prune the orphaned upload folders!\n{e}"));
+
+  if folders.is_empty() {
+    println!("There aren't any orphaned upload folders");
+    return;
+  }
+
+  println!(
+    "{}",
+    if dry_run {
+      "These orphaned upload folders would be removed:"
+    } else {
+      "Removed these orphaned upload folders:"
+    }
+  );
+  for f in folders {
+    println!("  \"{}\"", f.display());
+  }
+}
+
+// Move every upload inside a library folder to another library folder
+fn move_library(
+  library_root: &Path,
+  new_library_root: &Path,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
+) {
+  let library_root = library_root.canonicalize().unwrap_or_else(|e| {
+    eprintln_exit!(
+      "Couldn't get the canonical form of the library root: \"{}\"\n{e}",
+      library_root.display()
+    )
+  });
+
+  let results = scratch_io::move_game_folder(
+    installed_uploads
+      .values_mut()
+      .filter(|iu| iu.game_folder.parent() == Some(library_root.as_path())),
+    new_library_root,
+  );
+
+  if results.is_empty() {
+    println!(
+      "There aren't any installed uploads in: \"{}\"",
+      library_root.display()
+    );
+  }
+
+  for (upload_id, result) in results {
+    match result {
+      Ok(()) => println!("Moved upload {upload_id}"),
+      Err(e) => eprintln!("Couldn't move upload {upload_id}!\n{e}"),
+    }
+  }
+}
+
 // Launch an installed upload
+//
+// If the upload is launched with an explicit executable, it is saved as its launch override,
+// and when no launch method is provided, the saved override is used
 #[expect(clippy::too_many_arguments)]
 fn launch_upload(
   upload_id: UploadID,
   upload_executable_path: Option<PathBuf>,
   launch_action: Option<String>,
   platform: Option<GamePlatform>,
+  prefer_executable: Vec<String>,
   wrapper: Option<&str>,
   game_arguments: Option<&str>,
+  append_game_arguments: bool,
   environment_variables: Option<&str>,
-  installed_uploads: HashMap<UploadID, InstalledUpload>,
+  capture_output: bool,
+  verify: bool,
+  detach: bool,
+  wrapper_templates: &HashMap<scratch_io::GamePlatform, scratch_io::WrapperTemplate>,
+  installed_uploads: &mut HashMap<UploadID, InstalledUpload>,
 ) {
-  let upload_info = get_installed_upload_info(upload_id, installed_uploads);
+  let upload_info = get_installed_upload_info_mut(upload_id, installed_uploads);
   let game_folder = upload_info.game_folder.to_path_buf();
 
-  let wrapper: Vec<String> = wrapper.map_or(Vec::new(), |w| {
-    shell_words::split(w)
-      .unwrap_or_else(|e| eprintln_exit!("Couldn't split the wrapper arguments: {w}\n{e}"))
-  });
+  // The fingerprint is cloned, because the upload info is modified before launching
+  let verify_fingerprint: Option<scratch_io::Fingerprint> = if !verify {
+    None
+  } else if let Some(fingerprint) = upload_info.fingerprint.clone() {
+    Some(fingerprint)
+  } else {
+    eprintln!(
+      "Warning! The upload doesn't have a stored fingerprint, so its files can't be verified"
+    );
+    None
+  };
 
-  let game_arguments: Vec<String> = game_arguments.map_or(Vec::new(), |a| {
-    shell_words::split(a)
-      .unwrap_or_else(|e| eprintln_exit!("Couldn't split the game arguments: {a}\n{e}"))
+  // The executable is relative to the current directory, so resolve it once for saving and launching
+  let upload_executable_path: Option<PathBuf> = upload_executable_path.map(|p| {
+    std::path::absolute(&p).unwrap_or_else(|e| {
+      eprintln_exit!(
+        "Couldn't resolve the executable path: \"{}\"\n{e}",
+        p.display()
+      )
+    })
   });
 
-  let environment_variables: Vec<(String, String)> =
-    environment_variables.map_or(Vec::new(), |v| {
-      shell_words::split(v)
-        .unwrap_or_else(|e| eprintln_exit!("Couldn't split the environment variables: {v}\n{e}"))
-        .into_iter()
-        .map(|variable| {
-          variable.split_once("=")
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .unwrap_or_else(|| {
-              eprintln_exit!(
-                "Couldn't split the environment variable because it doesn't contain a \"=\": {variable}"
-              )
-            })
-        })
-        .collect()
-    });
+  // Only replace the saved options that have been provided
+  let saved = upload_info.launch_override.clone().unwrap_or_default();
+  let launch_override = scratch_io::LaunchOverride {
+    executable_path: upload_executable_path.clone().or(saved.executable_path),
+    wrapper: wrapper.map_or(saved.wrapper, |w| split_arguments(w, "wrapper arguments")),
+    game_arguments: game_arguments.map_or(saved.game_arguments, |a| {
+      split_arguments(a, "game arguments")
+    }),
+    environment_variables: environment_variables
+      .map_or(saved.environment_variables, split_environment_variables),
+  };
+
+  // Remember the explicitly chosen executable, so it doesn't have to be provided again
+  // Executables outside the upload folder can still be launched, but they aren't saved
+  if upload_executable_path.is_some()
+    && let Err(e) = upload_info.set_launch_override(Some(launch_override.clone()))
+  {
+    eprintln!("Warning! The launch executable won't be saved\n{e}");
+  }
+
+  let scratch_io::LaunchOverride {
+    wrapper,
+    game_arguments,
+    environment_variables,
+    ..
+  } = launch_override;
 
   let launch_method = if let Some(p) = upload_executable_path {
     scratch_io::LaunchMethod::AlternativeExecutable { executable_path: p }
@@ -464,32 +1778,131 @@ fn launch_upload(
     scratch_io::LaunchMethod::ManifestAction {
       manifest_action_name: action,
     }
+  } else if let Some(method) = upload_info.launch_override_method() {
+    method
+  } else if let Some(method) = upload_info.launch_target_method() {
+    method
   } else if let Some(platform) = platform {
     scratch_io::LaunchMethod::Heuristics {
       game_platform: platform.into(),
       game_title: upload_info.game_title.to_string(),
+      config: scratch_io::HeuristicsConfig {
+        preferred_names: prefer_executable,
+        ..Default::default()
+      },
     }
   } else {
     eprintln_exit!(
-      r#"A launch method is required! One of: "launch_action", "platform" or "upload_executable_path" must exist!"#
+      r#"A launch method is required! One of: "launch_action", "platform" or "upload_executable_path" must exist, or a launch executable must be saved with set-launch-option!"#
     )
   };
 
-  scratch_io::launch(
+  let arguments_merge_strategy = if append_game_arguments {
+    scratch_io::ArgumentsMergeStrategy::Append
+  } else {
+    scratch_io::ArgumentsMergeStrategy::Replace
+  };
+  let missing_prereqs = |prereqs: &[scratch_io::itch_api::types::ManifestPrerequisiteName]| {
+    println!("The game declares these prerequisites, which may have to be installed first:");
+    for p in prereqs {
+      println!("  {p:?}");
+    }
+  };
+  let launch_start = |up: &Path, command: &std::process::Command| {
+    println!(
+      "Launching game:\n  Executable path: \"{}\"\n  {command:?}",
+      up.display()
+    )
+  };
+
+  if detach {
+    // The CLI exits right away, so the game is adopted by the init process, which waits for it
+    #[allow(clippy::zombie_processes)]
+    let child = scratch_io::launch_detached(
+      upload_id,
+      &game_folder,
+      launch_method,
+      &wrapper,
+      wrapper_templates,
+      &game_arguments,
+      arguments_merge_strategy,
+      &environment_variables,
+      verify_fingerprint.as_ref(),
+      missing_prereqs,
+      launch_start,
+    )
+    .unwrap_or_else(|e| eprintln_exit!("Couldn't launch: {upload_id}\n{e}"));
+
+    upload_info.record_detached_launch();
+
+    println!("The game was started with the process ID {}", child.id());
+    return;
+  }
+
+  let launch_result = scratch_io::launch(
     upload_id,
     &game_folder,
     launch_method,
     &wrapper,
+    wrapper_templates,
     &game_arguments,
+    arguments_merge_strategy,
     &environment_variables,
-    |up, command| {
-      println!(
-        "Launching game:\n  Executable path: \"{}\"\n  {command:?}",
-        up.display()
-      )
-    },
+    capture_output,
+    verify_fingerprint.as_ref(),
+    missing_prereqs,
+    launch_start,
   )
   .unwrap_or_else(|e| eprintln_exit!("Couldn't launch: {upload_id}\n{e}"));
+
+  upload_info.record_launch(&launch_result);
+
+  println!(
+    "The game exited with {} after {}",
+    launch_result.exit_status,
+    indicatif::HumanDuration(launch_result.played_for)
+  );
+
+  if !launch_result.exit_status.success()
+    && let Some(stderr) = launch_result.stderr.filter(|s| !s.trim().is_empty())
+  {
+    eprintln!(
+      "The game failed! Its error output was:\n{}",
+      stderr.trim_end()
+    );
+  }
+}
+
+// Get the games folder from the command line, the config, or the default one
+fn get_games_dir(cli_games_dir: Option<PathBuf>, config_games_dir: Option<PathBuf>) -> PathBuf {
+  cli_games_dir
+    .or(config_games_dir)
+    .or_else(scratch_io::default_games_dir)
+    .unwrap_or_else(|| {
+      eprintln_exit!(
+        "The home folder is unknown! Provide an install path, or set a games folder with `config set-games-dir`"
+      )
+    })
+}
+
+// Get the folder named like the game inside the games folder
+fn default_game_folder(client: &ItchClient, game_id: GameID, games_dir: &Path) -> PathBuf {
+  let game = scratch_io::itch_api::endpoints::get_game_info(client, game_id)
+    .unwrap_or_else(|e| eprintln_exit!("{e}"));
+
+  scratch_io::get_game_folder(games_dir, &game.game_info.title)
+}
+
+// Get the folder named like the game of the upload inside the games folder
+fn default_upload_game_folder(
+  client: &ItchClient,
+  upload_id: UploadID,
+  games_dir: &Path,
+) -> PathBuf {
+  let upload = scratch_io::itch_api::endpoints::get_upload_info(client, upload_id)
+    .unwrap_or_else(|e| eprintln_exit!("{e}"));
+
+  default_game_folder(client, upload.game_id, games_dir)
 }
 
 fn main() {
@@ -499,6 +1912,9 @@ fn main() {
   // Get the config from the file
   let custom_config_file = cli.config_file;
   let mut config: Config = Config::load_unwrap(custom_config_file.clone());
+  if config.use_credential_store(cli.credential_store) {
+    config.save_unwrap(custom_config_file.clone());
+  }
 
   // Create itch.io client
   let client = get_itch_client(
@@ -510,8 +1926,14 @@ fn main() {
       config.api_key.to_owned(),
       // 3. If there isn't a saved config, throw an error
     ],
+    cli.retries,
+    cli.max_requests_per_sec,
+    cli.proxy,
   );
 
+  // The folder where the game folders are placed by default, only read when no install path is provided
+  let games_dir = || get_games_dir(cli.games_dir.clone(), config.games_dir.clone());
+
   /**** COMMANDS ****/
 
   match cli.command {
@@ -520,9 +1942,14 @@ fn main() {
       config.save_unwrap(custom_config_file);
     }
 
+    Commands::Config(command) => {
+      command.handle_command(&mut config);
+      config.save_unwrap(custom_config_file);
+    }
+
     Commands::Api(command) => {
       let client = client.unwrap_or_else(|e| eprintln_exit!("{e}"));
-      command.handle_command(&client);
+      command.handle_command(&client, cli.format);
     }
 
     Commands::Wharf(command) => {
@@ -534,15 +1961,95 @@ fn main() {
 
       match command {
         WithApiCommands::Download {
-          upload_id,
+          upload,
           install_path,
+          download_key_id,
+          download_key_uuid,
           skip_hash_verification,
+          lenient_extraction,
+          archive_password,
+          stream_extract,
+          network,
+          dry_run,
         } => {
-          download(
+          let upload_id = resolve_upload(&client, &upload);
+          let install_path: PathBuf = install_path
+            .unwrap_or_else(|| default_upload_game_folder(&client, upload_id, &games_dir()));
+          if dry_run {
+            print_download_plan(&client, upload_id, &install_path, download_key_id);
+          } else {
+            download(
+              &client,
+              upload_id,
+              &install_path,
+              &scratch_io::DownloadOptions {
+                download_key_id,
+                download_key_uuid,
+                skip_hash_verification,
+                lenient_extraction,
+                archive_password,
+                stream_extract,
+                ..network.download_options()
+              },
+              &mut config.installed_uploads,
+            );
+            config.save_unwrap(custom_config_file);
+          }
+        }
+        WithApiCommands::DownloadGame {
+          game,
+          install_path,
+          platform,
+          skip_hash_verification,
+          lenient_extraction,
+        } => {
+          let game_id = resolve_game(&client, &game);
+          let install_path: PathBuf =
+            install_path.unwrap_or_else(|| default_game_folder(&client, game_id, &games_dir()));
+          download_game(
             &client,
-            upload_id,
+            game_id,
+            &install_path,
+            platform,
+            &scratch_io::DownloadOptions {
+              skip_hash_verification,
+              lenient_extraction,
+              ..Default::default()
+            },
+            &mut config.installed_uploads,
+          );
+          config.save_unwrap(custom_config_file);
+        }
+        WithApiCommands::Search { query } => {
+          search_games(&client, &query);
+        }
+        WithApiCommands::DownloadUrl {
+          upload_id,
+          download_key_id,
+        } => {
+          print_download_url(&client, upload_id, download_key_id);
+        }
+        WithApiCommands::Updates => {
+          print_updates(&client, cli.format, &config.installed_uploads);
+        }
+        WithApiCommands::OwnedSummary => {
+          print_owned_summary(&client, &config.installed_uploads);
+        }
+        WithApiCommands::SyncCollection {
+          collection_id,
+          install_path,
+          platform,
+          prune,
+          network,
+        } => {
+          let install_path: PathBuf = install_path.unwrap_or_else(games_dir);
+          sync_collection(
+            &client,
+            collection_id,
             &install_path,
-            skip_hash_verification,
+            platform,
+            prune,
+            &network.download_options(),
             &mut config.installed_uploads,
           );
           config.save_unwrap(custom_config_file);
@@ -564,17 +2071,77 @@ fn main() {
         WithApiCommands::RemovePartialDownload {
           upload_id,
           install_path,
+          game_id,
         } => {
-          remove_partial_download(&client, upload_id, &install_path);
+          let install_path: PathBuf = install_path
+            .unwrap_or_else(|| default_upload_game_folder(&client, upload_id, &games_dir()));
+          remove_partial_download(&client, upload_id, &install_path, game_id);
         }
         WithApiCommands::Import {
           upload_id,
           install_path,
+          game_id,
         } => {
           import(
             &client,
             upload_id,
             &install_path,
+            game_id,
+            &mut config.installed_uploads,
+          );
+          config.save_unwrap(custom_config_file);
+        }
+        WithApiCommands::ScanImport { base_folder } => {
+          let base_folder: PathBuf = base_folder.unwrap_or_else(games_dir);
+          scan_and_import(&client, &base_folder, &mut config.installed_uploads);
+          config.save_unwrap(custom_config_file);
+        }
+        WithApiCommands::Verify {
+          upload_id,
+          install_path,
+        } => {
+          verify_installed(
+            &client,
+            upload_id,
+            install_path.as_deref(),
+            &config.installed_uploads,
+          );
+        }
+        WithApiCommands::LaunchTargets { upload_id } => {
+          print_launch_targets(&client, upload_id, &config.installed_uploads);
+        }
+        WithApiCommands::Upgrade {
+          upload_id,
+          build_id,
+          network,
+        } => {
+          let result = upgrade_upload(
+            &client,
+            upload_id,
+            build_id,
+            &network.download_options(),
+            &mut config.installed_uploads,
+          );
+          config.save_unwrap(custom_config_file);
+          result.unwrap_or_else(|e| eprintln_exit!("{e}"));
+        }
+        WithApiCommands::BuildDiff { current, target } => {
+          print_build_diff(&client, current, target);
+        }
+        WithApiCommands::Heal { upload_id, network } => {
+          heal_upload(
+            &client,
+            upload_id,
+            &network.download_options(),
+            &mut config.installed_uploads,
+          );
+          config.save_unwrap(custom_config_file);
+        }
+        WithApiCommands::Reinstall { upload_id, network } => {
+          reinstall_upload(
+            &client,
+            upload_id,
+            &network.download_options(),
             &mut config.installed_uploads,
           );
           config.save_unwrap(custom_config_file);
@@ -583,16 +2150,53 @@ fn main() {
     }
 
     Commands::WithoutApi(command) => match command {
-      WithoutApiCommands::Installed => {
-        print_installed_games(&mut config.installed_uploads);
+      WithoutApiCommands::Installed { refresh } => {
+        if refresh {
+          let client = client.unwrap_or_else(|e| eprintln_exit!("{e}"));
+          refresh_installed_games(&client, &mut config.installed_uploads);
+          config.save_unwrap(custom_config_file);
+        }
+        print_installed_games(cli.format, &config.installed_uploads);
       }
       WithoutApiCommands::InstalledUpload { upload_id } => {
-        print_installed_upload(upload_id, &mut config.installed_uploads);
+        print_installed_upload(
+          client.as_ref().ok(),
+          cli.format,
+          upload_id,
+          &mut config.installed_uploads,
+        );
       }
       WithoutApiCommands::Remove { upload_id } => {
         remove_upload(upload_id, &mut config.installed_uploads);
         config.save_unwrap(custom_config_file);
       }
+      WithoutApiCommands::Open { upload_id } => {
+        open_install_folder(upload_id, &config.installed_uploads);
+      }
+      WithoutApiCommands::ExportRegistry { file, library_root } => {
+        export_registry(&config, &file, &library_root);
+      }
+      WithoutApiCommands::ImportRegistry { file, library_root } => {
+        import_registry(&mut config, &file, &library_root);
+        config.save_unwrap(custom_config_file);
+      }
+      WithoutApiCommands::Prune {
+        library_root,
+        dry_run,
+      } => {
+        prune_library(&library_root, dry_run, &config.installed_uploads);
+      }
+      WithoutApiCommands::MoveLibrary {
+        library_root,
+        new_library_root,
+      } => {
+        move_library(
+          &library_root,
+          &new_library_root,
+          &mut config.installed_uploads,
+        );
+        config.save_unwrap(custom_config_file);
+      }
       WithoutApiCommands::Move {
         upload_id,
         game_path_dst,
@@ -600,25 +2204,70 @@ fn main() {
         move_upload(upload_id, &game_path_dst, &mut config.installed_uploads);
         config.save_unwrap(custom_config_file);
       }
+      WithoutApiCommands::SetLaunchOption {
+        upload_id,
+        executable_path,
+        wrapper,
+        game_arguments,
+        environment_variables,
+        clear,
+      } => {
+        set_launch_option(
+          upload_id,
+          executable_path,
+          wrapper.as_deref(),
+          game_arguments.as_deref(),
+          environment_variables.as_deref(),
+          clear,
+          &mut config.installed_uploads,
+        );
+        config.save_unwrap(custom_config_file);
+      }
+      WithoutApiCommands::Executables {
+        upload_id,
+        platform,
+        prefer_executable,
+        limit,
+      } => {
+        print_executables(
+          upload_id,
+          platform,
+          prefer_executable,
+          limit,
+          &config.installed_uploads,
+        );
+      }
       WithoutApiCommands::Launch {
         upload_id,
         launch_action,
         platform,
+        prefer_executable,
         upload_executable_path,
         wrapper,
         game_arguments,
+        append_game_arguments,
         environment_variables,
+        capture_output,
+        verify,
+        detach,
       } => {
         launch_upload(
           upload_id,
           upload_executable_path,
           launch_action,
           platform,
+          prefer_executable,
           wrapper.as_deref(),
           game_arguments.as_deref(),
+          append_game_arguments,
           environment_variables.as_deref(),
-          config.installed_uploads,
+          capture_output,
+          verify,
+          detach,
+          &config.wrapper_templates,
+          &mut config.installed_uploads,
         );
+        config.save_unwrap(custom_config_file);
       }
     },
   }
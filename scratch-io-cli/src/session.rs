@@ -10,7 +10,11 @@ pub enum SessionCommand {
   /// Print the currently saved API key
   PrintKey,
   /// Remove the saved API key
-  Logout,
+  Logout {
+    /// Also revoke the key on the itch.io servers, so it can't be used anymore
+    #[arg(long)]
+    revoke: bool,
+  },
   /// Log in with an API key
   Auth {
     /// The API key to save
@@ -50,12 +54,19 @@ fn print_key(config_api_key: &Option<String>) {
   println!("{key}");
 }
 
-// Remove the saved API key (if any)
-fn logout(config_api_key: &mut Option<String>) {
-  if config_api_key.is_none() {
+// Remove the saved API key (if any), and optionally revoke it
+fn logout(revoke: bool, config_api_key: &mut Option<String>) {
+  let Some(key) = config_api_key else {
     eprintln_exit!("There isn't any API key saved!");
   };
 
+  // Revoke the key before forgetting it, so it isn't lost if the revocation fails
+  if revoke {
+    endpoints::revoke_key(ItchClient::new(key.clone()))
+      .unwrap_or_else(|e| eprintln_exit!("Couldn't revoke the API key!\n{e}"));
+    println!("Revoked the API key.");
+  }
+
   *config_api_key = None;
   println!("Logged out.");
 }
@@ -121,7 +132,7 @@ impl SessionCommand {
   pub fn handle_command(self, config: &mut Config) {
     match self {
       Self::PrintKey => print_key(&config.api_key),
-      Self::Logout => logout(&mut config.api_key),
+      Self::Logout { revoke } => logout(revoke, &mut config.api_key),
       Self::Auth { api_key } => auth(api_key, &mut config.api_key),
       Self::Oauth(OauthCommand::Init) => oauth_init(),
       Self::Oauth(OauthCommand::Exchange {
@@ -0,0 +1,120 @@
+use crate::config::Config;
+use crate::eprintln_exit;
+
+use clap::Subcommand;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+  /// Print the folder where the game folders are placed by default
+  GamesDir,
+  /// Set the folder where the game folders are placed when no install path is provided
+  ///
+  /// The folder is created if it doesn't exist, and it must be writable
+  SetGamesDir {
+    /// The new games folder
+    path: PathBuf,
+  },
+  /// Go back to placing the game folders in the default folder, ~/Games
+  ResetGamesDir,
+  /// Print the wrappers used to launch the games of each platform
+  WrapperTemplates,
+  /// Set the wrapper used to launch the games of a platform when no wrapper is provided
+  ///
+  /// The platform is the one used by the heuristics, or the one detected from the executable,
+  /// like Windows for .exe files
+  SetWrapperTemplate {
+    /// The platform of the games launched with the wrapper
+    platform: crate::GamePlatform,
+    /// The wrapper and its options, like "wine" or "gamescope -- {executable}"
+    ///
+    /// The executable is added at the end, unless an argument contains "{executable}"
+    command: String,
+    /// A list of environment variables added to the games launched with the wrapper, like "WINEPREFIX=~/.wine-games"
+    #[arg(long)]
+    environment_variables: Option<String>,
+  },
+  /// Stop using a wrapper to launch the games of a platform
+  RemoveWrapperTemplate {
+    /// The platform of the wrapper to remove
+    platform: crate::GamePlatform,
+  },
+}
+
+// Print the configured games folder, or the default one
+fn print_games_dir(config_games_dir: &Option<PathBuf>) {
+  match config_games_dir {
+    Some(dir) => println!("{}", dir.display()),
+    None => match scratch_io::default_games_dir() {
+      Some(dir) => println!("{} (default)", dir.display()),
+      None => eprintln_exit!("There isn't any games folder set, and the home folder is unknown!"),
+    },
+  }
+}
+
+// Check that the folder is writable and save it
+fn set_games_dir(path: PathBuf, config_games_dir: &mut Option<PathBuf>) {
+  let dir = scratch_io::validate_games_dir(&path)
+    .unwrap_or_else(|e| eprintln_exit!("The folder can't be used to place the games!\n{e}"));
+
+  println!("The games will be placed in: \"{}\"", dir.display());
+  *config_games_dir = Some(dir);
+}
+
+// Print the wrapper template of each platform
+fn print_wrapper_templates(
+  wrapper_templates: &HashMap<scratch_io::GamePlatform, scratch_io::WrapperTemplate>,
+) {
+  if wrapper_templates.is_empty() {
+    println!("There aren't any wrapper templates.");
+  }
+
+  for (platform, template) in wrapper_templates {
+    println!("{platform:?}: {}", shell_words::join(&template.command));
+    for (k, v) in &template.environment_variables {
+      println!("  {k}={v}");
+    }
+  }
+}
+
+impl ConfigCommand {
+  pub fn handle_command(self, config: &mut Config) {
+    match self {
+      Self::GamesDir => print_games_dir(&config.games_dir),
+      Self::SetGamesDir { path } => set_games_dir(path, &mut config.games_dir),
+      Self::ResetGamesDir => {
+        config.games_dir = None;
+        println!("The games will be placed in the default folder.");
+      }
+      Self::WrapperTemplates => print_wrapper_templates(&config.wrapper_templates),
+      Self::SetWrapperTemplate {
+        platform,
+        command,
+        environment_variables,
+      } => {
+        let command: Vec<String> = crate::split_arguments(&command, "wrapper arguments");
+        if command.is_empty() {
+          eprintln_exit!("The wrapper command can't be empty!");
+        }
+
+        config.wrapper_templates.insert(
+          platform.into(),
+          scratch_io::WrapperTemplate {
+            command,
+            environment_variables: environment_variables
+              .as_deref()
+              .map_or(Vec::new(), crate::split_environment_variables),
+          },
+        );
+        println!("The wrapper template was saved.");
+      }
+      Self::RemoveWrapperTemplate { platform } => {
+        match config.wrapper_templates.remove(&platform.into()) {
+          Some(_) => println!("The wrapper template was removed."),
+          None => println!("There wasn't any wrapper template for that platform."),
+        }
+      }
+    }
+  }
+}
@@ -30,8 +30,7 @@ pub enum WharfCommand {
 
   /// Verify that the provided build folder is intact
   ///
-  /// Returns an struct containing the indexes in the signature
-  /// of the broken or missing files
+  /// Prints the paths of the corrupted, wrongly sized and missing files
   Verify {
     /// The path where the wharf signature file is placed
     signature_file: PathBuf,
@@ -122,20 +121,49 @@ fn verify(signature_file: &Path, build_folder: &Path) {
   let progress_bar = indicatif::ProgressBar::hidden();
   progress_bar.set_style(
           indicatif::ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) ({eta})").unwrap()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) ({eta}) {msg}").unwrap()
             .progress_chars("#>-")
         );
   progress_bar.set_length(signature.container_new.size as u64);
   progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
 
   // Do the files verification
-  let broken = signature
-    .verify_files(build_folder, |b| progress_bar.inc(b))
+  let report = signature
+    .verify_files_report(
+      build_folder,
+      |p| {
+        progress_bar.set_position(p.bytes_hashed);
+        progress_bar.set_message(format!("File {}/{}", p.file_index + 1, p.total_files));
+      },
+      std::time::Duration::from_millis(100),
+    )
     .unwrap_or_else(|e| eprintln_exit!("{e}"));
 
   progress_bar.finish();
 
-  println!("{broken:#?}");
+  if report.are_files_intact() {
+    println!("All the files are intact.");
+    return;
+  }
+
+  println!(
+    "{} of {} files are broken.",
+    report.broken_file_count(),
+    signature.container_new.files.len()
+  );
+  for (title, files) in [
+    ("Corrupted files", &report.mismatched_files),
+    ("Files with a wrong size", &report.size_mismatches),
+    ("Missing files", &report.missing_files),
+  ] {
+    if files.is_empty() {
+      continue;
+    }
+    println!("\n{title}:");
+    for file in files {
+      println!("  {}", file.display());
+    }
+  }
 }
 
 fn repair(signature_file: &Path, build_folder: &Path, zip_archive: &Path) {
@@ -227,7 +255,7 @@ fn patch(
   let progress_bar = indicatif::ProgressBar::hidden();
   progress_bar.set_style(
           indicatif::ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) ({eta})").unwrap()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) ({eta}) {msg}").unwrap()
             .progress_chars("#>-")
         );
   progress_bar.set_length(patch.container_new.size as u64);
@@ -235,12 +263,15 @@ fn patch(
 
   // Apply the patch
   patch
-    .apply(
+    .apply_with_progress(
       old_build_folder,
       staging_folder,
       new_build_folder,
       hash_iter.as_mut(),
-      |b| progress_bar.inc(b),
+      |p| {
+        progress_bar.set_position(p.bytes_written);
+        progress_bar.set_message(format!("File {}/{}", p.file_index + 1, p.total_files));
+      },
     )
     .unwrap_or_else(|e| eprintln_exit!("{e}"));
 
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A token used to cancel a long running operation from another thread
+///
+/// Cloning the token returns a handle to the same cancellation state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+  cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+  /// Create a new token which isn't cancelled
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Request the cancellation of the operations using this token
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  /// Check if the cancellation has been requested
+  #[must_use]
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+}
@@ -0,0 +1,118 @@
+use md5::Md5;
+use sha1::Sha1;
+use sha2::Sha256;
+use sha2::digest::Digest;
+
+/// A hash algorithm used to verify a downloaded file
+///
+/// The hosted uploads are always verified with MD5, but other files, like build files, can
+/// be provided with another algorithm
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+  #[default]
+  Md5,
+  Sha1,
+  Sha256,
+}
+
+/// A hash that a downloaded file must match
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Checksum<'a> {
+  /// The algorithm used to compute the hash
+  pub algorithm: ChecksumAlgorithm,
+  /// The expected hash, hex encoded
+  pub hex: &'a str,
+}
+
+impl<'a> Checksum<'a> {
+  /// A MD5 hash, like the one the itch.io API provides for the uploads
+  #[must_use]
+  pub const fn md5(hex: &'a str) -> Self {
+    Self {
+      algorithm: ChecksumAlgorithm::Md5,
+      hex,
+    }
+  }
+
+  /// Check if a computed hash matches this one
+  pub(crate) fn matches(&self, hex: &str) -> bool {
+    self.hex.eq_ignore_ascii_case(hex)
+  }
+}
+
+/// A running hasher of any [`ChecksumAlgorithm`]
+#[derive(Clone)]
+pub(crate) enum Hasher {
+  Md5(Md5),
+  Sha1(Sha1),
+  Sha256(Sha256),
+}
+
+impl Hasher {
+  /// Create an empty hasher for the algorithm
+  pub(crate) fn new(algorithm: ChecksumAlgorithm) -> Self {
+    match algorithm {
+      ChecksumAlgorithm::Md5 => Self::Md5(Md5::new()),
+      ChecksumAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+      ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+    }
+  }
+
+  /// Hash more data
+  pub(crate) fn update(&mut self, data: &[u8]) {
+    match self {
+      Self::Md5(h) => h.update(data),
+      Self::Sha1(h) => h.update(data),
+      Self::Sha256(h) => h.update(data),
+    }
+  }
+
+  /// Finish the hash
+  ///
+  /// # Returns
+  ///
+  /// The hash, hex encoded
+  pub(crate) fn finalize_hex(self) -> String {
+    match self {
+      Self::Md5(h) => hex::encode(h.finalize()),
+      Self::Sha1(h) => hex::encode(h.finalize()),
+      Self::Sha256(h) => hex::encode(h.finalize()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Checksum, ChecksumAlgorithm, Hasher};
+
+  fn hash(algorithm: ChecksumAlgorithm, data: &[u8]) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finalize_hex()
+  }
+
+  #[test]
+  fn test_known_hashes() {
+    let data = b"The quick brown fox jumps over the lazy dog";
+
+    assert_eq!(
+      hash(ChecksumAlgorithm::Md5, data),
+      "9e107d9d372bb6826bd81d3542a419d6"
+    );
+    assert_eq!(
+      hash(ChecksumAlgorithm::Sha1, data),
+      "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12"
+    );
+    assert_eq!(
+      hash(ChecksumAlgorithm::Sha256, data),
+      "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592"
+    );
+  }
+
+  #[test]
+  fn test_matches_ignores_case() {
+    assert!(
+      Checksum::md5("9E107D9D372BB6826BD81D3542A419D6").matches("9e107d9d372bb6826bd81d3542a419d6")
+    );
+  }
+}
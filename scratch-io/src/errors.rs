@@ -30,6 +30,167 @@ impl From<FilesystemError> for String {
   }
 }
 
+/// An error while extracting an archive
+#[derive(Error, Debug)]
+pub enum ExtractError {
+  #[error(transparent)]
+  Filesystem(#[from] FilesystemError),
+
+  #[error(
+    "The archive is encrypted, but no password was provided!
+  Archive: \"{0}\""
+  )]
+  PasswordRequired(PathBuf),
+
+  #[error(
+    "The provided password can't decrypt the archive!
+  Archive: \"{0}\""
+  )]
+  WrongPassword(PathBuf),
+
+  #[error(
+    "The archive entry would be written outside of the extraction folder!
+  Entry: \"{0}\""
+  )]
+  PathTraversal(String),
+
+  #[error("{0}")]
+  Other(String),
+}
+
+impl From<String> for ExtractError {
+  fn from(value: String) -> Self {
+    Self::Other(value)
+  }
+}
+
+/// The error returned by the public functions of the crate
+///
+/// The variants group the failures by their category, so they can be handled programmatically
+#[derive(Error, Debug)]
+pub enum ScratchError {
+  /// A filesystem operation failed
+  #[error(transparent)]
+  Filesystem(#[from] FilesystemError),
+
+  /// A request to the itch.io API failed, or the server replied with an error
+  #[error(transparent)]
+  Api(Box<dyn std::error::Error + Send + Sync>),
+
+  /// A file couldn't be downloaded
+  #[error("{0}")]
+  Download(String),
+
+  /// The downloaded or installed files don't match the expected ones
+  #[error("{0}")]
+  Verification(String),
+
+  /// The download speed stayed below [`crate::DownloadOptions::min_speed`], so it was stopped
+  ///
+  /// The partial download is kept, so it can be resumed later
+  #[error(
+    "The download stalled! Its speed stayed below {min_bytes_per_sec} bytes per second for {window:?}"
+  )]
+  StalledDownload {
+    /// The minimum download speed, in bytes per second
+    min_bytes_per_sec: u64,
+    /// The time span the speed was measured over
+    window: std::time::Duration,
+  },
+
+  /// The hash of a downloaded file is different from the hash provided by the server
+  ///
+  /// The file is corrupted, so it has to be removed and downloaded again
+  #[error(
+    "File verification failed! The file hash and the hash provided by the server are different.\n
+  File hash:   {actual}
+  Server hash: {expected}"
+  )]
+  HashMismatch {
+    /// The hash provided by the server
+    expected: String,
+    /// The hash of the downloaded file
+    actual: String,
+    /// The path of the downloaded file
+    file: PathBuf,
+  },
+
+  /// An archive couldn't be extracted
+  #[error(transparent)]
+  Extraction(#[from] ExtractError),
+
+  /// A wharf patch or signature couldn't be read or applied
+  #[error("{0}")]
+  Wharf(String),
+
+  /// The itch.io manifest of an upload couldn't be read
+  #[error("{0}")]
+  Manifest(String),
+
+  /// The game couldn't be launched
+  #[error("{0}")]
+  Launch(String),
+
+  /// The provided arguments don't match, like an upload which doesn't belong to the provided game,
+  /// or a URL which isn't an itch.io game page
+  #[error("{0}")]
+  InvalidInput(String),
+
+  /// The operation was stopped with a [`crate::CancellationToken`]
+  #[error("{}", crate::DOWNLOAD_CANCELLED_ERROR)]
+  Cancelled,
+
+  /// Any other error
+  #[error("{0}")]
+  Other(String),
+}
+
+impl<T> From<ItchRequestJSONError<T>> for ScratchError
+where
+  T: std::error::Error + std::fmt::Debug + Send + Sync + 'static,
+{
+  fn from(value: ItchRequestJSONError<T>) -> Self {
+    Self::Api(Box::new(value))
+  }
+}
+
+impl From<ScratchError> for String {
+  fn from(value: ScratchError) -> Self {
+    value.to_string()
+  }
+}
+
+#[cfg(feature = "open")]
+#[derive(Error, Debug)]
+pub enum OpenError {
+  #[error(
+    "Couldn't find a program to open: \"{target}\"
+{error}"
+  )]
+  NoOpenerAvailable {
+    target: String,
+    #[source]
+    error: std::io::Error,
+  },
+
+  #[error(
+    "Couldn't open: \"{target}\"
+{error}"
+  )]
+  CouldntOpen {
+    target: String,
+    #[source]
+    error: std::io::Error,
+  },
+}
+
+#[cfg(feature = "open")]
+impl From<OpenError> for String {
+  fn from(value: OpenError) -> Self {
+    value.to_string()
+  }
+}
+
 #[derive(Error, Debug)]
 pub enum FilesystemIOErrorKind {
   #[error("Couldn't check if the path exists: \"{0}\"")]
@@ -81,6 +242,12 @@ pub enum FilesystemIOErrorKind {
   #[error("Couldn't read the metadata of an open file!")]
   CouldntReadFileMetadata,
 
+  #[error("Couldn't read the last modification time of: \"{0}\"")]
+  CouldntReadModifiedTime(PathBuf),
+
+  #[error("Couldn't read the target of the symbolic link: \"{0}\"")]
+  CouldntReadLink(PathBuf),
+
   #[error("Couldn't set the permissions of: \"{0}\"")]
   CouldntSetPermissions(PathBuf),
 
@@ -90,6 +257,9 @@ pub enum FilesystemIOErrorKind {
   #[error("Couldn't set file length to: {0}")]
   SetFileLength(u64),
 
+  #[error("Couldn't seek the file to the position: {0}")]
+  CouldntSeek(u64),
+
   #[error("Couldn't sync file data to disk!")]
   SyncFile,
 
@@ -104,6 +274,9 @@ pub enum FilesystemIOErrorKind {
 
   #[error("Error while awaiting for child exit!")]
   CouldntWaitForChild,
+
+  #[error("Couldn't read the output of the child process!")]
+  CouldntReadChildOutput,
 }
 
 impl FilesystemIOErrorKind {
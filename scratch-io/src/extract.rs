@@ -1,8 +1,81 @@
-use crate::errors::FilesystemError;
+use crate::errors::{ExtractError, FilesystemError};
+use crate::storage::LocalStorage;
 use crate::{filesystem, game_files};
-use std::fs::File;
-use std::path::Path;
+use std::cell::Cell;
+use std::io::{BufRead, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
 
+/// The options of [`extract`]
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+  /// Skip the entries that fail to extract instead of aborting the whole extraction,
+  /// and return them at the end
+  pub lenient: bool,
+  /// The password of encrypted archives
+  pub password: Option<String>,
+}
+
+/// An archive entry that couldn't be extracted in lenient mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedEntry {
+  /// The path of the entry inside the archive
+  pub entry: String,
+  /// The reason why the entry couldn't be extracted
+  pub error: String,
+}
+
+/// What [`extract`] did with the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extracted {
+  /// The file was an archive, and it was extracted
+  ///
+  /// Contains the entries that couldn't be extracted in lenient mode
+  Archive(Vec<FailedEntry>),
+  /// The file wasn't a recognized archive, so it was moved into the folder unchanged
+  ///
+  /// Contains its path, relative to the extract folder
+  SingleFile(PathBuf),
+}
+
+/// A reader which reports how many bytes of the archive have been read
+///
+/// The read bytes are stored in a shared counter, so they can be reset after
+/// reading the archive metadata
+struct ProgressReader<'a, R> {
+  inner: R,
+  read_bytes: &'a Cell<u64>,
+  total_bytes: u64,
+  progress_callback: &'a dyn Fn(u64, u64),
+  callback_interval: Duration,
+  last_callback: Instant,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let len = self.inner.read(buf)?;
+    self.read_bytes.set(self.read_bytes.get() + len as u64);
+
+    if self.last_callback.elapsed() > self.callback_interval {
+      self.last_callback = Instant::now();
+      (self.progress_callback)(
+        self.read_bytes.get().min(self.total_bytes),
+        self.total_bytes,
+      );
+    }
+
+    Ok(len)
+  }
+}
+
+// Seeking doesn't count as reading, only the bytes actually read are reported
+impl<R: Seek> Seek for ProgressReader<'_, R> {
+  fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+    self.inner.seek(pos)
+  }
+}
+
+#[derive(Debug)]
 enum ArchiveFormat {
   Zip,
   Tar,
@@ -10,9 +83,110 @@ enum ArchiveFormat {
   TarBz2,
   TarXz,
   TarZst,
+  /// A macOS disk image, which is mounted to copy its contents
+  Dmg,
+  /// An archive which can't be extracted, with the name of its format
+  Unsupported(String),
   Other,
 }
 
+/// Get the volumes of a split archive, if the file is its first volume
+///
+/// Split archives are named like `archive.zip.001`, `archive.zip.002`, ...
+///
+/// # Returns
+///
+/// The path of the joined archive and the paths of all the existing volumes,
+/// or None if the file isn't the first volume of a split archive
+pub(crate) fn get_split_volumes(
+  first_volume: &Path,
+) -> Result<Option<(PathBuf, Vec<PathBuf>)>, FilesystemError> {
+  if filesystem::get_file_extension(first_volume).ok() != Some("001") {
+    return Ok(None);
+  }
+
+  let mut volumes: Vec<PathBuf> = Vec::new();
+  for number in 1.. {
+    let volume: PathBuf = first_volume.with_extension(format!("{number:03}"));
+    if !filesystem::exists(&volume)? {
+      break;
+    }
+    volumes.push(volume);
+  }
+
+  // The joined archive is the first volume without the volume number
+  Ok(Some((first_volume.with_extension(""), volumes)))
+}
+
+/// Join the volumes of a split archive into a single archive, removing the volumes
+///
+/// # Returns
+///
+/// The path of the joined archive, or None if the file isn't the first volume of a split archive
+fn join_split_volumes(first_volume: &Path) -> Result<Option<PathBuf>, FilesystemError> {
+  let Some((joined_archive, volumes)) = get_split_volumes(first_volume)? else {
+    return Ok(None);
+  };
+
+  debug!(
+    "Joining {} volumes into \"{}\"",
+    volumes.len(),
+    joined_archive.display()
+  );
+
+  let mut joined_file = filesystem::open_file(
+    &joined_archive,
+    std::fs::OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true),
+  )?;
+
+  for volume in &volumes {
+    let volume_file = filesystem::open_file(volume, std::fs::OpenOptions::new().read(true))?;
+    let mut reader = std::io::BufReader::new(volume_file);
+
+    loop {
+      let chunk = filesystem::fill_buffer(&mut reader)?;
+
+      // If chunk is empty then the reader has reached the EOF
+      if chunk.is_empty() {
+        break;
+      }
+
+      filesystem::write_all(&mut joined_file, chunk)?;
+
+      let len = chunk.len();
+      reader.consume(len);
+    }
+  }
+
+  filesystem::file_sync_all(&joined_file)?;
+
+  // The volumes are only removed once the joined archive is complete
+  for volume in &volumes {
+    filesystem::remove_file(volume)?;
+  }
+
+  Ok(Some(joined_archive))
+}
+
+/// Check if the file is a volume of a multi-part RAR archive, like `archive.part1.rar`
+fn is_multipart_rar(file: &Path) -> bool {
+  let (Ok(extension), Ok(stem)) = (
+    filesystem::get_file_extension(file),
+    filesystem::get_file_stem(file),
+  ) else {
+    return false;
+  };
+
+  extension.eq_ignore_ascii_case("rar")
+    && stem
+      .to_lowercase()
+      .rsplit_once(".part")
+      .is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
 /// Gets the archive format of the file
 ///
 /// If the file is not an archive, then the format is `ArchiveFormat::Other`
@@ -43,178 +217,809 @@ fn get_archive_format(file: &Path) -> Result<ArchiveFormat, FilesystemError> {
     "zst" if is_tar_compressed => ArchiveFormat::TarZst,
     "tzst" => ArchiveFormat::TarZst,
 
+    // Disk images can only be mounted on macOS, so elsewhere they are kept as they are
+    "dmg" if cfg!(target_os = "macos") => ArchiveFormat::Dmg,
+
+    // Installing these archives as they are would leave a game that can't be launched
+    "7z" | "rar" => ArchiveFormat::Unsupported(extension.clone()),
+    _ if is_tar_compressed => ArchiveFormat::Unsupported(format!("tar.{extension}")),
+
     _ => ArchiveFormat::Other,
   })
 }
 
 /// Extracts the archive into the given folder
 ///
-/// If the file isn't an archive it will be moved to the folder
-pub fn extract(file_path: &Path, extract_folder: &Path) -> Result<(), String> {
+/// If the file isn't a recognized archive it will be moved to the folder unchanged,
+/// and [`Extracted::SingleFile`] is returned so it can be used as the launch target
+///
+/// The files are placed in a `.part` folder next to `extract_folder` first, which is only
+/// renamed to `extract_folder` once everything has been extracted. If the extraction is
+/// interrupted, `extract_folder` isn't created, and the `.part` folder can be safely removed
+///
+/// Split archives (`archive.zip.001`, `archive.zip.002`, ...) are joined before being extracted.
+/// See [`ExtractOptions`] for the lenient mode and the password of encrypted archives
+///
+/// `progress_callback` is called with the number of bytes of the archive that have been
+/// processed and its total size, at most once every `callback_interval`. It isn't called
+/// if the file isn't an archive
+pub fn extract(
+  file_path: &Path,
+  extract_folder: &Path,
+  options: &ExtractOptions,
+  progress_callback: impl Fn(u64, u64),
+  callback_interval: Duration,
+) -> Result<Extracted, ExtractError> {
+  enter_span!("extract", archive = file_path.display());
+
   // If the extract folder isn't empty, return an error
   filesystem::ensure_is_empty(extract_folder)?;
 
+  // Multi-part RAR archives would be moved as a single file, which isn't useful
+  if is_multipart_rar(file_path) {
+    return Err(ExtractError::Other(format!(
+      "Multi-part RAR archives aren't supported! Extract it manually: \"{}\"",
+      file_path.display()
+    )));
+  }
+
+  // Split archives have to be joined before extracting them
+  let joined_archive: Option<PathBuf> = join_split_volumes(file_path)?;
+  let file_path: &Path = joined_archive.as_deref().unwrap_or(file_path);
+
   let format: ArchiveFormat = get_archive_format(file_path)?;
 
-  // If the file isn't an archive, return now
+  // The archive is kept, so it can be extracted manually
+  if let ArchiveFormat::Unsupported(format_name) = &format {
+    return Err(ExtractError::Other(format!(
+      "The {format_name} archive format isn't supported! Extract it manually: \"{}\"",
+      file_path.display()
+    )));
+  }
+
+  // The files will be placed in the extract_folder_temp, and then moved to its final destination once the extraction is completed
+  // This way, extract_folder never contains an incomplete extraction
+  let extract_folder_temp = game_files::add_part_extension(extract_folder)?;
+
+  // The extraction temporal folder could have contents if a previous extraction was cancelled
+  // For that reason, don't check if the folder is empty; but create it if it doesn't exist
+  filesystem::create_dir(&extract_folder_temp)?;
+
+  // If the file isn't an archive, move it and return now
   if let ArchiveFormat::Other = format {
-    // Create the destination folder
-    filesystem::create_dir(extract_folder)?;
+    debug!("The file isn't an archive, moving it unchanged");
 
     // Get the file destination
-    let destination = extract_folder.join(filesystem::get_file_name(file_path)?);
+    let file_name = PathBuf::from(filesystem::get_file_name(file_path)?);
+    let destination = extract_folder_temp.join(&file_name);
 
     // Move the file
     filesystem::rename(file_path, &destination)?;
 
-    // Make it executable
-    filesystem::make_executable(&destination)?;
+    // Make it executable, unless it is an Android package, which can't be run here anyway
+    if !game_files::is_android_package(&destination) {
+      filesystem::make_executable(&destination)?;
+    }
 
-    return Ok(());
-  }
-
-  // The archive will be extracted to the extract_folder_temp, and then moved to its final destination once the extraction is completed
-  let extract_folder_temp = game_files::add_part_extension(extract_folder)?;
+    // Move the temporal folder to its destination
+    game_files::move_folder(&LocalStorage, &extract_folder_temp, extract_folder)?;
 
-  // The extraction temporal folder could have contents if a previous extraction was cancelled
-  // For that reason, don't check if the folder is empty; but create it if it doesn't exist
-  filesystem::create_dir(&extract_folder_temp)?;
+    return Ok(Extracted::SingleFile(file_name));
+  }
 
   // Open the file in read-only mode
   let file = filesystem::open_file(file_path, std::fs::OpenOptions::new().read(true))?;
+  let total_bytes: u64 = filesystem::read_file_metadata(&file)?.len();
+
+  // Report the progress based on how much of the archive has been read
+  let read_bytes: Cell<u64> = Cell::new(0);
+  let reader = ProgressReader {
+    inner: &file,
+    read_bytes: &read_bytes,
+    total_bytes,
+    progress_callback: &progress_callback,
+    callback_interval,
+    last_callback: Instant::now(),
+  };
+
+  let lenient: bool = options.lenient;
+
+  debug!(
+    "Extracting the {format:?} archive ({total_bytes} bytes) into \"{}\"",
+    extract_folder_temp.display()
+  );
 
   // Extract the archive based on its format
-  match format {
-    ArchiveFormat::Other => unreachable!("If the format is Other, we should've exited before!"),
-    ArchiveFormat::Zip => extract_zip(&file, &extract_folder_temp)?,
-    ArchiveFormat::Tar => extract_tar(&file, &extract_folder_temp)?,
-    ArchiveFormat::TarGz => extract_tar_gz(&file, &extract_folder_temp)?,
-    ArchiveFormat::TarBz2 => extract_tar_bz2(&file, &extract_folder_temp)?,
-    ArchiveFormat::TarXz => extract_tar_xz(&file, &extract_folder_temp)?,
-    ArchiveFormat::TarZst => extract_tar_zst(&file, &extract_folder_temp)?,
-  }
+  let failed_entries = match format {
+    ArchiveFormat::Other | ArchiveFormat::Unsupported(_) => {
+      unreachable!("If the format is Other or Unsupported, we should've exited before!")
+    }
+    ArchiveFormat::Zip => extract_zip(
+      reader,
+      &read_bytes,
+      file_path,
+      &extract_folder_temp,
+      options,
+    )?,
+    ArchiveFormat::Tar => extract_tar(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarGz => extract_tar_gz(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarBz2 => extract_tar_bz2(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarXz => extract_tar_xz(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarZst => extract_tar_zst(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::Dmg => {
+      crate::macos::extract_dmg(file_path, &extract_folder_temp)?;
+      Vec::new()
+    }
+  };
+
+  progress_callback(total_bytes, total_bytes);
+  debug!(
+    "The archive was extracted, {} entries failed",
+    failed_entries.len()
+  );
 
   // Remove the archive
   filesystem::remove_file(file_path)?;
 
+  commit_extraction(extract_folder)?;
+
+  Ok(Extracted::Archive(failed_entries))
+}
+
+/// Check if an archive can be extracted while it is being read, without seeking
+///
+/// Only the tar based formats can, because the ZIP central directory is at the end of the file
+pub(crate) fn supports_streaming(file: &Path) -> Result<bool, FilesystemError> {
+  Ok(matches!(
+    get_archive_format(file)?,
+    ArchiveFormat::Tar
+      | ArchiveFormat::TarGz
+      | ArchiveFormat::TarBz2
+      | ArchiveFormat::TarXz
+      | ArchiveFormat::TarZst
+  ))
+}
+
+/// Extract an archive from a reader into the `.part` folder next to `extract_folder`
+///
+/// Unlike [`extract`], the extraction isn't finished here: once the archive has been verified,
+/// [`commit_extraction`] has to be called to move the files to `extract_folder`. If the
+/// verification fails, the `.part` folder should be removed instead
+///
+/// # Arguments
+///
+/// * `reader` - The archive data. The reader isn't read until its end, because some formats have trailing data
+///
+/// * `archive_name` - The name of the archive, used to get its format. See [`supports_streaming`]
+///
+/// * `extract_folder` - The folder where the files will be placed
+///
+/// * `lenient` - Skip the entries that fail to extract instead of aborting the whole extraction
+pub(crate) fn extract_stream(
+  reader: impl Read,
+  archive_name: &Path,
+  extract_folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
+  // If the extract folder isn't empty, return an error
+  filesystem::ensure_is_empty(extract_folder)?;
+
+  let extract_folder_temp = game_files::add_part_extension(extract_folder)?;
+  filesystem::create_dir(&extract_folder_temp)?;
+
+  let failed_entries = match get_archive_format(archive_name)? {
+    ArchiveFormat::Tar => extract_tar(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarGz => extract_tar_gz(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarBz2 => extract_tar_bz2(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarXz => extract_tar_xz(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::TarZst => extract_tar_zst(reader, &extract_folder_temp, lenient)?,
+    ArchiveFormat::Zip
+    | ArchiveFormat::Dmg
+    | ArchiveFormat::Unsupported(_)
+    | ArchiveFormat::Other => {
+      return Err(ExtractError::Other(format!(
+        "This archive can't be extracted while it is being downloaded: \"{}\"",
+        archive_name.display()
+      )));
+    }
+  };
+
+  Ok(failed_entries)
+}
+
+/// Move the files extracted into the `.part` folder next to `extract_folder` to their destination
+pub(crate) fn commit_extraction(extract_folder: &Path) -> Result<(), FilesystemError> {
+  let extract_folder_temp = game_files::add_part_extension(extract_folder)?;
+
   // If the extraction folder has any common roots, remove them
-  game_files::remove_root_folder(&extract_folder_temp)?;
+  game_files::remove_root_folder(&LocalStorage, &extract_folder_temp)?;
 
   // Move the temporal folder to its destination
-  game_files::move_folder(&extract_folder_temp, extract_folder)?;
+  game_files::move_folder(&LocalStorage, &extract_folder_temp, extract_folder)
+}
+
+/// Get the path where an archive entry has to be written, making sure it is inside `folder`
+///
+/// The entry path can't be absolute or contain `..`, and none of the already extracted
+/// folders in its path can be a symlink that points outside of `folder`
+///
+/// # Errors
+///
+/// [`ExtractError::PathTraversal`] if the entry would be written outside of `folder`
+#[cfg_attr(not(any(feature = "zip", feature = "tar")), allow(dead_code))]
+fn entry_destination(folder: &Path, entry_path: &Path) -> Result<PathBuf, ExtractError> {
+  let traversal = || ExtractError::PathTraversal(entry_path.display().to_string());
+
+  let mut destination: PathBuf = folder.to_path_buf();
+  for component in entry_path.components() {
+    match component {
+      Component::Normal(name) => destination.push(name),
+      Component::CurDir => (),
+      Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Err(traversal()),
+    }
+  }
+
+  // A symlink extracted before could redirect the entry, so resolve the deepest existing
+  // path (including the destination itself, because writing a file follows symlinks)
+  let canonical_folder: PathBuf = filesystem::get_canonical_path(folder)?;
+  for ancestor in destination.ancestors() {
+    if ancestor == folder {
+      break;
+    }
+    if std::fs::symlink_metadata(ancestor).is_err() {
+      continue;
+    }
+
+    // A broken symlink can't be resolved, and it could point anywhere once its target exists
+    let canonical_ancestor = std::fs::canonicalize(ancestor).map_err(|_| traversal())?;
+    if !canonical_ancestor.starts_with(&canonical_folder) {
+      return Err(traversal());
+    }
+    break;
+  }
+
+  Ok(destination)
+}
+
+/// Check that the target of a symlink entry stays inside the extraction folder
+///
+/// Absolute targets are rejected, and relative ones are resolved from the folder of the entry
+///
+/// # Errors
+///
+/// [`ExtractError::PathTraversal`] if the symlink would point outside of the extraction folder
+#[cfg_attr(not(any(feature = "zip", feature = "tar")), allow(dead_code))]
+fn check_symlink_target(entry_path: &Path, target: &Path) -> Result<(), ExtractError> {
+  let traversal =
+    || ExtractError::PathTraversal(format!("{} -> {}", entry_path.display(), target.display()));
+
+  // The number of folders between the extraction folder and the symlink target
+  let mut depth: usize = entry_path
+    .parent()
+    .map(|parent| {
+      parent
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .count()
+    })
+    .unwrap_or_default();
+
+  for component in target.components() {
+    match component {
+      Component::Normal(_) => depth += 1,
+      Component::CurDir => (),
+      Component::ParentDir => depth = depth.checked_sub(1).ok_or_else(traversal)?,
+      Component::RootDir | Component::Prefix(_) => return Err(traversal()),
+    }
+  }
 
   Ok(())
 }
 
+/// Unpack all the entries of a tar archive into the given folder
+///
+/// `format_name` is only used in the error messages
+#[cfg(feature = "tar")]
+fn unpack_tar<R: Read>(
+  mut archive: tar::Archive<R>,
+  folder: &Path,
+  lenient: bool,
+  format_name: &str,
+) -> Result<Vec<FailedEntry>, ExtractError> {
+  let mut failed_entries: Vec<FailedEntry> = Vec::new();
+
+  let entries = archive
+    .entries()
+    .map_err(|e| format!("Error reading {format_name} archive: {e}"))?;
+
+  for entry in entries {
+    // If an entry header can't be read, the following ones can't be found either
+    let mut entry = entry.map_err(|e| format!("Error reading {format_name} archive entry: {e}"))?;
+
+    let entry_path: PathBuf = entry
+      .path()
+      .map_err(|e| format!("Error reading {format_name} archive entry path: {e}"))?
+      .into_owned();
+
+    // A malicious archive is never extracted, even in lenient mode
+    entry_destination(folder, &entry_path)?;
+
+    let entry_type = entry.header().entry_type();
+    if let Some(link_name) = entry
+      .link_name()
+      .map_err(|e| format!("Error reading {format_name} archive entry link: {e}"))?
+    {
+      if entry_type.is_symlink() {
+        check_symlink_target(&entry_path, &link_name)?;
+      } else if entry_type.is_hard_link() {
+        // Hard link targets are relative to the root of the archive
+        entry_destination(folder, &link_name)?;
+      }
+    }
+
+    if let Err(e) = entry.unpack_in(folder) {
+      let entry_path = entry_path.display().to_string();
+      if !lenient {
+        return Err(
+          format!("Error extracting {format_name} archive entry \"{entry_path}\": {e}").into(),
+        );
+      }
+      debug!("Couldn't extract {format_name} archive entry \"{entry_path}\": {e}");
+      failed_entries.push(FailedEntry {
+        entry: entry_path,
+        error: e.to_string(),
+      });
+    }
+  }
+
+  Ok(failed_entries)
+}
+
 #[cfg_attr(not(feature = "zip"), allow(unused_variables))]
-fn extract_zip(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_zip(
+  file: impl Read + Seek,
+  read_bytes: &Cell<u64>,
+  archive_path: &Path,
+  folder: &Path,
+  options: &ExtractOptions,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(feature = "zip")]
   {
     let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
-    archive
-      .extract(folder)
-      .map_err(|e| format!("Error extracting ZIP archive: {e}"))
+
+    // Check if the archive is encrypted before extracting anything, so the error is clear
+    let is_encrypted: bool =
+      (0..archive.len()).any(|i| archive.by_index_raw(i).is_ok_and(|e| e.encrypted()));
+    if is_encrypted && options.password.is_none() {
+      return Err(ExtractError::PasswordRequired(archive_path.to_path_buf()));
+    }
+
+    // The central directory at the end of the archive has already been read,
+    // so only count the entries data from now on
+    read_bytes.set(0);
+
+    let mut failed_entries: Vec<FailedEntry> = Vec::new();
+
+    for index in 0..archive.len() {
+      let entry = match &options.password {
+        Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+        None => archive.by_index(index),
+      };
+
+      let mut entry = match entry {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::InvalidPassword) => {
+          return Err(ExtractError::WrongPassword(archive_path.to_path_buf()));
+        }
+        Err(e) if !options.lenient => {
+          return Err(format!("Error extracting ZIP archive: {e}").into());
+        }
+        Err(e) => {
+          failed_entries.push(FailedEntry {
+            entry: format!("#{index}"),
+            error: e.to_string(),
+          });
+          continue;
+        }
+      };
+
+      let entry_name = entry.name().to_owned();
+
+      // Refuse to extract entries that would be placed outside the folder
+      // A malicious archive is never extracted, even in lenient mode
+      let Some(relative_path) = entry.enclosed_name() else {
+        return Err(ExtractError::PathTraversal(entry_name));
+      };
+      let entry_path = entry_destination(folder, &relative_path)?;
+
+      // The symlink target is stored as the entry data
+      let symlink_target: Option<PathBuf> = if entry.is_symlink() {
+        let mut target = String::new();
+        entry
+          .read_to_string(&mut target)
+          .map_err(|e| format!("Error reading ZIP archive symlink \"{entry_name}\": {e}"))?;
+        let target = PathBuf::from(target);
+        check_symlink_target(&relative_path, &target)?;
+        Some(target)
+      } else {
+        None
+      };
+
+      let result: std::io::Result<()> = if entry.is_dir() {
+        std::fs::create_dir_all(&entry_path)
+      } else if let Some(target) = symlink_target {
+        (|| {
+          if let Some(parent) = entry_path.parent() {
+            std::fs::create_dir_all(parent)?;
+          }
+
+          #[cfg(unix)]
+          std::os::unix::fs::symlink(&target, &entry_path)?;
+
+          // Creating symlinks on Windows requires special privileges, so store the target instead
+          #[cfg(not(unix))]
+          std::fs::write(&entry_path, target.as_os_str().as_encoded_bytes())?;
+
+          Ok(())
+        })()
+      } else {
+        (|| {
+          if let Some(parent) = entry_path.parent() {
+            std::fs::create_dir_all(parent)?;
+          }
+
+          let mut entry_file = std::fs::File::create(&entry_path)?;
+          std::io::copy(&mut entry, &mut entry_file)?;
+
+          // Restore the entry permissions
+          #[cfg(unix)]
+          if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&entry_path, std::fs::Permissions::from_mode(mode))?;
+          }
+
+          Ok(())
+        })()
+      };
+
+      if let Err(e) = result {
+        if !options.lenient {
+          return Err(format!("Error extracting ZIP archive entry \"{entry_name}\": {e}").into());
+        }
+        debug!("Couldn't extract ZIP archive entry \"{entry_name}\": {e}");
+        failed_entries.push(FailedEntry {
+          entry: entry_name,
+          error: e.to_string(),
+        });
+      }
+    }
+
+    Ok(failed_entries)
   }
 
   #[cfg(not(feature = "zip"))]
   {
     Err(
-      "This binary was built without ZIP support. Recompile with `--features zip` to be able to extract this archive".to_string()
+      "This binary was built without ZIP support. Recompile with `--features zip` to be able to extract this archive".to_string().into()
     )
   }
 }
 
 #[cfg_attr(not(feature = "tar"), allow(unused_variables))]
-fn extract_tar(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_tar(
+  file: impl Read,
+  folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(feature = "tar")]
   {
-    let mut tar_decoder = tar::Archive::new(file);
-    tar_decoder
-      .unpack(folder)
-      .map_err(|e| format!("Error extracting tar archive: {e}"))
+    unpack_tar(tar::Archive::new(file), folder, lenient, "tar")
   }
 
   #[cfg(not(feature = "tar"))]
   {
     Err(
-      "This binary was built without TAR support. Recompile with `--features tar` to be able to extract this archive".to_string()
+      "This binary was built without TAR support. Recompile with `--features tar` to be able to extract this archive".to_string().into()
     )
   }
 }
 
 #[cfg_attr(not(all(feature = "gzip", feature = "tar")), allow(unused_variables))]
-fn extract_tar_gz(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_tar_gz(
+  file: impl Read,
+  folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(all(feature = "gzip", feature = "tar"))]
   {
     let gz_decoder = flate2::read::GzDecoder::new(file);
-    let mut tar_decoder = tar::Archive::new(gz_decoder);
-    tar_decoder
-      .unpack(folder)
-      .map_err(|e| format!("Error extracting tar.gz archive: {e}"))
+    unpack_tar(tar::Archive::new(gz_decoder), folder, lenient, "tar.gz")
   }
 
   #[cfg(not(all(feature = "gzip", feature = "tar")))]
   {
     Err(
-      r#"This binary was built without gzip or TAR support. Recompile with `--features "gzip tar"` to be able to extract this archive"#.to_string()
+      r#"This binary was built without gzip or TAR support. Recompile with `--features "gzip tar"` to be able to extract this archive"#.to_string().into()
     )
   }
 }
 
 #[cfg_attr(not(all(feature = "bzip2", feature = "tar")), allow(unused_variables))]
-fn extract_tar_bz2(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_tar_bz2(
+  file: impl Read,
+  folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(all(feature = "bzip2", feature = "tar"))]
   {
     let bz2_decoder = bzip2::read::BzDecoder::new(file);
-    let mut tar_decoder = tar::Archive::new(bz2_decoder);
-    tar_decoder
-      .unpack(folder)
-      .map_err(|e| format!("Error extracting tar.gz archive: {e}"))
+    unpack_tar(tar::Archive::new(bz2_decoder), folder, lenient, "tar.bz2")
   }
 
   #[cfg(not(all(feature = "bzip2", feature = "tar")))]
   {
     Err(
-      r#"This binary was built without bzip2 or TAR support. Recompile with `--features "bzip2 tar"` to be able to extract this archive"#.to_string()
+      r#"This binary was built without bzip2 or TAR support. Recompile with `--features "bzip2 tar"` to be able to extract this archive"#.to_string().into()
     )
   }
 }
 
 #[cfg_attr(not(all(feature = "xz", feature = "tar")), allow(unused_variables))]
-fn extract_tar_xz(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_tar_xz(
+  file: impl Read,
+  folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(all(feature = "xz", feature = "tar"))]
   {
     let xz_decoder = liblzma::read::XzDecoder::new(file);
-    let mut tar_decoder = tar::Archive::new(xz_decoder);
-    tar_decoder
-      .unpack(folder)
-      .map_err(|e| format!("Error extracting tar.xz archive: {e}"))
+    unpack_tar(tar::Archive::new(xz_decoder), folder, lenient, "tar.xz")
   }
 
   #[cfg(not(all(feature = "xz", feature = "tar")))]
   {
     Err(
-      r#"This binary was built without XZ or TAR support. Recompile with `--features "xz tar"` to be able to extract this archive"#.to_string()
+      r#"This binary was built without XZ or TAR support. Recompile with `--features "xz tar"` to be able to extract this archive"#.to_string().into()
     )
   }
 }
 
 #[cfg_attr(not(all(feature = "zstd", feature = "tar")), allow(unused_variables))]
-fn extract_tar_zst(file: &File, folder: &Path) -> Result<(), String> {
+fn extract_tar_zst(
+  file: impl Read,
+  folder: &Path,
+  lenient: bool,
+) -> Result<Vec<FailedEntry>, ExtractError> {
   #[cfg(all(feature = "zstd", feature = "tar"))]
   {
     let zstd_decoder =
       zstd::Decoder::new(file).map_err(|e| format!("Error reading tar.zst archive: {e}"))?;
-    let mut tar_decoder = tar::Archive::new(zstd_decoder);
-    tar_decoder
-      .unpack(folder)
-      .map_err(|e| format!("Error extracting tar.zst archive: {e}"))
+    unpack_tar(tar::Archive::new(zstd_decoder), folder, lenient, "tar.zst")
   }
 
   #[cfg(not(all(feature = "zstd", feature = "tar")))]
   {
     Err(
-      r#"This binary was built without Zstd or TAR support. Recompile with `--features "zstd tar"` to be able to extract this archive"#.to_string()
+      r#"This binary was built without Zstd or TAR support. Recompile with `--features "zstd tar"` to be able to extract this archive"#.to_string().into()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ExtractOptions, Extracted, extract};
+  use crate::errors::ExtractError;
+
+  use std::io::Write;
+  use std::path::{Path, PathBuf};
+  use std::time::Duration;
+
+  /// Get a new empty folder to create the test archives in
+  fn test_folder(name: &str) -> PathBuf {
+    let folder =
+      std::env::temp_dir().join(format!("scratch-io-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+  }
+
+  /// Extract `archive` into the "extracted" folder next to it
+  fn extract_archive(archive: &Path, lenient: bool) -> Result<PathBuf, ExtractError> {
+    let extract_folder = archive.with_file_name("extracted");
+    extract(
+      archive,
+      &extract_folder,
+      &ExtractOptions {
+        lenient,
+        password: None,
+      },
+      |_, _| (),
+      Duration::ZERO,
     )
+    .map(|_| extract_folder)
+  }
+
+  /// Write a ZIP archive at `path`, calling `add_entries` to fill it
+  #[cfg(feature = "zip")]
+  fn write_zip(
+    path: &Path,
+    add_entries: impl FnOnce(&mut zip::ZipWriter<std::fs::File>, zip::write::SimpleFileOptions),
+  ) {
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    add_entries(&mut writer, zip::write::SimpleFileOptions::default());
+    writer.finish().unwrap();
+  }
+
+  #[test]
+  #[cfg(feature = "zip")]
+  fn test_zip_parent_dir_entry_is_rejected() {
+    let folder = test_folder("zip-traversal");
+    let archive = folder.join("evil.zip");
+    write_zip(&archive, |writer, options| {
+      writer.start_file("game/readme.txt", options).unwrap();
+      writer.write_all(b"fine").unwrap();
+      writer.start_file("../evil", options).unwrap();
+      writer.write_all(b"evil").unwrap();
+    });
+
+    // The traversal is fatal even in lenient mode
+    for lenient in [false, true] {
+      let result = extract_archive(&archive, lenient);
+      assert!(matches!(result, Err(ExtractError::PathTraversal(_))));
+      assert!(!folder.join("evil").exists());
+      assert!(!folder.parent().unwrap().join("evil").exists());
+    }
+  }
+
+  #[test]
+  #[cfg(all(feature = "zip", unix))]
+  fn test_zip_absolute_symlink_is_rejected() {
+    let folder = test_folder("zip-symlink");
+    let archive = folder.join("evil.zip");
+    write_zip(&archive, |writer, options| {
+      writer.add_symlink("etc", "/etc", options).unwrap();
+      writer.start_file("etc/evil", options).unwrap();
+      writer.write_all(b"evil").unwrap();
+    });
+
+    let result = extract_archive(&archive, false);
+    assert!(matches!(result, Err(ExtractError::PathTraversal(_))));
+  }
+
+  #[test]
+  #[cfg(all(feature = "zip", unix))]
+  fn test_zip_relative_symlink_inside_folder_is_extracted() {
+    let folder = test_folder("zip-relative-symlink");
+    let archive = folder.join("game.zip");
+    write_zip(&archive, |writer, options| {
+      writer.start_file("game/data/file.txt", options).unwrap();
+      writer.write_all(b"data").unwrap();
+      writer
+        .add_symlink("game/link.txt", "data/file.txt", options)
+        .unwrap();
+    });
+
+    let extract_folder = extract_archive(&archive, false).unwrap();
+    // The common "game" root folder is removed after extracting
+    let link = extract_folder.join("link.txt");
+    assert!(link.symlink_metadata().unwrap().is_symlink());
+    assert_eq!(std::fs::read(link).unwrap(), b"data");
+  }
+
+  #[test]
+  #[cfg(all(feature = "zip", unix))]
+  fn test_zip_relative_symlink_outside_folder_is_rejected() {
+    let folder = test_folder("zip-escaping-symlink");
+    let archive = folder.join("evil.zip");
+    write_zip(&archive, |writer, options| {
+      writer
+        .add_symlink("game/link", "../../outside", options)
+        .unwrap();
+    });
+
+    let result = extract_archive(&archive, true);
+    assert!(matches!(result, Err(ExtractError::PathTraversal(_))));
+  }
+
+  #[test]
+  #[cfg(all(feature = "tar", unix))]
+  fn test_tar_absolute_symlink_is_rejected() {
+    let folder = test_folder("tar-symlink");
+    let archive = folder.join("evil.tar");
+
+    let mut builder = tar::Builder::new(std::fs::File::create(&archive).unwrap());
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    builder.append_link(&mut header, "etc", "/etc").unwrap();
+    builder.finish().unwrap();
+    drop(builder);
+
+    let result = extract_archive(&archive, false);
+    assert!(matches!(result, Err(ExtractError::PathTraversal(_))));
+  }
+
+  #[test]
+  #[cfg(all(feature = "tar", feature = "gzip", unix))]
+  fn test_tar_gz_keeps_modes_and_symlinks() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let folder = test_folder("tar-gz");
+    let archive = folder.join("game.tar.gz");
+
+    let encoder = flate2::write::GzEncoder::new(
+      std::fs::File::create(&archive).unwrap(),
+      flate2::Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+
+    let data = b"#!/bin/sh\necho game\n";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o755);
+    builder
+      .append_data(&mut header, "game/start.sh", &data[..])
+      .unwrap();
+
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    builder
+      .append_link(&mut header, "game/run.sh", "start.sh")
+      .unwrap();
+
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let extracted = extract_archive(&archive, false).unwrap();
+
+    // The common "game" root folder is removed after extracting
+    let script = extracted.join("start.sh");
+    let mode = std::fs::metadata(&script).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+    assert_eq!(
+      std::fs::read_link(extracted.join("run.sh")).unwrap(),
+      Path::new("start.sh")
+    );
+    assert!(!archive.exists());
+  }
+
+  #[test]
+  fn test_unsupported_archive_is_kept() {
+    let folder = test_folder("unsupported");
+    let archive = folder.join("game.tar.lz4");
+    std::fs::File::create(&archive)
+      .unwrap()
+      .write_all(b"not really lz4")
+      .unwrap();
+
+    let result = extract_archive(&archive, false);
+    assert!(matches!(result, Err(ExtractError::Other(e)) if e.contains("tar.lz4")));
+    assert!(archive.exists());
+  }
+
+  #[test]
+  fn test_single_file_is_moved_unchanged() {
+    let folder = test_folder("single-file");
+    let jar = folder.join("game.jar");
+    std::fs::write(&jar, b"PK not extracted").unwrap();
+
+    let extract_folder = folder.join("extracted");
+    let result = extract(
+      &jar,
+      &extract_folder,
+      &ExtractOptions::default(),
+      |_, _| (),
+      Duration::ZERO,
+    );
+
+    assert_eq!(
+      result.ok(),
+      Some(Extracted::SingleFile(PathBuf::from("game.jar")))
+    );
+    assert_eq!(
+      std::fs::read(extract_folder.join("game.jar")).unwrap(),
+      b"PK not extracted"
+    );
+    assert!(!jar.exists());
   }
 }
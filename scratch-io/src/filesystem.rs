@@ -76,6 +76,13 @@ pub fn file_type(
     .map_err(IOErr::CouldntGetFileType(path.to_owned()).attach())
 }
 
+/// [`std::fs::DirEntry::metadata`]
+pub fn entry_metadata(dir_entry: &fs::DirEntry) -> Result<std::fs::Metadata, FilesystemError> {
+  dir_entry
+    .metadata()
+    .map_err(IOErr::CouldntReadPathMetadata(dir_entry.path()).attach())
+}
+
 /// [`std::fs::canonicalize`]
 pub fn get_canonical_path(path: &Path) -> Result<PathBuf, FilesystemError> {
   fs::canonicalize(path).map_err(IOErr::CouldntGetCanonical(path.to_owned()).attach())
@@ -135,6 +142,21 @@ pub fn read_file_metadata(file: &fs::File) -> Result<std::fs::Metadata, Filesyst
     .map_err(IOErr::CouldntReadFileMetadata.attach())
 }
 
+/// [`std::fs::Metadata::modified`]
+pub fn modified_time(
+  metadata: &fs::Metadata,
+  path: &Path,
+) -> Result<std::time::SystemTime, FilesystemError> {
+  metadata
+    .modified()
+    .map_err(IOErr::CouldntReadModifiedTime(path.to_owned()).attach())
+}
+
+/// [`std::fs::read_link`]
+pub fn read_link(path: &Path) -> Result<PathBuf, FilesystemError> {
+  fs::read_link(path).map_err(IOErr::CouldntReadLink(path.to_owned()).attach())
+}
+
 /// Checks if a given path represents a directory on the filesystem
 ///
 /// Returns none if the path doesn't exist
@@ -220,6 +242,16 @@ pub fn set_file_len(file: &fs::File, size: u64) -> Result<(), FilesystemError> {
     .map_err(IOErr::SetFileLength(size).attach())
 }
 
+/// [`std::io::Seek::seek`] from the start of the file
+pub fn seek(file: &mut fs::File, position: u64) -> Result<(), FilesystemError> {
+  use std::io::Seek;
+
+  file
+    .seek(std::io::SeekFrom::Start(position))
+    .map(|_| ())
+    .map_err(IOErr::CouldntSeek(position).attach())
+}
+
 /// [`std::fs::File::sync_all`]
 pub fn file_sync_all(file: &fs::File) -> Result<(), FilesystemError> {
   file.sync_all().map_err(IOErr::SyncFile.attach())
@@ -279,3 +311,36 @@ pub fn wait_child(
 ) -> Result<std::process::ExitStatus, FilesystemError> {
   child.wait().map_err(IOErr::CouldntWaitForChild.attach())
 }
+
+/// Read the piped stderr of a child process until it closes
+///
+/// Everything read is also forwarded to the stderr of this process, so it is still shown
+///
+/// # Returns
+///
+/// The captured output, or None if the stderr of the child wasn't piped
+pub fn read_child_stderr(
+  child: &mut std::process::Child,
+) -> Result<Option<String>, FilesystemError> {
+  use std::io::{Read, Write};
+
+  let Some(mut pipe) = child.stderr.take() else {
+    return Ok(None);
+  };
+
+  let mut output: Vec<u8> = Vec::new();
+  let mut buffer = [0u8; 8192];
+  loop {
+    let len = pipe
+      .read(&mut buffer)
+      .map_err(IOErr::CouldntReadChildOutput.attach())?;
+    if len == 0 {
+      break;
+    }
+    // Forwarding is best effort, the output is captured anyway
+    let _ = std::io::stderr().write_all(&buffer[..len]);
+    output.extend_from_slice(&buffer[..len]);
+  }
+
+  Ok(Some(String::from_utf8_lossy(&output).into_owned()))
+}
@@ -0,0 +1,123 @@
+use crate::checksum::{ChecksumAlgorithm, Hasher};
+use crate::errors::FilesystemError;
+use crate::filesystem;
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How thoroughly a folder fingerprint is computed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FingerprintMode {
+  /// Only hash the paths, sizes and modification times of the files
+  ///
+  /// This is fast, but copying the folder to another filesystem changes the fingerprint
+  Fast,
+  /// Also hash the contents of every file
+  Thorough,
+}
+
+/// A combined hash over a folder's files, used to detect if they have been modified
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+  pub mode: FingerprintMode,
+  pub hash: String,
+}
+
+/// Update the hasher with the data of a file, based on the fingerprint mode
+fn hash_file(
+  path: &Path,
+  mode: FingerprintMode,
+  hasher: &mut Hasher,
+) -> Result<(), FilesystemError> {
+  let metadata = filesystem::read_path_metadata(path)?;
+  hasher.update(&metadata.len().to_le_bytes());
+
+  match mode {
+    FingerprintMode::Fast => {
+      // If the modification time is before the UNIX epoch, just use zero
+      let modified = filesystem::modified_time(&metadata, path)?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+      hasher.update(&modified.as_secs().to_le_bytes());
+      hasher.update(&modified.subsec_nanos().to_le_bytes());
+    }
+    FingerprintMode::Thorough => {
+      let file = filesystem::open_file(path, std::fs::OpenOptions::new().read(true))?;
+      crate::hash_readable(&mut std::io::BufReader::new(file), hasher)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Compute the fingerprint of a folder
+///
+/// The fingerprint covers the relative paths of all the files, folders and
+/// symlinks inside the folder, the sizes of the files, and either their
+/// modification times or their contents, depending on `mode`
+///
+/// # Arguments
+///
+/// * `folder` - The folder whose fingerprint will be computed
+///
+/// * `mode` - Whether to hash the modification times or the contents of the files
+///
+/// # Returns
+///
+/// The fingerprint of the folder
+///
+/// # Errors
+///
+/// If the folder doesn't exist or any filesystem operation fails
+pub fn folder_fingerprint(
+  folder: &Path,
+  mode: FingerprintMode,
+) -> Result<Fingerprint, FilesystemError> {
+  filesystem::ensure_is_dir(folder)?;
+
+  let mut hasher = Hasher::new(ChecksumAlgorithm::Md5);
+
+  let mut queue: Vec<PathBuf> = vec![folder.to_path_buf()];
+
+  while let Some(current) = queue.pop() {
+    // Read all the entries of the folder
+    let mut entries: Vec<std::fs::DirEntry> = Vec::new();
+    let mut read_dir = filesystem::read_dir(&current)?;
+    while let Some(entry) = filesystem::next_entry(&mut read_dir, &current)? {
+      entries.push(entry);
+    }
+
+    // Sort them, so the fingerprint doesn't depend on the order returned by the filesystem
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+
+    for entry in entries {
+      let path = entry.path();
+      let relative_path = path
+        .strip_prefix(folder)
+        .expect("The entry is always inside the fingerprinted folder");
+
+      // Hash the path followed by a separator, so different paths can't be confused
+      hasher.update(relative_path.as_os_str().as_encoded_bytes());
+      hasher.update(&[0]);
+
+      let file_type = filesystem::file_type(&entry, &current)?;
+
+      if file_type.is_dir() {
+        hasher.update(b"d");
+        queue.push(path);
+      } else if file_type.is_symlink() {
+        hasher.update(b"l");
+        hasher.update(filesystem::read_link(&path)?.as_os_str().as_encoded_bytes());
+      } else {
+        hasher.update(b"f");
+        hash_file(&path, mode, &mut hasher)?;
+      }
+    }
+  }
+
+  Ok(Fingerprint {
+    mode,
+    hash: hasher.finalize_hex(),
+  })
+}
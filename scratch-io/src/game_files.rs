@@ -1,17 +1,103 @@
 use crate::errors::{FilesystemError, OtherFilesystemErrorKind as OtherErr};
 use crate::filesystem::*;
 use crate::itch_api::types::UploadID;
+use crate::storage::GameStorage;
 
 use std::path::{Path, PathBuf};
 
 pub const UPLOAD_ARCHIVE_NAME: &str = "download";
 pub const COVER_IMAGE_DEFAULT_FILENAME: &str = "cover.png";
 
+// Characters which aren't allowed in file names on some filesystems
+const FORBIDDEN_NAME_CHARACTERS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+// Names reserved by Windows, even with an extension
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+  "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+  "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+// Most filesystems limit names to 255 bytes, so leave some margin
+const MAX_FOLDER_NAME_BYTES: usize = 200;
+const EMPTY_FOLDER_NAME: &str = "Untitled";
+
+/// Turn a game title into a folder name that is valid on every common filesystem
+///
+/// Path separators, forbidden and control characters are replaced with `_`, the spaces and dots
+/// at the ends are trimmed, and reserved Windows names like `CON` get a `_` suffix.
+/// The result only depends on the title, so the same title always gets the same folder
+pub fn sanitize_folder_name(title: &str) -> String {
+  let replaced: String = title
+    .chars()
+    .map(|c| {
+      if c.is_control() || FORBIDDEN_NAME_CHARACTERS.contains(&c) {
+        '_'
+      } else {
+        c
+      }
+    })
+    .collect();
+
+  let mut name: String = replaced
+    .trim_matches(|c: char| c.is_whitespace() || c == '.')
+    .to_string();
+
+  if name.len() > MAX_FOLDER_NAME_BYTES {
+    let mut end = MAX_FOLDER_NAME_BYTES;
+    while !name.is_char_boundary(end) {
+      end -= 1;
+    }
+    name.truncate(end);
+    name.truncate(
+      name
+        .trim_end_matches(|c: char| c.is_whitespace() || c == '.')
+        .len(),
+    );
+  }
+
+  if name.is_empty() {
+    return EMPTY_FOLDER_NAME.to_string();
+  }
+
+  // Windows ignores the extension when checking reserved names, so "con.txt" is reserved too
+  let stem: &str = name.split('.').next().unwrap_or_default().trim_end();
+  if RESERVED_WINDOWS_NAMES
+    .iter()
+    .any(|r| stem.eq_ignore_ascii_case(r))
+  {
+    name.insert(stem.len(), '_');
+  }
+
+  name
+}
+
 /// Get the upload folder based on its game folder
 pub fn get_upload_folder(game_folder: &Path, upload_id: UploadID) -> PathBuf {
   game_folder.join(format!("{upload_id}"))
 }
 
+/// Get the total size of the files inside a folder, recursively
+///
+/// Symlinks aren't followed, so their targets aren't counted twice
+pub fn get_folder_size(folder: &Path) -> Result<u64, FilesystemError> {
+  let mut size: u64 = 0;
+  let mut queue: Vec<PathBuf> = vec![folder.to_path_buf()];
+
+  while let Some(current) = queue.pop() {
+    let mut entries = read_dir(&current)?;
+
+    while let Some(entry) = next_entry(&mut entries, &current)? {
+      let entry_type = file_type(&entry, &current)?;
+
+      if entry_type.is_dir() {
+        queue.push(entry.path());
+      } else if entry_type.is_file() {
+        size += entry_metadata(&entry)?.len();
+      }
+    }
+  }
+
+  Ok(size)
+}
+
 /// Get the upload archive path based on its game folder and `upload_id`
 pub fn get_upload_archive_path(
   game_folder: &Path,
@@ -23,24 +109,77 @@ pub fn get_upload_archive_path(
   ))
 }
 
+/// Checks if the file is an Android package (.apk), which can't be run on desktop
+pub fn is_android_package(file: &Path) -> bool {
+  get_file_extension(file).is_ok_and(|e| e.eq_ignore_ascii_case("apk"))
+}
+
+/// Checks if the file is a Java archive (.jar), which is run with `java -jar`
+pub fn is_java_archive(file: &Path) -> bool {
+  get_file_extension(file).is_ok_and(|e| e.eq_ignore_ascii_case("jar"))
+}
+
+/// Checks if the path is a macOS application bundle (.app), which is a folder that runs as a program
+pub fn is_app_bundle(path: &Path) -> bool {
+  get_file_extension(path).is_ok_and(|e| e.eq_ignore_ascii_case("app"))
+}
+
+/// Checks if the file is a macOS disk image (.dmg)
+pub fn is_disk_image(file: &Path) -> bool {
+  get_file_extension(file).is_ok_and(|e| e.eq_ignore_ascii_case("dmg"))
+}
+
 /// Adds a .part extension to the given Path
 pub fn add_part_extension(file: &Path) -> Result<PathBuf, FilesystemError> {
   let filename = get_file_name(file)?;
   Ok(file.with_file_name(format!("{filename}.part")))
 }
 
+/// Adds a .md5state extension to the given Path
+///
+/// This file holds the MD5 hash state of a partial download, so it can be resumed without hashing it again
+pub fn add_hash_checkpoint_extension(file: &Path) -> Result<PathBuf, FilesystemError> {
+  let filename = get_file_name(file)?;
+  Ok(file.with_file_name(format!("{filename}.md5state")))
+}
+
+/// Adds a .parallel.part extension to the given Path
+///
+/// This file holds parallel downloads, which can't be resumed because they have gaps
+pub fn add_parallel_part_extension(file: &Path) -> Result<PathBuf, FilesystemError> {
+  let filename = get_file_name(file)?;
+  Ok(file.with_file_name(format!("{filename}.parallel.part")))
+}
+
+/// Check if a folder of the storage is empty
+///
+/// A folder that doesn't exist is empty, but a file is an error
+fn is_storage_folder_empty(
+  storage: &impl GameStorage,
+  folder: &Path,
+) -> Result<bool, FilesystemError> {
+  match storage.metadata(folder)? {
+    None => Ok(true),
+    Some(metadata) if !metadata.is_dir => Err(OtherErr::ShouldBeAFolder(folder.to_owned()).into()),
+    Some(_) => Ok(storage.read_dir(folder)?.is_empty()),
+  }
+}
+
 /// Remove a folder if it is empty
 ///
 /// Returns whether the folder was removed or not
-pub fn remove_folder_if_empty(folder: &Path) -> Result<bool, FilesystemError> {
+pub fn remove_folder_if_empty(
+  storage: &impl GameStorage,
+  folder: &Path,
+) -> Result<bool, FilesystemError> {
   // Return if the folder is not empty
-  let true = is_folder_empty(folder)? else {
+  let true = is_storage_folder_empty(storage, folder)? else {
     // The folder wasn't removed, so return false
     return Ok(false);
   };
 
   // Remove the empty folder
-  remove_empty_dir(folder)?;
+  storage.remove_empty_dir(folder)?;
 
   Ok(true)
 }
@@ -59,25 +198,28 @@ pub fn remove_folder_safely(path: &Path) -> Result<(), FilesystemError> {
 }
 
 /// Copy all the folder contents to another location
-fn copy_dir_all(from: PathBuf, to: PathBuf) -> Result<(), FilesystemError> {
-  ensure_is_dir(&from)?;
-  create_dir(&to)?;
+fn copy_dir_all(
+  storage: &impl GameStorage,
+  from: PathBuf,
+  to: PathBuf,
+) -> Result<(), FilesystemError> {
+  if !storage.metadata(&from)?.is_some_and(|m| m.is_dir) {
+    return Err(OtherErr::ShouldBeAFolder(from).into());
+  }
+  storage.create_dir(&to)?;
 
   let mut queue: std::collections::VecDeque<(PathBuf, PathBuf)> = std::collections::VecDeque::new();
   queue.push_back((from, to));
 
   while let Some((from, to)) = queue.pop_front() {
-    let mut entries = read_dir(&from)?;
+    for entry in storage.read_dir(&from)? {
+      let to_path = to.join(get_file_name(&entry.path)?);
 
-    while let Some(entry) = next_entry(&mut entries, &from)? {
-      let from_path = entry.path();
-      let to_path = to.join(entry.file_name());
-
-      if file_type(&entry, &from)?.is_dir() {
-        create_dir(&to)?;
-        queue.push_back((from_path, to_path));
+      if entry.is_dir {
+        storage.create_dir(&to_path)?;
+        queue.push_back((entry.path, to_path));
       } else {
-        copy_file(&from_path, &to_path)?;
+        storage.copy_file(&entry.path, &to_path)?;
       }
     }
   }
@@ -88,20 +230,33 @@ fn copy_dir_all(from: PathBuf, to: PathBuf) -> Result<(), FilesystemError> {
 /// Move a folder and its contents to another location
 ///
 /// It also works if the destination is on another filesystem
-pub fn move_folder(from: &Path, to: &Path) -> Result<(), FilesystemError> {
-  ensure_is_dir(from)?;
+pub fn move_folder(
+  storage: &impl GameStorage,
+  from: &Path,
+  to: &Path,
+) -> Result<(), FilesystemError> {
+  if !storage.metadata(from)?.is_some_and(|m| m.is_dir) {
+    return Err(OtherErr::ShouldBeAFolder(from.to_owned()).into());
+  }
 
   // Create the destination parent dir
-  create_dir(to)?;
+  storage.create_dir(to)?;
 
-  match rename(from, to) {
+  match storage.rename(from, to) {
     Ok(()) => Ok(()),
     Err(FilesystemError::IOError { error, .. })
       if error.kind() == std::io::ErrorKind::CrossesDevices =>
     {
       // fallback: copy + delete
-      copy_dir_all(from.to_owned(), to.to_owned())?;
-      remove_folder_safely(from)?;
+      // Copy next to the destination first, so an interrupted copy never looks like a finished one
+      let to_part: PathBuf = add_part_extension(to)?;
+      if storage.metadata(&to_part)?.is_some() {
+        storage.remove_dir_all(&to_part)?;
+      }
+      copy_dir_all(storage, from.to_owned(), to_part.clone())?;
+      storage.remove_empty_dir(to)?;
+      storage.rename(&to_part, to)?;
+      storage.remove_dir_all(from)?;
       Ok(())
     }
     Err(e) => Err(e),
@@ -109,7 +264,10 @@ pub fn move_folder(from: &Path, to: &Path) -> Result<(), FilesystemError> {
 }
 
 // If path already exists, change it a bit until it doesn't. Return the available path
-pub fn find_available_path(path: &Path) -> Result<PathBuf, FilesystemError> {
+pub fn find_available_path(
+  storage: &impl GameStorage,
+  path: &Path,
+) -> Result<PathBuf, FilesystemError> {
   let parent = parent(path)?;
   let filename = get_file_name(path)?;
 
@@ -119,7 +277,7 @@ pub fn find_available_path(path: &Path) -> Result<PathBuf, FilesystemError> {
     let current_filename = format!("{filename}{i:x}");
     let current_path: PathBuf = parent.join(current_filename);
 
-    if !exists(&current_path)? {
+    if storage.metadata(&current_path)?.is_none() {
       return Ok(current_path);
     }
     i += 1;
@@ -132,42 +290,44 @@ pub fn find_available_path(path: &Path) -> Result<PathBuf, FilesystemError> {
 /// and removes all the empty folders between `last_root` and `base_folder`
 ///
 /// If applied to the folder `foo/` and `foo/bar/` in `/foo/bar/baz.txt`, the remainig structure is `/foo/baz.txt`
-fn move_folder_child(last_root: &Path, base_folder: &Path) -> Result<(), FilesystemError> {
+fn move_folder_child(
+  storage: &impl GameStorage,
+  last_root: &Path,
+  base_folder: &Path,
+) -> Result<(), FilesystemError> {
   // If a file or a folder already exists in the destination folder, rename it and save the new name and
   // the original name to this Vector. At the end, after removing the parent folder, rename all elements of this Vector
   let mut collisions: Vec<(PathBuf, PathBuf)> = Vec::new();
 
-  let mut child_entries = read_dir(last_root)?;
-
   // Move its children up one level
-  while let Some(child) = next_entry(&mut child_entries, last_root)? {
-    let from = child.path();
-    let to = base_folder.join(child.file_name());
+  for child in storage.read_dir(last_root)? {
+    let from = child.path;
+    let to = base_folder.join(get_file_name(&from)?);
 
-    if exists(&to)? {
+    if storage.metadata(&to)?.is_some() {
       // If the children filename already exists on the parent, rename it to a
       // temporal name and, at the end, rename all the temporal names in order to the final names
-      let temporal_name: PathBuf = find_available_path(&to)?;
-      rename(&from, &temporal_name)?;
+      let temporal_name: PathBuf = find_available_path(storage, &to)?;
+      storage.rename(&from, &temporal_name)?;
 
       // save the change to the collisions vector
       collisions.push((temporal_name, to));
     } else {
-      rename(&from, &to)?;
+      storage.rename(&from, &to)?;
     }
   }
 
   // Remove the now-empty wrapper dirs
   let mut current_root = last_root.to_owned();
-  while is_folder_empty(&current_root)? {
+  while is_storage_folder_empty(storage, &current_root)? {
     let parent = parent(&current_root)?;
-    remove_empty_dir(&current_root)?;
+    storage.remove_empty_dir(&current_root)?;
     current_root = parent.to_owned();
   }
 
   // now move all of the filenames that have collided to their original name
   for (src, dst) in &collisions {
-    rename(src, dst)?;
+    storage.rename(src, dst)?;
   }
 
   Ok(())
@@ -177,36 +337,88 @@ fn move_folder_child(last_root: &Path, base_folder: &Path) -> Result<(), Filesys
 /// and unwraps its children to its parent
 ///
 /// If applied to the folder `foo` in `/foo/bar/baz.txt`, the remainig structure is `/foo/baz.txt`
-pub fn remove_root_folder(folder: &Path) -> Result<(), FilesystemError> {
+pub fn remove_root_folder(
+  storage: &impl GameStorage,
+  folder: &Path,
+) -> Result<(), FilesystemError> {
   // This variable is the last nested root of the folder
   let mut last_root: PathBuf = folder.to_path_buf();
   let mut is_there_any_root: bool = false;
 
   loop {
     // List entries
-    let mut entries = read_dir(&last_root)?;
+    let mut entries = storage.read_dir(&last_root)?;
 
-    // First entry (or empty)
-    let Some(first) = next_entry(&mut entries, &last_root)? else {
+    // If there isn't exactly one entry, stop (empty or not a single root)
+    // If the entry is a file, also stop
+    let (Some(first), None) = (entries.pop(), entries.pop()) else {
       break;
     };
+    if !first.is_dir {
+      break;
+    }
 
-    // If there’s another entry, stop (not a single root)
-    // If the entry is a file, also stop
-    if next_entry(&mut entries, &last_root)?.is_some() || file_type(&first, &last_root)?.is_file() {
+    // A macOS application bundle is a folder, but it must be kept as it is
+    if is_app_bundle(&first.path) {
       break;
     }
 
     // At this point, we know that first is a wrapper dir,
     // so set last_root to that and loop again in case there are nested roots
     is_there_any_root = true;
-    last_root = first.path();
+    last_root = first.path;
   }
 
   // Remove the wrappers
   if is_there_any_root {
-    move_folder_child(&last_root, folder)?;
+    move_folder_child(storage, &last_root, folder)?;
   }
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::sanitize_folder_name;
+
+  #[test]
+  fn test_sanitize_keeps_normal_titles() {
+    assert_eq!(sanitize_folder_name("My Game"), "My Game");
+    assert_eq!(sanitize_folder_name("Café: Ñandú"), "Café_ Ñandú");
+    assert_eq!(sanitize_folder_name("v1.0"), "v1.0");
+  }
+
+  #[test]
+  fn test_sanitize_separators_and_control_characters() {
+    assert_eq!(sanitize_folder_name("AC/DC"), "AC_DC");
+    assert_eq!(sanitize_folder_name("..\\..\\Windows"), "_.._Windows");
+    assert_eq!(sanitize_folder_name("What?*"), "What__");
+    assert_eq!(sanitize_folder_name("Line\nBreak\t"), "Line_Break_");
+  }
+
+  #[test]
+  fn test_sanitize_trailing_dots_and_spaces() {
+    assert_eq!(sanitize_folder_name("  Wait... "), "Wait");
+    assert_eq!(sanitize_folder_name(".hidden"), "hidden");
+    assert_eq!(sanitize_folder_name(".."), "Untitled");
+    assert_eq!(sanitize_folder_name(" "), "Untitled");
+    assert_eq!(sanitize_folder_name(""), "Untitled");
+  }
+
+  #[test]
+  fn test_sanitize_reserved_windows_names() {
+    assert_eq!(sanitize_folder_name("CON"), "CON_");
+    assert_eq!(sanitize_folder_name("nul"), "nul_");
+    assert_eq!(sanitize_folder_name("com1.txt"), "com1_.txt");
+    assert_eq!(sanitize_folder_name("Console"), "Console");
+  }
+
+  #[test]
+  fn test_sanitize_long_titles() {
+    let name = sanitize_folder_name(&"ñ".repeat(300));
+    assert!(name.len() <= 200);
+    assert_eq!(name, "ñ".repeat(100));
+    // The result is always the same for the same title
+    assert_eq!(name, sanitize_folder_name(&"ñ".repeat(300)));
+  }
+}
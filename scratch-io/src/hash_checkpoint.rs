@@ -0,0 +1,155 @@
+use crate::errors::FilesystemError;
+use crate::{filesystem, game_files};
+
+use md5::Md5;
+use md5::digest::common::hazmat::{SerializableState, SerializedState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The number of downloaded bytes between two saved checkpoints
+pub(crate) const CHECKPOINT_INTERVAL_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The contents of the checkpoint file
+#[derive(Serialize, Deserialize)]
+struct CheckpointData {
+  /// The hash of the file being downloaded, so a checkpoint of another file isn't used
+  expected_hash: String,
+  /// The number of bytes of the partial file that were hashed
+  offset: u64,
+  /// The serialized MD5 state after hashing those bytes, hex encoded
+  state: String,
+}
+
+/// The MD5 state of a partial download, saved next to the `.part` file
+///
+/// When a download is resumed, the partial file is hashed again from the last checkpoint
+/// instead of from the beginning. If the checkpoint is missing or it doesn't match the
+/// partial file, the whole file is hashed again
+pub(crate) struct HashCheckpoint<'a> {
+  path: PathBuf,
+  expected_hash: &'a str,
+}
+
+impl<'a> HashCheckpoint<'a> {
+  /// Get the checkpoint of a partial file, which doesn't have to exist yet
+  pub(crate) fn new(
+    partial_file_path: &Path,
+    expected_hash: &'a str,
+  ) -> Result<Self, FilesystemError> {
+    Ok(Self {
+      path: game_files::add_hash_checkpoint_extension(partial_file_path)?,
+      expected_hash,
+    })
+  }
+
+  /// Read the saved checkpoint
+  ///
+  /// # Returns
+  ///
+  /// The MD5 state and the number of bytes hashed into it, or None if the checkpoint
+  /// doesn't exist, is for another file, or is beyond the end of the partial file
+  pub(crate) fn load(&self, partial_file_len: u64) -> Option<(Md5, u64)> {
+    let text = std::fs::read_to_string(&self.path).ok()?;
+
+    let Ok(data) = serde_json::from_str::<CheckpointData>(&text) else {
+      debug!(
+        "Ignoring an invalid hash checkpoint: \"{}\"",
+        self.path.display()
+      );
+      return None;
+    };
+
+    if !data.expected_hash.eq_ignore_ascii_case(self.expected_hash)
+      || data.offset > partial_file_len
+    {
+      debug!(
+        "Ignoring a stale hash checkpoint: \"{}\"",
+        self.path.display()
+      );
+      return None;
+    }
+
+    let state_bytes = hex::decode(&data.state).ok()?;
+    let state = SerializedState::<Md5>::try_from(state_bytes.as_slice()).ok()?;
+    let hasher = Md5::deserialize(&state).ok()?;
+
+    Some((hasher, data.offset))
+  }
+
+  /// Save the MD5 state after hashing the first `offset` bytes of the partial file
+  ///
+  /// The partial file must be synced to the disk first. Failing to save the checkpoint
+  /// isn't an error, because the file can still be hashed fully when resuming
+  pub(crate) fn save(&self, hasher: &Md5, offset: u64) {
+    let data = CheckpointData {
+      expected_hash: self.expected_hash.to_string(),
+      offset,
+      state: hex::encode(hasher.serialize()),
+    };
+
+    // Write to a temporary file first, so a crash never leaves a half written checkpoint
+    let temp_path: PathBuf = self.path.with_extension("md5state.tmp");
+    let result = serde_json::to_vec(&data)
+      .map_err(|e| e.to_string())
+      .and_then(|json| std::fs::write(&temp_path, json).map_err(|e| e.to_string()))
+      .and_then(|()| std::fs::rename(&temp_path, &self.path).map_err(|e| e.to_string()));
+
+    match result {
+      Ok(()) => trace!("Saved a hash checkpoint at byte {offset}"),
+      Err(e) => debug!(
+        "Couldn't save the hash checkpoint: \"{}\"\n{e}",
+        self.path.display()
+      ),
+    }
+  }
+
+  /// Remove the checkpoint, if it exists
+  pub(crate) fn remove(&self) -> Result<(), FilesystemError> {
+    if filesystem::exists(&self.path)? {
+      filesystem::remove_file(&self.path)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::HashCheckpoint;
+
+  use md5::{Digest, Md5};
+
+  #[test]
+  fn test_resume_hash_from_checkpoint() {
+    let folder = std::env::temp_dir().join("scratch-io-test-hash-checkpoint");
+    std::fs::create_dir_all(&folder).unwrap();
+    let partial_file = folder.join("download.zip.part");
+
+    let data = b"The quick brown fox jumps over the lazy dog";
+    let expected = hex::encode(Md5::digest(data));
+    let checkpoint = HashCheckpoint::new(&partial_file, &expected).unwrap();
+
+    // Save the state in the middle of a block, and finish hashing from there
+    let mut hasher = Md5::new();
+    hasher.update(&data[..10]);
+    checkpoint.save(&hasher, 10);
+
+    let (mut resumed, offset) = checkpoint.load(data.len() as u64).unwrap();
+    assert_eq!(offset, 10);
+    resumed.update(&data[10..]);
+    assert_eq!(hex::encode(resumed.finalize()), expected);
+
+    // A checkpoint beyond the end of the partial file, or for another file, is stale
+    assert!(checkpoint.load(5).is_none());
+    assert!(
+      HashCheckpoint::new(&partial_file, "00000000000000000000000000000000")
+        .unwrap()
+        .load(data.len() as u64)
+        .is_none()
+    );
+
+    checkpoint.remove().unwrap();
+    assert!(checkpoint.load(data.len() as u64).is_none());
+
+    std::fs::remove_dir_all(&folder).unwrap();
+  }
+}
@@ -0,0 +1,365 @@
+use crate::errors::{FilesystemError, ScratchError};
+use crate::itch_api::types::{BuildFileSubtype, BuildFileType, BuildID};
+use crate::itch_api::{ItchApiUrl, ItchClient};
+use crate::transport::{ByteRange, DownloadTransport};
+use crate::upgrade::{UpgradeStatus, download_build_file, reinstall_build};
+use crate::{
+  DownloadOptions, DownloadStatus, Fingerprint, FingerprintMode, InstalledUpload, filesystem,
+  folder_fingerprint,
+};
+
+use rc_zip_sync::{HasCursor, ReadZipWithSize};
+use reqwest::StatusCode;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use wharf::verify::{IntegrityIssues, VerifyReport};
+
+/// The progress of [`heal_upload`]
+#[derive(Debug)]
+pub enum HealStatus {
+  /// The installed files are being verified against the build signature
+  Verify { bytes_hashed: u64, total_bytes: u64 },
+  /// The broken files started being fetched from the build archive
+  Healing {
+    broken_files: usize,
+    bytes_to_fix: u64,
+  },
+  /// The number of bytes of the broken files that have been rewritten
+  HealProgress { bytes_written: u64 },
+  /// The build archive can't be read in parts, so the upload is downloaded again fully
+  FullReinstall,
+  /// The progress of the full reinstall download
+  Download(DownloadStatus),
+}
+
+/// The outcome of a successful [`heal_upload`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealResult {
+  /// All the installed files were already intact
+  Intact,
+  /// The broken files listed in the report were fetched again and rewritten
+  Healed(VerifyReport),
+  /// The files listed in the report were broken, and the upload was downloaded again fully
+  Reinstalled(VerifyReport),
+}
+
+/// A build archive stored on the itch.io servers, read with range requests
+///
+/// This allows reading single entries of the ZIP archive without downloading all of it
+struct RemoteArchive<'a> {
+  client: &'a ItchClient,
+  url: ItchApiUrl,
+}
+
+impl<'a> RemoteArchive<'a> {
+  /// Check if the server supports range requests for the archive, and get its size
+  ///
+  /// # Returns
+  ///
+  /// The archive and its size, or None if the server doesn't support range requests
+  fn open(client: &'a ItchClient, url: ItchApiUrl) -> Result<Option<(Self, u64)>, ScratchError> {
+    let probe = client
+      .get(
+        &url,
+        Some(ByteRange {
+          start: 0,
+          end: None,
+        }),
+      )
+      .map_err(ScratchError::Download)?;
+
+    if probe.status != StatusCode::PARTIAL_CONTENT {
+      debug!(
+        "The server returned code {} for a range request of the build archive",
+        probe.status.as_str()
+      );
+      return Ok(None);
+    }
+
+    let Some(size) = probe.content_length else {
+      return Ok(None);
+    };
+
+    Ok(Some((Self { client, url }, size)))
+  }
+}
+
+/// A reader of the remote archive from an offset, which only sends the request when it is first read
+struct RemoteCursor<'a> {
+  client: &'a ItchClient,
+  url: &'a ItchApiUrl,
+  offset: u64,
+  body: Option<reqwest::blocking::Response>,
+}
+
+impl Read for RemoteCursor<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let body = match &mut self.body {
+      Some(body) => body,
+      None => {
+        let res = self
+          .client
+          .get(
+            self.url,
+            Some(ByteRange {
+              start: self.offset,
+              end: None,
+            }),
+          )
+          .map_err(std::io::Error::other)?;
+
+        if res.status != StatusCode::PARTIAL_CONTENT {
+          return Err(std::io::Error::other(format!(
+            "The server didn't return HTTP code 206 for a range request!
+  It returned code: {}
+  URL: {}",
+            res.status.as_str(),
+            self.url
+          )));
+        }
+
+        self.body.insert(res.body)
+      }
+    };
+
+    let len = body.read(buf)?;
+    self.offset += len as u64;
+    Ok(len)
+  }
+}
+
+impl HasCursor for RemoteArchive<'_> {
+  type Cursor<'c>
+    = RemoteCursor<'c>
+  where
+    Self: 'c;
+
+  fn cursor_at(&self, offset: u64) -> Self::Cursor<'_> {
+    RemoteCursor {
+      client: self.client,
+      url: &self.url,
+      offset,
+      body: None,
+    }
+  }
+}
+
+/// Open a wharf signature file to read it
+fn open_signature(signature_path: &Path) -> Result<BufReader<File>, FilesystemError> {
+  let file = filesystem::open_file(signature_path, std::fs::OpenOptions::new().read(true))?;
+  Ok(BufReader::new(file))
+}
+
+/// Verify the upload folder against the build signature, reporting the progress
+fn verify_upload_folder(
+  signature: &mut wharf::Signature,
+  upload_folder: &Path,
+  options: &DownloadOptions,
+  progress_callback: &(impl Fn(HealStatus) + Sync),
+) -> Result<VerifyReport, ScratchError> {
+  signature
+    .verify_files_report(
+      upload_folder,
+      |p| {
+        progress_callback(HealStatus::Verify {
+          bytes_hashed: p.bytes_hashed,
+          total_bytes: p.total_bytes,
+        });
+      },
+      options.callback_interval,
+    )
+    .map_err(ScratchError::Wharf)
+}
+
+/// Get the indexes in the signature container of the files listed in the report
+fn report_to_integrity_issues(
+  signature: &wharf::Signature,
+  report: &VerifyReport,
+) -> IntegrityIssues {
+  let broken_paths: Vec<&PathBuf> = report
+    .mismatched_files
+    .iter()
+    .chain(&report.size_mismatches)
+    .chain(&report.missing_files)
+    .collect();
+
+  let files: Vec<usize> = signature
+    .container_new
+    .files
+    .iter()
+    .enumerate()
+    .filter(|(_, f)| broken_paths.iter().any(|p| Path::new(&f.path) == *p))
+    .map(|(index, _)| index)
+    .collect();
+
+  IntegrityIssues {
+    files: files.into_boxed_slice(),
+  }
+}
+
+/// Rewrite the broken files with the entries of the remote build archive
+///
+/// # Returns
+///
+/// False if the archive can't be read in parts, so the files weren't healed
+fn heal_from_archive(
+  client: &ItchClient,
+  build_id: BuildID,
+  signature: &wharf::Signature,
+  issues: &IntegrityIssues,
+  upload_folder: &Path,
+  progress_callback: &(impl Fn(HealStatus) + Sync),
+) -> Result<bool, ScratchError> {
+  let url = ItchApiUrl::v2(&format!("builds/{build_id}/download/archive/default"));
+
+  let Some((archive, size)) = RemoteArchive::open(client, url)? else {
+    return Ok(false);
+  };
+
+  // Only the central directory at the end of the archive is read here
+  let archive_handle = match archive.read_zip_with_size(size) {
+    Ok(handle) => handle,
+    Err(e) => {
+      debug!("Couldn't read the build archive as a ZIP file: {e}");
+      return Ok(false);
+    }
+  };
+
+  progress_callback(HealStatus::Healing {
+    broken_files: issues.files.len(),
+    bytes_to_fix: issues.bytes_to_fix(&signature.container_new),
+  });
+
+  let mut bytes_written: u64 = 0;
+  signature
+    .repair(issues, upload_folder, &archive_handle, |b| {
+      bytes_written += b;
+      progress_callback(HealStatus::HealProgress { bytes_written });
+    })
+    .map_err(|e| ScratchError::Wharf(e.to_string()))?;
+
+  Ok(true)
+}
+
+/// Heal an installed upload, fetching again only the files that don't match the build signature
+///
+/// The build signature is downloaded and the installed files are verified against it.
+/// Every broken or missing file is then read from the build archive on the server with range
+/// requests, so only the affected files are downloaded, and the files are verified again.
+/// If the server doesn't allow reading the archive in parts, the upload is downloaded again
+/// fully instead, which is only possible if the installed build is the latest build of the upload
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_upload` - The installed upload to heal. It must be a wharf build with a known build ID
+///
+/// * `options` - The download options of the build files and of the full reinstall
+///
+/// * `progress_callback` - A closure which reports the verification and healing progress
+///
+/// # Returns
+///
+/// If the files were intact, healed or downloaded again, and which ones were broken
+///
+/// # Errors
+///
+/// If the installed build is unknown, something goes wrong while downloading or rewriting
+/// the files, or the files are still broken after healing them
+pub fn heal_upload(
+  client: &ItchClient,
+  installed_upload: &mut InstalledUpload,
+  options: &DownloadOptions,
+  progress_callback: impl Fn(HealStatus) + Sync,
+) -> Result<HealResult, ScratchError> {
+  let Some(build_id) = installed_upload.installed_build_id else {
+    return Err(ScratchError::InvalidInput(format!(
+      "The installed build of the upload is unknown, so it can't be verified against its signature!
+  Upload ID: {}",
+      installed_upload.upload_id
+    )));
+  };
+
+  let upload_id = installed_upload.upload_id;
+  let upload_folder: PathBuf = installed_upload.upload_folder();
+  let signature_path: PathBuf = installed_upload
+    .game_folder
+    .join(format!("{upload_id}-{build_id}-signature.pws"));
+
+  download_build_file(
+    client,
+    build_id,
+    BuildFileType::Signature,
+    BuildFileSubtype::Default,
+    &signature_path,
+    None,
+    options,
+  )?;
+
+  let result = (|| {
+    let mut signature_reader = open_signature(&signature_path)?;
+    let mut signature =
+      wharf::Signature::read(&mut signature_reader).map_err(ScratchError::Wharf)?;
+    let report = verify_upload_folder(&mut signature, &upload_folder, options, &progress_callback)?;
+
+    if report.are_files_intact() {
+      return Ok(HealResult::Intact);
+    }
+
+    debug!(
+      "{} files of the upload {upload_id} are broken",
+      report.broken_file_count()
+    );
+
+    if options.cancel.is_cancelled() {
+      return Err(ScratchError::Cancelled);
+    }
+
+    let issues: IntegrityIssues = report_to_integrity_issues(&signature, &report);
+
+    let healed = heal_from_archive(
+      client,
+      build_id,
+      &signature,
+      &issues,
+      &upload_folder,
+      &progress_callback,
+    )?;
+
+    if !healed {
+      progress_callback(HealStatus::FullReinstall);
+      reinstall_build(client, installed_upload, build_id, options, &|status| {
+        if let UpgradeStatus::Download(status) = status {
+          progress_callback(HealStatus::Download(status));
+        }
+      })?;
+      return Ok(HealResult::Reinstalled(report));
+    }
+
+    // The signature can only be read once, so read it again to verify the healed files
+    let mut signature_reader = open_signature(&signature_path)?;
+    let mut signature =
+      wharf::Signature::read(&mut signature_reader).map_err(ScratchError::Wharf)?;
+    let new_report =
+      verify_upload_folder(&mut signature, &upload_folder, options, &progress_callback)?;
+    if !new_report.are_files_intact() {
+      return Err(ScratchError::Verification(format!(
+        "The healed files don't match the build signature! {} files are still broken",
+        new_report.broken_file_count()
+      )));
+    }
+
+    // The files have changed, so fingerprint them again
+    let fingerprint: Fingerprint = folder_fingerprint(&upload_folder, FingerprintMode::Fast)?;
+    installed_upload.fingerprint = Some(fingerprint);
+
+    Ok(HealResult::Healed(report))
+  })();
+
+  // The signature isn't needed anymore
+  filesystem::remove_file(&signature_path)?;
+
+  result
+}
@@ -1,4 +1,5 @@
-use crate::{GamePlatform, errors::FilesystemError, filesystem};
+use crate::pe::{self, PeInfo, PeSubsystem};
+use crate::{GamePlatform, errors::FilesystemError, filesystem, game_files};
 use std::path::{Path, PathBuf};
 
 const GOOD_LAUNCH_FILENAMES: &[&str] = &[
@@ -18,6 +19,50 @@ const ARCHITECTURE_SUFFIXES: &[&str] = {
 const BEST_PROXIMITY_MULTIPLIER: f64 = 0.34;
 // If the level is 3 or more, stop searching the executable
 const MAX_DIRECTORY_LEVEL_DEPTH: usize = 2;
+const DEFAULT_IGNORED_SUBSTRINGS: &[&str] = &["unins", "setup", "crash"];
+// Windows executables whose name contains any of these are likely installers of the game or its dependencies
+const INSTALLER_SUBSTRINGS: &[&str] = &[
+  "install", "redist", "vcredist", "dxsetup", "directx", "dotnet", "prereq",
+];
+
+/// A file of an upload folder rated as the game executable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedExecutable {
+  /// The path of the file
+  pub path: PathBuf,
+  /// The rating of the file, the higher the better
+  pub rating: i64,
+  /// The subsystem read from the PE headers, if the file is a Windows executable
+  pub subsystem: Option<PeSubsystem>,
+}
+
+/// Options to influence which file the heuristics pick as the game executable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeuristicsConfig {
+  /// Files whose name contains any of these patterns are strongly preferred
+  ///
+  /// Only the ASCII alphanumeric characters are compared, ignoring case
+  pub preferred_names: Vec<String>,
+  /// Files whose name contains any of these substrings are strongly penalized, like uninstallers or crash handlers
+  ///
+  /// Only the ASCII alphanumeric characters are compared, ignoring case
+  pub ignored_substrings: Vec<String>,
+  /// The maximum number of directory levels below the upload folder where files are searched
+  pub max_depth: usize,
+}
+
+impl Default for HeuristicsConfig {
+  fn default() -> Self {
+    Self {
+      preferred_names: Vec::new(),
+      ignored_substrings: DEFAULT_IGNORED_SUBSTRINGS
+        .iter()
+        .map(|s| (*s).to_string())
+        .collect(),
+      max_depth: MAX_DIRECTORY_LEVEL_DEPTH,
+    }
+  }
+}
 
 impl GamePlatform {
   const fn get_best_filenames(self) -> &'static [&'static str] {
@@ -43,7 +88,9 @@ impl GamePlatform {
 ///
 /// * `platform` - The platform the game executable will be run on
 ///
-/// * `game_info` - Information about the game
+/// * `game_title` - The title of the game
+///
+/// * `config` - Options to influence the heuristics
 ///
 /// # Returns
 ///
@@ -54,7 +101,44 @@ pub fn get_game_executable(
   upload_folder: &Path,
   platform: GamePlatform,
   game_title: String,
+  config: &HeuristicsConfig,
 ) -> Result<PathBuf, String> {
+  rank_executables(upload_folder, platform, game_title, config)?
+    .into_iter()
+    .next()
+    .map(|executable| executable.path)
+    .ok_or_else(|| {
+      format!(
+        "Couldn't find any game file executable in: \"{}\"",
+        upload_folder.display()
+      )
+    })
+}
+
+/// Rate every file of an upload folder as the game executable
+///
+/// # Arguments
+///
+/// * `upload_folder` - The folder where the search will be done
+///
+/// * `platform` - The platform the game executable will be run on
+///
+/// * `game_title` - The title of the game
+///
+/// * `config` - Options to influence the heuristics
+///
+/// # Returns
+///
+/// The files and their ratings, sorted from the best to the worst candidate.
+/// DLLs are skipped when searching a Windows executable
+///
+/// An error if something goes wrong
+pub fn rank_executables(
+  upload_folder: &Path,
+  platform: GamePlatform,
+  game_title: String,
+  config: &HeuristicsConfig,
+) -> Result<Vec<RankedExecutable>, String> {
   // If the folder is not a directory, return
   filesystem::ensure_is_dir(upload_folder)?;
 
@@ -62,8 +146,26 @@ pub fn get_game_executable(
   // to compare it with other alphanumeric lowercase strings
   let game_title = make_alphanumeric_lowercase(game_title);
 
-  // This variable will store the best executable found at the moment and its rating
-  let mut best_executable: (Option<PathBuf>, i64) = (None, i64::MIN);
+  // The config patterns are compared the same way
+  let config = HeuristicsConfig {
+    preferred_names: config
+      .preferred_names
+      .iter()
+      .cloned()
+      .map(make_alphanumeric_lowercase)
+      .filter(|n| !n.is_empty())
+      .collect(),
+    ignored_substrings: config
+      .ignored_substrings
+      .iter()
+      .cloned()
+      .map(make_alphanumeric_lowercase)
+      .filter(|n| !n.is_empty())
+      .collect(),
+    max_depth: config.max_depth,
+  };
+
+  let mut executables: Vec<RankedExecutable> = Vec::new();
 
   // We will add the folders and their depth to this VecDeque
   let mut queue: std::collections::VecDeque<(PathBuf, usize)> = std::collections::VecDeque::new();
@@ -75,28 +177,63 @@ pub fn get_game_executable(
     while let Some(entry) = filesystem::next_entry(&mut entries, &folder)? {
       let entry_path = entry.path();
 
-      if filesystem::file_type(&entry, &folder)?.is_dir() {
+      // On macOS, application bundles are folders which are launched as a single program
+      let is_app_bundle: bool =
+        platform == GamePlatform::OSX && game_files::is_app_bundle(&entry_path);
+
+      if filesystem::file_type(&entry, &folder)?.is_dir() && !is_app_bundle {
         // If we are on the last depth, don't go to the next one, stop now
         // For this reason it is < and not <=
-        if depth < MAX_DIRECTORY_LEVEL_DEPTH {
+        if depth < config.max_depth {
           queue.push_back((entry_path, depth + 1));
         }
       } else {
-        let rating = rate_executable(&entry_path, depth, platform, &game_title)?;
-        if rating > best_executable.1 {
-          best_executable = (Some(entry_path), rating);
+        let pe_info: Option<PeInfo> = read_windows_pe_info(&entry_path, platform);
+
+        // Libraries can't be launched, so they aren't candidates
+        if pe_info.is_some_and(|i| i.is_dll) {
+          continue;
         }
+
+        let subsystem: Option<PeSubsystem> = pe_info.map(|i| i.subsystem);
+        let rating = rate_executable(
+          &entry_path,
+          depth,
+          platform,
+          subsystem,
+          &game_title,
+          &config,
+        )?;
+        executables.push(RankedExecutable {
+          path: entry_path,
+          rating,
+          subsystem,
+        });
       }
     }
   }
 
-  if let Some(executable) = best_executable.0 {
-    Ok(executable)
-  } else {
-    Err(format!(
-      "Couldn't find any game file executable in: \"{}\"",
-      upload_folder.display()
-    ))
+  // The sort is stable, so files with the same rating keep the search order
+  executables.sort_by_key(|e| std::cmp::Reverse(e.rating));
+
+  Ok(executables)
+}
+
+/// Read the PE headers of a file if the game executable is searched for Windows
+///
+/// Only the files with an `exe` or `dll` extension are read, to avoid opening every game asset
+fn read_windows_pe_info(file_path: &Path, platform: GamePlatform) -> Option<PeInfo> {
+  if platform != GamePlatform::Windows {
+    return None;
+  }
+
+  let extension = filesystem::get_file_extension(file_path)
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+
+  match extension.as_str() {
+    "exe" | "dll" => pe::read_pe_info(file_path),
+    _ => None,
   }
 }
 
@@ -110,8 +247,12 @@ pub fn get_game_executable(
 ///
 /// * `platform` - The platform the game executable will be run on
 ///
+/// * `subsystem` - The subsystem read from the PE headers, if the file is a Windows executable
+///
 /// * `game_title` - Information about the game
 ///
+/// * `config` - Options to influence the heuristics, with its patterns already made alphanumeric lowercase
+///
 /// # Returns
 ///
 /// The rating
@@ -121,16 +262,19 @@ fn rate_executable(
   file_path: &Path,
   directory_levels: usize,
   platform: GamePlatform,
+  subsystem: Option<PeSubsystem>,
   game_title: &str,
+  config: &HeuristicsConfig,
 ) -> Result<i64, FilesystemError> {
   let mut rating: i64 = 0;
 
   // base level: keep the rating
   // 1st level: lower it by 1000
   // 2nd level: lower it by 4000
-  // saturating_pow so it doesn't overflow
-  assert!(directory_levels <= MAX_DIRECTORY_LEVEL_DEPTH);
-  rating -= (directory_levels as i64).saturating_pow(2) * 1000;
+  // saturating_pow and saturating_mul so it doesn't overflow
+  rating -= (directory_levels as i64)
+    .saturating_pow(2)
+    .saturating_mul(1000);
 
   // Most of the checks will be based on the filename
   let filename: String =
@@ -170,6 +314,38 @@ fn rate_executable(
     rating += proximity_rating(n, &filename, 1, 1200, 500, BEST_PROXIMITY_MULTIPLIER);
   }
 
+  // If the user prefers this filename, raise the rating above any other heuristic
+  if config
+    .preferred_names
+    .iter()
+    .any(|n| filename.contains(n.as_str()))
+  {
+    rating += 5000;
+  }
+
+  // If the filename looks like an uninstaller or a crash handler, lower the rating a lot,
+  // but still less than a wrong extension, so it is picked if there isn't anything better
+  if config
+    .ignored_substrings
+    .iter()
+    .any(|s| filename.contains(s.as_str()))
+  {
+    rating -= 5000;
+  }
+
+  // Games are usually GUI applications, while console executables are often tools or servers
+  rating += match subsystem {
+    Some(PeSubsystem::Gui) => 600,
+    Some(PeSubsystem::Console) | None => 0,
+    Some(PeSubsystem::Other(_)) => -3000,
+  };
+
+  // Installers of the game or its dependencies (e.g: vcredist_x64.exe) aren't the game
+  if platform == GamePlatform::Windows && INSTALLER_SUBSTRINGS.iter().any(|s| filename.contains(s))
+  {
+    rating -= 3000;
+  }
+
   Ok(rating)
 }
 
@@ -0,0 +1,270 @@
+use crate::errors::ScratchError;
+use crate::itch_api::types::{BuildID, Game, Upload, UploadID, UploadStorage};
+use crate::itch_api::{ItchApiUrl, ItchClient};
+use crate::{CancellationToken, DownloadOptions, DownloadStatus, InstalledUpload, filesystem};
+
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The options of [`install_upload`]
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+  /// The folder where the game files will be placed
+  pub game_folder: PathBuf,
+  /// The download options
+  ///
+  /// The cancellation token passed to [`install_upload`] is used instead of the one in these options
+  pub download: DownloadOptions,
+  /// If true and the upload is a wharf build, verify the installed files against the build signature
+  pub verify_signature: bool,
+}
+
+impl InstallOptions {
+  /// Create the default options to install an upload into `game_folder`
+  #[must_use]
+  pub fn new(game_folder: PathBuf) -> Self {
+    Self {
+      game_folder,
+      download: DownloadOptions::default(),
+      verify_signature: true,
+    }
+  }
+}
+
+/// The progress of [`install_upload`]
+pub enum InstallStatus {
+  /// The upload and the game info, sent before the download starts
+  UploadInfo {
+    upload: Box<Upload>,
+    game: Box<Game>,
+  },
+  /// The download and extraction progress
+  Download(DownloadStatus),
+  /// The installed files are being verified against the build signature
+  Verify { bytes_hashed: u64, total_bytes: u64 },
+}
+
+/// A handle to an installation running in another thread
+pub struct InstallHandle {
+  cancel: CancellationToken,
+  thread: std::thread::JoinHandle<Result<InstalledUpload, ScratchError>>,
+}
+
+impl InstallHandle {
+  /// Request the installation to stop as soon as possible
+  ///
+  /// The partially downloaded data is kept, so the installation can be resumed later
+  pub fn cancel(&self) {
+    self.cancel.cancel();
+  }
+
+  /// Check if the installation has finished, either successfully or not
+  #[must_use]
+  pub fn is_finished(&self) -> bool {
+    self.thread.is_finished()
+  }
+
+  /// Wait for the installation to finish and get its result
+  ///
+  /// # Errors
+  ///
+  /// If the installation failed, was cancelled, or its thread panicked
+  pub fn join(self) -> Result<InstalledUpload, ScratchError> {
+    self
+      .thread
+      .join()
+      .map_err(|_| ScratchError::Other("The installation thread panicked!".to_string()))?
+  }
+}
+
+/// Return an error if the installation has been cancelled
+fn check_cancelled(cancel: &CancellationToken) -> Result<(), ScratchError> {
+  if cancel.is_cancelled() {
+    Err(ScratchError::Cancelled)
+  } else {
+    Ok(())
+  }
+}
+
+/// Download the signature of a build and verify the files of the installed upload against it
+fn verify_build_signature(
+  client: &ItchClient,
+  installed_upload: &InstalledUpload,
+  build_id: BuildID,
+  progress_callback: &(impl Fn(InstallStatus) + Sync),
+  options: &InstallOptions,
+  cancel: &CancellationToken,
+) -> Result<(), ScratchError> {
+  let upload_id: UploadID = installed_upload.upload_id;
+  let upload_folder: PathBuf = installed_upload.upload_folder();
+  let signature_path: PathBuf = installed_upload
+    .game_folder
+    .join(format!("{upload_id}-signature.pws"));
+
+  crate::download_file(
+    client,
+    &ItchApiUrl::v2(&format!("builds/{build_id}/download/signature/default")),
+    &[],
+    &signature_path,
+    None,
+    |_| (),
+    |_| (),
+    Duration::MAX,
+    cancel,
+    NonZeroUsize::MIN,
+    options.download.max_bytes_per_sec,
+    options.download.min_speed,
+  )?;
+
+  let result = (|| {
+    let file = filesystem::open_file(&signature_path, std::fs::OpenOptions::new().read(true))?;
+    let mut reader = std::io::BufReader::new(file);
+    let mut signature = wharf::Signature::read(&mut reader).map_err(ScratchError::Wharf)?;
+
+    signature
+      .verify_files_with_progress(
+        &upload_folder,
+        |p| {
+          progress_callback(InstallStatus::Verify {
+            bytes_hashed: p.bytes_hashed,
+            total_bytes: p.total_bytes,
+          });
+        },
+        options.download.callback_interval,
+      )
+      .map_err(ScratchError::Wharf)
+  })();
+
+  // The signature isn't needed anymore
+  filesystem::remove_file(&signature_path)?;
+
+  let issues = result?;
+  if !issues.are_files_intact() {
+    return Err(ScratchError::Verification(format!(
+      "The installed files don't match the build signature! {} files are broken",
+      issues.files.len()
+    )));
+  }
+
+  Ok(())
+}
+
+/// Install an upload: download it, extract it and verify the installed files
+///
+/// This composes [`crate::download_upload_with_options`] and the build signature verification into
+/// a single operation, which reports its progress with [`InstallStatus`] events and
+/// can be stopped at any point with `cancel`. A cancelled installation can be resumed
+/// by calling this function again with the same options
+///
+/// The returned [`InstalledUpload`] should be stored in the caller's installed uploads
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be installed
+///
+/// * `options` - The installation options
+///
+/// * `progress_callback` - A closure which reports the installation progress
+///
+/// * `cancel` - A token to stop the installation
+///
+/// # Returns
+///
+/// The installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong, or the installation is cancelled
+pub fn install_upload(
+  client: &ItchClient,
+  upload_id: UploadID,
+  options: &InstallOptions,
+  progress_callback: impl Fn(InstallStatus) + Sync,
+  cancel: &CancellationToken,
+) -> Result<InstalledUpload, ScratchError> {
+  check_cancelled(cancel)?;
+
+  // --- DOWNLOAD AND EXTRACTION ---
+
+  let mut build_id: Option<BuildID> = None;
+
+  let installed_upload = crate::download_upload_with_options(
+    client,
+    upload_id,
+    &options.game_folder,
+    &DownloadOptions {
+      cancel: cancel.clone(),
+      ..options.download.clone()
+    },
+    |upload, game| {
+      if let UploadStorage::Build { build_id: id, .. } = upload.storage {
+        build_id = Some(id);
+      }
+
+      progress_callback(InstallStatus::UploadInfo {
+        upload: Box::new(upload.clone()),
+        game: Box::new(game.clone()),
+      });
+    },
+    |status| progress_callback(InstallStatus::Download(status)),
+  )?;
+
+  check_cancelled(cancel)?;
+
+  // --- VERIFICATION ---
+
+  // Only wharf builds have a signature to verify the files against
+  if options.verify_signature
+    && let Some(build_id) = build_id
+  {
+    verify_build_signature(
+      client,
+      &installed_upload,
+      build_id,
+      &progress_callback,
+      options,
+      cancel,
+    )?;
+  }
+
+  Ok(installed_upload)
+}
+
+/// Run [`install_upload`] in a background thread
+///
+/// The returned [`InstallHandle`] can be used to cancel the installation or wait for it
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be installed
+///
+/// * `options` - The installation options
+///
+/// * `progress_callback` - A closure which reports the installation progress, called from the background thread
+#[must_use]
+pub fn spawn_install_upload(
+  client: ItchClient,
+  upload_id: UploadID,
+  options: InstallOptions,
+  progress_callback: impl Fn(InstallStatus) + Send + Sync + 'static,
+) -> InstallHandle {
+  let cancel = CancellationToken::new();
+  let thread_cancel = cancel.clone();
+
+  let thread = std::thread::spawn(move || {
+    install_upload(
+      &client,
+      upload_id,
+      &options,
+      progress_callback,
+      &thread_cancel,
+    )
+  });
+
+  InstallHandle { cancel, thread }
+}
@@ -3,7 +3,18 @@ pub mod errors;
 pub mod oauth;
 pub mod types;
 
+mod cache;
+mod client_config;
+mod rate_limit;
 mod responses;
+mod retry;
+
+pub use cache::DEFAULT_CACHE_TTL;
+pub use client_config::{ClientConfig, DEFAULT_USER_AGENT};
+pub use retry::RetryPolicy;
+
+use cache::ApiCache;
+use rate_limit::RateLimiter;
 
 use errors::{ItchRequestJSONError, ItchRequestJSONErrorKind};
 use responses::{ApiResponse, IntoResponseResult};
@@ -14,9 +25,29 @@ use reqwest::{
   header,
 };
 
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
 pub const ITCH_API_V1_BASE_URL: &str = "https://itch.io/api/1/";
 pub const ITCH_API_V2_BASE_URL: &str = "https://api.itch.io/";
 
+/// The domains owned by itch.io, the only ones which receive the API key
+///
+/// Their subdomains, like `api.itch.io`, are also included
+const ITCH_DOMAINS: [&str; 2] = ["itch.io", "itch.zone"];
+
+/// The default maximum time an API call can take, see [`ItchClient::with_request_timeout`]
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The delay reported in [`ItchRequestJSONErrorKind::RateLimited`] if the server didn't specify one
+pub const DEFAULT_RATE_LIMIT_DELAY: Duration = Duration::from_secs(60);
+
+/// The longest delay requested by the server that is waited before retrying a request
+///
+/// If the server asks to wait longer, the request isn't retried
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+
 /// An itch.io API version
 ///
 /// Its possible values are:
@@ -73,6 +104,66 @@ impl ItchApiUrl {
     }
   }
 
+  /// Creates the same [`ItchApiUrl`] on another base URL, like a mirror of the itch.io API
+  ///
+  /// The endpoint and the API version are kept, so the request is authenticated in the same way.
+  /// That's why the base URL must be an HTTPS address of a domain owned by itch.io
+  ///
+  /// # Returns
+  ///
+  /// None if this isn't an address of a known API version, or if the base URL isn't owned by itch.io
+  #[must_use]
+  pub fn with_base(&self, base_url: &str) -> Option<Self> {
+    if !is_itch_owned(base_url) {
+      return None;
+    }
+
+    let endpoint: &str = match self.version {
+      ItchApiVersion::V1 => self.url.strip_prefix(ITCH_API_V1_BASE_URL)?,
+      ItchApiVersion::V2 => self.url.strip_prefix(ITCH_API_V2_BASE_URL)?,
+      ItchApiVersion::Other => return None,
+    };
+
+    Some(Self {
+      version: self.version,
+      url: format!("{}/{endpoint}", base_url.trim_end_matches('/')),
+    })
+  }
+
+  /// Get the URL with its secrets hidden, so it can be written to the logs
+  ///
+  /// The UUID of a download key and the values of the query parameters, other than the page,
+  /// are replaced. The API key is never part of the URL, it is sent in a header
+  #[must_use]
+  pub(crate) fn redacted(&self) -> String {
+    let (path, query) = match self.url.split_once('?') {
+      Some((path, query)) => (path, Some(query)),
+      None => (self.url.as_str(), None),
+    };
+
+    let mut redacted: String = match path.split_once("/download-key/") {
+      Some((start, rest)) => match rest.split_once('/') {
+        Some((_, rest)) => format!("{start}/download-key/<redacted>/{rest}"),
+        None => format!("{start}/download-key/<redacted>"),
+      },
+      None => path.to_string(),
+    };
+
+    if let Some(query) = query {
+      let pairs: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+          Some((key, _)) if key != "page" => format!("{key}=<redacted>"),
+          _ => pair.to_string(),
+        })
+        .collect();
+      redacted.push('?');
+      redacted.push_str(&pairs.join("&"));
+    }
+
+    redacted
+  }
+
   /// Returns the API version of this [`ItchApiUrl`]
   #[must_use]
   pub const fn version(&self) -> ItchApiVersion {
@@ -96,10 +187,34 @@ impl std::fmt::Display for ItchApiUrl {
 }
 
 /// A client able to send requests to the itch.io API
-#[derive(Debug, Clone)]
+///
+/// The game, upload and user info responses are cached in memory for [`DEFAULT_CACHE_TTL`]
+/// by default, see [`ItchClient::with_cache_ttl`]. Cloned clients share the same cache
+///
+/// When the server replies with HTTP 429 (too many requests), all the clones of a client
+/// wait before sending any other request, see [`ItchClient::with_rate_limit`]
+#[derive(Clone)]
 pub struct ItchClient {
   client: Client,
   api_key: String,
+  retry_policy: RetryPolicy,
+  request_timeout: Option<Duration>,
+  cache: Option<Arc<ApiCache>>,
+  rate_limiter: Arc<RateLimiter>,
+}
+
+/// The API key is never printed, so the client can be part of any error or log
+impl std::fmt::Debug for ItchClient {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ItchClient")
+      .field("client", &self.client)
+      .field("api_key", &"<redacted>")
+      .field("retry_policy", &self.retry_policy)
+      .field("request_timeout", &self.request_timeout)
+      .field("cache", &self.cache)
+      .field("rate_limiter", &self.rate_limiter)
+      .finish()
+  }
 }
 
 /// This block defiles the [`ItchClient`] API calls
@@ -127,11 +242,15 @@ impl ItchClient {
     method: Method,
     options: impl FnOnce(RequestBuilder) -> RequestBuilder,
   ) -> Result<Response, reqwest::Error> {
+    enter_span!("itch_request", method = method, url = url.redacted());
+
     // Create the base request
-    let mut request: RequestBuilder = self.client.request(method, url.as_str());
+    let mut request: RequestBuilder = self.client.request(method.clone(), url.as_str());
 
     // Add authentication based on the API's version.
+    // The API key is never sent outside itch.io, even if the URL has a known API version
     request = match url.version() {
+      ItchApiVersion::V1 | ItchApiVersion::V2 if !is_itch_owned(url.as_str()) => request,
       // https://itchapi.ryhn.link/API/V1/index.html#authentication
       ItchApiVersion::V1 => request.bearer_auth(&self.api_key),
       // https://itchapi.ryhn.link/API/V2/index.html#authentication
@@ -151,7 +270,52 @@ impl ItchClient {
     // it needs to be able to modify anything
     request = options(request);
 
-    request.send()
+    let mut attempt: u32 = 1;
+    loop {
+      // Requests with a streamed body can't be cloned, so they are only sent once
+      let Some(retry_request) = request.try_clone() else {
+        self.rate_limiter.wait();
+        debug!("Sending {method} request");
+        return request.send();
+      };
+
+      self.rate_limiter.wait();
+      debug!("Sending {method} request (attempt {attempt})");
+
+      let result = request.send();
+
+      // Find out if the request failed with a transient error, and how much to wait
+      let delay: Option<Duration> = match &result {
+        Err(e) if retry::is_retryable_error(e) => Some(self.retry_policy.delay(attempt)),
+        // Too many requests were sent, so every request has to slow down, not only this one
+        Ok(res) if retry::is_rate_limited(res) => {
+          let delay = retry::retry_after(res).unwrap_or_else(|| self.retry_policy.delay(attempt));
+          debug!("The server rate limited the requests for {delay:?}");
+          self.rate_limiter.pause(delay);
+          Some(delay)
+        }
+        // If the server asked to wait for some time, respect it
+        Ok(res) if retry::is_retryable_response(res) => {
+          Some(retry::retry_after(res).unwrap_or_else(|| self.retry_policy.delay(attempt)))
+        }
+        _ => None,
+      };
+
+      match delay {
+        Some(delay) if self.retry_policy.should_retry(attempt) && delay <= MAX_RETRY_DELAY => {
+          debug!("The request failed, retrying in {delay:?}");
+          std::thread::sleep(delay);
+          attempt += 1;
+          request = retry_request;
+        }
+        _ => {
+          if let Ok(res) = &result {
+            debug!("The server replied with {}", res.status());
+          }
+          return result;
+        }
+      }
+    }
   }
 
   /// Make a request to the itch.io API and parse the response as JSON
@@ -180,18 +344,47 @@ impl ItchClient {
   where
     T: serde::de::DeserializeOwned + IntoResponseResult,
   {
-    // Get the response text
-    let text = self
-      .itch_request(url, method, options)
-      .map_err(|e| ItchRequestJSONError {
-        url: url.to_string(),
-        kind: ItchRequestJSONErrorKind::CouldntSend(e),
-      })?
-      .text()
-      .map_err(|e| ItchRequestJSONError {
+    // The API calls have their own timeout, unlike the downloads, which can take much longer
+    let request_timeout: Option<Duration> = self.request_timeout;
+    let options = |b: RequestBuilder| {
+      let b = options(b);
+      match request_timeout {
+        Some(timeout) => b.timeout(timeout),
+        None => b,
+      }
+    };
+
+    let res: Response =
+      self
+        .itch_request(url, method, options)
+        .map_err(|e| ItchRequestJSONError {
+          url: url.to_string(),
+          kind: if e.is_timeout() {
+            ItchRequestJSONErrorKind::Timeout(e)
+          } else {
+            ItchRequestJSONErrorKind::CouldntSend(e)
+          },
+        })?;
+
+    // The body of a rate limited response isn't JSON, so don't try to parse it
+    if retry::is_rate_limited(&res) {
+      return Err(ItchRequestJSONError {
         url: url.to_string(),
-        kind: ItchRequestJSONErrorKind::CouldntGetText(e),
-      })?;
+        kind: ItchRequestJSONErrorKind::RateLimited {
+          retry_after: retry::retry_after(&res).unwrap_or(DEFAULT_RATE_LIMIT_DELAY),
+        },
+      });
+    }
+
+    // Get the response text
+    let text = res.text().map_err(|e| ItchRequestJSONError {
+      url: url.to_string(),
+      kind: if e.is_timeout() {
+        ItchRequestJSONErrorKind::Timeout(e)
+      } else {
+        ItchRequestJSONErrorKind::CouldntGetText(e)
+      },
+    })?;
 
     // Parse the response into JSON
     serde_json::from_str::<ApiResponse<T>>(&text)
@@ -218,8 +411,16 @@ impl ItchClient {
   /// An [`ItchClient`] struct with an empty API key
   pub fn unauthenticated() -> Self {
     Self {
-      client: Client::new(),
+      // The default config doesn't have a proxy, so building the client only fails if the TLS
+      // backend can't be initialized. In that case, fall back to the reqwest defaults
+      client: ClientConfig::default()
+        .build_client()
+        .unwrap_or_else(|_| Client::new()),
       api_key: String::new(),
+      retry_policy: RetryPolicy::default(),
+      request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+      cache: Some(Arc::new(ApiCache::new(DEFAULT_CACHE_TTL))),
+      rate_limiter: Arc::new(RateLimiter::new(None)),
     }
   }
 
@@ -240,9 +441,193 @@ impl ItchClient {
     }
   }
 
+  /// Create a new client using the provided itch.io API key and HTTP client configuration
+  ///
+  /// # Arguments
+  ///
+  /// * `api_key` - A valid itch.io API key to store in the client
+  ///
+  /// * `config` - How the HTTP client connects to the servers, like its proxy or timeouts
+  ///
+  /// # Returns
+  ///
+  /// An [`ItchClient`] struct with the given key
+  ///
+  /// # Errors
+  ///
+  /// If the HTTP client couldn't be built, like when the proxy URL is invalid
+  pub fn with_client_config(api_key: String, config: ClientConfig) -> Result<Self, reqwest::Error> {
+    Ok(Self::new(api_key).with_http_client(config.build_client()?))
+  }
+
+  /// Use a pre-built reqwest client to send the requests
+  ///
+  /// # Arguments
+  ///
+  /// * `client` - The reqwest client
+  ///
+  /// # Returns
+  ///
+  /// The modified [`ItchClient`]
+  #[must_use]
+  pub fn with_http_client(mut self, client: Client) -> Self {
+    self.client = client;
+    self
+  }
+
   /// Obtain the API key associated with this [`ItchClient`]
   #[must_use]
   pub fn api_key(&self) -> &str {
     &self.api_key
   }
+
+  /// Set how failed requests are retried
+  ///
+  /// By default, requests are never retried
+  ///
+  /// # Arguments
+  ///
+  /// * `retry_policy` - The policy used to retry the requests that fail because of transient errors
+  ///
+  /// # Returns
+  ///
+  /// The [`ItchClient`] with the new retry policy
+  #[must_use]
+  pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+    self.retry_policy = retry_policy;
+    self
+  }
+
+  /// Obtain the retry policy of this [`ItchClient`]
+  #[must_use]
+  pub fn retry_policy(&self) -> &RetryPolicy {
+    &self.retry_policy
+  }
+
+  /// Limit the number of requests sent per second by this client and its clones
+  ///
+  /// This avoids being rate limited by the server when making a lot of API calls, like when
+  /// checking the updates of every installed game. By default, the requests aren't paced
+  ///
+  /// # Arguments
+  ///
+  /// * `requests_per_sec` - The maximum number of requests per second, or None to not limit them
+  ///
+  /// # Returns
+  ///
+  /// The modified [`ItchClient`]
+  #[must_use]
+  pub fn with_rate_limit(mut self, requests_per_sec: Option<NonZeroU32>) -> Self {
+    self.rate_limiter = Arc::new(RateLimiter::new(requests_per_sec));
+    self
+  }
+
+  /// Set the maximum time an API call can take, [`DEFAULT_REQUEST_TIMEOUT`] by default
+  ///
+  /// This timeout isn't applied to the file downloads, which are only limited by [`ClientConfig::timeout`]
+  ///
+  /// # Arguments
+  ///
+  /// * `timeout` - The maximum time, or None to wait indefinitely
+  ///
+  /// # Returns
+  ///
+  /// The modified [`ItchClient`]
+  #[must_use]
+  pub fn with_request_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.request_timeout = timeout;
+    self
+  }
+
+  /// Set how long the game, upload and user info responses are cached
+  ///
+  /// The cached uploads include their hashes, so a short time avoids verifying
+  /// a download against an outdated hash. By default, it is [`DEFAULT_CACHE_TTL`]
+  ///
+  /// # Arguments
+  ///
+  /// * `ttl` - The time a response is cached, or None to disable the cache
+  ///
+  /// # Returns
+  ///
+  /// The [`ItchClient`] with a new empty cache
+  #[must_use]
+  pub fn with_cache_ttl(mut self, ttl: Option<Duration>) -> Self {
+    self.cache = ttl.map(|ttl| Arc::new(ApiCache::new(ttl)));
+    self
+  }
+
+  /// Remove all the cached responses, so the next requests get fresh data
+  pub fn clear_cache(&self) {
+    if let Some(cache) = &self.cache {
+      cache.clear();
+    }
+  }
+
+  /// Obtain the response cache of this [`ItchClient`], if it is enabled
+  pub(crate) fn cache(&self) -> Option<&ApiCache> {
+    self.cache.as_deref()
+  }
+}
+
+/// Check if a URL is an HTTPS address of a domain owned by itch.io, see [`ITCH_DOMAINS`]
+fn is_itch_owned(url: &str) -> bool {
+  let Ok(url) = reqwest::Url::parse(url) else {
+    return false;
+  };
+
+  url.scheme() == "https"
+    && url.host_str().is_some_and(|host| {
+      ITCH_DOMAINS.iter().any(|domain| {
+        host == *domain
+          || host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+      })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ItchApiUrl, ItchClient};
+
+  #[test]
+  fn test_mirrors_are_only_itch_owned() {
+    let url = ItchApiUrl::v2("uploads/42/download");
+
+    assert_eq!(
+      url.with_base("https://mirror.itch.zone/").unwrap().as_str(),
+      "https://mirror.itch.zone/uploads/42/download"
+    );
+    assert!(url.with_base("https://itch.io").is_some());
+    assert!(url.with_base("https://mirror.example.com").is_none());
+    assert!(url.with_base("https://notitch.io").is_none());
+    assert!(url.with_base("https://itch.io.example.com").is_none());
+    assert!(url.with_base("http://api.itch.io").is_none());
+  }
+
+  #[test]
+  fn test_redacted_url_hides_secrets() {
+    assert_eq!(
+      ItchApiUrl::v1("download-key/0123-abcd/download/42").redacted(),
+      "https://itch.io/api/1/download-key/<redacted>/download/42"
+    );
+    assert_eq!(
+      ItchApiUrl::other("https://example.com/file?token=secret&page=2".to_string()).redacted(),
+      "https://example.com/file?token=<redacted>&page=2"
+    );
+    assert_eq!(
+      ItchApiUrl::v2("uploads/42/download").redacted(),
+      "https://api.itch.io/uploads/42/download"
+    );
+  }
+
+  #[test]
+  fn test_debug_hides_api_key() {
+    let client = ItchClient::new("secret-api-key".to_string());
+    let debug = format!("{client:?}");
+
+    assert!(!debug.contains("secret-api-key"));
+    assert!(debug.contains("api_key: \"<redacted>\""));
+  }
 }
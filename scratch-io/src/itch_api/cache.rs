@@ -0,0 +1,74 @@
+use super::types::{Game, GameID, Upload, UploadID, User, UserID};
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// The time a cached response is used before requesting it again, by default
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// A map whose entries expire after some time
+#[derive(Debug)]
+pub(crate) struct ExpiringMap<K, V> {
+  entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K, V> Default for ExpiringMap<K, V> {
+  fn default() -> Self {
+    Self {
+      entries: Mutex::new(HashMap::new()),
+    }
+  }
+}
+
+impl<K: Eq + Hash, V: Clone> ExpiringMap<K, V> {
+  /// Get a clone of the value, if it was inserted less than `ttl` ago
+  pub(crate) fn get(&self, key: &K, ttl: Duration) -> Option<V> {
+    let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+    entries
+      .get(key)
+      .filter(|(inserted, _)| inserted.elapsed() < ttl)
+      .map(|(_, value)| value.clone())
+  }
+
+  pub(crate) fn insert(&self, key: K, value: V) {
+    let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+    entries.insert(key, (Instant::now(), value));
+  }
+
+  pub(crate) fn clear(&self) {
+    self
+      .entries
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clear();
+  }
+}
+
+/// An in-memory cache of the itch.io API responses that rarely change
+#[derive(Debug)]
+pub(crate) struct ApiCache {
+  pub(crate) ttl: Duration,
+  pub(crate) games: ExpiringMap<GameID, Game>,
+  pub(crate) uploads: ExpiringMap<UploadID, Upload>,
+  pub(crate) users: ExpiringMap<UserID, User>,
+}
+
+impl ApiCache {
+  pub(crate) fn new(ttl: Duration) -> Self {
+    Self {
+      ttl,
+      games: ExpiringMap::default(),
+      uploads: ExpiringMap::default(),
+      users: ExpiringMap::default(),
+    }
+  }
+
+  pub(crate) fn clear(&self) {
+    self.games.clear();
+    self.uploads.clear();
+    self.users.clear();
+  }
+}
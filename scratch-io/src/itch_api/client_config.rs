@@ -0,0 +1,60 @@
+use reqwest::blocking::Client;
+use std::time::Duration;
+
+/// The User-Agent sent by default, so itch.io can identify the client
+pub const DEFAULT_USER_AGENT: &str = concat!("scratch-io/", env!("CARGO_PKG_VERSION"));
+
+/// How the HTTP client used by an [`ItchClient`](super::ItchClient) connects to the servers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+  /// The URL of a proxy used for all the requests, like `http://proxy.example.com:8080`
+  ///
+  /// If None, the system proxy is used, if there is one
+  pub proxy: Option<String>,
+  /// The maximum time to wait for a connection to be established
+  ///
+  /// If None, it isn't limited
+  pub connect_timeout: Option<Duration>,
+  /// The maximum time a whole request can take, from connecting until the response body is read
+  ///
+  /// This applies to the file downloads too, so it should be generous. If None, it isn't limited.
+  /// The API calls have their own shorter timeout, see [`ItchClient::with_request_timeout`](super::ItchClient::with_request_timeout)
+  pub timeout: Option<Duration>,
+  /// The User-Agent header sent with every request
+  pub user_agent: String,
+}
+
+impl Default for ClientConfig {
+  fn default() -> Self {
+    Self {
+      proxy: None,
+      connect_timeout: None,
+      timeout: None,
+      user_agent: DEFAULT_USER_AGENT.to_string(),
+    }
+  }
+}
+
+impl ClientConfig {
+  /// Build a reqwest client with this configuration
+  ///
+  /// # Errors
+  ///
+  /// If the proxy URL is invalid or the client couldn't be initialized
+  pub fn build_client(&self) -> Result<Client, reqwest::Error> {
+    let mut builder = Client::builder().user_agent(&self.user_agent);
+
+    if let Some(proxy) = &self.proxy {
+      builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    if let Some(connect_timeout) = self.connect_timeout {
+      builder = builder.connect_timeout(connect_timeout);
+    }
+    // Always set the timeout, so None disables the reqwest default, which would cut off large downloads
+    builder = builder.timeout(self.timeout);
+
+    // With the compressed-api feature, reqwest asks for compressed responses and decompresses
+    // them by itself. The downloads opt out of it, see crate::transport
+    builder.build()
+  }
+}
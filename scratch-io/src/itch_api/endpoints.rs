@@ -6,6 +6,95 @@ use super::types::*;
 
 use reqwest::Method;
 
+/// The pages requested from a paginated endpoint
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PageRange {
+  /// The first page to fetch. Pages start at 1
+  pub start: u64,
+  /// The maximum number of pages to fetch. If None, fetch until the last page
+  pub limit: Option<u64>,
+}
+
+impl Default for PageRange {
+  fn default() -> Self {
+    Self {
+      start: 1,
+      limit: None,
+    }
+  }
+}
+
+impl PageRange {
+  /// A range containing only the given page
+  #[must_use]
+  pub fn single(page: u64) -> Self {
+    Self {
+      start: page,
+      limit: Some(1),
+    }
+  }
+}
+
+/// A single page of the values of a paginated endpoint
+///
+/// It allows the callers to drive their own pagination, like an infinite scroll
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Page<T> {
+  /// The values of this page
+  pub items: Vec<T>,
+  /// The number of this page, starting at 1
+  pub page: u64,
+  /// The maximum number of values of a page
+  pub per_page: u64,
+  /// If the next page may have more values
+  ///
+  /// The API doesn't report the total number of pages, so this is true when
+  /// this page is full, even if the next one turns out to be empty
+  pub has_more: bool,
+}
+
+impl<T> Page<T> {
+  fn new(items: Vec<T>, page: u64, per_page: u64) -> Self {
+    let num_elements: u64 = items.len() as u64;
+    Self {
+      items,
+      page,
+      per_page,
+      has_more: num_elements > 0 && num_elements >= per_page,
+    }
+  }
+}
+
+/// Fetch the pages of a paginated endpoint inside the given range
+///
+/// `fetch_page` returns a single page. Fetching stops when a page doesn't have more
+/// values after it, or when the range limit is reached
+fn get_pages<T, E>(
+  range: PageRange,
+  mut fetch_page: impl FnMut(u64) -> Result<Page<T>, E>,
+) -> Result<Vec<T>, E> {
+  let mut values: Vec<T> = Vec::new();
+  let mut page: u64 = range.start.max(1);
+  let mut fetched_pages: u64 = 0;
+  loop {
+    let response: Page<T> = fetch_page(page)?;
+    let has_more: bool = response.has_more;
+    values.extend(response.items);
+    fetched_pages += 1;
+
+    if !has_more {
+      break;
+    }
+    if range.limit.is_some_and(|limit| fetched_pages >= limit) {
+      break;
+    }
+
+    page += 1;
+  }
+
+  Ok(values)
+}
+
 /// Get a user's info
 ///
 /// # Arguments
@@ -23,6 +112,10 @@ pub fn get_user_info(
   client: &ItchClient,
   user_id: UserID,
 ) -> Result<User, ItchRequestJSONError<UserResponseError>> {
+  if let Some(user) = client.cache().and_then(|c| c.users.get(&user_id, c.ttl)) {
+    return Ok(user);
+  }
+
   client
     .itch_request_json::<UserInfoResponse>(
       &ItchApiUrl::v2(&format!("users/{user_id}")),
@@ -30,6 +123,11 @@ pub fn get_user_info(
       |b| b,
     )
     .map(|res| res.user)
+    .inspect(|user| {
+      if let Some(c) = client.cache() {
+        c.users.insert(user_id, user.clone());
+      }
+    })
 }
 
 /// Get the API key's profile
@@ -76,6 +174,88 @@ pub fn get_created_games(
     .map(|res| res.games)
 }
 
+/// Get the public games of any user by their username
+///
+/// Unlike [`get_created_games`], this works for any user, but it doesn't include
+/// the unpublished games
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `username` - The username of the user, like the one in their itch.io page URL
+///
+/// # Returns
+///
+/// A vector of [`GameCommon`] structs with the info provided by the API. It is empty
+/// if the user doesn't have any public games
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_user_games(
+  client: &ItchClient,
+  username: &str,
+) -> Result<Vec<GameCommon>, ItchRequestJSONError<UserResponseError>> {
+  get_user_games_range(client, username, PageRange::default())
+}
+
+/// Get some pages of the public games of any user by their username
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `username` - The username of the user, like the one in their itch.io page URL
+///
+/// * `range` - The pages to fetch
+///
+/// # Returns
+///
+/// A vector of [`GameCommon`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_user_games_range(
+  client: &ItchClient,
+  username: &str,
+  range: PageRange,
+) -> Result<Vec<GameCommon>, ItchRequestJSONError<UserResponseError>> {
+  get_pages(range, |page| get_user_games_page(client, username, page))
+}
+
+/// Get a single page of the public games of any user by their username
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `username` - The username of the user, like the one in their itch.io page URL
+///
+/// * `page` - The page to fetch, starting at 1
+///
+/// # Returns
+///
+/// A [`Page`] of [`GameCommon`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_user_games_page(
+  client: &ItchClient,
+  username: &str,
+  page: u64,
+) -> Result<Page<GameCommon>, ItchRequestJSONError<UserResponseError>> {
+  client
+    .itch_request_json::<UserGamesResponse>(
+      &ItchApiUrl::v2(&format!("users/{username}/games")),
+      Method::GET,
+      |b| b.query(&[("page", page)]),
+    )
+    .map(|res| Page::new(res.games, res.page, res.per_page))
+}
+
 /// Get the user's owned game keys
 ///
 /// # Arguments
@@ -92,27 +272,95 @@ pub fn get_created_games(
 pub fn get_owned_keys(
   client: &ItchClient,
 ) -> Result<Vec<OwnedKey>, ItchRequestJSONError<ApiResponseCommonErrors>> {
-  let mut values: Vec<OwnedKey> = Vec::new();
-  let mut page: u64 = 1;
-  loop {
-    let response = client.itch_request_json::<OwnedKeysResponse>(
+  get_owned_keys_range(client, PageRange::default())
+}
+
+/// Get some pages of the user's owned game keys
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `range` - The pages to fetch
+///
+/// # Returns
+///
+/// A vector of [`OwnedKey`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_owned_keys_range(
+  client: &ItchClient,
+  range: PageRange,
+) -> Result<Vec<OwnedKey>, ItchRequestJSONError<ApiResponseCommonErrors>> {
+  get_pages(range, |page| get_owned_keys_page(client, page))
+}
+
+/// Get a single page of the user's owned game keys
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `page` - The page to fetch, starting at 1
+///
+/// # Returns
+///
+/// A [`Page`] of [`OwnedKey`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_owned_keys_page(
+  client: &ItchClient,
+  page: u64,
+) -> Result<Page<OwnedKey>, ItchRequestJSONError<ApiResponseCommonErrors>> {
+  client
+    .itch_request_json::<OwnedKeysResponse>(
       &ItchApiUrl::v2("profile/owned-keys"),
       Method::GET,
       |b| b.query(&[("page", page)]),
-    )?;
-
-    let response_values = response.owned_keys;
-    let num_elements: u64 = response_values.len() as u64;
-    values.extend(response_values);
+    )
+    .map(|res| Page::new(res.owned_keys, res.page, res.per_page))
+}
 
-    if num_elements == 0 || num_elements < response.per_page {
-      break;
-    }
+/// Get the user's download key of a single game
+///
+/// Unlike [`get_owned_keys`], only the key of the requested game is fetched
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `game_id` - The ID of the game whose download key will be obtained
+///
+/// # Returns
+///
+/// The [`OwnedKey`] of the game, or None if the user doesn't own it
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_game_download_key(
+  client: &ItchClient,
+  game_id: GameID,
+) -> Result<Option<OwnedKey>, ItchRequestJSONError<DownloadKeyResponseError>> {
+  let result = client.itch_request_json::<GameDownloadKeyResponse>(
+    &ItchApiUrl::v2(&format!("games/{game_id}/download-keys")),
+    Method::GET,
+    |b| b,
+  );
 
-    page += 1;
+  match result {
+    Ok(res) => Ok(Some(res.download_key)),
+    Err(ItchRequestJSONError {
+      kind:
+        ItchRequestJSONErrorKind::ServerRepliedWithError(DownloadKeyResponseError::NoDownloadKey(_)),
+      ..
+    }) => Ok(None),
+    Err(e) => Err(e),
   }
-
-  Ok(values)
 }
 
 /// List the user's game collections
@@ -187,27 +435,118 @@ pub fn get_collection_games(
   client: &ItchClient,
   collection_id: CollectionID,
 ) -> Result<Vec<CollectionGameItem>, ItchRequestJSONError<CollectionResponseError>> {
-  let mut values: Vec<CollectionGameItem> = Vec::new();
-  let mut page: u64 = 1;
-  loop {
-    let response = client.itch_request_json::<CollectionGamesResponse>(
+  get_collection_games_range(client, collection_id, PageRange::default())
+}
+
+/// List some pages of a collection's games
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `collection_id` - The ID of the collection from which information will be obtained
+///
+/// * `range` - The pages to fetch
+///
+/// # Returns
+///
+/// A vector of [`CollectionGameItem`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_collection_games_range(
+  client: &ItchClient,
+  collection_id: CollectionID,
+  range: PageRange,
+) -> Result<Vec<CollectionGameItem>, ItchRequestJSONError<CollectionResponseError>> {
+  get_pages(range, |page| {
+    get_collection_games_page(client, collection_id, page)
+  })
+}
+
+/// Get a single page of a collection's games
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `collection_id` - The ID of the collection from which information will be obtained
+///
+/// * `page` - The page to fetch, starting at 1
+///
+/// # Returns
+///
+/// A [`Page`] of [`CollectionGameItem`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_collection_games_page(
+  client: &ItchClient,
+  collection_id: CollectionID,
+  page: u64,
+) -> Result<Page<CollectionGameItem>, ItchRequestJSONError<CollectionResponseError>> {
+  client
+    .itch_request_json::<CollectionGamesResponse>(
       &ItchApiUrl::v2(&format!("collections/{collection_id}/collection-games")),
       Method::GET,
       |b| b.query(&[("page", page)]),
-    )?;
-
-    let response_values = response.collection_games;
-    let num_elements: u64 = response_values.len() as u64;
-    values.extend(response_values);
-
-    if num_elements == 0 || num_elements < response.per_page {
-      break;
-    }
+    )
+    .map(|res| Page::new(res.collection_games, res.page, res.per_page))
+}
 
-    page += 1;
-  }
+/// Search games in itch.io by their title
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `query` - The text to search for
+///
+/// # Returns
+///
+/// A vector of [`GameCommon`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn search_games(
+  client: &ItchClient,
+  query: &str,
+) -> Result<Vec<GameCommon>, ItchRequestJSONError<ApiResponseCommonErrors>> {
+  get_pages(PageRange::default(), |page| {
+    search_games_page(client, query, page)
+  })
+}
 
-  Ok(values)
+/// Get a single page of the games found searching by their title
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `query` - The text to search for
+///
+/// * `page` - The page to fetch, starting at 1
+///
+/// # Returns
+///
+/// A [`Page`] of [`GameCommon`] structs with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn search_games_page(
+  client: &ItchClient,
+  query: &str,
+  page: u64,
+) -> Result<Page<GameCommon>, ItchRequestJSONError<ApiResponseCommonErrors>> {
+  client
+    .itch_request_json::<SearchGamesResponse>(&ItchApiUrl::v2("search/games"), Method::GET, |b| {
+      b.query(&[("query", query)]).query(&[("page", page)])
+    })
+    .map(|res| Page::new(res.games, res.page, res.per_page))
 }
 
 /// Get the information about a game in itch.io
@@ -229,6 +568,10 @@ pub fn get_game_info(
   client: &ItchClient,
   game_id: GameID,
 ) -> Result<Game, ItchRequestJSONError<GameResponseError>> {
+  if let Some(game) = client.cache().and_then(|c| c.games.get(&game_id, c.ttl)) {
+    return Ok(game);
+  }
+
   client
     .itch_request_json::<GameInfoResponse>(
       &ItchApiUrl::v2(&format!("games/{game_id}")),
@@ -236,6 +579,41 @@ pub fn get_game_info(
       |b| b,
     )
     .map(|res| res.game)
+    .inspect(|game| {
+      if let Some(c) = client.cache() {
+        c.games.insert(game_id, game.clone());
+      }
+    })
+}
+
+/// Get the information about a game in itch.io, including its description, tags and rating
+///
+/// Unlike [`get_game_info`], the response isn't cached, and the optional page metadata is kept
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `game_id` - The ID of the game from which information will be obtained
+///
+/// # Returns
+///
+/// A [`GameDetails`] struct with the info provided by the API
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn get_game_details(
+  client: &ItchClient,
+  game_id: GameID,
+) -> Result<GameDetails, ItchRequestJSONError<GameResponseError>> {
+  client
+    .itch_request_json::<GameDetailsResponse>(
+      &ItchApiUrl::v2(&format!("games/{game_id}")),
+      Method::GET,
+      |b| b,
+    )
+    .map(|res| res.game)
 }
 
 /// Get a scoped API subkey for a specific game
@@ -269,6 +647,37 @@ pub fn get_game_subkey(
     .map(|res| res.subkey)
 }
 
+/// Revoke the client's API key, so it can't be used anymore
+///
+/// The client is consumed, because every request made with a revoked key fails.
+/// If the key was already revoked or isn't valid, it is considered a success
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client, whose key will be revoked
+///
+/// # Errors
+///
+/// If the request, retrieving its text, or parsing fails, or if the server returned an error
+pub fn revoke_key(client: ItchClient) -> Result<(), ItchRequestJSONError<ApiResponseCommonErrors>> {
+  let res = client.itch_request_json::<RevokeKeyResponse>(
+    &ItchApiUrl::v2("credentials/revoke"),
+    Method::POST,
+    |b| b,
+  );
+
+  match res {
+    Ok(_) => Ok(()),
+    // The key is already unusable, which is what was requested
+    Err(ItchRequestJSONError {
+      kind:
+        ItchRequestJSONErrorKind::ServerRepliedWithError(ApiResponseCommonErrors::InvalidApiKey(_)),
+      ..
+    }) => Ok(()),
+    Err(e) => Err(e),
+  }
+}
+
 /// Get the game's uploads (downloadable files)
 ///
 /// # Arguments
@@ -316,6 +725,13 @@ pub fn get_upload_info(
   client: &ItchClient,
   upload_id: UploadID,
 ) -> Result<Upload, ItchRequestJSONError<UploadResponseError>> {
+  if let Some(upload) = client
+    .cache()
+    .and_then(|c| c.uploads.get(&upload_id, c.ttl))
+  {
+    return Ok(upload);
+  }
+
   client
     .itch_request_json::<UploadInfoResponse>(
       &ItchApiUrl::v2(&format!("uploads/{upload_id}")),
@@ -323,6 +739,11 @@ pub fn get_upload_info(
       |b| b,
     )
     .map(|res| res.upload)
+    .inspect(|upload| {
+      if let Some(c) = client.cache() {
+        c.uploads.insert(upload_id, upload.clone());
+      }
+    })
 }
 
 /// Get the upload's builds (downloadable versions)
@@ -469,3 +890,163 @@ pub fn get_build_scanned_archive(
     )
     .map(|res| res.scanned_archive)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Fetch pages from a list of 5 values with 2 values per page
+  fn fetch_pages(range: PageRange) -> (Vec<u64>, Vec<u64>) {
+    let mut requested: Vec<u64> = Vec::new();
+    let values = get_pages::<u64, ()>(range, |page| {
+      requested.push(page);
+      let values = (1..=5).skip((page as usize - 1) * 2).take(2).collect();
+      Ok(Page::new(values, page, 2))
+    })
+    .unwrap();
+    (values, requested)
+  }
+
+  #[test]
+  fn get_pages_fetches_all_pages() {
+    assert_eq!(
+      fetch_pages(PageRange::default()),
+      (vec![1, 2, 3, 4, 5], vec![1, 2, 3])
+    );
+  }
+
+  #[test]
+  fn page_has_more_only_if_full() {
+    assert!(Page::new(vec![1, 2], 1, 2).has_more);
+    assert!(!Page::new(vec![1], 2, 2).has_more);
+    assert!(!Page::<u64>::new(Vec::new(), 3, 2).has_more);
+  }
+
+  #[test]
+  fn get_pages_respects_the_range() {
+    assert_eq!(fetch_pages(PageRange::single(2)), (vec![3, 4], vec![2]));
+    assert_eq!(
+      fetch_pages(PageRange {
+        start: 2,
+        limit: Some(5)
+      }),
+      (vec![3, 4, 5], vec![2, 3])
+    );
+  }
+
+  #[test]
+  fn game_details_optional_fields() {
+    let game = r#"{
+      "id": 1, "url": "https://user.itch.io/game", "title": "Game", "short_text": null,
+      "type": "default", "classification": "game", "cover_url": null,
+      "created_at": "2024-01-01T00:00:00Z", "min_price": 0, "sale": null, "traits": ["p_windows"],
+      "user": { "id": 2, "username": "user", "display_name": null, "url": "https://user.itch.io",
+        "cover_url": null, "still_cover_url": null }
+    "#;
+
+    // Restricted games omit the page metadata
+    let details: GameDetails = serde_json::from_str(&format!("{game}}}")).unwrap();
+    assert_eq!(details.description, None);
+    assert!(details.tags.is_empty());
+    assert_eq!(details.rating, None);
+    assert_eq!(details.game_info.traits, vec![GameTrait::PWindows]);
+
+    let details: GameDetails = serde_json::from_str(&format!(
+      r#"{game}, "description": "<p>Long</p>", "tags": ["puzzle"], "rating": {{ "average": 4.5, "count": 10 }} }}"#
+    ))
+    .unwrap();
+    assert_eq!(details.description.as_deref(), Some("<p>Long</p>"));
+    assert_eq!(details.tags, vec!["puzzle".to_string()]);
+    assert_eq!(
+      details.rating,
+      Some(GameRating {
+        average: 4.5,
+        count: 10
+      })
+    );
+  }
+
+  #[test]
+  fn download_key_error_shape() {
+    let response = |json: &str| {
+      serde_json::from_str::<ApiResponse<GameDownloadKeyResponse>>(json)
+        .unwrap()
+        .into_result()
+    };
+
+    // The user doesn't own the game
+    assert!(matches!(
+      response(r#"{ "errors": ["no download key found"] }"#),
+      Err(DownloadKeyResponseError::NoDownloadKey(_))
+    ));
+    assert!(matches!(
+      response(r#"{ "errors": ["invalid game"] }"#),
+      Err(DownloadKeyResponseError::InvalidGameID(_))
+    ));
+    assert!(matches!(
+      response(r#"{ "errors": ["invalid key"] }"#),
+      Err(DownloadKeyResponseError::Other(
+        ApiResponseCommonErrors::InvalidApiKey(_)
+      ))
+    ));
+    assert!(matches!(
+      response(r#"{ "errors": ["something else"] }"#),
+      Err(DownloadKeyResponseError::Other(
+        ApiResponseCommonErrors::Other(_)
+      ))
+    ));
+  }
+
+  #[test]
+  fn build_file_names_match_the_api() {
+    for file_type in [
+      BuildFileType::Archive,
+      BuildFileType::Patch,
+      BuildFileType::Signature,
+      BuildFileType::Manifest,
+      BuildFileType::Unpacked,
+    ] {
+      assert_eq!(
+        serde_json::to_string(&file_type).unwrap(),
+        format!("\"{}\"", file_type.as_str())
+      );
+    }
+    for sub_type in [
+      BuildFileSubtype::Default,
+      BuildFileSubtype::Optimized,
+      BuildFileSubtype::Accelerated,
+      BuildFileSubtype::Gzip,
+    ] {
+      assert_eq!(
+        serde_json::to_string(&sub_type).unwrap(),
+        format!("\"{}\"", sub_type.as_str())
+      );
+    }
+  }
+
+  #[test]
+  fn scanned_archive_launch_targets() {
+    let archive = r#"{
+      "object_type": "upload", "object_id": 1, "extracted_size": 100, "manifest": null,
+      "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+    "#;
+
+    let scanned: ScannedArchive = serde_json::from_str(&format!("{archive}}}")).unwrap();
+    assert_eq!(scanned.launch_targets, None);
+
+    let scanned: ScannedArchive = serde_json::from_str(&format!(
+      r#"{archive}, "launch_targets": [
+        {{ "path": "game/Game.exe", "flavor": "windows", "arch": "amd64", "size": 50 }},
+        {{ "path": "game.x86_64", "flavor": "linux" }},
+        {{ "path": "game.unknown", "flavor": "something-new" }}
+      ] }}"#
+    ))
+    .unwrap();
+    let targets = scanned.launch_targets.unwrap();
+    assert_eq!(targets[0].path, "game/Game.exe");
+    assert_eq!(targets[0].flavor, LaunchTargetFlavor::Windows);
+    assert_eq!(targets[0].arch.as_deref(), Some("amd64"));
+    assert_eq!(targets[1].size, None);
+    assert_eq!(targets[2].flavor, LaunchTargetFlavor::Unknown);
+  }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 const ERROR_INVALID_API_KEY: &str = "invalid key";
@@ -29,6 +30,7 @@ const ERROR_INVALID_BUILD: &[&str] = &[
 const ERROR_INVALID_TARGET_BUILD: &str =
   "target_build_id: expected empty, or integer then database id";
 const ERROR_NO_UPGRADE_PATH: &str = "no upgrade path";
+const ERROR_NO_DOWNLOAD_KEY: &[&str] = &["no download key found", "download key not found"];
 
 #[derive(Error, Debug)]
 #[error("An API call to \"{url}\" failed:\n{kind}")]
@@ -54,6 +56,12 @@ where
   #[error("Couldn't get the network request response body:\n{0}")]
   CouldntGetText(#[source] reqwest::Error),
 
+  #[error("The request timed out:\n{0}")]
+  Timeout(#[source] reqwest::Error),
+
+  #[error("The itch.io API server received too many requests. Try again in {retry_after:?}")]
+  RateLimited { retry_after: Duration },
+
   #[error("Couldn't parse the request response body into JSON:\n{body}\n\n{error}")]
   InvalidJSON {
     body: String,
@@ -111,6 +119,10 @@ pub struct InvalidTargetBuildID;
 #[error("No upgrade path was found.")]
 pub struct NoUpgradePath;
 
+#[derive(Error, Debug)]
+#[error("The user doesn't own a download key of the game.")]
+pub struct NoDownloadKey;
+
 /// All possible errors returned from the Itch.io API
 #[derive(Error, Debug)]
 pub enum ApiResponseErrorKind {
@@ -147,6 +159,9 @@ pub enum ApiResponseErrorKind {
   #[error(transparent)]
   NoUpgradePath(#[from] NoUpgradePath),
 
+  #[error(transparent)]
+  NoDownloadKey(#[from] NoDownloadKey),
+
   #[error("An unknown error occurred!")]
   Other,
 }
@@ -165,6 +180,7 @@ impl From<&[String]> for ApiResponseErrorKind {
       [v] if ERROR_INVALID_BUILD.contains(&&**v) => InvalidBuildID.into(),
       [v] if ERROR_INVALID_TARGET_BUILD == v => InvalidTargetBuildID.into(),
       [v] if v == ERROR_NO_UPGRADE_PATH => NoUpgradePath.into(),
+      [v] if ERROR_NO_DOWNLOAD_KEY.contains(&&**v) => NoDownloadKey.into(),
       _ => Self::Other,
     }
   }
@@ -361,3 +377,26 @@ impl From<ApiResponseError> for UpgradePathResponseError {
     }
   }
 }
+
+/// Errors returned from the API call that finds the user's download key of a game
+#[derive(Error, Debug)]
+pub enum DownloadKeyResponseError {
+  #[error(transparent)]
+  InvalidGameID(#[from] InvalidGameID),
+
+  #[error(transparent)]
+  NoDownloadKey(#[from] NoDownloadKey),
+
+  #[error(transparent)]
+  Other(#[from] ApiResponseCommonErrors),
+}
+
+impl From<ApiResponseError> for DownloadKeyResponseError {
+  fn from(value: ApiResponseError) -> Self {
+    match value.kind {
+      ApiResponseErrorKind::InvalidGameID(v) => v.into(),
+      ApiResponseErrorKind::NoDownloadKey(v) => v.into(),
+      _ => Self::Other(value.into()),
+    }
+  }
+}
@@ -0,0 +1,92 @@
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Paces the requests of an [`ItchClient`](super::ItchClient) and its clones
+///
+/// Each request reserves the next free slot, which is at least `min_interval` after the
+/// previous one. When the server replies with HTTP 429, every request waits until the
+/// delay it asked for has passed, not only the one which was rate limited
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+  min_interval: Duration,
+  next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+  /// Create a limiter which allows up to `requests_per_sec`, or doesn't pace the requests if None
+  pub(crate) fn new(requests_per_sec: Option<NonZeroU32>) -> Self {
+    Self {
+      min_interval: requests_per_sec.map_or(Duration::ZERO, |r| Duration::from_secs(1) / r.get()),
+      next_slot: Mutex::new(Instant::now()),
+    }
+  }
+
+  /// Sleep until the request is allowed to be sent
+  pub(crate) fn wait(&self) {
+    let now = Instant::now();
+
+    // Reserve the slot while holding the lock, but sleep after releasing it
+    let slot: Instant = {
+      let mut next_slot = self
+        .next_slot
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+      let slot = (*next_slot).max(now);
+      *next_slot = slot + self.min_interval;
+      slot
+    };
+
+    if slot > now {
+      trace!("Waiting {:?} before sending the request", slot - now);
+      std::thread::sleep(slot - now);
+    }
+  }
+
+  /// Don't allow any request to be sent until `delay` has passed
+  pub(crate) fn pause(&self, delay: Duration) {
+    let mut next_slot = self
+      .next_slot
+      .lock()
+      .unwrap_or_else(std::sync::PoisonError::into_inner);
+    *next_slot = (*next_slot).max(Instant::now() + delay);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::RateLimiter;
+
+  use std::num::NonZeroU32;
+  use std::time::{Duration, Instant};
+
+  #[test]
+  fn test_unlimited_doesnt_wait() {
+    let limiter = RateLimiter::new(None);
+    let start = Instant::now();
+    for _ in 0..100 {
+      limiter.wait();
+    }
+    assert!(start.elapsed() < Duration::from_millis(50));
+  }
+
+  #[test]
+  fn test_requests_are_paced() {
+    let limiter = RateLimiter::new(NonZeroU32::new(20));
+    let start = Instant::now();
+    // The first request is sent immediately, and the other two wait 50ms each
+    for _ in 0..3 {
+      limiter.wait();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+  }
+
+  #[test]
+  fn test_pause_delays_next_request() {
+    let limiter = RateLimiter::new(None);
+    limiter.pause(Duration::from_millis(50));
+    let start = Instant::now();
+    limiter.wait();
+    assert!(start.elapsed() >= Duration::from_millis(40));
+  }
+}
@@ -69,6 +69,19 @@ impl IntoResponseResult for CreatedGamesResponse {
   type Err = ApiResponseCommonErrors;
 }
 
+/// Response struct for: <https://api.itch.io/users/{username}/games>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserGamesResponse {
+  pub page: u64,
+  pub per_page: u64,
+  #[serde(deserialize_with = "empty_object_as_vec")]
+  pub games: Vec<GameCommon>,
+}
+
+impl IntoResponseResult for UserGamesResponse {
+  type Err = UserResponseError;
+}
+
 /// Response struct for: <https://api.itch.io/profile/owned-keys>
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OwnedKeysResponse {
@@ -82,6 +95,16 @@ impl IntoResponseResult for OwnedKeysResponse {
   type Err = ApiResponseCommonErrors;
 }
 
+/// Response struct for: <https://api.itch.io/games/{game_id}/download-keys>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameDownloadKeyResponse {
+  pub download_key: OwnedKey,
+}
+
+impl IntoResponseResult for GameDownloadKeyResponse {
+  type Err = DownloadKeyResponseError;
+}
+
 /// Response struct for: <https://api.itch.io/profile/collections>
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProfileCollectionsResponse {
@@ -116,6 +139,19 @@ impl IntoResponseResult for CollectionGamesResponse {
   type Err = CollectionResponseError;
 }
 
+/// Response struct for: <https://api.itch.io/search/games>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchGamesResponse {
+  pub page: u64,
+  pub per_page: u64,
+  #[serde(deserialize_with = "empty_object_as_vec")]
+  pub games: Vec<GameCommon>,
+}
+
+impl IntoResponseResult for SearchGamesResponse {
+  type Err = ApiResponseCommonErrors;
+}
+
 /// Response struct for: <https://api.itch.io/games/{game_id}>
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameInfoResponse {
@@ -126,6 +162,16 @@ impl IntoResponseResult for GameInfoResponse {
   type Err = GameResponseError;
 }
 
+/// Response struct for: <https://api.itch.io/games/{game_id}>, including the full page metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameDetailsResponse {
+  pub game: GameDetails,
+}
+
+impl IntoResponseResult for GameDetailsResponse {
+  type Err = GameResponseError;
+}
+
 /// Response struct for: <https://api.itch.io/credentials/subkey>
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameSubkeyResponse {
@@ -137,6 +183,16 @@ impl IntoResponseResult for GameSubkeyResponse {
   type Err = ApiResponseCommonErrors;
 }
 
+/// Response struct for: <https://api.itch.io/credentials/revoke>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokeKeyResponse {
+  pub success: bool,
+}
+
+impl IntoResponseResult for RevokeKeyResponse {
+  type Err = ApiResponseCommonErrors;
+}
+
 /// Response struct for: <https://api.itch.io/games/{game_id}/uploads>
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameUploadsResponse {
@@ -0,0 +1,109 @@
+use rand::RngExt;
+use reqwest::{StatusCode, blocking::Response, header};
+use std::time::Duration;
+
+/// How failed requests to the itch.io API are retried
+///
+/// The delay between attempts grows exponentially, starting at `base_delay`
+/// and never exceeding `max_delay`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+  /// The maximum number of attempts, including the first one
+  ///
+  /// A value of 1 (or 0) disables the retries
+  pub max_attempts: u32,
+  /// The delay before the first retry
+  pub base_delay: Duration,
+  /// The maximum delay between two attempts
+  pub max_delay: Duration,
+  /// If true, wait a random fraction of the delay, so many clients don't retry at the same time
+  pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+  /// The default policy doesn't retry
+  fn default() -> Self {
+    Self::no_retry()
+  }
+}
+
+impl RetryPolicy {
+  /// A policy which never retries
+  #[must_use]
+  pub const fn no_retry() -> Self {
+    Self {
+      max_attempts: 1,
+      base_delay: Duration::ZERO,
+      max_delay: Duration::ZERO,
+      jitter: false,
+    }
+  }
+
+  /// A policy with exponential backoff and jitter
+  ///
+  /// # Arguments
+  ///
+  /// * `max_attempts` - The maximum number of attempts, including the first one
+  #[must_use]
+  pub const fn exponential(max_attempts: u32) -> Self {
+    Self {
+      max_attempts,
+      base_delay: Duration::from_millis(500),
+      max_delay: Duration::from_secs(30),
+      jitter: true,
+    }
+  }
+
+  /// Check if another attempt can be made after `attempt` attempts failed
+  #[must_use]
+  pub fn should_retry(&self, attempt: u32) -> bool {
+    attempt < self.max_attempts
+  }
+
+  /// Get the time to wait after `attempt` attempts failed
+  #[must_use]
+  pub fn delay(&self, attempt: u32) -> Duration {
+    // base_delay * 2^(attempt - 1), saturating to avoid overflows
+    let exponent = attempt.saturating_sub(1).min(31);
+    let delay = self
+      .base_delay
+      .saturating_mul(1 << exponent)
+      .min(self.max_delay);
+
+    if self.jitter {
+      delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+    } else {
+      delay
+    }
+  }
+}
+
+/// Check if a request error is caused by a transient network failure
+pub(crate) fn is_retryable_error(error: &reqwest::Error) -> bool {
+  error.is_connect() || error.is_timeout() || error.is_request() || error.is_body()
+}
+
+/// Check if the server replied with a transient error
+pub(crate) fn is_retryable_response(response: &Response) -> bool {
+  response.status().is_server_error()
+}
+
+/// Check if the server rejected the request because too many were sent (HTTP 429)
+pub(crate) fn is_rate_limited(response: &Response) -> bool {
+  response.status() == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Get the delay requested by the server with the `Retry-After` header, if any
+///
+/// Only the delay-seconds form is supported, HTTP dates are ignored
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+  response
+    .headers()
+    .get(header::RETRY_AFTER)?
+    .to_str()
+    .ok()?
+    .trim()
+    .parse::<u64>()
+    .ok()
+    .map(Duration::from_secs)
+}
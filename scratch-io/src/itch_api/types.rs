@@ -200,6 +200,33 @@ pub struct Game {
   pub user: User,
 }
 
+/// The average rating given to a game by its players
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRating {
+  /// The average of the ratings, from 0 to 5
+  pub average: f64,
+  /// The number of ratings
+  pub count: u64,
+}
+
+/// A game with its full page metadata, as returned by [`get_game_details`](super::endpoints::get_game_details)
+///
+/// The platforms of the game are listed as `p_*` values in [`GameCommon::traits`].
+/// The extra fields are optional because itch.io omits them for unpublished or restricted games
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameDetails {
+  #[serde(flatten)]
+  pub game_info: GameCommon,
+  pub user: User,
+  /// The long description of the game page, in HTML
+  #[serde(default)]
+  pub description: Option<String>,
+  #[serde(default, deserialize_with = "empty_object_as_vec")]
+  pub tags: Vec<String>,
+  #[serde(default)]
+  pub rating: Option<GameRating>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Collection {
   pub id: CollectionID,
@@ -276,6 +303,20 @@ pub enum BuildFileType {
   Unpacked,
 }
 
+impl BuildFileType {
+  /// Get the name of the file type, as used in the API URLs
+  #[must_use]
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::Archive => "archive",
+      Self::Patch => "patch",
+      Self::Signature => "signature",
+      Self::Manifest => "manifest",
+      Self::Unpacked => "unpacked",
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BuildFileSubtype {
@@ -285,6 +326,19 @@ pub enum BuildFileSubtype {
   Gzip,
 }
 
+impl BuildFileSubtype {
+  /// Get the name of the file subtype, as used in the API URLs
+  #[must_use]
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::Default => "default",
+      Self::Optimized => "optimized",
+      Self::Accelerated => "accelerated",
+      Self::Gzip => "gzip",
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BuildFileState {
@@ -482,6 +536,36 @@ pub struct Manifest {
   pub prereqs: Option<Vec<ManifestPrerequisite>>,
 }
 
+/// The kind of file of a [`ScannedLaunchTarget`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LaunchTargetFlavor {
+  Linux,
+  Macos,
+  AppMacos,
+  Windows,
+  Script,
+  ScriptWindows,
+  Jar,
+  Html,
+  Love,
+  Msi,
+  #[serde(other)]
+  Unknown,
+}
+
+/// A file of a scanned archive that can be launched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScannedLaunchTarget {
+  /// The path of the file, relative to the root of the archive
+  pub path: String,
+  pub flavor: LaunchTargetFlavor,
+  #[serde(default)]
+  pub arch: Option<String>,
+  #[serde(default)]
+  pub size: Option<u64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "object_type", rename_all = "snake_case")]
 pub enum ScannedArchiveObject {
@@ -495,8 +579,9 @@ pub struct ScannedArchive {
   pub object_type: ScannedArchiveObject,
   pub extracted_size: Option<u64>,
   pub manifest: Option<Manifest>,
-  // TODO: add launch targets structure
-  //pub launch_targets: Option<Vec<>>,
+  /// The files of the archive that can be launched, found by the itch.io servers
+  #[serde(default)]
+  pub launch_targets: Option<Vec<ScannedLaunchTarget>>,
   #[serde(with = "rfc3339")]
   pub created_at: OffsetDateTime,
   #[serde(with = "rfc3339")]
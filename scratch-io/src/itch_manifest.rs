@@ -30,22 +30,55 @@ pub fn read_manifest(upload_folder: &Path) -> Result<Option<Manifest>, String> {
     })
 }
 
+/// Returns all the actions of the itch.io manifest of the folder, if any
+pub fn actions(upload_folder: &Path) -> Result<Vec<ManifestAction>, String> {
+  let Some(manifest) = read_manifest(upload_folder)? else {
+    return Ok(Vec::new());
+  };
+
+  Ok(manifest.actions.unwrap_or_default())
+}
+
 /// Returns an itch.io [`ManifestAction`] given its name and the folder where the game manifest is located
+///
+/// If `platform` is provided, the action for that platform is preferred, falling back to an
+/// action without a platform. Actions for other platforms are never returned in that case
 pub fn launch_action(
   upload_folder: &Path,
   action_name: Option<&str>,
+  platform: Option<&ManifestActionPlatform>,
 ) -> Result<Option<ManifestAction>, String> {
-  let Some(manifest) = read_manifest(upload_folder)? else {
-    return Ok(None);
+  let action_name = action_name.unwrap_or(MANIFEST_PLAY_ACTION);
+
+  let mut candidates: Vec<ManifestAction> = actions(upload_folder)?
+    .into_iter()
+    .filter(|a| a.name == action_name)
+    .collect();
+
+  let Some(platform) = platform else {
+    return Ok(candidates.into_iter().next());
   };
 
-  let action_name = action_name.unwrap_or(MANIFEST_PLAY_ACTION);
+  let index = candidates
+    .iter()
+    .position(|a| a.platform.as_ref() == Some(platform))
+    .or_else(|| candidates.iter().position(|a| a.platform.is_none()));
+
+  Ok(index.map(|i| candidates.swap_remove(i)))
+}
+
+/// Returns the prerequisites declared in the itch.io manifest of the folder, if any
+pub fn prerequisites(upload_folder: &Path) -> Result<Vec<ManifestPrerequisiteName>, String> {
+  let Some(manifest) = read_manifest(upload_folder)? else {
+    return Ok(Vec::new());
+  };
 
   Ok(
     manifest
-      .actions
+      .prereqs
       .unwrap_or_default()
       .into_iter()
-      .find(|a| a.name == action_name),
+      .map(|p| p.name)
+      .collect(),
   )
 }
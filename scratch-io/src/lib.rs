@@ -1,25 +1,90 @@
+//! A library for managing, downloading, and launching games from itch.io
+//!
+//! Every function is blocking: the requests are sent with the blocking reqwest client and the
+//! files are written with `std::fs`, so no async runtime is needed. Long operations like
+//! [`download_upload`] can be run in their own thread, and stopped with a [`CancellationToken`]
+
+// The logging macros (debug!, trace! and enter_span!) are shared with wharf.
+// The URLs are logged with ItchApiUrl::redacted, and the API key is never logged
+#[macro_use]
+extern crate wharf;
+
+mod cancel;
+mod checksum;
 pub mod errors;
 mod extract;
 mod filesystem;
+mod fingerprint;
 mod game_files;
+mod hash_checkpoint;
+mod heal;
 mod heuristics;
+mod install;
 pub mod itch_api;
 pub mod itch_manifest;
+mod macos;
+#[cfg(feature = "open")]
+mod open;
+mod parallel;
+mod patch;
+mod pe;
+mod queue;
+mod reporter;
+mod speed;
+mod storage;
+mod stream_extract;
+mod throttle;
+mod transport;
+mod upgrade;
 
+pub use crate::cancel::CancellationToken;
+use crate::checksum::Hasher;
+pub use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::errors::{FilesystemError, ScratchError};
+use crate::extract::Extracted;
+pub use crate::extract::{ExtractOptions, FailedEntry};
+pub use crate::fingerprint::{Fingerprint, FingerprintMode, folder_fingerprint};
+use crate::hash_checkpoint::{CHECKPOINT_INTERVAL_BYTES, HashCheckpoint};
+pub use crate::heal::{HealResult, HealStatus, heal_upload};
+pub use crate::heuristics::{HeuristicsConfig, RankedExecutable};
+pub use crate::install::{
+  InstallHandle, InstallOptions, InstallStatus, install_upload, spawn_install_upload,
+};
 pub use crate::itch_api::ItchClient;
+use crate::itch_api::RetryPolicy;
+use crate::itch_api::errors::{
+  ApiResponseCommonErrors, DownloadKeyResponseError, GameResponseError, ItchRequestJSONError,
+  ItchRequestJSONErrorKind,
+};
 use crate::itch_api::{ItchApiUrl, endpoints::*, types::*};
+#[cfg(feature = "open")]
+pub use crate::open::{open_game_page, open_install_folder};
+pub use crate::patch::{
+  BuildDiff, ChangeMethod, FileChange, PatchProgress, apply_patch, spawn_apply_patch,
+};
+pub use crate::pe::PeSubsystem;
+pub use crate::queue::DownloadQueue;
+pub use crate::reporter::{DownloadReporter, NoopReporter};
+use crate::speed::{SpeedMeter, StallDetector, TimeoutReader};
+pub use crate::storage::{GameStorage, LocalStorage, OpenMode, StorageEntry, StorageMetadata};
+use crate::throttle::Throttle;
+use crate::transport::{ByteRange, DownloadTransport, TransportResponse};
+pub use crate::upgrade::{UpgradeStatus, diff_builds, download_build_file, upgrade_upload};
 
-use md5::{Digest, Md5};
-use reqwest::{Method, blocking::Response, header};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
+use time::OffsetDateTime;
 
 // This isn't inside itch_types because it is not something that the itch API returns
 // These platforms are *interpreted* from the data provided by the API
 /// The different platforms a upload can be made for
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GamePlatform {
   Linux,
   Windows,
@@ -31,6 +96,18 @@ pub enum GamePlatform {
   UnityWebPlayer,
 }
 
+impl GamePlatform {
+  /// Get the platform of the manifest actions that run on this platform, if there is one
+  fn to_manifest_platform(self) -> Option<ManifestActionPlatform> {
+    match self {
+      Self::Linux => Some(ManifestActionPlatform::Linux),
+      Self::Windows => Some(ManifestActionPlatform::Windows),
+      Self::OSX => Some(ManifestActionPlatform::Osx),
+      _ => None,
+    }
+  }
+}
+
 impl Upload {
   #[must_use]
   pub fn to_game_platforms(&self) -> Vec<GamePlatform> {
@@ -58,11 +135,115 @@ impl Upload {
   }
 }
 
+/// The platforms in which a game's uploads are available
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GamePlatformsResult {
+  /// The game doesn't have any uploads
+  NoUploads,
+  /// The game has uploads, but none of them is available in a recognized platform
+  NoRecognizedPlatforms,
+  /// A list of tuples containing an upload ID and the [`GamePlatform`] in which it is available
+  Platforms(Vec<(UploadID, GamePlatform)>),
+}
+
+#[derive(Debug)]
 pub enum DownloadStatus {
   Warning(String),
-  StartingDownload { bytes_to_download: u64 },
-  DownloadProgress { downloaded_bytes: u64 },
+  StartingDownload {
+    bytes_to_download: u64,
+  },
+  /// The download progress, with the speed already computed
+  DownloadProgress {
+    downloaded_bytes: u64,
+    /// The size of the whole file, the same as in [`DownloadStatus::StartingDownload`]
+    total_bytes: u64,
+    /// The current download speed, measured since the previous progress report
+    instantaneous_bytes_per_sec: u64,
+    /// The time since the download started
+    elapsed: Duration,
+  },
   Extract,
+  /// The number of bytes of the archive that have been extracted, and its total size
+  ExtractProgress {
+    extracted_bytes: u64,
+    total_bytes: u64,
+  },
+  ExtractionFailedEntries(Vec<FailedEntry>),
+  Cancelled,
+  /// The upload was installed
+  Finished {
+    /// The bytes received from the network, including the ones of every retry and resumed range
+    ///
+    /// Unlike `downloaded_bytes`, it isn't the size of the file, so it can be used to track the data usage
+    bytes_transferred: u64,
+    /// The address of the mirror the upload was downloaded from, see [`DownloadOptions::mirrors`]
+    ///
+    /// None if it was downloaded from the itch.io API
+    mirror: Option<String>,
+  },
+}
+
+/// How the game arguments passed to [`launch`] are combined with the manifest action's arguments
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ArgumentsMergeStrategy {
+  /// Use the provided game arguments instead of the manifest ones, unless they are empty
+  #[default]
+  Replace,
+  /// Append the provided game arguments after the manifest ones
+  Append,
+}
+
+impl ArgumentsMergeStrategy {
+  /// Combine the manifest action's arguments with the provided game arguments
+  fn merge<'a>(
+    self,
+    manifest_arguments: Option<Vec<String>>,
+    game_arguments: &'a [String],
+  ) -> Cow<'a, [String]> {
+    match self {
+      // a) If the function's game arguments are empty, use the ones from the manifest
+      // b) Otherwise, use the provided ones
+      Self::Replace if game_arguments.is_empty() => {
+        Cow::Owned(manifest_arguments.unwrap_or_default())
+      }
+      Self::Replace => Cow::Borrowed(game_arguments),
+      // Put the manifest arguments first, and then the provided ones
+      Self::Append => match manifest_arguments {
+        None => Cow::Borrowed(game_arguments),
+        Some(mut args) => {
+          args.extend_from_slice(game_arguments);
+          Cow::Owned(args)
+        }
+      },
+    }
+  }
+}
+
+/// What was done with a game while syncing a collection, or with an upload while downloading all
+/// the uploads of a game
+#[derive(Debug)]
+pub enum SyncAction {
+  /// The game wasn't installed, so an upload was downloaded
+  Installed(UploadID),
+  /// The installed upload was upgraded to its latest build
+  Updated(UploadID),
+  /// The game was already installed, and there isn't a newer build of it
+  UpToDate,
+  /// The upload was already installed, so it wasn't downloaded again nor checked for updates
+  AlreadyInstalled,
+  /// The game isn't in the collection anymore, so its upload was removed
+  Pruned(UploadID),
+  /// Something went wrong, but the sync continued with the other games
+  Failed(ScratchError),
+}
+
+/// The progress of each upload in [`download_all_uploads`]
+#[derive(Debug)]
+pub enum UploadBatchStatus<'a> {
+  /// The download progress of the upload
+  Download(DownloadStatus),
+  /// The upload was processed, with the action done with it
+  Done(&'a SyncAction),
 }
 
 pub enum LaunchMethod {
@@ -75,6 +256,7 @@ pub enum LaunchMethod {
   Heuristics {
     game_platform: GamePlatform,
     game_title: String,
+    config: HeuristicsConfig,
   },
 }
 
@@ -85,20 +267,461 @@ pub struct InstalledUpload {
   pub game_folder: PathBuf,
   pub game_id: GameID,
   pub game_title: String,
+  /// The fingerprint of the upload folder when it was installed, if it was computed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub fingerprint: Option<Fingerprint>,
+  /// The game's minimum price when it was installed or last refreshed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub game_min_price: Option<u64>,
+  /// The ID of the installed build, if the upload is backed by a wharf channel and the build is known
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub installed_build_id: Option<BuildID>,
+  /// How the user chose to launch the upload, see [`InstalledUpload::set_launch_override`]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub launch_override: Option<LaunchOverride>,
+  /// When the upload was downloaded or imported
+  ///
+  /// Uploads installed before this was tracked are set to the Unix epoch
+  #[serde(with = "time::serde::rfc3339", default = "unknown_install_date")]
+  pub installed_at: OffsetDateTime,
+  /// When the upload was last launched, see [`LaunchResult::started_at`]
+  #[serde(
+    with = "time::serde::rfc3339::option",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub last_played_at: Option<OffsetDateTime>,
+  /// The total time the upload has been played, accumulated with [`InstalledUpload::record_launch`]
+  #[serde(default, skip_serializing_if = "Duration::is_zero")]
+  pub total_playtime: Duration,
+  /// The file to launch, if the upload wasn't an archive and was installed without extracting it
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub launch_target: Option<SingleFileTarget>,
+}
+
+/// The file of an upload that wasn't an archive, which was installed as it was downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SingleFileTarget {
+  /// The file to launch, relative to the upload folder
+  pub file: PathBuf,
+  /// The platform of the file, if it is known from its type
+  ///
+  /// Java archives are [`GamePlatform::Java`], and [`launch`] runs them with `java -jar`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub platform: Option<GamePlatform>,
+}
+
+impl SingleFileTarget {
+  // Get the launch target of an upload installed without extracting it,
+  // or None if the file can't be launched on this device
+  fn from_single_file(file: PathBuf) -> Option<Self> {
+    if game_files::is_android_package(&file) || game_files::is_disk_image(&file) {
+      return None;
+    }
+
+    let platform = game_files::is_java_archive(&file).then_some(GamePlatform::Java);
+    Some(Self { file, platform })
+  }
+}
+
+// The install date of the uploads installed before it was tracked
+const fn unknown_install_date() -> OffsetDateTime {
+  OffsetDateTime::UNIX_EPOCH
+}
+
+/// Information about a finished [`launch`] of an installed upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchResult {
+  /// When the game process was started
+  ///
+  /// It should be saved in [`InstalledUpload::last_played_at`]
+  pub started_at: OffsetDateTime,
+  /// How long the game process ran
+  pub played_for: Duration,
+  /// The exit status of the game process, to tell a clean quit from a crash
+  pub exit_status: std::process::ExitStatus,
+  /// The standard error output of the game process, if it was captured
+  pub stderr: Option<String>,
+}
+
+/// A launch configuration chosen by the user for an installed upload, used instead of the heuristics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchOverride {
+  /// The executable to launch, relative to the upload folder
+  ///
+  /// If None, the executable is still chosen by the launch method, but the rest of the options are used
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub executable_path: Option<PathBuf>,
+  /// A wrapper and its options to run the executable with
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub wrapper: Vec<String>,
+  /// The arguments to launch the executable with
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub game_arguments: Vec<String>,
+  /// The environment variables added to the executable process's environment
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub environment_variables: Vec<(String, String)>,
+}
+
+/// The placeholder of a [`WrapperTemplate`] argument replaced by the game executable
+pub const EXECUTABLE_PLACEHOLDER: &str = "{executable}";
+
+/// A wrapper used to launch the games of a platform, like Wine for the Windows games on Linux
+///
+/// See the `wrapper_templates` argument of [`launch`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrapperTemplate {
+  /// The wrapper and its options, like `["wine"]`
+  ///
+  /// The game executable is added after them, unless an argument contains
+  /// [`EXECUTABLE_PLACEHOLDER`], like `["gamescope", "--", "{executable}"]`
+  pub command: Vec<String>,
+  /// The environment variables added to the game process, like the `WINEPREFIX`
+  ///
+  /// The environment variables passed to [`launch`] take precedence over them
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub environment_variables: Vec<(String, String)>,
+}
+
+/// Where a [`LaunchTarget`] was found
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LaunchTargetSource {
+  /// An action of the itch manifest of the installed upload
+  Manifest,
+  /// A file of the archive found by the itch.io servers when the upload was scanned
+  ServerScan,
+  /// The executable chosen by the launch heuristics for one of the upload's platforms
+  Heuristics,
+}
+
+/// Something that can be launched from an installed upload, see [`get_launch_targets`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchTarget {
+  /// A name to show to the user, like the manifest action name or the file name
+  pub name: String,
+  /// The path of the file to launch, inside the upload folder
+  pub path: PathBuf,
+  /// The platform the target runs on, if it is known
+  pub platform: Option<GamePlatform>,
+  pub source: LaunchTargetSource,
+}
+
+impl LaunchTarget {
+  /// Get the launch method to pass to [`launch`] to run this target
+  #[must_use]
+  pub fn launch_method(&self) -> LaunchMethod {
+    match self.source {
+      LaunchTargetSource::Manifest => LaunchMethod::ManifestAction {
+        manifest_action_name: self.name.clone(),
+      },
+      LaunchTargetSource::ServerScan | LaunchTargetSource::Heuristics => {
+        LaunchMethod::AlternativeExecutable {
+          executable_path: self.path.clone(),
+        }
+      }
+    }
+  }
+}
+
+impl LaunchTargetFlavor {
+  /// Get the platform the files of this flavor run on, if it is known
+  const fn to_game_platform(self) -> Option<GamePlatform> {
+    match self {
+      Self::Linux => Some(GamePlatform::Linux),
+      Self::Macos | Self::AppMacos => Some(GamePlatform::OSX),
+      Self::Windows | Self::ScriptWindows | Self::Msi => Some(GamePlatform::Windows),
+      Self::Jar => Some(GamePlatform::Java),
+      Self::Html => Some(GamePlatform::Web),
+      Self::Script | Self::Love | Self::Unknown => None,
+    }
+  }
+}
+
+/// A change in the status of an installed upload's game since it was installed or last refreshed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusChange {
+  /// The game was free, but now it has a minimum price
+  BecamePaid { min_price: u64 },
+  /// The game had a minimum price, but now it is free
+  BecameFree,
+  /// The game's title has changed
+  TitleChanged {
+    old_title: String,
+    new_title: String,
+  },
+  /// The game doesn't exist anymore, or it isn't accessible
+  Delisted,
+}
+
+impl InstalledUpload {
+  /// Get the folder where the upload files are placed
+  #[must_use]
+  pub fn upload_folder(&self) -> PathBuf {
+    game_files::get_upload_folder(&self.game_folder, self.upload_id)
+  }
+
+  /// Save the last time the upload was played and add the session to its total playtime
+  ///
+  /// # Arguments
+  ///
+  /// * `launch_result` - The result of a finished [`launch`] of this upload
+  pub fn record_launch(&mut self, launch_result: &LaunchResult) {
+    self.last_played_at = Some(launch_result.started_at);
+    self.total_playtime = self.total_playtime.saturating_add(launch_result.played_for);
+  }
+
+  /// Save the current time as the last time the upload was played
+  ///
+  /// A game started with [`launch_detached`] isn't waited for, so its playtime can't be recorded
+  pub fn record_detached_launch(&mut self) {
+    self.last_played_at = Some(OffsetDateTime::now_utc());
+  }
+
+  /// Set or clear the launch configuration chosen by the user for this upload
+  ///
+  /// The executable path can be absolute or relative to the upload folder, and it is stored
+  /// relative to it, so the override keeps working if the game folder is moved
+  ///
+  /// # Arguments
+  ///
+  /// * `launch_override` - The new launch configuration, or None to clear it
+  ///
+  /// # Errors
+  ///
+  /// If the executable doesn't exist or it isn't inside the upload folder
+  pub fn set_launch_override(
+    &mut self,
+    launch_override: Option<LaunchOverride>,
+  ) -> Result<(), ScratchError> {
+    let Some(mut launch_override) = launch_override else {
+      self.launch_override = None;
+      return Ok(());
+    };
+
+    if let Some(executable_path) = &launch_override.executable_path {
+      let upload_folder: PathBuf = filesystem::get_canonical_path(&self.upload_folder())?;
+      let executable_path: PathBuf =
+        filesystem::get_canonical_path(&upload_folder.join(executable_path))?;
+
+      let relative_path = executable_path.strip_prefix(&upload_folder).map_err(|_| {
+        ScratchError::Launch(format!(
+          "The launch executable must be inside the upload folder!\n  Executable: \"{}\"\n  Upload folder: \"{}\"",
+          executable_path.display(),
+          upload_folder.display()
+        ))
+      })?;
+
+      launch_override.executable_path = Some(relative_path.to_path_buf());
+    }
+
+    self.launch_override = Some(launch_override);
+    Ok(())
+  }
+
+  /// Get the launch method of the executable chosen by the user, if there is one
+  ///
+  /// The returned method can be passed to [`launch`] instead of [`LaunchMethod::Heuristics`]
+  #[must_use]
+  pub fn launch_override_method(&self) -> Option<LaunchMethod> {
+    let executable_path = self.launch_override.as_ref()?.executable_path.as_ref()?;
+
+    Some(LaunchMethod::AlternativeExecutable {
+      executable_path: self.upload_folder().join(executable_path),
+    })
+  }
+
+  /// Get the launch method of the file installed without extracting it, if the upload wasn't an archive
+  ///
+  /// The returned method can be passed to [`launch`] instead of [`LaunchMethod::Heuristics`]
+  #[must_use]
+  pub fn launch_target_method(&self) -> Option<LaunchMethod> {
+    let launch_target = self.launch_target.as_ref()?;
+
+    Some(LaunchMethod::AlternativeExecutable {
+      executable_path: self.upload_folder().join(&launch_target.file),
+    })
+  }
+
+  /// Compare the stored game info with its current info
+  ///
+  /// # Arguments
+  ///
+  /// * `game` - The current info of the game, or None if it has been delisted
+  ///
+  /// # Returns
+  ///
+  /// A vector with all the detected changes
+  #[must_use]
+  pub fn detect_status_changes(&self, game: Option<&GameCommon>) -> Vec<StatusChange> {
+    let Some(game) = game else {
+      return vec![StatusChange::Delisted];
+    };
+
+    let mut changes: Vec<StatusChange> = Vec::new();
+
+    // The price can only be compared if it was stored
+    match self.game_min_price {
+      Some(0) if game.min_price > 0 => changes.push(StatusChange::BecamePaid {
+        min_price: game.min_price,
+      }),
+      Some(p) if p > 0 && game.min_price == 0 => changes.push(StatusChange::BecameFree),
+      _ => (),
+    }
+
+    if self.game_title != game.title {
+      changes.push(StatusChange::TitleChanged {
+        old_title: self.game_title.clone(),
+        new_title: game.title.clone(),
+      });
+    }
+
+    changes
+  }
+}
+
+/// The options of [`download_upload_with_options`]
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+  /// The owned key which grants access to the upload
+  ///
+  /// If None and the game isn't free, it is searched in the user's owned keys
+  pub download_key_id: Option<OwnedKeyID>,
+  /// The UUID of a download key, like the one in the download page URL of a purchase
+  ///
+  /// If provided, it is used instead of `download_key_id`
+  pub download_key_uuid: Option<String>,
+  /// If true, don't check the downloaded upload integrity (insecure)
+  pub skip_hash_verification: bool,
+  /// If true, skip the archive entries that can't be extracted instead of failing
+  pub lenient_extraction: bool,
+  /// The password used to extract the upload archive, if it is encrypted
+  pub archive_password: Option<String>,
+  /// The number of connections used to download the upload file in parallel
+  ///
+  /// Parallel downloads are only used for new downloads when the server supports
+  /// range requests. If they are interrupted, they start again from the beginning
+  pub download_connections: NonZeroUsize,
+  /// The maximum download speed in bytes per second, shared by all the connections
+  ///
+  /// If None, the speed isn't limited
+  pub max_bytes_per_sec: Option<u64>,
+  /// If true, extract tar based archives while they are being downloaded, without storing them on disk
+  ///
+  /// This halves the disk space and writes needed to install large games, but an interrupted
+  /// download can't be resumed. The archive hash is still verified, and the extracted files are
+  /// discarded if it doesn't match. Other formats, and downloads which are already partially
+  /// downloaded, are downloaded and extracted as usual
+  pub stream_extract: bool,
+  /// A scratch folder where the upload is downloaded and extracted before being moved to the game folder
+  ///
+  /// It can be on another filesystem, like a fast local disk when the games are stored on a network
+  /// drive. If None, the upload is downloaded and extracted directly inside the game folder.
+  /// A partial download left in it can be removed passing it as the game folder of [`remove_partial_download`]
+  pub temp_dir: Option<PathBuf>,
+  /// Other base URLs of the itch.io API, like `https://mirror.itch.zone/`, tried in order
+  /// if the server of the previous one fails with a 5xx code or the connection can't be recovered
+  ///
+  /// The download endpoint is requested from them with the API key, so they must be HTTPS addresses
+  /// of a domain owned by itch.io, like `itch.io` or `itch.zone`, or the download fails with
+  /// [`ScratchError::InvalidInput`]. The partially downloaded file is resumed from the next mirror.
+  /// They aren't used when the archive is extracted while it is being downloaded
+  pub mirrors: Vec<String>,
+  /// Stop the download if its speed stays below these bytes per second during the whole time span
+  ///
+  /// The download is resumed from the next mirror, if there is one, or it fails with
+  /// [`ScratchError::StalledDownload`], keeping the partial download. The speed must be lower than
+  /// `max_bytes_per_sec`, and the time span long enough for a slow but steady connection.
+  /// A connection which doesn't receive any data during the time span is stopped too.
+  /// Parallel and streamed downloads aren't checked. If None, a slow download is never stopped
+  pub min_speed: Option<(u64, Duration)>,
+  /// The minimum time span between each progress callback call
+  pub callback_interval: Duration,
+  /// A token to stop the download
+  ///
+  /// The partial download is kept, so it can be resumed later or removed with [`remove_partial_download`]
+  pub cancel: CancellationToken,
+}
+
+impl Default for DownloadOptions {
+  fn default() -> Self {
+    Self {
+      download_key_id: None,
+      download_key_uuid: None,
+      skip_hash_verification: false,
+      lenient_extraction: false,
+      archive_password: None,
+      download_connections: NonZeroUsize::MIN,
+      max_bytes_per_sec: None,
+      stream_extract: false,
+      temp_dir: None,
+      mirrors: Vec::new(),
+      min_speed: None,
+      callback_interval: Duration::from_millis(100),
+      cancel: CancellationToken::new(),
+    }
+  }
+}
+
+/// Where an upload would be downloaded and how big it is, returned by [`plan_download`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadPlan {
+  /// The folder where the game files would be placed
+  pub game_folder: PathBuf,
+  /// The path where the upload file would be downloaded
+  pub upload_archive: PathBuf,
+  /// The folder where the upload would be extracted
+  pub upload_folder: PathBuf,
+  /// The size of the upload file reported by the server
+  pub content_length: Option<u64>,
+  /// The size of the partially downloaded file, if there is one
+  pub partial_download_size: Option<u64>,
+  /// Whether the server provides a hash to verify the download
+  pub has_hash: bool,
+}
+
+/// The error returned when a download is stopped with a [`CancellationToken`]
+pub const DOWNLOAD_CANCELLED_ERROR: &str = "The download was cancelled";
+
+/// An installed upload with a newer build available, returned by [`check_updates`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateInfo {
+  pub upload_id: UploadID,
+  pub game_id: GameID,
+  pub game_title: String,
+  /// The ID of the installed build
+  pub installed_build_id: BuildID,
+  /// The ID of the latest build
+  pub latest_build_id: BuildID,
+  /// The version name of the latest build, if the developer set one
+  pub latest_user_version: Option<String>,
+}
+
+/// A summary of the user's library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnedSummary {
+  /// The number of different games owned by the user
+  pub owned_games: u64,
+  /// The sum of the download counts of all the owned keys
+  pub total_downloads: u64,
+  /// The number of owned games with at least one installed upload
+  pub installed_games: u64,
 }
 
-/// Hash a file into a MD5 hasher
+/// Hash a file into a hasher
 ///
 /// # Arguments
 ///
 /// * `readable` - Anything that implements [`std::io::BufRead`] to read the data from, could be a File
 ///
-/// * `hasher` - A mutable reference to a MD5 hasher, which will be updated with the file data
+/// * `hasher` - A mutable reference to a hasher of any [`ChecksumAlgorithm`], which will be updated with the file data
 ///
 /// # Returns
 ///
 /// An error if something goes wrong
-fn hash_readable(reader: &mut impl std::io::BufRead, hasher: &mut Md5) -> Result<(), String> {
+fn hash_readable(
+  reader: &mut impl std::io::BufRead,
+  hasher: &mut Hasher,
+) -> Result<(), FilesystemError> {
   loop {
     let chunk = filesystem::fill_buffer(reader)?;
 
@@ -116,43 +739,157 @@ fn hash_readable(reader: &mut impl std::io::BufRead, hasher: &mut Md5) -> Result
   }
 }
 
-/// Stream a reqwest [`Response`] into a [`std::fs::File`]
+/// An error while downloading a file
+pub(crate) enum DownloadError {
+  /// The connection failed while receiving the data, so the download can be resumed
+  Connection(String),
+  /// The server replied with an error code (5xx), so the download can be resumed from a mirror
+  Server(String),
+  /// The download speed stayed below the minimum speed, so it can be resumed from a mirror
+  Stalled {
+    min_bytes_per_sec: u64,
+    window: Duration,
+  },
+  /// The downloaded file doesn't match the hash provided by the server
+  HashMismatch {
+    expected: String,
+    actual: String,
+    file: PathBuf,
+  },
+  /// Any other error
+  Other(String),
+}
+
+impl From<String> for DownloadError {
+  fn from(value: String) -> Self {
+    Self::Other(value)
+  }
+}
+
+impl From<FilesystemError> for DownloadError {
+  fn from(value: FilesystemError) -> Self {
+    Self::Other(value.into())
+  }
+}
+
+impl From<DownloadError> for ScratchError {
+  fn from(value: DownloadError) -> Self {
+    match value {
+      DownloadError::Connection(e) | DownloadError::Server(e) | DownloadError::Other(e) => {
+        Self::Download(e)
+      }
+      DownloadError::Stalled {
+        min_bytes_per_sec,
+        window,
+      } => Self::StalledDownload {
+        min_bytes_per_sec,
+        window,
+      },
+      DownloadError::HashMismatch {
+        expected,
+        actual,
+        file,
+      } => Self::HashMismatch {
+        expected,
+        actual,
+        file,
+      },
+    }
+  }
+}
+
+/// Stream a download response body into a [`std::fs::File`]
 ///
 /// # Arguments
 ///
-/// * `response` - A file download response
+/// * `response` - The body of a file download response
 ///
 /// * `file` - An opened [`std::fs::File`] with write access
 ///
-/// * `md5_hash` - If provided, the hasher to update with the received data
+/// * `hasher` - If provided, the hasher to update with the received data
+///
+/// * `checkpoint` - If provided, where the hasher state is saved periodically and when the download
+///   stops, with the number of bytes of the file that were already hashed before this response.
+///   Only the MD5 state can be saved
 ///
 /// * `progress_callback` - A closure called with the number of downloaded bytes at the moment
 ///
 /// * `callback_interval` - The minimum time span between each `progress_callback` call
 ///
+/// * `cancel` - A token to stop the download. The received data is synced to the file before returning
+///
+/// * `min_speed` - If provided, stop the download if its speed stays below these bytes per second
+///   during the whole time span, or if no data is received during it
+///
 /// # Returns
 ///
 /// The total downloaded bytes
 ///
 /// An error if something goes wrong
+#[expect(clippy::too_many_arguments)]
 fn stream_response_into_file(
-  response: Response,
+  response: impl std::io::Read + Send + 'static,
   file: &mut std::fs::File,
-  mut md5_hash: Option<&mut Md5>,
+  mut hasher: Option<&mut Hasher>,
+  checkpoint: Option<(&HashCheckpoint, u64)>,
   progress_callback: impl Fn(u64),
   callback_interval: Duration,
-) -> Result<u64, String> {
+  cancel: &CancellationToken,
+  throttle: &Throttle,
+  min_speed: Option<(u64, Duration)>,
+) -> Result<u64, DownloadError> {
   use std::io::BufRead;
 
   // Prepare the download and the callback variables
   let mut downloaded_bytes: u64 = 0;
   let mut last_callback = Instant::now();
+  let mut last_checkpoint: u64 = 0;
+  // An idle connection never sends a chunk to the stall detector, so its reads time out instead
+  let response: Box<dyn std::io::Read> = match min_speed {
+    Some((_, window)) => Box::new(TimeoutReader::new(response, window)),
+    None => Box::new(response),
+  };
   let mut reader = std::io::BufReader::new(response);
+  let mut stall_detector: Option<StallDetector> =
+    min_speed.map(|(min_bytes_per_sec, window)| StallDetector::new(min_bytes_per_sec, window));
+
+  // Save the hasher state after syncing the hashed data, so the file is never behind the checkpoint
+  let save_checkpoint = |file: &std::fs::File, hasher: Option<&Hasher>, downloaded_bytes: u64| {
+    if let (Some(Hasher::Md5(hasher)), Some((checkpoint, start))) = (hasher, checkpoint) {
+      filesystem::file_sync_all(file)?;
+      checkpoint.save(hasher, start + downloaded_bytes);
+    }
+    Ok::<(), DownloadError>(())
+  };
 
   // Save chunks to the file
   // Also, compute the MD5 hash while it is being downloaded
   loop {
-    let chunk = filesystem::fill_buffer(&mut reader)?;
+    // If the download was cancelled, ensure the received data is on the disk so it can be resumed
+    if cancel.is_cancelled() {
+      filesystem::file_sync_all(file)?;
+      save_checkpoint(file, hasher.as_deref(), downloaded_bytes)?;
+      return Err(DOWNLOAD_CANCELLED_ERROR.to_string().into());
+    }
+
+    // A failure while reading means the connection was lost, or that it stayed idle
+    let chunk = match filesystem::fill_buffer(&mut reader) {
+      Ok(chunk) => chunk,
+      Err(e) => {
+        save_checkpoint(file, hasher.as_deref(), downloaded_bytes)?;
+        return Err(match (min_speed, &e) {
+          (Some((min_bytes_per_sec, window)), FilesystemError::IOError { error, .. })
+            if error.kind() == std::io::ErrorKind::TimedOut =>
+          {
+            DownloadError::Stalled {
+              min_bytes_per_sec,
+              window,
+            }
+          }
+          _ => DownloadError::Connection(String::from(e)),
+        });
+      }
+    };
 
     // If chunk is empty then the reader has reached the EOF
     if chunk.is_empty() {
@@ -163,8 +900,8 @@ fn stream_response_into_file(
     // Write the chunk to the file
     filesystem::write_all(file, chunk)?;
 
-    // If the file has a MD5 hash, update the hasher
-    if let Some(hasher) = &mut md5_hash {
+    // If the file has a hash, update the hasher
+    if let Some(hasher) = &mut hasher {
       hasher.update(chunk);
     }
 
@@ -175,23 +912,71 @@ fn stream_response_into_file(
       progress_callback(downloaded_bytes);
     }
 
+    // Save a checkpoint from time to time, in case the process is killed
+    if downloaded_bytes - last_checkpoint >= CHECKPOINT_INTERVAL_BYTES {
+      last_checkpoint = downloaded_bytes;
+      save_checkpoint(file, hasher.as_deref(), downloaded_bytes)?;
+    }
+
     // Marked the hashed bytes as read
     let len = chunk.len();
     reader.consume(len);
+
+    // Wait if the download is going faster than the speed limit
+    throttle.consume(len as u64, cancel);
+
+    // Stop a download that is too slow, keeping the received data so it can be resumed
+    if let Some(detector) = &mut stall_detector
+      && detector.record(len as u64)
+    {
+      let (min_bytes_per_sec, window) = min_speed.unwrap_or_default();
+      filesystem::file_sync_all(file)?;
+      save_checkpoint(file, hasher.as_deref(), downloaded_bytes)?;
+      return Err(DownloadError::Stalled {
+        min_bytes_per_sec,
+        window,
+      });
+    }
   }
 }
 
+/// The progress callbacks and the flow control shared by the parts of a download
+pub(crate) struct DownloadContext<'a> {
+  /// A closure called with the total size the downloaded file will have after the download
+  pub file_size_callback: &'a dyn Fn(u64),
+  /// A closure called with the number of downloaded bytes at the moment
+  pub progress_callback: &'a dyn Fn(u64),
+  /// The minimum time span between each `progress_callback` call
+  pub callback_interval: Duration,
+  /// A token to stop the download
+  pub cancel: &'a CancellationToken,
+  /// The bandwidth limiter of the download
+  pub throttle: &'a Throttle,
+}
+
+/// A file downloaded by [`download_file`]
+pub(crate) struct DownloadedFile {
+  /// The number of bytes received from the network, counting the ones of every retry and
+  /// resumed range
+  pub bytes_transferred: u64,
+  /// The index of the mirror the download finished from, or None if it was the main URL
+  pub mirror: Option<usize>,
+}
+
 /// Download a file from an itch API URL
 ///
 /// # Arguments
 ///
-/// * `client` - An itch.io API client
+/// * `transport` - Something which sends the download requests, usually an itch.io API client
 ///
 /// * `url` - A itch.io API address to download the file from
 ///
+/// * `mirrors` - Other addresses of the same file, tried in order if the server of the previous
+///   one fails. The partially downloaded file is resumed from them
+///
 /// * `file_path` - The path where the file will be placed
 ///
-/// * `md5_hash` - A MD5 hash to check the file against. If none, don't verify the download
+/// * `checksum` - A hash to check the file against. If none, don't verify the download
 ///
 /// * `file_size_callback` - A clousure called with total size the downloaded file will have after the download
 ///
@@ -199,20 +984,186 @@ fn stream_response_into_file(
 ///
 /// * `callback_interval` - The minimum time span between each `progress_callback` call
 ///
+/// * `cancel` - A token to stop the download, leaving the partial file to be resumed later
+///
+/// * `connections` - The number of connections used to download a new file in parallel
+///
+/// * `max_bytes_per_sec` - The maximum download speed, shared by all the connections. If None, the speed isn't limited
+///
+/// * `min_speed` - The minimum download speed and the time span it is measured over.
+///   If None, a slow download is never stopped
+///
 /// # Returns
 ///
-/// An error if something goes wrong
-fn download_file(
-  client: &ItchClient,
+/// The transferred bytes and the mirror the file was downloaded from, or an error if something goes wrong
+#[expect(clippy::too_many_arguments)]
+fn download_file<T: DownloadTransport + Sync>(
+  transport: &T,
   url: &ItchApiUrl,
+  mirrors: &[ItchApiUrl],
   file_path: &Path,
-  md5_hash: Option<&str>,
+  checksum: Option<Checksum<'_>>,
   file_size_callback: impl Fn(u64),
   progress_callback: impl Fn(u64),
   callback_interval: Duration,
-) -> Result<(), String> {
+  cancel: &CancellationToken,
+  connections: NonZeroUsize,
+  max_bytes_per_sec: Option<u64>,
+  min_speed: Option<(u64, Duration)>,
+) -> Result<DownloadedFile, ScratchError> {
+  enter_span!(
+    "download_file",
+    url = url.redacted(),
+    file = file_path.display()
+  );
+
+  // The throttle is shared by every attempt, so retries don't reset the speed limit
+  let throttle = Throttle::new(max_bytes_per_sec);
+
+  let mut mirror: Option<usize> = None;
+  loop {
+    let current_url: &ItchApiUrl = mirror.map_or(url, |i| &mirrors[i]);
+
+    let result = download_file_from(
+      transport,
+      current_url,
+      file_path,
+      checksum,
+      &file_size_callback,
+      &progress_callback,
+      callback_interval,
+      cancel,
+      connections,
+      &throttle,
+      min_speed,
+    );
+
+    let next_mirror: usize = mirror.map_or(0, |i| i + 1);
+    match result {
+      // The .part file is kept, so the next mirror resumes it with a new range request
+      Err(
+        e @ (DownloadError::Connection(_)
+        | DownloadError::Server(_)
+        | DownloadError::Stalled { .. }),
+      ) if next_mirror < mirrors.len() && !cancel.is_cancelled() => {
+        debug!(
+          "The download from \"{}\" failed, switching to the mirror \"{}\"\n{}",
+          current_url.redacted(),
+          mirrors[next_mirror].redacted(),
+          ScratchError::from(e)
+        );
+        mirror = Some(next_mirror);
+      }
+      r => {
+        if r.is_ok() {
+          debug!(
+            "Downloaded \"{}\", receiving {} bytes from the network",
+            file_path.display(),
+            throttle.transferred_bytes()
+          );
+        }
+
+        return r
+          .map(|()| DownloadedFile {
+            bytes_transferred: throttle.transferred_bytes(),
+            mirror,
+          })
+          .map_err(ScratchError::from);
+      }
+    }
+  }
+}
+
+/// Download a file from a single address of [`download_file`], retrying if the connection is lost
+#[expect(clippy::too_many_arguments)]
+fn download_file_from<T: DownloadTransport + Sync>(
+  transport: &T,
+  url: &ItchApiUrl,
+  file_path: &Path,
+  checksum: Option<Checksum<'_>>,
+  file_size_callback: &impl Fn(u64),
+  progress_callback: &impl Fn(u64),
+  callback_interval: Duration,
+  cancel: &CancellationToken,
+  connections: NonZeroUsize,
+  throttle: &Throttle,
+  min_speed: Option<(u64, Duration)>,
+) -> Result<(), DownloadError> {
+  // Parallel downloads can't resume a partial download, so only use them for new downloads
+  if connections.get() > 1
+    && !filesystem::exists(file_path)?
+    && !filesystem::exists(&game_files::add_part_extension(file_path)?)?
+    && parallel::download_file_parallel(
+      transport,
+      url,
+      file_path,
+      checksum,
+      connections,
+      &DownloadContext {
+        file_size_callback,
+        progress_callback,
+        callback_interval,
+        cancel,
+        throttle,
+      },
+    )?
+  {
+    return Ok(());
+  }
+
+  let retry_policy: RetryPolicy = transport.retry_policy();
+
+  let mut attempt: u32 = 1;
+  loop {
+    let result = download_file_attempt(
+      transport,
+      url,
+      file_path,
+      checksum,
+      file_size_callback,
+      progress_callback,
+      callback_interval,
+      cancel,
+      throttle,
+      min_speed,
+    );
+
+    match result {
+      // If the connection was lost, the next attempt resumes from the partially downloaded file
+      Err(DownloadError::Connection(e))
+        if retry_policy.should_retry(attempt) && !cancel.is_cancelled() =>
+      {
+        let delay = retry_policy.delay(attempt);
+        debug!(
+          "The download of \"{}\" was interrupted, retrying in {delay:?} (attempt {attempt})\n{e}",
+          url.redacted()
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+      }
+      r => return r,
+    }
+  }
+}
+
+/// A single attempt of [`download_file`]
+///
+/// The partially downloaded file is kept if the connection fails, so the next attempt resumes it
+#[expect(clippy::too_many_arguments)]
+fn download_file_attempt<T: DownloadTransport>(
+  transport: &T,
+  url: &ItchApiUrl,
+  file_path: &Path,
+  checksum: Option<Checksum<'_>>,
+  file_size_callback: &impl Fn(u64),
+  progress_callback: &impl Fn(u64),
+  callback_interval: Duration,
+  cancel: &CancellationToken,
+  throttle: &Throttle,
+  min_speed: Option<(u64, Duration)>,
+) -> Result<(), DownloadError> {
   // Create the hasher variable
-  let mut md5_hash: Option<(Md5, &str)> = md5_hash.map(|s| (Md5::new(), s));
+  let mut hasher: Option<(Hasher, Checksum)> = checksum.map(|c| (Hasher::new(c.algorithm), c));
 
   // The file will be downloaded to this file with the .part extension,
   // and then the extension will be removed when the download ends
@@ -236,13 +1187,32 @@ fn download_file(
 
   let mut downloaded_bytes: u64 = filesystem::read_file_metadata(&file)?.len();
 
-  let file_response: Option<Response> = 'r: {
+  debug!(
+    "Downloading \"{}\" into \"{}\" ({downloaded_bytes} bytes already present)",
+    url.redacted(),
+    partial_file_path.display()
+  );
+
+  let file_response: Option<TransportResponse<T::Body>> = 'r: {
     // Send a request for the whole file
-    let res = client
-      .itch_request(url, Method::GET, |b| b)
-      .map_err(|e| e.to_string())?;
+    let res = transport.get(url, None)?;
 
-    let download_size = res.content_length().ok_or_else(|| {
+    // If the server refused to send the file, don't save its error page as the file
+    if !res.status.is_success() {
+      let e = format!(
+        "The HTTP server to download the file from returned an error code!
+  It returned code: {}
+  URL: {url}",
+        res.status.as_str()
+      );
+      return Err(if res.status.is_server_error() {
+        DownloadError::Server(e)
+      } else {
+        e.into()
+      });
+    }
+
+    let download_size = res.content_length.ok_or_else(|| {
       format!(
         "Couldn't get content length!
   URL: {url}"
@@ -253,21 +1223,27 @@ fn download_file(
 
     // If the file is empty, then return the request for the whole file
     if downloaded_bytes == 0 {
+      debug!("Starting a new download of {download_size} bytes");
       break 'r Some(res);
     }
     // If the file is exactly the size it should be, then return None so nothing more is downloaded
     else if downloaded_bytes == download_size {
+      debug!("The file is already fully downloaded, skipping the download");
       break 'r None;
     }
     // If the file is not empty, and smaller than the whole file, download the remaining file range
     else if downloaded_bytes < download_size {
-      let part_res = client
-        .itch_request(url, Method::GET, |b| {
-          b.header(header::RANGE, format!("bytes={downloaded_bytes}-"))
-        })
-        .map_err(|e| e.to_string())?;
+      debug!("Resuming the download from byte {downloaded_bytes} of {download_size}");
 
-      match part_res.status() {
+      let part_res = transport.get(
+        url,
+        Some(ByteRange {
+          start: downloaded_bytes,
+          end: None,
+        }),
+      )?;
+
+      match part_res.status {
         // 206 Partial Content code means the server will send the requested range
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Status/206
         reqwest::StatusCode::PARTIAL_CONTENT => break 'r Some(part_res),
@@ -275,16 +1251,27 @@ fn download_file(
         // 200 OK code means the server doesn't support ranges
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/Range
         // Don't break, so the fallback code is run instead and the whole file is downloaded
-        reqwest::StatusCode::OK => (),
+        reqwest::StatusCode::OK => {
+          debug!("The server doesn't support ranges, downloading the whole file again");
+        }
 
         // Any code other than 200 or 206 means that something went wrong
-        _ => {
-          return Err(format!(
+        status => {
+          let e = format!(
             "The HTTP server to download the file from didn't return HTTP code 200 nor 206, so exiting!
   It returned code: {}
-  URL: {url}", part_res.status().as_str()));
+  URL: {url}", status.as_str());
+          return Err(if status.is_server_error() {
+            DownloadError::Server(e)
+          } else {
+            e.into()
+          });
         }
       }
+    } else {
+      debug!(
+        "The partial file is bigger than expected ({downloaded_bytes} > {download_size}), downloading the whole file again"
+      );
     }
 
     // If we're here, that means one of two things:
@@ -299,10 +1286,31 @@ fn download_file(
     Some(res)
   };
 
+  // The hasher state is saved next to the partial file, so resuming doesn't hash all of it again
+  // Only the MD5 state can be saved, so the files verified with other algorithms are hashed fully
+  let checkpoint: Option<HashCheckpoint> = checksum
+    .filter(|c| c.algorithm == ChecksumAlgorithm::Md5)
+    .map(|c| HashCheckpoint::new(&partial_file_path, c.hex))
+    .transpose()?;
+
   // If a partial file was already downloaded, hash the old downloaded data
-  if let Some((ref mut hasher, _)) = md5_hash
+  if let Some((ref mut hasher, _)) = hasher
     && downloaded_bytes > 0
   {
+    // Start hashing from the last checkpoint, if there is a valid one
+    let hashed_bytes: u64 = match checkpoint.as_ref().and_then(|c| c.load(downloaded_bytes)) {
+      Some((saved_hasher, offset)) => {
+        *hasher = Hasher::Md5(saved_hasher);
+        offset
+      }
+      None => 0,
+    };
+
+    debug!(
+      "Hashing the {} bytes that were already downloaded ({hashed_bytes} bytes restored from the checkpoint)",
+      downloaded_bytes - hashed_bytes
+    );
+    filesystem::seek(&mut file, hashed_bytes)?;
     let mut file_reader = std::io::BufReader::new(&mut file);
     hash_readable(&mut file_reader, hasher)?;
   }
@@ -310,23 +1318,37 @@ fn download_file(
   // Stream the Response into the File
   if let Some(res) = file_response {
     stream_response_into_file(
-      res,
+      res.body,
       &mut file,
-      md5_hash.as_mut().map(|(h, _)| h),
+      hasher.as_mut().map(|(h, _)| h),
+      checkpoint.as_ref().map(|c| (c, downloaded_bytes)),
       |b| progress_callback(downloaded_bytes + b),
       callback_interval,
+      cancel,
+      throttle,
+      min_speed,
     )?;
   }
 
+  // The whole file is hashed, so the checkpoint isn't needed anymore
+  if let Some(c) = &checkpoint {
+    c.remove()?;
+  }
+
   // If the hashes aren't equal, exit with an error
-  if let Some((hasher, hash)) = md5_hash {
-    let file_hash = hex::encode(hasher.finalize());
+  if let Some((hasher, checksum)) = hasher {
+    let file_hash = hasher.finalize_hex();
+    trace!(
+      "Downloaded file hash: {file_hash}, expected hash: {}",
+      checksum.hex
+    );
 
-    if !file_hash.eq_ignore_ascii_case(hash) {
-      return Err(format!("File verification failed! The file hash and the hash provided by the server are different.\n
-  File hash:   {file_hash}
-  Server hash: {hash}"
-      ));
+    if !checksum.matches(&file_hash) {
+      return Err(DownloadError::HashMismatch {
+        expected: checksum.hex.to_string(),
+        actual: file_hash,
+        file: partial_file_path,
+      });
     }
   }
 
@@ -340,404 +1362,2349 @@ fn download_file(
   Ok(())
 }
 
-/// Find out which platforms a game's uploads are available in
+/// Obtain the information about an upload and the game it belongs to
 ///
 /// # Arguments
 ///
-/// * `uploads` - A list of a game's uploads
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload from which information will be obtained
 ///
 /// # Returns
 ///
-/// A vector of tuples containing an upload ID and the [`GamePlatform`] in which it is available
-#[must_use]
-pub fn get_game_platforms(uploads: &[Upload]) -> Vec<(UploadID, GamePlatform)> {
-  let mut platforms: Vec<(UploadID, GamePlatform)> = Vec::new();
+/// The upload and the game info
+///
+/// An error if something goes wrong, or if the returned game doesn't match the upload's game
+fn get_upload_and_game(
+  client: &ItchClient,
+  upload_id: UploadID,
+) -> Result<(Upload, Game), ScratchError> {
+  let upload: Upload = get_upload_info(client, upload_id)?;
+  let game: Game = get_game_info(client, upload.game_id)?;
 
-  for u in uploads {
-    for p in u.to_game_platforms() {
-      platforms.push((u.id, p));
-    }
+  // The API should always return the game the upload belongs to, but check it anyways
+  if game.game_info.id != upload.game_id {
+    return Err(ScratchError::Api(
+      format!(
+        "The itch.io API returned a game that doesn't match the upload's game!
+  Upload ID: {upload_id}
+  Upload's game ID: {}
+  Returned game ID: {}",
+        upload.game_id, game.game_info.id
+      )
+      .into(),
+    ));
   }
 
-  platforms
+  Ok((upload, game))
 }
 
-/// Download a game cover image from its game ID
+/// Check that an upload belongs to a game
 ///
-/// The image will be a PNG. This is because the itch.io servers return that type of image
+/// This should be called when both the upload ID and the game ID are provided
+/// by the user, to avoid operating on the wrong game folder if they don't match
 ///
 /// # Arguments
 ///
 /// * `client` - An itch.io API client
 ///
-/// * `game_id` - The ID of the game from which the cover will be downloaded
-///
-/// * `folder` - The game folder where the cover will be placed
-///
-/// * `cover_filename` - The new filename of the cover
+/// * `upload_id` - The ID of the upload to check
 ///
-/// * `force_download` - If true, download the cover image again, even if it already exists
+/// * `game_id` - The ID of the game the upload should belong to
 ///
 /// # Returns
 ///
-/// The path of the downloaded image, or None if the game doesn't have one
+/// The upload info
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn download_game_cover(
+/// If the upload doesn't belong to the game, or something else goes wrong
+pub fn assert_upload_in_game(
   client: &ItchClient,
+  upload_id: UploadID,
   game_id: GameID,
-  folder: &Path,
-  cover_filename: Option<&str>,
-  force_download: bool,
-) -> Result<Option<PathBuf>, String> {
-  // Get the game info from the server
-  let game = get_game_info(client, game_id).map_err(|e| e.to_string())?;
-  // If the game doesn't have a cover, return
-  let Some(cover_url) = game.game_info.cover_url else {
-    return Ok(None);
-  };
-
-  // Create the folder where the file is going to be placed if it doesn't already exist
-  filesystem::create_dir(folder)?;
-
-  // If the cover filename isn't set, set it to "cover"
-  let cover_filename = match cover_filename {
-    Some(f) => f,
-    None => game_files::COVER_IMAGE_DEFAULT_FILENAME,
-  };
+) -> Result<Upload, ScratchError> {
+  let upload: Upload = get_upload_info(client, upload_id)?;
 
-  let cover_path = folder.join(cover_filename);
-
-  // If the cover image already exists and the force variable is false, don't replace the original image
-  if !force_download && filesystem::exists(&cover_path)? {
-    return Ok(Some(cover_path));
+  if upload.game_id != game_id {
+    return Err(ScratchError::InvalidInput(format!(
+      "The upload doesn't belong to the provided game!
+  Upload ID: {upload_id}
+  Provided game ID: {game_id}
+  Upload's game ID: {}",
+      upload.game_id
+    )));
   }
 
-  download_file(
-    client,
-    &ItchApiUrl::other(cover_url),
-    &cover_path,
-    None,
-    |_| (),
-    |_| (),
-    Duration::MAX,
-  )?;
-
-  Ok(Some(cover_path))
+  Ok(upload)
 }
 
-/// Download a game upload
+/// Get the ID of a game from the URL of its itch.io page
+///
+/// Both game pages (`https://dev.itch.io/mygame`) and their download pages are supported,
+/// as well as `itch.io/...` URLs. The URL scheme can be omitted
 ///
 /// # Arguments
 ///
 /// * `client` - An itch.io API client
 ///
-/// * `upload_id` - The ID of the upload which will be downloaded
-///
-/// * `game_folder` - The folder where the downloadeded game files will be placed
-///
-/// * `skip_hash_verification` - If true, don't check the downloaded upload integrity (insecure)
-///
-/// * `upload_info` - A closure which reports the upload and the game info before the download starts
-///
-/// * `progress_callback` - A closure which reports the download progress
-///
-/// * `callback_interval` - The minimum time span between each `progress_callback` call
+/// * `url` - The URL of an itch.io game page or download page
 ///
 /// # Returns
 ///
-/// The installation info about the upload
+/// The ID of the game
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn download_upload(
-  client: &ItchClient,
-  upload_id: UploadID,
-  game_folder: &Path,
-  skip_hash_verification: bool,
-  upload_info: impl FnOnce(&Upload, &Game),
-  progress_callback: impl Fn(DownloadStatus),
-  callback_interval: Duration,
-) -> Result<InstalledUpload, String> {
-  // --- DOWNLOAD PREPARATION ---
+/// If the URL isn't an itch.io URL, the page couldn't be retrieved, or it isn't a game page
+pub fn resolve_game_url(client: &ItchClient, url: &str) -> Result<GameID, ScratchError> {
+  // Allow URLs without a scheme, like the ones copied from the address bar
+  let url: String = if url.contains("://") {
+    url.to_string()
+  } else {
+    format!("https://{url}")
+  };
 
-  // Obtain information about the game and the upload that will be downloaeded
-  let upload: Upload = get_upload_info(client, upload_id).map_err(|e| e.to_string())?;
-  let game: Game = get_game_info(client, upload.game_id).map_err(|e| e.to_string())?;
+  let parsed_url = reqwest::Url::parse(&url)
+    .map_err(|e| ScratchError::InvalidInput(format!("Invalid URL: \"{url}\"\n{e}")))?;
 
-  // Send to the caller the game and the upload info
-  upload_info(&upload, &game);
+  // Only itch.io pages can be resolved, so don't send requests to other sites
+  let is_itch_url: bool = parsed_url
+    .host_str()
+    .is_some_and(|h| h == "itch.io" || h.ends_with(".itch.io"));
+  if !is_itch_url {
+    return Err(ScratchError::InvalidInput(format!(
+      "The URL isn't an itch.io URL: \"{url}\""
+    )));
+  }
 
-  // upload_archive is the location where the upload will be downloaded
-  let upload_archive: PathBuf =
-    game_files::get_upload_archive_path(game_folder, upload_id, &upload.filename);
+  let page: String = client
+    .itch_request(&ItchApiUrl::other(url.clone()), Method::GET, |b| b)
+    .and_then(reqwest::blocking::Response::error_for_status)
+    .and_then(reqwest::blocking::Response::text)
+    .map_err(|e| ScratchError::Api(e.into()))?;
 
-  // Create the game folder if it doesn't already exist
-  filesystem::create_dir(game_folder)?;
+  // Game pages have a meta tag like this: <meta name="itch:path" content="games/12345"/>
+  let game_id: Option<GameID> = page
+    .split_once(r#"name="itch:path""#)
+    .and_then(|(_, rest)| rest.split_once(r#"content=""#))
+    .and_then(|(_, rest)| rest.split_once('"'))
+    .and_then(|(path, _)| path.strip_prefix("games/"))
+    .and_then(|id| id.parse().ok());
 
-  // Get the upload's hash
-  let hash: Option<&str> = upload.get_hash();
+  game_id.ok_or_else(|| {
+    ScratchError::InvalidInput(format!("The URL isn't an itch.io game page: \"{url}\""))
+  })
+}
 
-  // --- DOWNLOAD ---
+/// Find out which platforms a game's uploads are available in
+///
+/// # Arguments
+///
+/// * `uploads` - A list of a game's uploads
+///
+/// # Returns
+///
+/// A [`GamePlatformsResult`] which distinguishes a game without uploads from
+/// a game whose uploads aren't available in any recognized platform
+#[must_use]
+pub fn get_game_platforms(uploads: &[Upload]) -> GamePlatformsResult {
+  // The game has no downloadable files (maybe web-only or not yet released)
+  if uploads.is_empty() {
+    return GamePlatformsResult::NoUploads;
+  }
 
-  // Download the file
-  download_file(
-    client,
-    &ItchApiUrl::v2(&format!("uploads/{upload_id}/download")),
-    &upload_archive,
-    // Only pass the hash if skip_hash_verification is false
-    hash.filter(|_| !skip_hash_verification),
-    |bytes| {
-      progress_callback(DownloadStatus::StartingDownload {
-        bytes_to_download: bytes,
-      });
-    },
-    |bytes| {
-      progress_callback(DownloadStatus::DownloadProgress {
-        downloaded_bytes: bytes,
-      });
-    },
-    callback_interval,
-  )?;
+  let mut platforms: Vec<(UploadID, GamePlatform)> = Vec::new();
 
-  // Print a warning if the upload doesn't have a hash in the server
-  // or the hash verification is skipped
-  if skip_hash_verification {
-    progress_callback(DownloadStatus::Warning(
-      "Skipping hash verification! The file integrity won't be checked!".to_string(),
-    ));
-  } else if hash.is_none() {
-    progress_callback(DownloadStatus::Warning(
-      "Missing MD5 hash. Couldn't verify the file integrity!".to_string(),
-    ));
+  for u in uploads {
+    for p in u.to_game_platforms() {
+      platforms.push((u.id, p));
+    }
   }
 
-  // --- FILE EXTRACTION ---
+  if platforms.is_empty() {
+    GamePlatformsResult::NoRecognizedPlatforms
+  } else {
+    GamePlatformsResult::Platforms(platforms)
+  }
+}
 
-  progress_callback(DownloadStatus::Extract);
+/// Select the best upload of a game for the given platform
+///
+/// Non-demo uploads are preferred over demos, unless `prefer_demo` is true.
+/// If there are several candidates, the most recently updated one is selected
+///
+/// # Arguments
+///
+/// * `uploads` - A list of a game's uploads
+///
+/// * `platform` - The platform the upload has to be available in
+///
+/// * `prefer_demo` - If true, prefer demo uploads over the full game
+///
+/// # Returns
+///
+/// The selected upload, or None if no upload is available in the platform
+#[must_use]
+pub fn select_upload_for_platform<'a>(
+  uploads: &'a [Upload],
+  platform: &GamePlatform,
+  prefer_demo: bool,
+) -> Option<&'a Upload> {
+  uploads
+    .iter()
+    .filter(|u| u.to_game_platforms().contains(platform))
+    .max_by_key(|u| {
+      (
+        u.traits.contains(&UploadTrait::Demo) == prefer_demo,
+        u.updated_at,
+      )
+    })
+}
 
-  // The new upload_folder is game_folder + the upload id
-  let upload_folder: PathBuf = game_files::get_upload_folder(game_folder, upload_id);
+/// Get the API URL used to download an upload, optionally authorized by a download key
+///
+/// If the download key UUID is provided, like the one in the download page URL of a purchase,
+/// the itch.io API V1 download endpoint is used, because the V2 one only accepts key IDs
+fn get_upload_download_url(
+  upload_id: UploadID,
+  download_key_id: Option<OwnedKeyID>,
+  download_key_uuid: Option<&str>,
+) -> ItchApiUrl {
+  match (download_key_uuid, download_key_id) {
+    (Some(uuid), _) => ItchApiUrl::v1(&format!("download-key/{uuid}/download/{upload_id}")),
+    (None, None) => ItchApiUrl::v2(&format!("uploads/{upload_id}/download")),
+    (None, Some(key)) => ItchApiUrl::v2(&format!(
+      "uploads/{upload_id}/download?download_key_id={key}"
+    )),
+  }
+}
 
-  // Extracts the downloaded archive (if it's an archive)
-  // game_files can be the path of an executable or the path to the extracted folder
-  extract::extract(&upload_archive, &upload_folder)?;
+/// Get the user's download key of a game
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `game_id` - The ID of the game whose download key will be searched
+///
+/// # Returns
+///
+/// The owned key, or None if the user doesn't own the game
+///
+/// # Errors
+///
+/// If the request to get the download key fails
+pub fn get_download_key(
+  client: &ItchClient,
+  game_id: GameID,
+) -> Result<Option<OwnedKey>, ScratchError> {
+  match get_game_download_key(client, game_id) {
+    Ok(key) => Ok(key),
+    // If the targeted endpoint isn't available, search the game in all the owned keys
+    Err(ItchRequestJSONError {
+      kind:
+        ItchRequestJSONErrorKind::InvalidJSON { .. }
+        | ItchRequestJSONErrorKind::ServerRepliedWithError(DownloadKeyResponseError::Other(
+          ApiResponseCommonErrors::Other(_),
+        )),
+      ..
+    }) => {
+      debug!("The download key endpoint isn't available, searching the game in the owned keys");
+      let owned_keys: Vec<OwnedKey> = get_owned_keys(client)?;
+      Ok(owned_keys.into_iter().find(|k| k.game_id == game_id))
+    }
+    Err(e) => Err(e.into()),
+  }
+}
 
-  Ok(InstalledUpload {
-    upload_id,
-    // Get the absolute (canonical) form of the path
-    game_folder: filesystem::get_canonical_path(game_folder)?,
-    game_id: game.game_info.id,
-    game_title: game.game_info.title,
-  })
+/// Find the user's owned key of a game
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `game_id` - The ID of the game whose owned key will be searched
+///
+/// # Returns
+///
+/// The ID of the owned key, or None if the user doesn't own the game
+///
+/// # Errors
+///
+/// If the request to get the owned keys fails
+pub fn find_owned_key(
+  client: &ItchClient,
+  game_id: GameID,
+) -> Result<Option<OwnedKeyID>, ScratchError> {
+  get_download_key(client, game_id).map(|k| k.map(|k| k.id))
 }
 
-/// Import an already installed upload
+/// Get the URL where an upload's file is stored, without downloading it
+///
+/// This can be used to download the file with an external download manager
+///
+/// **Warning:** The returned URL grants access to the file without any other
+/// authentication, and it may stop working after some time. Don't hand it to
+/// untrusted tools or share it, because it could be leaked
 ///
 /// # Arguments
 ///
 /// * `client` - An itch.io API client
 ///
-/// * `upload_id` - The ID of the upload which will be imported
+/// * `upload_id` - The ID of the upload whose download URL will be obtained
 ///
-/// * `game_folder` - The folder where the game files are currectly placed
+/// * `download_key_id` - The owned key which grants access to the upload, required for paid games
 ///
 /// # Returns
 ///
-/// The installation info about the upload
+/// The final URL where the upload's file is stored, after following all redirects
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn import(
+/// If the request fails or the server doesn't return a success code
+pub fn get_download_url(
+  client: &ItchClient,
+  upload_id: UploadID,
+  download_key_id: Option<OwnedKeyID>,
+) -> Result<String, ScratchError> {
+  let url = get_upload_download_url(upload_id, download_key_id, None);
+
+  // Send a HEAD request to avoid downloading the file
+  // The redirects are followed automatically, so the response URL is the storage one
+  let res = client
+    .itch_request(&url, Method::HEAD, |b| b)
+    .map_err(|e| ScratchError::Api(e.into()))?;
+
+  if !res.status().is_success() {
+    return Err(ScratchError::Download(format!(
+      "The server returned an error while resolving the download URL!
+  It returned code: {}
+  URL: {url}",
+      res.status().as_str()
+    )));
+  }
+
+  Ok(res.url().to_string())
+}
+
+/// Find out where an upload would be downloaded and how big it is, without downloading it
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which would be downloaded
+///
+/// * `game_folder` - The folder where the downloadeded game files would be placed
+///
+/// * `download_key_id` - The owned key which grants access to the upload. If None and the game isn't free, it is searched in the user's owned keys
+///
+/// # Returns
+///
+/// A [`DownloadPlan`] with the same paths [`download_upload_with_options`] would use
+///
+/// # Errors
+///
+/// If the requests to the itch.io API fail
+pub fn plan_download(
   client: &ItchClient,
   upload_id: UploadID,
   game_folder: &Path,
-) -> Result<InstalledUpload, String> {
-  // Obtain information about the game and the upload that will be downloaeded
-  let upload: Upload = get_upload_info(client, upload_id).map_err(|e| e.to_string())?;
-  let game: Game = get_game_info(client, upload.game_id).map_err(|e| e.to_string())?;
+  download_key_id: Option<OwnedKeyID>,
+) -> Result<DownloadPlan, ScratchError> {
+  let (upload, game): (Upload, Game) = get_upload_and_game(client, upload_id)?;
 
-  Ok(InstalledUpload {
-    upload_id,
-    // Get the absolute (canonical) form of the path
-    game_folder: filesystem::get_canonical_path(game_folder)?,
-    game_id: game.game_info.id,
-    game_title: game.game_info.title,
+  let download_key_id: Option<OwnedKeyID> = match download_key_id {
+    Some(key) => Some(key),
+    None if game.game_info.min_price > 0 => find_owned_key(client, game.game_info.id)?,
+    None => None,
+  };
+
+  let upload_archive: PathBuf =
+    game_files::get_upload_archive_path(game_folder, upload_id, &upload.filename);
+  let upload_folder: PathBuf = game_files::get_upload_folder(game_folder, upload_id);
+
+  // Send a HEAD request to get the file size without downloading it
+  // The file must not be compressed, so the content length is the one of the file
+  let url = get_upload_download_url(upload_id, download_key_id, None);
+  let res = client
+    .itch_request(&url, Method::HEAD, |b| {
+      b.header(reqwest::header::ACCEPT_ENCODING, "identity")
+    })
+    .map_err(|e| ScratchError::Api(e.into()))?;
+
+  // Read the header directly, because the body of a HEAD response is always empty
+  let content_length: Option<u64> = res
+    .status()
+    .is_success()
+    .then(|| res.headers().get(reqwest::header::CONTENT_LENGTH))
+    .flatten()
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.parse().ok());
+
+  // The partial download could be the file itself, if it was downloaded but not extracted yet
+  let partial_file_path: PathBuf = game_files::add_part_extension(&upload_archive)?;
+  let partial_download_size: Option<u64> = [&partial_file_path, &upload_archive]
+    .into_iter()
+    .find_map(|p| filesystem::read_path_metadata(p).ok())
+    .map(|m| m.len());
+
+  Ok(DownloadPlan {
+    game_folder: game_folder.to_path_buf(),
+    upload_archive,
+    upload_folder,
+    content_length,
+    partial_download_size,
+    has_hash: upload.get_hash().is_some(),
   })
 }
 
-/// Remove partially downloaded game files from a cancelled download
+/// The result of [`download_game_cover`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoverDownload {
+  /// The cover image was downloaded into this path
+  Downloaded(PathBuf),
+  /// The cover image in this path is already up to date, so it wasn't downloaded
+  NotModified(PathBuf),
+  /// The game doesn't have a cover image
+  NoCover,
+}
+
+/// Get the path of the file which stores the HTTP validators (`ETag` and `Last-Modified`) of a cover
+fn get_cover_validators_path(cover_path: &Path) -> Result<PathBuf, FilesystemError> {
+  let filename = filesystem::get_file_name(cover_path)?;
+  Ok(cover_path.with_file_name(format!("{filename}.cache")))
+}
+
+/// Download a game cover image from its game ID
+///
+/// The image will be a PNG. This is because the itch.io servers return that type of image
+///
+/// The `ETag` and `Last-Modified` headers of the image are stored in a `.cache` file next to it,
+/// so downloading it again only transfers the image if it has changed on the server
 ///
 /// # Arguments
 ///
 /// * `client` - An itch.io API client
 ///
-/// * `upload_id` - The ID of the upload whose download was canceled
+/// * `game_id` - The ID of the game from which the cover will be downloaded
 ///
-/// * `game_folder` - The folder where the game files are currectly placed
+/// * `folder` - The game folder where the cover will be placed
+///
+/// * `cover_filename` - The new filename of the cover
+///
+/// * `force_download` - If true, check if the cover image has changed on the server even if it already exists
 ///
 /// # Returns
 ///
-/// True if something was actually deleted
+/// A [`CoverDownload`] with the path of the image, or [`CoverDownload::NoCover`] if the game doesn't have one
 ///
 /// # Errors
 ///
 /// If something goes wrong
-pub fn remove_partial_download(
+pub fn download_game_cover(
   client: &ItchClient,
-  upload_id: UploadID,
-  game_folder: &Path,
-) -> Result<bool, String> {
-  // Obtain information about the game and the upload
-  let upload: Upload = get_upload_info(client, upload_id).map_err(|e| e.to_string())?;
+  game_id: GameID,
+  folder: &Path,
+  cover_filename: Option<&str>,
+  force_download: bool,
+) -> Result<CoverDownload, ScratchError> {
+  // Get the game info from the server
+  let game = get_game_info(client, game_id)?;
+  // If the game doesn't have a cover, return
+  let Some(cover_url) = game.game_info.cover_url else {
+    return Ok(CoverDownload::NoCover);
+  };
 
-  // Vector of files and folders to be removed
-  let to_be_removed_folders: &[PathBuf] = &[
-    // **Do not remove the upload folder!**
+  // Create the folder where the file is going to be placed if it doesn't already exist
+  filesystem::create_dir(folder)?;
 
-    // The upload partial folder
-    // Example: ~/Games/ExampleGame/123456.part/
-    game_files::add_part_extension(&game_files::get_upload_folder(game_folder, upload_id))?,
-  ];
+  // If the cover filename isn't set, set it to "cover"
+  let cover_filename = match cover_filename {
+    Some(f) => f,
+    None => game_files::COVER_IMAGE_DEFAULT_FILENAME,
+  };
 
-  let to_be_removed_files: &[PathBuf] = {
-    let upload_archive =
-      game_files::get_upload_archive_path(game_folder, upload_id, &upload.filename);
+  let cover_path = folder.join(cover_filename);
+  let validators_path = get_cover_validators_path(&cover_path)?;
+  let cover_exists: bool = filesystem::exists(&cover_path)?;
 
-    &[
-      // The upload partial archive
-      // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip.part
-      game_files::add_part_extension(&upload_archive)?,
-      // The upload downloaded archive
-      // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip
-      upload_archive,
-    ]
+  // If the cover image already exists and the force variable is false, don't replace the original image
+  if !force_download && cover_exists {
+    return Ok(CoverDownload::NotModified(cover_path));
+  }
+
+  // The validators are only useful if the image they belong to is still there
+  // The file has the ETag in the first line, and the Last-Modified date in the second one
+  let validators: String = if cover_exists && filesystem::exists(&validators_path)? {
+    std::fs::read_to_string(&validators_path).unwrap_or_default()
+  } else {
+    String::new()
   };
+  let mut validator_lines = validators.lines();
+  let etag: Option<&str> = validator_lines.next().filter(|l| !l.is_empty());
+  let last_modified: Option<&str> = validator_lines.next().filter(|l| !l.is_empty());
 
-  // Set this variable to true if some file or folder was deleted
-  let mut was_something_deleted: bool = false;
+  let res = client
+    .itch_request(
+      &ItchApiUrl::other(cover_url.clone()),
+      Method::GET,
+      |mut b| {
+        if let Some(etag) = etag {
+          b = b.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+          b = b.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+        b
+      },
+    )
+    .map_err(|e| ScratchError::Api(e.into()))?;
 
-  // Remove the partially downloaded files
-  for f in to_be_removed_files {
-    if filesystem::exists(f)? {
-      filesystem::remove_file(f)?;
-      was_something_deleted = true;
-    }
+  if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+    debug!("The cover image of the game {game_id} hasn't changed");
+    return Ok(CoverDownload::NotModified(cover_path));
   }
 
-  // Remove the partially downloaded folders
-  for f in to_be_removed_folders {
-    if filesystem::exists(f)? {
-      game_files::remove_folder_safely(f)?;
-      was_something_deleted = true;
-    }
+  if !res.status().is_success() {
+    return Err(ScratchError::Download(format!(
+      "The server returned an error while downloading the cover image!
+  It returned code: {}
+  URL: {cover_url}",
+      res.status().as_str()
+    )));
   }
 
-  // If the game folder is now useless, remove it
-  was_something_deleted |= game_files::remove_folder_if_empty(game_folder)?;
+  let header_value = |name: reqwest::header::HeaderName| -> String {
+    res
+      .headers()
+      .get(name)
+      .and_then(|v| v.to_str().ok())
+      .unwrap_or_default()
+      .to_string()
+  };
+  let new_validators = format!(
+    "{}\n{}\n",
+    header_value(reqwest::header::ETAG),
+    header_value(reqwest::header::LAST_MODIFIED)
+  );
+
+  let image = res.bytes().map_err(|e| ScratchError::Api(e.into()))?;
+
+  // Write the image into a .part file first, so an interrupted download never replaces a valid image
+  let partial_cover_path: PathBuf = game_files::add_part_extension(&cover_path)?;
+  let mut file = filesystem::open_file(
+    &partial_cover_path,
+    std::fs::OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true),
+  )?;
+  filesystem::write_all(&mut file, &image)?;
+  filesystem::file_sync_all(&file)?;
+  drop(file);
+
+  filesystem::rename(&partial_cover_path, &cover_path)?;
+
+  // If the validators can't be stored, the image will just be downloaded fully the next time
+  if let Err(e) = std::fs::write(&validators_path, new_validators) {
+    debug!("Couldn't store the cover validators: {e}");
+  }
+
+  Ok(CoverDownload::Downloaded(cover_path))
+}
+
+/// Download a game upload
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be downloaded
+///
+/// * `game_folder` - The folder where the downloadeded game files will be placed
+///
+/// * `skip_hash_verification` - If true, don't check the downloaded upload integrity (insecure)
+///
+/// * `upload_info` - A closure which reports the upload and the game info before the download starts
+///
+/// * `progress_callback` - A closure which reports the download progress
+///
+/// * `callback_interval` - The minimum time span between each `progress_callback` call
+///
+/// # Returns
+///
+/// The installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong
+#[deprecated(note = "use `download_upload_with_options` instead")]
+pub fn download_upload(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  skip_hash_verification: bool,
+  upload_info: impl FnOnce(&Upload, &Game),
+  progress_callback: impl Fn(DownloadStatus),
+  callback_interval: Duration,
+) -> Result<InstalledUpload, ScratchError> {
+  download_upload_with_options(
+    client,
+    upload_id,
+    game_folder,
+    &DownloadOptions {
+      skip_hash_verification,
+      callback_interval,
+      ..Default::default()
+    },
+    upload_info,
+    progress_callback,
+  )
+}
+
+/// Download a game upload
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be downloaded
+///
+/// * `game_folder` - The folder where the downloadeded game files will be placed
+///
+/// * `options` - The download options, see [`DownloadOptions`]
+///
+/// * `upload_info` - A closure which reports the upload and the game info before the download starts
+///
+/// * `progress_callback` - A closure which reports the download progress
+///
+/// # Returns
+///
+/// The installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn download_upload_with_options(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  options: &DownloadOptions,
+  upload_info: impl FnOnce(&Upload, &Game),
+  progress_callback: impl Fn(DownloadStatus),
+) -> Result<InstalledUpload, ScratchError> {
+  let cancel: &CancellationToken = &options.cancel;
+
+  // --- DOWNLOAD PREPARATION ---
+
+  // Obtain information about the game and the upload that will be downloaeded
+  let (upload, game): (Upload, Game) = get_upload_and_game(client, upload_id)?;
+
+  // Send to the caller the game and the upload info
+  upload_info(&upload, &game);
+
+  // Paid games require an owned key to be downloaded, so look it up if it wasn't provided
+  let download_key_id: Option<OwnedKeyID> = match options.download_key_id {
+    Some(key) => Some(key),
+    None if options.download_key_uuid.is_some() => None,
+    None if game.game_info.min_price > 0 => find_owned_key(client, game.game_info.id)?,
+    None => None,
+  };
+
+  // The upload is downloaded and extracted in the temp dir, if there is one
+  let staging_folder: &Path = options.temp_dir.as_deref().unwrap_or(game_folder);
+
+  // upload_archive is the location where the upload will be downloaded
+  let upload_archive: PathBuf =
+    game_files::get_upload_archive_path(staging_folder, upload_id, &upload.filename);
+
+  // Create the game folder (and the temp dir) if they don't already exist
+  filesystem::create_dir(game_folder)?;
+  filesystem::create_dir(staging_folder)?;
+
+  // Get the upload's hash
+  let hash: Option<&str> = upload.get_hash();
+
+  // Uploads backed by a wharf channel are a build, which can be upgraded later
+  let installed_build_id: Option<BuildID> = match upload.storage {
+    UploadStorage::Build { build_id, .. } => Some(build_id),
+    _ => None,
+  };
+
+  // --- DOWNLOAD ---
+
+  let url: ItchApiUrl = get_upload_download_url(
+    upload_id,
+    download_key_id,
+    options.download_key_uuid.as_deref(),
+  );
+  let mirrors: Vec<ItchApiUrl> = options
+    .mirrors
+    .iter()
+    .map(|base| {
+      url.with_base(base).ok_or_else(|| {
+        ScratchError::InvalidInput(format!(
+          "The mirror isn't an HTTPS address owned by itch.io: \"{base}\""
+        ))
+      })
+    })
+    .collect::<Result<_, _>>()?;
+
+  // Only pass the hash if skip_hash_verification is false
+  let hash_to_verify: Option<Checksum> = hash
+    .filter(|_| !options.skip_hash_verification)
+    .map(Checksum::md5);
+
+  // The new upload_folder is game_folder + the upload id
+  let upload_folder: PathBuf = game_files::get_upload_folder(game_folder, upload_id);
+
+  // The folder where the upload is extracted before moving it to upload_folder
+  let extract_folder: PathBuf = game_files::get_upload_folder(staging_folder, upload_id);
+
+  // Streaming the extraction can't resume a partial download, so only use it for new downloads
+  let stream_extract: bool = options.stream_extract
+    && extract::supports_streaming(&upload_archive)?
+    && !filesystem::exists(&upload_archive)?
+    && !filesystem::exists(&game_files::add_part_extension(&upload_archive)?)?;
+
+  // Compute the download speed here, so every frontend doesn't have to
+  let total_bytes: Cell<u64> = Cell::new(0);
+  let speed_meter = SpeedMeter::new();
+  let report_download_start = |bytes: u64| {
+    total_bytes.set(bytes);
+    progress_callback(DownloadStatus::StartingDownload {
+      bytes_to_download: bytes,
+    });
+  };
+  let report_download_progress = |bytes: u64| {
+    let (instantaneous_bytes_per_sec, elapsed) = speed_meter.sample(bytes);
+    progress_callback(DownloadStatus::DownloadProgress {
+      downloaded_bytes: bytes,
+      total_bytes: total_bytes.get(),
+      instantaneous_bytes_per_sec,
+      elapsed,
+    });
+  };
+
+  // The number of bytes received from the network and the mirror used, and if the archive was
+  // extracted while downloading it, its failed entries
+  let download_result: Result<(DownloadedFile, Option<Vec<FailedEntry>>), ScratchError> =
+    if stream_extract {
+      let throttle = Throttle::new(options.max_bytes_per_sec);
+      stream_extract::download_and_extract(
+        client,
+        &url,
+        &upload_archive,
+        &extract_folder,
+        hash_to_verify,
+        options,
+        &DownloadContext {
+          file_size_callback: &report_download_start,
+          progress_callback: &report_download_progress,
+          callback_interval: options.callback_interval,
+          cancel,
+          throttle: &throttle,
+        },
+      )
+      .map(|failed_entries| {
+        let downloaded_file = DownloadedFile {
+          bytes_transferred: throttle.transferred_bytes(),
+          mirror: None,
+        };
+        (downloaded_file, Some(failed_entries))
+      })
+    } else {
+      download_file(
+        client,
+        &url,
+        &mirrors,
+        &upload_archive,
+        hash_to_verify,
+        report_download_start,
+        report_download_progress,
+        options.callback_interval,
+        cancel,
+        options.download_connections,
+        options.max_bytes_per_sec,
+        options.min_speed,
+      )
+      .map(|downloaded_file| (downloaded_file, None))
+    };
+
+  let (downloaded_file, streamed_failed_entries): (DownloadedFile, Option<Vec<FailedEntry>>) =
+    download_result
+      .inspect_err(|_| {
+        if cancel.is_cancelled() {
+          progress_callback(DownloadStatus::Cancelled);
+        }
+      })
+      .map_err(|e| match e {
+        _ if cancel.is_cancelled() => ScratchError::Cancelled,
+        // Explain the most likely reason why a paid game couldn't be downloaded
+        ScratchError::Download(e)
+          if download_key_id.is_none()
+            && options.download_key_uuid.is_none()
+            && game.game_info.min_price > 0 =>
+        {
+          ScratchError::Download(format!(
+            "{e}\n\nThe game isn't free (minimum price: {}) and no owned key was found for it.
+  Buy the game, or provide the ID of the key that grants access to it",
+            game.game_info.min_price
+          ))
+        }
+        e => e,
+      })?;
+
+  // Don't start the extraction if the operation was cancelled after the download
+  if cancel.is_cancelled() {
+    progress_callback(DownloadStatus::Cancelled);
+    return Err(ScratchError::Cancelled);
+  }
+
+  // Print a warning if the upload doesn't have a hash in the server
+  // or the hash verification is skipped
+  if options.skip_hash_verification {
+    progress_callback(DownloadStatus::Warning(
+      "Skipping hash verification! The file integrity won't be checked!".to_string(),
+    ));
+  } else if hash.is_none() {
+    progress_callback(DownloadStatus::Warning(
+      "Missing MD5 hash. Couldn't verify the file integrity!".to_string(),
+    ));
+  }
+
+  // --- FILE EXTRACTION ---
+
+  let extracted: Extracted = match streamed_failed_entries {
+    // The archive has already been extracted while it was downloaded
+    Some(failed_entries) => Extracted::Archive(failed_entries),
+    None => {
+      progress_callback(DownloadStatus::Extract);
+
+      // Extracts the downloaded archive (if it's an archive)
+      // game_files can be the path of an executable or the path to the extracted folder
+      extract::extract(
+        &upload_archive,
+        &extract_folder,
+        &ExtractOptions {
+          lenient: options.lenient_extraction,
+          password: options.archive_password.clone(),
+        },
+        |extracted_bytes, total_bytes| {
+          progress_callback(DownloadStatus::ExtractProgress {
+            extracted_bytes,
+            total_bytes,
+          });
+        },
+        options.callback_interval,
+      )
+      .map_err(ScratchError::Extraction)?
+    }
+  };
+
+  // Move the extracted files out of the temp dir. If it is on another filesystem, they are copied
+  if extract_folder != upload_folder {
+    game_files::move_folder(&LocalStorage, &extract_folder, &upload_folder)?;
+  }
+
+  // A file that isn't an archive is launched as it is
+  let launch_target: Option<SingleFileTarget> = match extracted {
+    Extracted::Archive(failed_entries) => {
+      if !failed_entries.is_empty() {
+        progress_callback(DownloadStatus::ExtractionFailedEntries(failed_entries));
+      }
+      None
+    }
+    Extracted::SingleFile(file) => {
+      // The file was moved along with the rest of the extracted files
+      let file: PathBuf = match file.strip_prefix(&extract_folder) {
+        Ok(relative) => upload_folder.join(relative),
+        Err(_) => file,
+      };
+      SingleFileTarget::from_single_file(file)
+    }
+  };
+
+  // Android packages are left as they are, because they have to be installed on a device
+  if game_files::is_android_package(&upload_archive) {
+    progress_callback(DownloadStatus::Warning(
+      "Android package downloaded; transfer it to an Android device to install it".to_string(),
+    ));
+  }
+
+  // Fingerprint the installed files, so later modifications can be detected
+  let fingerprint: Fingerprint = folder_fingerprint(&upload_folder, FingerprintMode::Fast)?;
+
+  progress_callback(DownloadStatus::Finished {
+    bytes_transferred: downloaded_file.bytes_transferred,
+    mirror: downloaded_file.mirror.map(|i| mirrors[i].to_string()),
+  });
+
+  Ok(InstalledUpload {
+    upload_id,
+    // Get the absolute (canonical) form of the path
+    game_folder: filesystem::get_canonical_path(game_folder)?,
+    game_id: game.game_info.id,
+    game_min_price: Some(game.game_info.min_price),
+    game_title: game.game_info.title,
+    fingerprint: Some(fingerprint),
+    installed_build_id,
+    launch_override: None,
+    installed_at: OffsetDateTime::now_utc(),
+    last_played_at: None,
+    total_playtime: Duration::ZERO,
+    launch_target,
+  })
+}
+
+/// Download a game upload, reporting the progress to a [`DownloadReporter`]
+///
+/// This behaves exactly like [`download_upload_with_options`], which is the lower level
+/// closure-based API
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be downloaded
+///
+/// * `game_folder` - The folder where the downloadeded game files will be placed
+///
+/// * `options` - The download options, see [`DownloadOptions`]
+///
+/// * `reporter` - The receiver of the download progress, or [`NoopReporter`] to ignore it
+///
+/// # Returns
+///
+/// The installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn download_upload_with_reporter(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  options: &DownloadOptions,
+  reporter: &impl DownloadReporter,
+) -> Result<InstalledUpload, ScratchError> {
+  download_upload_with_options(
+    client,
+    upload_id,
+    game_folder,
+    options,
+    |_, _| (),
+    |status| reporter::report(reporter, status),
+  )
+}
+
+/// Download a game upload in a new thread, sending its progress over a channel
+///
+/// This behaves exactly like [`download_upload_with_options`], but the caller isn't blocked,
+/// and the progress can be forwarded to another thread or process by receiving from the channel.
+/// The channel is closed when the download finishes, and the result is returned when joining the thread.
+/// Use the cancellation token of the options to stop the download
+///
+/// If the receiver is dropped, the download continues without reporting its progress
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be downloaded
+///
+/// * `game_folder` - The folder where the downloadeded game files will be placed
+///
+/// * `options` - The download options, see [`DownloadOptions`]
+///
+/// # Returns
+///
+/// The handle of the download thread and the receiver of its progress
+#[must_use]
+pub fn download_upload_channel(
+  client: ItchClient,
+  upload_id: UploadID,
+  game_folder: PathBuf,
+  options: DownloadOptions,
+) -> (
+  std::thread::JoinHandle<Result<InstalledUpload, ScratchError>>,
+  std::sync::mpsc::Receiver<DownloadStatus>,
+) {
+  let (sender, receiver) = std::sync::mpsc::channel::<DownloadStatus>();
+
+  let thread = std::thread::spawn(move || {
+    download_upload_with_options(
+      &client,
+      upload_id,
+      &game_folder,
+      &options,
+      |_, _| (),
+      // A closed channel only means nobody is listening anymore
+      |status| {
+        let _ = sender.send(status);
+      },
+    )
+  });
+
+  (thread, receiver)
+}
+
+/// Import an already installed upload
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload which will be imported
+///
+/// * `game_folder` - The folder where the game files are currectly placed
+///
+/// # Returns
+///
+/// The installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn import(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+) -> Result<InstalledUpload, ScratchError> {
+  // Obtain information about the game and the upload that will be downloaeded
+  let (_, game): (Upload, Game) = get_upload_and_game(client, upload_id)?;
+
+  // Fingerprint the imported files, so later modifications can be detected
+  let upload_folder: PathBuf = game_files::get_upload_folder(game_folder, upload_id);
+  let fingerprint: Fingerprint = folder_fingerprint(&upload_folder, FingerprintMode::Fast)?;
+
+  Ok(InstalledUpload {
+    upload_id,
+    // Get the absolute (canonical) form of the path
+    game_folder: filesystem::get_canonical_path(game_folder)?,
+    game_id: game.game_info.id,
+    game_min_price: Some(game.game_info.min_price),
+    game_title: game.game_info.title,
+    fingerprint: Some(fingerprint),
+    // The imported files could be any build, so it is unknown
+    installed_build_id: None,
+    launch_override: None,
+    installed_at: OffsetDateTime::now_utc(),
+    last_played_at: None,
+    total_playtime: Duration::ZERO,
+    launch_target: None,
+  })
+}
+
+/// The outcome of [`scan_and_import`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanImportResult {
+  /// The installation info of the uploads found in the scanned folder
+  pub imported: Vec<InstalledUpload>,
+  /// The upload folders that couldn't be imported, and why
+  pub unmatched: Vec<(PathBuf, String)>,
+}
+
+/// Import all the untracked upload folders found inside a base folder
+///
+/// The upload folders are searched like in [`find_orphaned_uploads`], so the folders named after
+/// an upload ID, directly inside the base folder or inside one of its game folders, are imported
+/// with [`import`]. The folders which the itch.io API doesn't recognize are reported instead
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_uploads` - The tracked installed uploads, which are skipped
+///
+/// * `base_folder` - The folder to scan, usually the one containing all the game folders
+///
+/// # Returns
+///
+/// The imported uploads, and the folders that couldn't be imported
+///
+/// # Errors
+///
+/// If the base folder or its subfolders couldn't be read
+pub fn scan_and_import<'a>(
+  client: &ItchClient,
+  installed_uploads: impl IntoIterator<Item = &'a InstalledUpload>,
+  base_folder: &Path,
+) -> Result<ScanImportResult, ScratchError> {
+  let installed_uploads: Vec<&InstalledUpload> = installed_uploads.into_iter().collect();
+
+  let mut result = ScanImportResult {
+    imported: Vec::new(),
+    unmatched: Vec::new(),
+  };
+
+  for upload_folder in find_orphaned_uploads(installed_uploads.iter().copied(), base_folder)? {
+    let upload_id: UploadID = filesystem::get_file_name(&upload_folder)?
+      .parse()
+      .expect("The orphaned upload folders are named after their upload ID");
+    let game_folder: &Path = filesystem::parent(&upload_folder)?;
+
+    // An upload can only be tracked once, so don't replace the one installed elsewhere
+    if installed_uploads.iter().any(|iu| iu.upload_id == upload_id)
+      || result.imported.iter().any(|iu| iu.upload_id == upload_id)
+    {
+      result.unmatched.push((
+        upload_folder,
+        format!("The upload {upload_id} is already installed in another folder"),
+      ));
+      continue;
+    }
+
+    match import(client, upload_id, game_folder) {
+      Ok(iu) => result.imported.push(iu),
+      Err(e) => {
+        debug!("Couldn't import \"{}\": {e}", upload_folder.display());
+        result.unmatched.push((upload_folder, e.to_string()));
+      }
+    }
+  }
+
+  Ok(result)
+}
+
+/// Verify an installed upload against the MD5 hash provided by itch.io, without downloading it again
+///
+/// The hashed file is the upload archive if it is still in the game folder, or the
+/// upload file inside the upload folder if it wasn't an archive. Extracted archives
+/// can't be verified this way, because the server only has the hash of the archive
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload to verify
+///
+/// * `game_folder` - The folder where the upload is installed
+///
+/// * `progress_callback` - A closure which reports warnings about the verification
+///
+/// # Returns
+///
+/// True if the file hash matches the server hash, false if it doesn't or if the upload has no hash
+///
+/// # Errors
+///
+/// If the upload file couldn't be found, or something goes wrong while hashing it
+pub fn verify_installed(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+  progress_callback: impl Fn(DownloadStatus),
+) -> Result<bool, ScratchError> {
+  let upload: Upload = get_upload_info(client, upload_id)?;
+
+  let Some(hash) = upload.get_hash() else {
+    progress_callback(DownloadStatus::Warning(
+      "Missing MD5 hash. Couldn't verify the file integrity!".to_string(),
+    ));
+    return Ok(false);
+  };
+
+  // The upload file is either the archive, or the non-archive file moved into the upload folder
+  let upload_archive: PathBuf =
+    game_files::get_upload_archive_path(game_folder, upload_id, &upload.filename);
+  let upload_file: PathBuf = if filesystem::exists(&upload_archive)? {
+    upload_archive
+  } else {
+    game_files::get_upload_folder(game_folder, upload_id)
+      .join(filesystem::get_file_name(&upload_archive)?)
+  };
+
+  if !filesystem::exists(&upload_file)? {
+    return Err(ScratchError::Verification(format!(
+      "Couldn't find the upload file to verify! If the upload was an archive, it has already been extracted and removed.
+  Expected file: {}",
+      upload_file.display()
+    )));
+  }
+
+  debug!(
+    "Verifying \"{}\" against the hash {hash}",
+    upload_file.display()
+  );
+
+  let file = filesystem::open_file(&upload_file, std::fs::OpenOptions::new().read(true))?;
+  let mut hasher = Hasher::new(ChecksumAlgorithm::Md5);
+  hash_readable(&mut std::io::BufReader::new(file), &mut hasher)?;
+
+  let file_hash = hasher.finalize_hex();
+  trace!("Installed file hash: {file_hash}, expected hash: {hash}");
+
+  Ok(file_hash.eq_ignore_ascii_case(hash))
+}
+
+/// Get the latest build of an installed upload, if it is newer than the installed build
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_upload` - The installed upload to check
+///
+/// # Returns
+///
+/// The latest build, or None if the installed build is the latest one or it is unknown
+///
+/// # Errors
+///
+/// If the request to get the upload builds fails
+pub fn get_newer_build(
+  client: &ItchClient,
+  installed_upload: &InstalledUpload,
+) -> Result<Option<UploadBuild>, ScratchError> {
+  let Some(installed_build_id) = installed_upload.installed_build_id else {
+    return Ok(None);
+  };
+
+  let builds: Vec<UploadBuild> = get_upload_builds(client, installed_upload.upload_id)?;
+
+  Ok(
+    builds
+      .into_iter()
+      .max_by_key(|b| b.build_info.version)
+      .filter(|b| b.build_info.id != installed_build_id),
+  )
+}
+
+/// Check which installed uploads have a newer build available
+///
+/// Only the uploads backed by a wharf channel whose installed build is known can be checked, the
+/// rest are skipped. The uploads are checked one after another, so the API isn't flooded with
+/// requests, and rate-limited requests are retried according to the client's [`RetryPolicy`]
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_uploads` - The installed uploads to check
+///
+/// # Returns
+///
+/// The info about the uploads with a newer build available
+///
+/// # Errors
+///
+/// If the request to get the builds of an upload fails
+pub fn check_updates<'a>(
+  client: &ItchClient,
+  installed_uploads: impl IntoIterator<Item = &'a InstalledUpload>,
+) -> Result<Vec<UpdateInfo>, ScratchError> {
+  let mut updates: Vec<UpdateInfo> = Vec::new();
+
+  for iu in installed_uploads {
+    // The installed build ID is only known for uploads stored as wharf builds
+    let Some(installed_build_id) = iu.installed_build_id else {
+      continue;
+    };
+
+    if let Some(build) = get_newer_build(client, iu)? {
+      updates.push(UpdateInfo {
+        upload_id: iu.upload_id,
+        game_id: iu.game_id,
+        game_title: iu.game_title.clone(),
+        installed_build_id,
+        latest_build_id: build.build_info.id,
+        latest_user_version: build.build_info.user_version,
+      });
+    }
+  }
+
+  Ok(updates)
+}
+
+/// Check if the files of an installed upload have been modified since it was installed
+///
+/// The folder is fingerprinted again using the same mode as the stored fingerprint.
+/// Note that moving the game folder to another filesystem changes the modification
+/// times of the files, so uploads with a [`FingerprintMode::Fast`] fingerprint will
+/// be reported as modified after such a move
+///
+/// # Arguments
+///
+/// * `installed_upload` - The installed upload to check
+///
+/// # Returns
+///
+/// `None` if the installed upload doesn't have a stored fingerprint, or whether its files have changed
+///
+/// # Errors
+///
+/// If something goes wrong while fingerprinting the upload folder
+pub fn is_upload_modified(
+  installed_upload: &InstalledUpload,
+) -> Result<Option<bool>, ScratchError> {
+  let Some(stored) = &installed_upload.fingerprint else {
+    return Ok(None);
+  };
+
+  let current: Fingerprint = folder_fingerprint(&installed_upload.upload_folder(), stored.mode)?;
+
+  Ok(Some(current.hash != stored.hash))
+}
+
+/// Refresh the stored game info of an installed upload, reporting what has changed
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_upload` - The installed upload to refresh, which will be updated with the new info
+///
+/// # Returns
+///
+/// A vector with all the detected changes
+///
+/// # Errors
+///
+/// If the request to get the game info fails for a reason other than the game being delisted
+pub fn refresh_game_info(
+  client: &ItchClient,
+  installed_upload: &mut InstalledUpload,
+) -> Result<Vec<StatusChange>, ScratchError> {
+  let game: Option<Game> = match get_game_info(client, installed_upload.game_id) {
+    Ok(game) => Some(game),
+    // If the game ID isn't valid anymore, the game has been delisted
+    Err(ItchRequestJSONError {
+      kind: ItchRequestJSONErrorKind::ServerRepliedWithError(GameResponseError::InvalidGameID(_)),
+      ..
+    }) => None,
+    Err(e) => return Err(e.into()),
+  };
+
+  let changes = installed_upload.detect_status_changes(game.as_ref().map(|g| &g.game_info));
+
+  // Store the new info, so the same changes aren't reported again
+  if let Some(game) = game {
+    installed_upload.game_min_price = Some(game.game_info.min_price);
+    installed_upload.game_title = game.game_info.title;
+  }
+
+  Ok(changes)
+}
+
+/// Get a summary of the user's library, such as the number of owned and installed games
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_uploads` - The uploads currently installed
+///
+/// # Returns
+///
+/// An [`OwnedSummary`] struct with the totals
+///
+/// # Errors
+///
+/// If the request to get the owned keys fails
+pub fn get_owned_summary<'a>(
+  client: &ItchClient,
+  installed_uploads: impl IntoIterator<Item = &'a InstalledUpload>,
+) -> Result<OwnedSummary, ScratchError> {
+  let owned_keys: Vec<OwnedKey> = get_owned_keys(client)?;
+
+  // A game can be owned through more than one key, so count each game only once
+  let owned_games: std::collections::HashSet<GameID> =
+    owned_keys.iter().map(|k| k.game_id).collect();
+
+  let installed_games: std::collections::HashSet<GameID> = installed_uploads
+    .into_iter()
+    .map(|iu| iu.game_id)
+    .filter(|id| owned_games.contains(id))
+    .collect();
+
+  Ok(OwnedSummary {
+    owned_games: owned_games.len() as u64,
+    total_downloads: owned_keys.iter().map(|k| k.downloads).sum(),
+    installed_games: installed_games.len() as u64,
+  })
+}
+
+/// Sync a collection into a folder, downloading the games that aren't installed yet
+/// and upgrading the installed ones with a newer build available
+///
+/// Each game is installed into its own folder inside `games_folder`, named after its ID.
+/// Only the uploads whose installed build is known can be checked for updates, see [`check_updates`].
+/// The rest are reported as up to date
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `collection_id` - The ID of the collection to sync
+///
+/// * `games_folder` - The folder where the game folders will be placed
+///
+/// * `platform` - The platform the downloaded uploads must be available in
+///
+/// * `prune` - If true, remove the installed games inside `games_folder` that aren't in the collection anymore
+///
+/// * `options` - The download options used for every download and upgrade, see [`DownloadOptions`]
+///
+/// * `installed_uploads` - The installed uploads, which will be updated with the changes
+///
+/// * `progress_callback` - A closure called with the action done for each game, after doing it
+///
+/// # Returns
+///
+/// A vector of tuples containing each game ID and the action done with it
+///
+/// # Errors
+///
+/// If the collection games couldn't be obtained. Errors with single games are reported as [`SyncAction::Failed`]
+#[expect(clippy::too_many_arguments)]
+pub fn sync_collection(
+  client: &ItchClient,
+  collection_id: CollectionID,
+  games_folder: &Path,
+  platform: GamePlatform,
+  prune: bool,
+  options: &DownloadOptions,
+  installed_uploads: &mut std::collections::HashMap<UploadID, InstalledUpload>,
+  progress_callback: impl Fn(GameID, &SyncAction),
+) -> Result<Vec<(GameID, SyncAction)>, ScratchError> {
+  let collection_games: Vec<CollectionGameItem> = get_collection_games(client, collection_id)?;
+
+  let mut actions: Vec<(GameID, SyncAction)> = Vec::new();
+
+  // Install the games that aren't installed yet, and update the installed ones
+  for item in &collection_games {
+    let game_id: GameID = item.game.game_info.id;
+
+    let game_actions: Vec<SyncAction> =
+      if installed_uploads.values().any(|iu| iu.game_id == game_id) {
+        sync_update_game(client, game_id, options, installed_uploads)
+      } else {
+        vec![
+          match sync_install_game(client, game_id, games_folder, platform, options) {
+            Ok(iu) => {
+              let upload_id = iu.upload_id;
+              installed_uploads.insert(upload_id, iu);
+              SyncAction::Installed(upload_id)
+            }
+            Err(e) => SyncAction::Failed(e),
+          },
+        ]
+      };
+
+    for action in game_actions {
+      progress_callback(game_id, &action);
+      actions.push((game_id, action));
+    }
+  }
+
+  if !prune {
+    return Ok(actions);
+  }
+
+  // Only prune the uploads inside games_folder, so games installed elsewhere are never touched
+  let games_folder: PathBuf = filesystem::get_canonical_path(games_folder)?;
+
+  let to_be_pruned: Vec<(UploadID, GameID)> = installed_uploads
+    .values()
+    .filter(|iu| iu.game_folder.starts_with(&games_folder))
+    .filter(|iu| {
+      !collection_games
+        .iter()
+        .any(|item| item.game.game_info.id == iu.game_id)
+    })
+    .map(|iu| (iu.upload_id, iu.game_id))
+    .collect();
+
+  for (upload_id, game_id) in to_be_pruned {
+    let game_folder: PathBuf = installed_uploads[&upload_id].game_folder.clone();
+
+    let action: SyncAction = match remove(upload_id, &game_folder) {
+      Ok(()) => {
+        installed_uploads.remove(&upload_id);
+        SyncAction::Pruned(upload_id)
+      }
+      Err(e) => SyncAction::Failed(e),
+    };
+
+    progress_callback(game_id, &action);
+    actions.push((game_id, action));
+  }
+
+  Ok(actions)
+}
+
+/// Download all the uploads of a game, like the builds for each platform
+///
+/// Each upload is downloaded into its own folder inside `game_folder`, one after another.
+/// The uploads that are already installed are skipped, and reported as [`SyncAction::AlreadyInstalled`]
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `game_id` - The ID of the game whose uploads will be downloaded
+///
+/// * `game_folder` - The folder where the upload folders will be placed
+///
+/// * `platforms` - If provided, only download the uploads available in any of these platforms
+///
+/// * `options` - The download options used for every upload, see [`DownloadOptions`]
+///
+/// * `installed_uploads` - The installed uploads, which will be updated with the new ones
+///
+/// * `progress_callback` - A closure which reports the download progress of each upload, and the
+///   action done with it after doing it
+///
+/// # Returns
+///
+/// A vector of tuples containing each upload ID and the action done with it
+///
+/// # Errors
+///
+/// If the game uploads couldn't be obtained. Errors with single uploads are reported as [`SyncAction::Failed`]
+pub fn download_all_uploads(
+  client: &ItchClient,
+  game_id: GameID,
+  game_folder: &Path,
+  platforms: Option<&[GamePlatform]>,
+  options: &DownloadOptions,
+  installed_uploads: &mut std::collections::HashMap<UploadID, InstalledUpload>,
+  progress_callback: impl Fn(UploadID, UploadBatchStatus),
+) -> Result<Vec<(UploadID, SyncAction)>, ScratchError> {
+  let uploads: Vec<Upload> = get_game_uploads(client, game_id)?;
+
+  let mut actions: Vec<(UploadID, SyncAction)> = Vec::new();
+
+  for upload in uploads.iter().filter(|u| {
+    platforms.is_none_or(|platforms| u.to_game_platforms().iter().any(|p| platforms.contains(p)))
+  }) {
+    let upload_id: UploadID = upload.id;
+
+    let action: SyncAction = match installed_uploads.entry(upload_id) {
+      std::collections::hash_map::Entry::Occupied(_) => SyncAction::AlreadyInstalled,
+      std::collections::hash_map::Entry::Vacant(entry) => match download_upload_with_options(
+        client,
+        upload_id,
+        game_folder,
+        options,
+        |_, _| (),
+        |status| progress_callback(upload_id, UploadBatchStatus::Download(status)),
+      ) {
+        Ok(iu) => {
+          entry.insert(iu);
+          SyncAction::Installed(upload_id)
+        }
+        Err(e) => SyncAction::Failed(e),
+      },
+    };
+
+    progress_callback(upload_id, UploadBatchStatus::Done(&action));
+    actions.push((upload_id, action));
+  }
+
+  Ok(actions)
+}
+
+/// Upgrade the installed uploads of a game which have a newer build available
+///
+/// Returns the action done with each upgraded upload, or [`SyncAction::UpToDate`] if none of them was upgraded
+fn sync_update_game(
+  client: &ItchClient,
+  game_id: GameID,
+  options: &DownloadOptions,
+  installed_uploads: &mut std::collections::HashMap<UploadID, InstalledUpload>,
+) -> Vec<SyncAction> {
+  let updates: Vec<UpdateInfo> = match check_updates(
+    client,
+    installed_uploads
+      .values()
+      .filter(|iu| iu.game_id == game_id),
+  ) {
+    Ok(updates) => updates,
+    Err(e) => return vec![SyncAction::Failed(e)],
+  };
+
+  if updates.is_empty() {
+    return vec![SyncAction::UpToDate];
+  }
+
+  updates
+    .into_iter()
+    .map(|update| {
+      let iu: &mut InstalledUpload = installed_uploads
+        .get_mut(&update.upload_id)
+        .expect("The updates are only checked for the installed uploads");
+
+      match upgrade_upload(client, iu, update.latest_build_id, options, |_| ()) {
+        Ok(()) => SyncAction::Updated(update.upload_id),
+        Err(e) => SyncAction::Failed(e),
+      }
+    })
+    .collect()
+}
+
+/// Download the first upload of a game available in the given platform
+fn sync_install_game(
+  client: &ItchClient,
+  game_id: GameID,
+  games_folder: &Path,
+  platform: GamePlatform,
+  options: &DownloadOptions,
+) -> Result<InstalledUpload, ScratchError> {
+  let uploads: Vec<Upload> = get_game_uploads(client, game_id)?;
+
+  let upload: &Upload = uploads
+    .iter()
+    .filter(|u| u.to_game_platforms().contains(&platform))
+    .min_by_key(|u| u.position)
+    .ok_or_else(|| {
+      ScratchError::InvalidInput(format!(
+        "The game doesn't have any upload available for {platform:?}"
+      ))
+    })?;
+
+  download_upload_with_options(
+    client,
+    upload.id,
+    &games_folder.join(game_id.to_string()),
+    options,
+    |_, _| (),
+    |_| (),
+  )
+}
+
+/// Remove partially downloaded game files from a cancelled download
+///
+/// An upload goes through these files while it is being installed:
+///
+/// 1. The archive is downloaded into `{upload_id}-download-{filename}.part`
+/// 2. Once it is complete and verified, the `.part` extension is removed
+/// 3. The archive is extracted into the `{upload_id}.part` folder, and then removed
+/// 4. The `{upload_id}.part` folder is renamed to `{upload_id}`
+///
+/// This function removes the files of the steps 1 to 3, so it can be called at any point of an
+/// interrupted installation. The `{upload_id}` folder only exists once the upload is completely
+/// installed, so it is never removed
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `upload_id` - The ID of the upload whose download was canceled
+///
+/// * `game_folder` - The folder where the game files are currectly placed
+///
+/// # Returns
+///
+/// True if something was actually deleted
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn remove_partial_download(
+  client: &ItchClient,
+  upload_id: UploadID,
+  game_folder: &Path,
+) -> Result<bool, ScratchError> {
+  // Obtain information about the game and the upload
+  let upload: Upload = get_upload_info(client, upload_id)?;
+
+  // Vector of files and folders to be removed
+  let to_be_removed_folders: &[PathBuf] = &[
+    // **Do not remove the upload folder!**
+
+    // The upload partial folder
+    // Example: ~/Games/ExampleGame/123456.part/
+    game_files::add_part_extension(&game_files::get_upload_folder(game_folder, upload_id))?,
+  ];
+
+  let to_be_removed_files: Vec<PathBuf> = {
+    let upload_archive =
+      game_files::get_upload_archive_path(game_folder, upload_id, &upload.filename);
+
+    let mut files: Vec<PathBuf> = vec![
+      // The upload partial archive
+      // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip.part
+      game_files::add_part_extension(&upload_archive)?,
+      // The hash state of the partial archive
+      // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip.part.md5state
+      game_files::add_hash_checkpoint_extension(&game_files::add_part_extension(&upload_archive)?)?,
+    ];
+
+    // If the upload is a split archive, the other volumes and the joined archive
+    // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip.002
+    // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip
+    if let Some((joined_archive, volumes)) = extract::get_split_volumes(&upload_archive)? {
+      files.push(joined_archive);
+      files.extend(volumes.into_iter().skip(1));
+    }
+
+    // The upload downloaded archive
+    // Example: ~/Games/ExampleGame/123456-download-ArchiveName.zip
+    files.push(upload_archive);
+
+    files
+  };
+
+  // Set this variable to true if some file or folder was deleted
+  let mut was_something_deleted: bool = false;
+
+  // Remove the partially downloaded files
+  for f in &to_be_removed_files {
+    if filesystem::exists(f)? {
+      filesystem::remove_file(f)?;
+      was_something_deleted = true;
+    }
+  }
+
+  // Remove the partially downloaded folders
+  for f in to_be_removed_folders {
+    if filesystem::exists(f)? {
+      game_files::remove_folder_safely(f)?;
+      was_something_deleted = true;
+    }
+  }
+
+  // If the game folder is now useless, remove it
+  was_something_deleted |= game_files::remove_folder_if_empty(&LocalStorage, game_folder)?;
+
+  Ok(was_something_deleted)
+}
+
+/// Remove an installed upload
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of upload which will be removed
+///
+/// * `game_folder` - The folder with the game files where the upload will be removed from
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn remove(upload_id: UploadID, game_folder: &Path) -> Result<(), ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+
+  // If there isn't a upload_folder, or it is empty, that means the game
+  // has already been removed, so return Ok(())
+  if filesystem::is_folder_empty(&upload_folder)? {
+    return Ok(());
+  }
+
+  game_files::remove_folder_safely(&upload_folder)?;
+  // The upload folder has been removed
+
+  // If the game folder is empty, remove it
+  game_files::remove_folder_if_empty(&LocalStorage, game_folder)?;
+
+  Ok(())
+}
+
+/// Download an installed upload again from scratch, replacing its installed files
+///
+/// The upload folder and any leftovers of a partial download are removed first, so it fixes
+/// installations that are broken in any way. It only accepts a tracked installed upload, so
+/// arbitrary folders are never wiped. The launch configuration and the playtime are kept
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_upload` - The installed upload to reinstall
+///
+/// * `options` - The download options, see [`DownloadOptions`]
+///
+/// * `upload_info` - A closure which reports the upload and the game info before the download starts
+///
+/// * `progress_callback` - A closure which reports the download progress
+///
+/// # Returns
+///
+/// The new installation info about the upload
+///
+/// # Errors
+///
+/// If something goes wrong. The old files have already been removed by then, so the upload
+/// should be downloaded again
+pub fn reinstall_upload(
+  client: &ItchClient,
+  installed_upload: &InstalledUpload,
+  options: &DownloadOptions,
+  upload_info: impl FnOnce(&Upload, &Game),
+  progress_callback: impl Fn(DownloadStatus),
+) -> Result<InstalledUpload, ScratchError> {
+  let upload_id: UploadID = installed_upload.upload_id;
+  let game_folder: &Path = &installed_upload.game_folder;
+  let upload_folder: PathBuf = installed_upload.upload_folder();
+
+  debug!(
+    "Reinstalling the upload {upload_id} in \"{}\"",
+    game_folder.display()
+  );
+
+  if filesystem::exists(&upload_folder)? {
+    game_files::remove_folder_safely(&upload_folder)?;
+  }
+
+  // A leftover archive would be used instead of downloading it again
+  remove_partial_download(client, upload_id, game_folder)?;
+  if let Some(temp_dir) = &options.temp_dir {
+    remove_partial_download(client, upload_id, temp_dir)?;
+  }
+
+  let reinstalled: InstalledUpload = download_upload_with_options(
+    client,
+    upload_id,
+    game_folder,
+    options,
+    upload_info,
+    progress_callback,
+  )?;
+
+  Ok(InstalledUpload {
+    launch_override: installed_upload.launch_override.clone(),
+    last_played_at: installed_upload.last_played_at,
+    total_playtime: installed_upload.total_playtime,
+    ..reinstalled
+  })
+}
+
+/// Find the upload folders inside a base folder which aren't tracked as installed uploads
+///
+/// The base folder and each of its subfolders are treated as game folders, and the folders
+/// inside them named after an upload ID are the upload candidates. A candidate is never returned
+/// if it is a tracked upload folder, or if a tracked upload is placed inside it
+///
+/// # Arguments
+///
+/// * `installed_uploads` - The tracked installed uploads
+///
+/// * `base_folder` - The folder to scan, usually the one containing all the game folders
+///
+/// # Returns
+///
+/// The canonical paths of the orphaned upload folders
+///
+/// # Errors
+///
+/// If the base folder or its subfolders couldn't be read
+pub fn find_orphaned_uploads<'a>(
+  installed_uploads: impl IntoIterator<Item = &'a InstalledUpload>,
+  base_folder: &Path,
+) -> Result<Vec<PathBuf>, ScratchError> {
+  let base_folder: PathBuf = filesystem::get_canonical_path(base_folder)?;
+
+  // The tracked upload folders which still exist, in their canonical form
+  let tracked: Vec<PathBuf> = installed_uploads
+    .into_iter()
+    .filter_map(|iu| filesystem::get_canonical_path(&iu.upload_folder()).ok())
+    .collect();
+
+  // Find the folders inside `folder` whose name is a number, like the upload folders
+  let numeric_subfolders = |folder: &Path| -> Result<Vec<PathBuf>, FilesystemError> {
+    let mut folders: Vec<PathBuf> = Vec::new();
+    let mut entries = filesystem::read_dir(folder)?;
+
+    while let Some(entry) = filesystem::next_entry(&mut entries, folder)? {
+      // Symlinks are never followed, so nothing outside the base folder is touched
+      if !filesystem::file_type(&entry, folder)?.is_dir() {
+        continue;
+      }
+
+      let path: PathBuf = entry.path();
+      if filesystem::get_file_name(&path)?
+        .parse::<UploadID>()
+        .is_ok()
+      {
+        folders.push(path);
+      }
+    }
+
+    Ok(folders)
+  };
+
+  let mut candidates: Vec<PathBuf> = numeric_subfolders(&base_folder)?;
+  let mut entries = filesystem::read_dir(&base_folder)?;
+  while let Some(entry) = filesystem::next_entry(&mut entries, &base_folder)? {
+    if filesystem::file_type(&entry, &base_folder)?.is_dir() {
+      candidates.extend(numeric_subfolders(&entry.path())?);
+    }
+  }
+
+  let mut orphans: Vec<PathBuf> = candidates
+    .into_iter()
+    .filter(|c| !tracked.iter().any(|t| t.starts_with(c)))
+    .collect();
+
+  // If an orphaned folder is inside another one, removing the outer folder is enough
+  orphans.sort();
+  orphans.dedup_by(|inner, outer| inner.starts_with(outer));
+
+  Ok(orphans)
+}
+
+/// Remove the upload folders inside a base folder which aren't tracked as installed uploads
+///
+/// The orphans are searched again with [`find_orphaned_uploads`], so a tracked upload is never removed.
+/// The game folders left empty are removed too
+///
+/// # Arguments
+///
+/// * `installed_uploads` - The tracked installed uploads
+///
+/// * `base_folder` - The folder to scan, usually the one containing all the game folders
+///
+/// # Returns
+///
+/// The paths of the removed upload folders
+///
+/// # Errors
+///
+/// If the base folder couldn't be read or a folder couldn't be removed
+pub fn prune_orphaned_uploads<'a>(
+  installed_uploads: impl IntoIterator<Item = &'a InstalledUpload>,
+  base_folder: &Path,
+) -> Result<Vec<PathBuf>, ScratchError> {
+  let base_folder: PathBuf = filesystem::get_canonical_path(base_folder)?;
+  let orphans: Vec<PathBuf> = find_orphaned_uploads(installed_uploads, &base_folder)?;
+
+  for orphan in &orphans {
+    debug!(
+      "Removing the orphaned upload folder: \"{}\"",
+      orphan.display()
+    );
+    game_files::remove_folder_safely(orphan)?;
+
+    // If the game folder is now empty, remove it, but never remove the base folder
+    let game_folder: &Path = filesystem::parent(orphan)?;
+    if game_folder != base_folder {
+      game_files::remove_folder_if_empty(&LocalStorage, game_folder)?;
+    }
+  }
+
+  Ok(orphans)
+}
+
+/// Move an installed upload to a new game folder
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of upload which will be moved
+///
+/// * `src_game_folder` - The folder where the game files are currently placed
+///
+/// * `dst_game_folder` - The folder where the game files will be moved to
+///
+/// # Returns
+///
+/// The new game folder in its absolute (canonical) form
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn r#move(
+  upload_id: UploadID,
+  src_game_folder: &Path,
+  dst_game_folder: &Path,
+) -> Result<PathBuf, ScratchError> {
+  let src_upload_folder = game_files::get_upload_folder(src_game_folder, upload_id);
+
+  // If there isn't a src_upload_folder, exit with error
+  filesystem::ensure_is_dir(&src_upload_folder)?;
+
+  let dst_upload_folder = game_files::get_upload_folder(dst_game_folder, upload_id);
+
+  // If there is a dst_upload_folder with contents, exit with error
+  filesystem::ensure_is_empty(&dst_upload_folder)?;
+
+  // Move the upload folder
+  game_files::move_folder(&LocalStorage, &src_upload_folder, &dst_upload_folder)?;
+
+  // If src_game_folder is empty, remove it
+  game_files::remove_folder_if_empty(&LocalStorage, src_game_folder)?;
+
+  filesystem::get_canonical_path(dst_game_folder).map_err(std::convert::Into::into)
+}
+
+/// Move the game folders of several installed uploads into a new base folder, like when relocating a whole library
+///
+/// Each game folder keeps its name, so an upload in `old_base/game` is moved to `new_base/game`.
+/// The uploads are moved one by one with [`r#move`], and if one of them fails the rest are still moved
+///
+/// # Arguments
+///
+/// * `installed_uploads` - The installed uploads to move. The game folder of each moved upload is updated
+///
+/// * `new_base` - The folder where the game folders will be placed
+///
+/// # Returns
+///
+/// A vector of tuples containing each upload ID and the result of moving it. The uploads which
+/// failed still have their original game folder
+pub fn move_game_folder<'a>(
+  installed_uploads: impl IntoIterator<Item = &'a mut InstalledUpload>,
+  new_base: &Path,
+) -> Vec<(UploadID, Result<(), ScratchError>)> {
+  installed_uploads
+    .into_iter()
+    .map(|iu| {
+      let result = filesystem::get_file_name(&iu.game_folder)
+        .map_err(ScratchError::from)
+        .and_then(|name| r#move(iu.upload_id, &iu.game_folder, &new_base.join(name)))
+        .map(|new_game_folder| iu.game_folder = new_game_folder);
+
+      (iu.upload_id, result)
+    })
+    .collect()
+}
+
+/// Get how much disk space the files of an installed upload occupy
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of the upload
+///
+/// * `game_folder` - The folder with the game files where the upload folder is placed
+///
+/// # Returns
+///
+/// The total size in bytes of the files inside the upload folder. Symlinks aren't followed
+///
+/// # Errors
+///
+/// If the upload folder doesn't exist or it couldn't be read
+pub fn get_upload_disk_usage(upload_id: UploadID, game_folder: &Path) -> Result<u64, ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+
+  filesystem::ensure_is_dir(&upload_folder)?;
+
+  game_files::get_folder_size(&upload_folder).map_err(std::convert::Into::into)
+}
+
+/// Retrieve the itch manifest from an installed upload
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of upload from which the info will be retrieved
+///
+/// * `game_folder` - The folder with the game files where the upload folder is placed
+///
+/// # Returns
+///
+/// A [`Manifest`] struct with the manifest actions info, or None if the manifest isn't present
+///
+/// # Errors
+///
+/// If something goes wrong
+pub fn get_upload_manifest(
+  upload_id: UploadID,
+  game_folder: &Path,
+) -> Result<Option<Manifest>, ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+
+  itch_manifest::read_manifest(&upload_folder).map_err(ScratchError::Manifest)
+}
+
+/// List the actions of the itch manifest of an installed upload
+///
+/// This can be used to let the user choose which action to launch with [`LaunchMethod::ManifestAction`]
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of upload from which the actions will be retrieved
+///
+/// * `game_folder` - The folder with the game files where the upload folder is placed
+///
+/// # Returns
+///
+/// All the manifest actions, which is empty if the manifest isn't present
+///
+/// # Errors
+///
+/// If the manifest couldn't be read or parsed
+pub fn list_manifest_actions(
+  upload_id: UploadID,
+  game_folder: &Path,
+) -> Result<Vec<ManifestAction>, ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+
+  itch_manifest::actions(&upload_folder).map_err(ScratchError::Manifest)
+}
+
+/// Retrieve the prerequisites declared in the itch manifest of an installed upload
+///
+/// The prerequisites are runtimes the game needs, like the Visual C++ redistributables
+/// or .NET, which have to be installed separately (for example, with winetricks under Wine)
+///
+/// # Arguments
+///
+/// * `upload_id` - The ID of upload from which the prerequisites will be retrieved
+///
+/// * `game_folder` - The folder with the game files where the upload folder is placed
+///
+/// # Returns
+///
+/// The names of the prerequisites, which is empty if the manifest isn't present or doesn't declare any
+///
+/// # Errors
+///
+/// If the manifest couldn't be read or parsed
+pub fn get_manifest_prereqs(
+  upload_id: UploadID,
+  game_folder: &Path,
+) -> Result<Vec<ManifestPrerequisiteName>, ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
 
-  Ok(was_something_deleted)
+  itch_manifest::prerequisites(&upload_folder).map_err(ScratchError::Manifest)
 }
 
-/// Remove an installed upload
+/// Rate the files of an installed upload as its game executable, like the launch heuristics do
+///
+/// This can be used to show alternatives when the heuristics pick the wrong file
 ///
 /// # Arguments
 ///
-/// * `upload_id` - The ID of upload which will be removed
+/// * `upload_id` - The ID of the upload whose files will be rated
 ///
-/// * `game_folder` - The folder with the game files where the upload will be removed from
+/// * `game_folder` - The folder with the game files where the upload folder is placed
+///
+/// * `platform` - The platform the game executable will be run on
+///
+/// * `game_title` - The title of the game
+///
+/// * `config` - Options to influence the heuristics
+///
+/// # Returns
+///
+/// The files, their ratings and the subsystem of the Windows executables,
+/// sorted from the best to the worst candidate
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn remove(upload_id: UploadID, game_folder: &Path) -> Result<(), String> {
+/// If the upload folder couldn't be read
+pub fn rank_executables(
+  upload_id: UploadID,
+  game_folder: &Path,
+  platform: GamePlatform,
+  game_title: String,
+  config: &HeuristicsConfig,
+) -> Result<Vec<RankedExecutable>, ScratchError> {
   let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
 
-  // If there isn't a upload_folder, or it is empty, that means the game
-  // has already been removed, so return Ok(())
-  if filesystem::is_folder_empty(&upload_folder)? {
-    return Ok(());
-  }
-
-  game_files::remove_folder_safely(&upload_folder)?;
-  // The upload folder has been removed
-
-  // If the game folder is empty, remove it
-  game_files::remove_folder_if_empty(game_folder)?;
-
-  Ok(())
+  heuristics::rank_executables(&upload_folder, platform, game_title, config)
+    .map_err(ScratchError::Launch)
 }
 
-/// Move an installed upload to a new game folder
+/// List everything that can be launched from an installed upload, like for a "Play" menu
+///
+/// The targets are collected from, in this order:
+/// 1. The actions of the itch manifest of the installed files
+/// 2. The launch targets found by the itch.io servers when the upload archive was scanned
+/// 3. The executables chosen by the launch heuristics for each of the upload's platforms
+///
+/// A file found by more than one source is only listed once, with the first source.
+/// Uploads that were never scanned by the servers just don't have server targets
 ///
 /// # Arguments
 ///
-/// * `upload_id` - The ID of upload which will be moved
+/// * `client` - An itch.io API client
 ///
-/// * `src_game_folder` - The folder where the game files are currently placed
+/// * `upload_id` - The ID of the installed upload
 ///
-/// * `dst_game_folder` - The folder where the game files will be moved to
+/// * `game_folder` - The folder with the game files where the upload folder is placed
 ///
 /// # Returns
 ///
-/// The new game folder in its absolute (canonical) form
+/// The launch targets, see [`LaunchTarget::launch_method`] to launch them
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn r#move(
+/// If the manifest couldn't be read, or a request to the API fails
+pub fn get_launch_targets(
+  client: &ItchClient,
   upload_id: UploadID,
-  src_game_folder: &Path,
-  dst_game_folder: &Path,
-) -> Result<PathBuf, String> {
-  let src_upload_folder = game_files::get_upload_folder(src_game_folder, upload_id);
-
-  // If there isn't a src_upload_folder, exit with error
-  filesystem::ensure_is_dir(&src_upload_folder)?;
+  game_folder: &Path,
+) -> Result<Vec<LaunchTarget>, ScratchError> {
+  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+  filesystem::ensure_is_dir(&upload_folder)?;
 
-  let dst_upload_folder = game_files::get_upload_folder(dst_game_folder, upload_id);
+  let mut targets: Vec<LaunchTarget> = Vec::new();
+  let mut push_target = |target: LaunchTarget| {
+    if !targets.iter().any(|t| t.path == target.path) {
+      targets.push(target);
+    }
+  };
 
-  // If there is a dst_upload_folder with contents, exit with error
-  filesystem::ensure_is_empty(&dst_upload_folder)?;
+  // 1. The manifest actions
+  for action in itch_manifest::actions(&upload_folder).map_err(ScratchError::Manifest)? {
+    push_target(LaunchTarget {
+      path: upload_folder.join(&action.path),
+      platform: match action.platform {
+        Some(ManifestActionPlatform::Linux) => Some(GamePlatform::Linux),
+        Some(ManifestActionPlatform::Windows) => Some(GamePlatform::Windows),
+        Some(ManifestActionPlatform::Osx) => Some(GamePlatform::OSX),
+        Some(ManifestActionPlatform::Unknown) | None => None,
+      },
+      name: action.name,
+      source: LaunchTargetSource::Manifest,
+    });
+  }
 
-  // Move the upload folder
-  game_files::move_folder(&src_upload_folder, &dst_upload_folder)?;
+  // 2. The targets of the server scan, if the upload has been scanned
+  match get_upload_scanned_archive(client, upload_id) {
+    Ok(scanned_archive) => {
+      for target in scanned_archive.launch_targets.unwrap_or_default() {
+        let path = upload_folder.join(&target.path);
+        push_target(LaunchTarget {
+          name: filesystem::get_file_name(&path)?.to_string(),
+          path,
+          platform: target.flavor.to_game_platform(),
+          source: LaunchTargetSource::ServerScan,
+        });
+      }
+    }
+    Err(ItchRequestJSONError {
+      kind: ItchRequestJSONErrorKind::ServerRepliedWithError(e),
+      ..
+    }) => debug!("The upload doesn't have a scanned archive: {e}"),
+    Err(e) => return Err(e.into()),
+  }
 
-  // If src_game_folder is empty, remove it
-  game_files::remove_folder_if_empty(src_game_folder)?;
+  // 3. The executables chosen by the heuristics
+  let (upload, game): (Upload, Game) = get_upload_and_game(client, upload_id)?;
+  for platform in upload.to_game_platforms() {
+    match heuristics::get_game_executable(
+      &upload_folder,
+      platform,
+      game.game_info.title.clone(),
+      &HeuristicsConfig::default(),
+    ) {
+      Ok(path) => push_target(LaunchTarget {
+        name: filesystem::get_file_name(&path)?.to_string(),
+        path,
+        platform: Some(platform),
+        source: LaunchTargetSource::Heuristics,
+      }),
+      Err(e) => debug!("The heuristics didn't find an executable for {platform:?}: {e}"),
+    }
+  }
 
-  filesystem::get_canonical_path(dst_game_folder).map_err(std::convert::Into::into)
+  Ok(targets)
 }
 
-/// Retrieve the itch manifest from an installed upload
+/// Launchs an installed upload
+///
+/// It waits until the game exits, to measure how long it was played. To return as soon as the
+/// game is started, use [`launch_detached`]
 ///
 /// # Arguments
 ///
-/// * `upload_id` - The ID of upload from which the info will be retrieved
+/// * `upload_id` - The ID of upload which will be launched
 ///
-/// * `game_folder` - The folder with the game files where the upload folder is placed
+/// * `game_folder` - The folder where the game uploads are placed
+///
+/// * `launch_method` - The launch method to use to determine the upload executable file.
+///   To use the executable chosen by the user, see [`InstalledUpload::launch_override_method`]
+///   For uploads installed without extracting them, see [`InstalledUpload::launch_target_method`]
+///
+/// * `wrapper` - A list of a wrapper and its options to run the upload executable with.
+///   An argument can contain [`EXECUTABLE_PLACEHOLDER`] to place the executable there instead of after the wrapper
+///
+/// * `wrapper_templates` - The wrappers used when `wrapper` is empty, chosen by the platform of the
+///   executable: the heuristics platform, or the platform detected from the file, like Windows for PE executables
+///
+/// * `game_arguments` - A list of arguments to launch the upload executable with
+///
+/// * `arguments_merge_strategy` - How `game_arguments` are combined with the manifest action's arguments, if launching from the manifest
+///
+/// * `environment_variables` - A list of environment variables to be added to the upload executable process's environment.
+///   They are added on top of the inherited environment, and if a key is repeated, the last value wins
+///
+/// * `capture_output` - Capture the standard error output of the game, to inspect why it failed.
+///   It is still forwarded to the standard error of this process
+///
+/// * `verify_fingerprint` - If Some, the upload folder is fingerprinted again and compared with it before running
+///   the game, and the launch is aborted if the files have been modified. Pass the [`InstalledUpload::fingerprint`]
+///   stored when the upload was installed. If the user asked for verification but the upload doesn't have a stored
+///   fingerprint, the files can't be verified, so the caller should warn about it.
+///   Games that save their data inside the upload folder will always fail the verification
+///
+/// * `missing_prereqs` - A callback triggered with the prerequisites declared in the itch manifest, if there are any, so they can be installed before the game runs. See [`get_manifest_prereqs`]
+///
+/// * `launch_start_callback` - A callback triggered just before the upload executable runs, providing information about what is about to be executed
 ///
 /// # Returns
 ///
-/// A [`Manifest`] struct with the manifest actions info, or None if the manifest isn't present
+/// When the game was started, how long it ran, its exit status and its captured output.
+/// The caller should save it in the installed upload with [`InstalledUpload::record_launch`]
+///
+/// A game which exits with an error isn't an error of this function, check [`LaunchResult::exit_status`]
 ///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn get_upload_manifest(
+/// If something goes wrong, or the installed files don't match `verify_fingerprint`
+#[expect(clippy::too_many_arguments)]
+pub fn launch(
   upload_id: UploadID,
   game_folder: &Path,
-) -> Result<Option<Manifest>, String> {
-  let upload_folder = game_files::get_upload_folder(game_folder, upload_id);
+  launch_method: LaunchMethod,
+  wrapper: &[String],
+  wrapper_templates: &HashMap<GamePlatform, WrapperTemplate>,
+  game_arguments: &[String],
+  arguments_merge_strategy: ArgumentsMergeStrategy,
+  environment_variables: &[(String, String)],
+  capture_output: bool,
+  verify_fingerprint: Option<&Fingerprint>,
+  missing_prereqs: impl FnOnce(&[ManifestPrerequisiteName]),
+  launch_start_callback: impl FnOnce(&Path, &std::process::Command),
+) -> Result<LaunchResult, ScratchError> {
+  enter_span!("launch", upload_id = upload_id);
 
-  itch_manifest::read_manifest(&upload_folder)
+  let (upload_executable, mut game_process) = prepare_launch(
+    upload_id,
+    game_folder,
+    launch_method,
+    wrapper,
+    wrapper_templates,
+    game_arguments,
+    arguments_merge_strategy,
+    environment_variables,
+    verify_fingerprint,
+    missing_prereqs,
+  )?;
+
+  if capture_output {
+    game_process.stderr(std::process::Stdio::piped());
+  }
+
+  launch_start_callback(&upload_executable, &game_process);
+
+  let started_at = OffsetDateTime::now_utc();
+  let start = Instant::now();
+  let mut child = filesystem::spawn_command(&mut game_process)?;
+  // The pipe closes when the game exits, so read it before waiting to avoid filling it up
+  let stderr: Option<String> = filesystem::read_child_stderr(&mut child)?;
+  let exit_status = filesystem::wait_child(&mut child)?;
+  let played_for = start.elapsed();
+
+  debug!("The game exited with {exit_status} after {played_for:?}");
+
+  Ok(LaunchResult {
+    started_at,
+    played_for,
+    exit_status,
+    stderr,
+  })
 }
 
-/// Launchs an installed upload
+/// Launchs an installed upload without waiting for it to exit
+///
+/// It works like [`launch`], but it returns as soon as the game process is started, so the
+/// caller isn't blocked while the game runs. On Unix, the game runs in its own process group,
+/// so it doesn't receive the signals sent to the caller's terminal, like Ctrl+C
 ///
 /// # Arguments
 ///
@@ -745,30 +3712,127 @@ pub fn get_upload_manifest(
 ///
 /// * `game_folder` - The folder where the game uploads are placed
 ///
-/// * `launch_method` - The launch method to use to determine the upload executable file
+/// * `launch_method` - The launch method to use to determine the upload executable file, see [`launch`]
 ///
 /// * `wrapper` - A list of a wrapper and its options to run the upload executable with
 ///
+/// * `wrapper_templates` - The wrappers used when `wrapper` is empty, see [`launch`]
+///
 /// * `game_arguments` - A list of arguments to launch the upload executable with
 ///
+/// * `arguments_merge_strategy` - How `game_arguments` are combined with the manifest action's arguments, if launching from the manifest
+///
 /// * `environment_variables` - A list of environment variables to be added to the upload executable process's environment
 ///
+/// * `verify_fingerprint` - If Some, the launch is aborted if the installed files don't match it, see [`launch`]
+///
+/// * `missing_prereqs` - A callback triggered with the prerequisites declared in the itch manifest, if there are any
+///
 /// * `launch_start_callback` - A callback triggered just before the upload executable runs, providing information about what is about to be executed
 ///
+/// # Returns
+///
+/// The started game process. The caller manages its lifecycle: waiting for it is the only way to
+/// know how long the game ran, so the playtime isn't recorded unless the caller does it
+///
 /// # Errors
 ///
-/// If something goes wrong
-pub fn launch(
+/// If something goes wrong, or the installed files don't match `verify_fingerprint`
+#[expect(clippy::too_many_arguments)]
+pub fn launch_detached(
   upload_id: UploadID,
   game_folder: &Path,
   launch_method: LaunchMethod,
   wrapper: &[String],
+  wrapper_templates: &HashMap<GamePlatform, WrapperTemplate>,
   game_arguments: &[String],
+  arguments_merge_strategy: ArgumentsMergeStrategy,
   environment_variables: &[(String, String)],
+  verify_fingerprint: Option<&Fingerprint>,
+  missing_prereqs: impl FnOnce(&[ManifestPrerequisiteName]),
   launch_start_callback: impl FnOnce(&Path, &std::process::Command),
-) -> Result<(), String> {
+) -> Result<std::process::Child, ScratchError> {
+  enter_span!("launch_detached", upload_id = upload_id);
+
+  let (upload_executable, mut game_process) = prepare_launch(
+    upload_id,
+    game_folder,
+    launch_method,
+    wrapper,
+    wrapper_templates,
+    game_arguments,
+    arguments_merge_strategy,
+    environment_variables,
+    verify_fingerprint,
+    missing_prereqs,
+  )?;
+
+  // A new process group keeps the game running when the caller's terminal is interrupted
+  #[cfg(unix)]
+  std::os::unix::process::CommandExt::process_group(&mut game_process, 0);
+
+  launch_start_callback(&upload_executable, &game_process);
+
+  let child = filesystem::spawn_command(&mut game_process)?;
+  debug!(
+    "The game was started detached with the process ID {}",
+    child.id()
+  );
+
+  Ok(child)
+}
+
+/// Build the command which runs an installed upload, shared by [`launch`] and [`launch_detached`]
+///
+/// # Returns
+///
+/// The upload executable and the command to run it
+#[expect(clippy::too_many_arguments)]
+fn prepare_launch(
+  upload_id: UploadID,
+  game_folder: &Path,
+  launch_method: LaunchMethod,
+  wrapper: &[String],
+  wrapper_templates: &HashMap<GamePlatform, WrapperTemplate>,
+  game_arguments: &[String],
+  arguments_merge_strategy: ArgumentsMergeStrategy,
+  environment_variables: &[(String, String)],
+  verify_fingerprint: Option<&Fingerprint>,
+  missing_prereqs: impl FnOnce(&[ManifestPrerequisiteName]),
+) -> Result<(PathBuf, std::process::Command), ScratchError> {
   let upload_folder: PathBuf = game_files::get_upload_folder(game_folder, upload_id);
 
+  // Don't run anything if the installed files have been tampered with
+  if let Some(stored) = verify_fingerprint {
+    debug!(
+      "Verifying \"{}\" against the fingerprint {}",
+      upload_folder.display(),
+      stored.hash
+    );
+
+    let current: Fingerprint = folder_fingerprint(&upload_folder, stored.mode)?;
+    if current.hash != stored.hash {
+      return Err(ScratchError::Verification(format!(
+        "The installed files have been modified since the upload was installed, so it won't be launched!
+  Reinstall the upload, or launch it without verification if the changes are expected: \"{}\"",
+        upload_folder.display()
+      )));
+    }
+  }
+
+  // The installed runtimes can't be detected, so report all the declared prerequisites
+  let prereqs: Vec<ManifestPrerequisiteName> =
+    itch_manifest::prerequisites(&upload_folder).map_err(ScratchError::Manifest)?;
+  if !prereqs.is_empty() {
+    missing_prereqs(&prereqs);
+  }
+
+  // The heuristics platform chooses the wrapper template, even if the manifest provides the executable
+  let heuristics_platform: Option<GamePlatform> = match &launch_method {
+    LaunchMethod::Heuristics { game_platform, .. } => Some(*game_platform),
+    _ => None,
+  };
+
   // Determine the upload executable and its launch arguments from the function arguments, manifest, or heuristics.
   let (upload_executable, game_arguments): (PathBuf, Cow<[String]>) = match launch_method {
     // 1. If the launch method is an alternative executable, then that executable with the arguments provided to the function
@@ -779,48 +3843,49 @@ pub fn launch(
     LaunchMethod::ManifestAction {
       manifest_action_name,
     } => {
-      let ma = itch_manifest::launch_action(&upload_folder, Some(&manifest_action_name))?
+      let ma = itch_manifest::launch_action(&upload_folder, Some(&manifest_action_name), None)
+        .map_err(ScratchError::Manifest)?
         .ok_or_else(|| {
-          format!(
+          ScratchError::Launch(format!(
             "The provided launch action doesn't exist in the manifest: {manifest_action_name}"
-          )
+          ))
         })?;
       (
         ma.get_canonical_path(&upload_folder)?,
-        // a) If the function's game arguments are empty, use the ones from the manifest
-        if game_arguments.is_empty() {
-          Cow::Owned(ma.args.unwrap_or_default())
-        }
-        // b) Otherwise, use the provided ones
-        else {
-          Cow::Borrowed(game_arguments)
-        },
+        arguments_merge_strategy.merge(ma.args, game_arguments),
       )
     }
     // 3. Otherwise, if the launch method are the heuristics, use them to locate the executable
     LaunchMethod::Heuristics {
       game_platform,
       game_title,
+      config,
     } => {
-      // But first, check if the game has a manifest with a "play" action, and use it if possible
-      let mao = itch_manifest::launch_action(&upload_folder, None)?;
+      // But first, check if the game has a manifest with a "play" action for the platform, and use it if possible
+      let mao = itch_manifest::launch_action(
+        &upload_folder,
+        None,
+        game_platform.to_manifest_platform().as_ref(),
+      )
+      .map_err(ScratchError::Manifest)?;
 
       match mao {
         // If the manifest has a "play" action, launch from it
         Some(ma) => (
           ma.get_canonical_path(&upload_folder)?,
-          // a) If the function's game arguments are empty, use the ones from the manifest
-          if game_arguments.is_empty() {
-            Cow::Owned(ma.args.unwrap_or_default())
-          }
-          // b) Otherwise, use the provided ones
-          else {
-            Cow::Borrowed(game_arguments)
-          },
+          arguments_merge_strategy.merge(ma.args, game_arguments),
         ),
+        // Android packages can't be launched on desktop, so don't even search them
+        None if game_platform == GamePlatform::Android => {
+          return Err(ScratchError::Launch(
+            "Android games can't be launched on this device! Transfer the .apk file to an Android device to install it"
+              .to_string(),
+          ));
+        }
         // Else, now use the heuristics to determine the executable, with the function's game arguments
         None => (
-          heuristics::get_game_executable(&upload_folder, game_platform, game_title)?,
+          heuristics::get_game_executable(&upload_folder, game_platform, game_title, &config)
+            .map_err(ScratchError::Launch)?,
           Cow::Borrowed(game_arguments),
         ),
       }
@@ -829,37 +3894,151 @@ pub fn launch(
 
   let upload_executable = filesystem::get_canonical_path(&upload_executable)?;
 
+  // Refuse to run Android packages, which would fail in a confusing way
+  if game_files::is_android_package(&upload_executable) {
+    return Err(ScratchError::Launch(format!(
+      "The executable is an Android package, which can't be launched on this device! Transfer it to an Android device to install it: \"{}\"",
+      upload_executable.display()
+    )));
+  }
+
+  // Disk images are only extracted on macOS, and they can't be run as they are
+  if game_files::is_disk_image(&upload_executable) {
+    return Err(ScratchError::Launch(format!(
+      "The executable is a macOS disk image, which can only be opened on macOS: \"{}\"",
+      upload_executable.display()
+    )));
+  }
+
+  // A macOS application bundle is run through its inner executable, from the bundle folder
+  let (upload_executable, working_directory): (PathBuf, PathBuf) =
+    if game_files::is_app_bundle(&upload_executable)
+      && filesystem::is_dir(&upload_executable)? == Some(true)
+    {
+      (
+        macos::get_bundle_executable(&upload_executable).map_err(ScratchError::Launch)?,
+        upload_executable,
+      )
+    } else {
+      (upload_executable, upload_folder)
+    };
+
   // Make the file executable
   filesystem::make_executable(&upload_executable)?;
 
+  // Java archives aren't executables, so they are run by the Java runtime
+  let runner: &[&str] = if game_files::is_java_archive(&upload_executable) {
+    &["java", "-jar"]
+  } else {
+    &[]
+  };
+
+  // Without an explicit wrapper, use the template of the executable's platform, if there is one
+  let wrapper_template: Option<&WrapperTemplate> = if wrapper.is_empty() {
+    heuristics_platform
+      .or_else(|| detect_executable_platform(&upload_executable))
+      .and_then(|platform| wrapper_templates.get(&platform))
+  } else {
+    None
+  };
+  let wrapper: &[String] = wrapper_template.map_or(wrapper, |t| t.command.as_slice());
+
   // Create the process
   let mut game_process = {
-    let mut wrapper_iter = wrapper.iter();
-    match wrapper_iter.next() {
-      // If it doesn't have a wrapper, just run the executable
-      None => std::process::Command::new(&upload_executable),
-      Some(w) => {
-        // If the game has a wrapper, then run the wrapper with its
-        // arguments and add the game executable as the last argument
-        let mut gp = std::process::Command::new(w);
-        gp.args(wrapper_iter).arg(&upload_executable);
-        gp
-      }
-    }
+    let mut executable_command: Vec<std::ffi::OsString> = runner.iter().map(Into::into).collect();
+    executable_command.push(upload_executable.clone().into_os_string());
+
+    let command = wrap_command(wrapper, executable_command);
+    let mut gp = std::process::Command::new(&command[0]);
+    gp.args(&command[1..]);
+    gp
   };
 
   // Add the working directory, the game arguments and the environment variables
+  // The variables extend the inherited environment, and Command keeps the last value of a repeated key,
+  // so the explicit environment variables override the ones of the wrapper template
   game_process
-    .current_dir(&upload_folder)
+    .current_dir(&working_directory)
     .args(&*game_arguments)
-    .envs(environment_variables.iter().map(|(k, v)| (k, v)));
+    .envs(
+      wrapper_template
+        .iter()
+        .flat_map(|t| t.environment_variables.iter())
+        .chain(environment_variables)
+        .map(|(k, v)| (k, v)),
+    );
 
-  launch_start_callback(&upload_executable, &game_process);
+  // The environment variables aren't logged, because they can contain secrets
+  debug!(
+    "Running {:?} with the arguments {:?} in \"{}\"",
+    game_process.get_program(),
+    game_process.get_args().collect::<Vec<_>>(),
+    working_directory.display()
+  );
 
-  let mut child = filesystem::spawn_command(&mut game_process)?;
-  filesystem::wait_child(&mut child)?;
+  Ok((upload_executable, game_process))
+}
 
-  Ok(())
+/// Guess the platform of an executable from its file, to choose its wrapper template
+///
+/// # Arguments
+///
+/// * `executable` - The path of the executable
+///
+/// # Returns
+///
+/// Windows for PE executables, Java for Java archives, and None otherwise
+fn detect_executable_platform(executable: &Path) -> Option<GamePlatform> {
+  if game_files::is_java_archive(executable) {
+    Some(GamePlatform::Java)
+  } else if pe::read_pe_info(executable).is_some() {
+    Some(GamePlatform::Windows)
+  } else {
+    None
+  }
+}
+
+/// Combine a wrapper with the command which runs the executable
+///
+/// # Arguments
+///
+/// * `wrapper` - The wrapper and its options, which can contain [`EXECUTABLE_PLACEHOLDER`]
+///
+/// * `executable_command` - The executable, preceded by its runner if it needs one. It can't be empty
+///
+/// # Returns
+///
+/// The whole command, with the program first. If no wrapper argument contains the
+/// placeholder, the executable command is added after the wrapper.
+/// Otherwise, an argument which is exactly the placeholder is replaced by the whole executable
+/// command, and the placeholder inside other arguments is replaced by the executable path
+fn wrap_command(
+  wrapper: &[String],
+  executable_command: Vec<std::ffi::OsString>,
+) -> Vec<std::ffi::OsString> {
+  if !wrapper.iter().any(|w| w.contains(EXECUTABLE_PLACEHOLDER)) {
+    return wrapper
+      .iter()
+      .map(Into::into)
+      .chain(executable_command)
+      .collect();
+  }
+
+  let executable = executable_command
+    .last()
+    .expect("The executable command can't be empty")
+    .to_string_lossy()
+    .into_owned();
+
+  let mut command = Vec::new();
+  for w in wrapper {
+    if w == EXECUTABLE_PLACEHOLDER {
+      command.extend(executable_command.iter().cloned());
+    } else {
+      command.push(w.replace(EXECUTABLE_PLACEHOLDER, &executable).into());
+    }
+  }
+  command
 }
 
 /// Get the url to a itch.io web game
@@ -875,3 +4054,195 @@ pub fn launch(
 pub fn get_web_game_url(upload_id: UploadID) -> String {
   format!("https://html-classic.itch.zone/html/{upload_id}/index.html")
 }
+
+/// Get the default folder where the game folders are placed: `~/Games`
+///
+/// # Returns
+///
+/// The folder, or None if the home directory is unknown
+#[must_use]
+pub fn default_games_dir() -> Option<PathBuf> {
+  std::env::home_dir().map(|home| home.join("Games"))
+}
+
+/// Get the folder where the files of a game are placed inside the games directory
+///
+/// # Arguments
+///
+/// * `games_dir` - The folder where the game folders are placed, see [`default_games_dir`]
+///
+/// * `game_title` - The title of the game
+///
+/// # Returns
+///
+/// The game folder, `{games_dir}/{game_title}`, with the title made a valid folder name.
+/// The same title always gets the same folder
+#[must_use]
+pub fn get_game_folder(games_dir: &Path, game_title: &str) -> PathBuf {
+  games_dir.join(game_files::sanitize_folder_name(game_title))
+}
+
+/// Check that a folder can be used as the games directory, creating it if it doesn't exist
+///
+/// # Arguments
+///
+/// * `games_dir` - The folder to check
+///
+/// # Returns
+///
+/// The canonical form of the folder
+///
+/// # Errors
+///
+/// If the folder couldn't be created, or a file can't be written inside it
+pub fn validate_games_dir(games_dir: &Path) -> Result<PathBuf, ScratchError> {
+  filesystem::create_dir(games_dir)?;
+  filesystem::ensure_is_dir(games_dir)?;
+
+  // Write and remove a file to check that the folder is writable
+  let probe: PathBuf = games_dir.join(".scratch-io-write-test");
+  filesystem::open_file(
+    &probe,
+    std::fs::OpenOptions::new()
+      .create(true)
+      .truncate(true)
+      .write(true),
+  )?;
+  filesystem::remove_file(&probe)?;
+
+  Ok(filesystem::get_canonical_path(games_dir)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    EXECUTABLE_PLACEHOLDER, GamePlatform, get_upload_download_url, select_upload_for_platform,
+    wrap_command,
+  };
+  use crate::itch_api::ItchApiVersion;
+  use crate::itch_api::types::{Upload, UploadStorage, UploadTrait, UploadType};
+
+  use time::OffsetDateTime;
+
+  /// Create an upload with the given traits, updated at the given UNIX timestamp
+  fn upload(id: u64, traits: &[UploadTrait], updated_at: i64) -> Upload {
+    let updated_at = OffsetDateTime::from_unix_timestamp(updated_at).unwrap();
+
+    Upload {
+      position: id,
+      id,
+      game_id: 1,
+      r#type: UploadType::Default,
+      traits: traits.to_vec(),
+      filename: format!("upload-{id}.zip"),
+      display_name: None,
+      storage: UploadStorage::Hosted {
+        size: 0,
+        md5_hash: None,
+      },
+      created_at: updated_at,
+      updated_at,
+    }
+  }
+
+  fn selected_id(uploads: &[Upload], platform: GamePlatform, prefer_demo: bool) -> Option<u64> {
+    select_upload_for_platform(uploads, &platform, prefer_demo).map(|u| u.id)
+  }
+
+  #[test]
+  fn no_upload_for_platform() {
+    let uploads = [upload(1, &[UploadTrait::PWindows], 0)];
+
+    assert_eq!(selected_id(&uploads, GamePlatform::Linux, false), None);
+    assert_eq!(selected_id(&[], GamePlatform::Linux, false), None);
+  }
+
+  #[test]
+  fn prefers_full_game_over_demo() {
+    let uploads = [
+      upload(1, &[UploadTrait::PLinux, UploadTrait::Demo], 20),
+      upload(2, &[UploadTrait::PLinux], 10),
+    ];
+
+    assert_eq!(selected_id(&uploads, GamePlatform::Linux, false), Some(2));
+    assert_eq!(selected_id(&uploads, GamePlatform::Linux, true), Some(1));
+  }
+
+  #[test]
+  fn falls_back_to_demo() {
+    let uploads = [
+      upload(1, &[UploadTrait::PLinux, UploadTrait::Demo], 0),
+      upload(2, &[UploadTrait::PWindows], 0),
+    ];
+
+    assert_eq!(selected_id(&uploads, GamePlatform::Linux, false), Some(1));
+  }
+
+  #[test]
+  fn prefers_most_recent_upload() {
+    let uploads = [
+      upload(1, &[UploadTrait::PLinux, UploadTrait::PWindows], 30),
+      upload(2, &[UploadTrait::PLinux], 20),
+      upload(3, &[UploadTrait::PWindows], 10),
+    ];
+
+    assert_eq!(selected_id(&uploads, GamePlatform::Linux, false), Some(1));
+    assert_eq!(selected_id(&uploads, GamePlatform::Windows, false), Some(1));
+    assert_eq!(selected_id(&uploads, GamePlatform::OSX, false), None);
+  }
+
+  #[test]
+  fn download_url_with_key_id_uses_v2() {
+    let url = get_upload_download_url(10, Some(20), None);
+
+    assert_eq!(url.version(), ItchApiVersion::V2);
+    assert_eq!(
+      url.as_str(),
+      "https://api.itch.io/uploads/10/download?download_key_id=20"
+    );
+  }
+
+  #[test]
+  fn download_url_with_key_uuid_uses_v1() {
+    let uuid = "0123abcd-4567-89ef-0123-456789abcdef";
+    let url = get_upload_download_url(10, Some(20), Some(uuid));
+
+    assert_eq!(url.version(), ItchApiVersion::V1);
+    assert_eq!(
+      url.as_str(),
+      format!("https://itch.io/api/1/download-key/{uuid}/download/10")
+    );
+  }
+
+  #[test]
+  fn wrapper_placeholder_is_replaced() {
+    let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let os_strings = |v: &[&str]| {
+      v.iter()
+        .map(Into::into)
+        .collect::<Vec<std::ffi::OsString>>()
+    };
+    let executable = os_strings(&["java", "-jar", "game.jar"]);
+
+    // Without a placeholder, the executable goes last
+    assert_eq!(
+      wrap_command(&strings(&["wine"]), executable.clone()),
+      os_strings(&["wine", "java", "-jar", "game.jar"])
+    );
+
+    // A whole placeholder argument is replaced by the runner and the executable
+    assert_eq!(
+      wrap_command(
+        &strings(&["gamescope", "--", EXECUTABLE_PLACEHOLDER, "-v"]),
+        executable.clone()
+      ),
+      os_strings(&["gamescope", "--", "java", "-jar", "game.jar", "-v"])
+    );
+
+    // Inside another argument, only the executable path is inserted
+    assert_eq!(
+      wrap_command(&strings(&["sh", "-c", "exec '{executable}'"]), executable),
+      os_strings(&["sh", "-c", "exec 'game.jar'"])
+    );
+  }
+}
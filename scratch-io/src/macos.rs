@@ -0,0 +1,208 @@
+use crate::filesystem;
+use crate::game_files::is_app_bundle;
+
+use std::path::{Path, PathBuf};
+
+/// The key of the bundle's `Info.plist` with the name of its executable
+const BUNDLE_EXECUTABLE_KEY: &str = "CFBundleExecutable";
+
+/// Get the value of a string key from an XML property list
+///
+/// This isn't a full plist parser: it only finds `<key>{key}</key>` followed by a
+/// `<string>` element, which is enough for the flat keys of an `Info.plist`
+fn read_plist_string(plist: &str, key: &str) -> Option<String> {
+  let key_element = format!("<key>{key}</key>");
+  let after_key = &plist[plist.find(&key_element)? + key_element.len()..];
+
+  let value = after_key.trim_start().strip_prefix("<string>")?;
+  let value = &value[..value.find("</string>")?];
+
+  Some(
+    value
+      .trim()
+      .replace("&lt;", "<")
+      .replace("&gt;", ">")
+      .replace("&quot;", "\"")
+      .replace("&apos;", "'")
+      .replace("&amp;", "&"),
+  )
+}
+
+/// Get the executable of a macOS application bundle (a `Game.app` folder)
+///
+/// The executable name is read from the `CFBundleExecutable` key of `Contents/Info.plist`.
+/// If it can't be read, for example because the plist is in the binary format, the
+/// executable is the file of `Contents/MacOS` named like the bundle, or its only file
+///
+/// # Errors
+///
+/// If the path isn't a bundle, or its executable couldn't be found
+pub(crate) fn get_bundle_executable(bundle: &Path) -> Result<PathBuf, String> {
+  if !is_app_bundle(bundle) || filesystem::is_dir(bundle)? != Some(true) {
+    return Err(format!(
+      "The path isn't a macOS application bundle: \"{}\"",
+      bundle.display()
+    ));
+  }
+
+  let macos_folder: PathBuf = bundle.join("Contents").join("MacOS");
+
+  // Binary plists aren't valid UTF-8, so they are skipped here
+  let plist_executable: Option<String> =
+    std::fs::read_to_string(bundle.join("Contents/Info.plist"))
+      .ok()
+      .and_then(|plist| read_plist_string(&plist, BUNDLE_EXECUTABLE_KEY));
+
+  if let Some(name) = plist_executable {
+    let executable = macos_folder.join(&name);
+    if filesystem::exists(&executable)? {
+      return Ok(executable);
+    }
+    debug!(
+      "The bundle executable declared in Info.plist doesn't exist: \"{}\"",
+      executable.display()
+    );
+  }
+
+  // Fall back to the file named like the bundle, or to the only file in the folder
+  let named_executable = macos_folder.join(filesystem::get_file_stem(bundle)?);
+  if filesystem::exists(&named_executable)? {
+    return Ok(named_executable);
+  }
+
+  let mut files: Vec<PathBuf> = Vec::new();
+  let mut entries = filesystem::read_dir(&macos_folder)?;
+  while let Some(entry) = filesystem::next_entry(&mut entries, &macos_folder)? {
+    if filesystem::file_type(&entry, &macos_folder)?.is_file() {
+      files.push(entry.path());
+    }
+  }
+
+  match <[PathBuf; 1]>::try_from(files) {
+    Ok([executable]) => Ok(executable),
+    Err(_) => Err(format!(
+      "Couldn't find the executable of the macOS application bundle: \"{}\"",
+      bundle.display()
+    )),
+  }
+}
+
+/// Run a command and return an error with its output if it fails
+#[cfg(target_os = "macos")]
+fn run_command(command: &mut std::process::Command) -> Result<(), String> {
+  let output = command
+    .output()
+    .map_err(|e| format!("Couldn't run {command:?}: {e}"))?;
+
+  if !output.status.success() {
+    return Err(format!(
+      "The command {command:?} failed with {}:\n{}",
+      output.status,
+      String::from_utf8_lossy(&output.stderr)
+    ));
+  }
+
+  Ok(())
+}
+
+/// Copy the contents of a macOS disk image (`.dmg`) into a folder
+///
+/// The image is mounted read-only with `hdiutil`, its contents are copied with `ditto`
+/// (which keeps the bundles, symlinks and permissions intact), and it is unmounted again.
+/// The symlinks at the root of the image, like the usual link to `/Applications`, aren't copied
+///
+/// # Errors
+///
+/// If the image couldn't be mounted or copied, or if this isn't macOS
+#[cfg(target_os = "macos")]
+pub(crate) fn extract_dmg(dmg: &Path, folder: &Path) -> Result<(), String> {
+  use std::process::Command;
+
+  let mount_point: PathBuf =
+    folder.with_file_name(format!("{}.mount", filesystem::get_file_name(folder)?));
+  filesystem::create_dir(&mount_point)?;
+
+  run_command(
+    Command::new("hdiutil")
+      .args([
+        "attach",
+        "-nobrowse",
+        "-readonly",
+        "-noautoopen",
+        "-mountpoint",
+      ])
+      .arg(&mount_point)
+      .arg(dmg),
+  )?;
+
+  let copy_result = (|| {
+    let mut entries = filesystem::read_dir(&mount_point)?;
+    while let Some(entry) = filesystem::next_entry(&mut entries, &mount_point)? {
+      if filesystem::file_type(&entry, &mount_point)?.is_symlink() {
+        continue;
+      }
+      run_command(
+        Command::new("ditto")
+          .arg(entry.path())
+          .arg(folder.join(entry.file_name())),
+      )?;
+    }
+    Ok::<(), String>(())
+  })();
+
+  // Always unmount the image, even if the copy failed
+  let detach_result = run_command(
+    Command::new("hdiutil")
+      .args(["detach", "-quiet"])
+      .arg(&mount_point),
+  );
+
+  copy_result?;
+  detach_result?;
+
+  filesystem::remove_empty_dir(&mount_point)?;
+
+  Ok(())
+}
+
+/// Copy the contents of a macOS disk image (`.dmg`) into a folder
+///
+/// # Errors
+///
+/// Always, because disk images can only be mounted on macOS
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn extract_dmg(dmg: &Path, _folder: &Path) -> Result<(), String> {
+  Err(format!(
+    "macOS disk images can only be extracted on macOS: \"{}\"",
+    dmg.display()
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::read_plist_string;
+
+  const INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>CFBundleName</key>
+  <string>My Game</string>
+  <key>CFBundleExecutable</key>
+  <string>My Game &amp; Friends</string>
+</dict>
+</plist>"#;
+
+  #[test]
+  fn test_read_plist_string() {
+    assert_eq!(
+      read_plist_string(INFO_PLIST, "CFBundleExecutable").as_deref(),
+      Some("My Game & Friends")
+    );
+    assert_eq!(
+      read_plist_string(INFO_PLIST, "CFBundleName").as_deref(),
+      Some("My Game")
+    );
+    assert_eq!(read_plist_string(INFO_PLIST, "CFBundleIdentifier"), None);
+  }
+}
@@ -0,0 +1,45 @@
+use crate::InstalledUpload;
+use crate::errors::OpenError;
+use crate::itch_api::types::GameCommon;
+
+use std::ffi::OsStr;
+
+/// Open a path or an URL with the platform opener (`xdg-open`, `open` or `explorer`)
+fn open_with_platform_opener(target: &OsStr) -> Result<(), OpenError> {
+  open::that(target).map_err(|error| {
+    let target = target.to_string_lossy().into_owned();
+
+    // The opener returns NotFound if none of the platform openers could be launched
+    if error.kind() == std::io::ErrorKind::NotFound {
+      OpenError::NoOpenerAvailable { target, error }
+    } else {
+      OpenError::CouldntOpen { target, error }
+    }
+  })
+}
+
+/// Reveal the folder of an installed upload in the platform's file manager
+///
+/// # Arguments
+///
+/// * `installed_upload` - The installed upload whose folder will be opened
+///
+/// # Errors
+///
+/// If no opener is available or it fails to open the folder
+pub fn open_install_folder(installed_upload: &InstalledUpload) -> Result<(), OpenError> {
+  open_with_platform_opener(installed_upload.upload_folder().as_os_str())
+}
+
+/// Open the itch.io page of a game in the default web browser
+///
+/// # Arguments
+///
+/// * `game` - The game whose page will be opened
+///
+/// # Errors
+///
+/// If no opener is available or it fails to open the URL
+pub fn open_game_page(game: &GameCommon) -> Result<(), OpenError> {
+  open_with_platform_opener(OsStr::new(&game.url))
+}
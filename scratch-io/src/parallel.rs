@@ -0,0 +1,326 @@
+use crate::checksum::{Checksum, Hasher};
+use crate::itch_api::{ItchApiUrl, RetryPolicy};
+use crate::throttle::Throttle;
+use crate::transport::{ByteRange, DownloadTransport};
+use crate::{
+  CancellationToken, DOWNLOAD_CANCELLED_ERROR, DownloadContext, DownloadError, filesystem,
+  game_files,
+};
+
+use reqwest::StatusCode;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Download a range of the file into its position of the parallel download file
+///
+/// `received_bytes` is increased with the bytes written to the file, so a failed range
+/// can be resumed from where it stopped
+#[expect(clippy::too_many_arguments)]
+fn download_range<T: DownloadTransport>(
+  transport: &T,
+  url: &ItchApiUrl,
+  parallel_file_path: &Path,
+  range: ByteRange,
+  received_bytes: &mut u64,
+  downloaded_bytes: &AtomicU64,
+  cancel: &CancellationToken,
+  throttle: &Throttle,
+) -> Result<(), DownloadError> {
+  use std::io::BufRead;
+
+  // The client already retried the request, so the server couldn't be reached
+  let res = transport
+    .get(url, Some(range))
+    .map_err(DownloadError::Connection)?;
+
+  if res.status != StatusCode::PARTIAL_CONTENT {
+    let e = format!(
+      "The server didn't return HTTP code 206 for a range request!
+  It returned code: {}
+  URL: {url}",
+      res.status.as_str()
+    );
+    return Err(if res.status.is_server_error() {
+      DownloadError::Server(e)
+    } else {
+      DownloadError::Other(e)
+    });
+  }
+
+  // Every range has its own file handle, placed at the start of the range
+  let mut file =
+    filesystem::open_file(parallel_file_path, std::fs::OpenOptions::new().write(true))?;
+  filesystem::seek(&mut file, range.start)?;
+
+  let expected_bytes: u64 = range.end.map_or(0, |end| end + 1 - range.start);
+  let mut range_bytes: u64 = 0;
+  let mut reader = std::io::BufReader::new(res.body);
+
+  loop {
+    if cancel.is_cancelled() {
+      return Err(DownloadError::Other(DOWNLOAD_CANCELLED_ERROR.to_string()));
+    }
+
+    // A failure while reading means the connection was lost
+    let chunk = filesystem::fill_buffer(&mut reader)
+      .map_err(|e| DownloadError::Connection(String::from(e)))?;
+
+    // If chunk is empty then the reader has reached the EOF
+    if chunk.is_empty() {
+      break;
+    }
+
+    filesystem::write_all(&mut file, chunk)?;
+
+    let len = chunk.len();
+    range_bytes += len as u64;
+    *received_bytes += len as u64;
+    downloaded_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    reader.consume(len);
+
+    // Wait if the connections together are going faster than the speed limit
+    throttle.consume(len as u64, cancel);
+  }
+
+  if range_bytes != expected_bytes {
+    let e = format!(
+      "The server sent {range_bytes} bytes for a range of {expected_bytes} bytes!
+  URL: {url}"
+    );
+    // A range cut short can be resumed, but extra bytes were written over the next range
+    return Err(if range_bytes < expected_bytes {
+      DownloadError::Connection(e)
+    } else {
+      DownloadError::Other(e)
+    });
+  }
+
+  Ok(())
+}
+
+/// Download a range of the file, resuming it from where it stopped if the connection
+/// or the server fail, as long as the retry policy allows it
+#[expect(clippy::too_many_arguments)]
+fn download_range_with_retries<T: DownloadTransport>(
+  transport: &T,
+  url: &ItchApiUrl,
+  parallel_file_path: &Path,
+  range: ByteRange,
+  downloaded_bytes: &AtomicU64,
+  cancel: &CancellationToken,
+  throttle: &Throttle,
+  retry_policy: &RetryPolicy,
+) -> Result<(), DownloadError> {
+  let mut received_bytes: u64 = 0;
+
+  let mut attempt: u32 = 1;
+  loop {
+    let remaining = ByteRange {
+      start: range.start + received_bytes,
+      end: range.end,
+    };
+
+    match download_range(
+      transport,
+      url,
+      parallel_file_path,
+      remaining,
+      &mut received_bytes,
+      downloaded_bytes,
+      cancel,
+      throttle,
+    ) {
+      Err(DownloadError::Connection(e) | DownloadError::Server(e))
+        if retry_policy.should_retry(attempt) && !cancel.is_cancelled() =>
+      {
+        let delay = retry_policy.delay(attempt);
+        debug!(
+          "The range starting at byte {} failed, retrying in {delay:?} (attempt {attempt})\n{e}",
+          remaining.start
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+      }
+      r => return r,
+    }
+  }
+}
+
+/// Split the file into `connections` ranges of similar sizes
+fn split_ranges(file_size: u64, connections: NonZeroUsize) -> Vec<ByteRange> {
+  let connections = (connections.get() as u64).min(file_size).max(1);
+  let range_size = file_size.div_ceil(connections);
+
+  (0..connections)
+    .map(|i| i * range_size)
+    .take_while(|&start| start < file_size)
+    .map(|start| ByteRange {
+      start,
+      end: Some((start + range_size).min(file_size) - 1),
+    })
+    .collect()
+}
+
+/// Download a file using multiple connections in parallel
+///
+/// The file is downloaded into a .parallel.part file. A range that fails is retried according
+/// to the transport's retry policy, and the file is only removed if the retries are exhausted,
+/// because the downloaded ranges can't be resumed later
+///
+/// # Arguments
+///
+/// * `transport` - Something which sends the download requests, usually an itch.io API client
+///
+/// * `url` - A itch.io API address to download the file from
+///
+/// * `file_path` - The path where the file will be placed
+///
+/// * `checksum` - A hash to check the file against. If none, don't verify the download
+///
+/// * `connections` - The number of connections used to download the file
+///
+/// * `ctx` - The progress callbacks, the cancellation token and the bandwidth limiter of the download
+///
+/// # Returns
+///
+/// False if the server doesn't support range requests, so nothing was downloaded
+///
+/// # Errors
+///
+/// If something goes wrong
+pub(crate) fn download_file_parallel<T: DownloadTransport + Sync>(
+  transport: &T,
+  url: &ItchApiUrl,
+  file_path: &Path,
+  checksum: Option<Checksum<'_>>,
+  connections: NonZeroUsize,
+  ctx: &DownloadContext<'_>,
+) -> Result<bool, DownloadError> {
+  // Probe if the server supports ranges, and get the file size
+  let probe = transport.get(
+    url,
+    Some(ByteRange {
+      start: 0,
+      end: None,
+    }),
+  )?;
+
+  if probe.status != StatusCode::PARTIAL_CONTENT {
+    debug!("The server doesn't support ranges, falling back to a single connection download");
+    return Ok(false);
+  }
+
+  let file_size: u64 = probe.content_length.ok_or_else(|| {
+    format!(
+      "Couldn't get content length!
+  URL: {url}"
+    )
+  })?;
+
+  // The probe response isn't needed anymore
+  drop(probe);
+
+  (ctx.file_size_callback)(file_size);
+
+  let parallel_file_path: PathBuf = game_files::add_parallel_part_extension(file_path)?;
+
+  // Create the file with its final size, so every range can be written into its position
+  let file = filesystem::open_file(
+    &parallel_file_path,
+    std::fs::OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true),
+  )?;
+  filesystem::set_file_len(&file, file_size)?;
+  drop(file);
+
+  let ranges = split_ranges(file_size, connections);
+  debug!(
+    "Downloading \"{}\" using {} parallel connections",
+    url.redacted(),
+    ranges.len()
+  );
+
+  let downloaded_bytes = AtomicU64::new(0);
+  // The callbacks can't be shared between threads, so only pass the flow control to the workers
+  let (cancel, throttle): (&CancellationToken, &Throttle) = (ctx.cancel, ctx.throttle);
+  let retry_policy: &RetryPolicy = &transport.retry_policy();
+
+  let result: Result<(), DownloadError> = std::thread::scope(|scope| {
+    let workers: Vec<_> = ranges
+      .iter()
+      .map(|&range| {
+        let (parallel_file_path, downloaded_bytes) = (&parallel_file_path, &downloaded_bytes);
+        scope.spawn(move || {
+          download_range_with_retries(
+            transport,
+            url,
+            parallel_file_path,
+            range,
+            downloaded_bytes,
+            cancel,
+            throttle,
+            retry_policy,
+          )
+        })
+      })
+      .collect();
+
+    // Report the progress while the workers are running
+    while !workers
+      .iter()
+      .all(std::thread::ScopedJoinHandle::is_finished)
+    {
+      std::thread::sleep(ctx.callback_interval.min(Duration::from_millis(100)));
+      (ctx.progress_callback)(downloaded_bytes.load(Ordering::Relaxed));
+    }
+
+    workers.into_iter().try_for_each(|w| {
+      w.join().unwrap_or_else(|_| {
+        Err(DownloadError::Other(
+          "A download thread panicked!".to_string(),
+        ))
+      })
+    })
+  });
+
+  (ctx.progress_callback)(downloaded_bytes.load(Ordering::Relaxed));
+
+  // The ranges can't be resumed, so remove the file if anything failed
+  let result = result.and_then(|()| {
+    let mut file = filesystem::open_file(
+      &parallel_file_path,
+      std::fs::OpenOptions::new().read(true).write(true),
+    )?;
+
+    // The file is assembled, so hash it as a whole
+    if let Some(checksum) = checksum {
+      let mut hasher = Hasher::new(checksum.algorithm);
+      crate::hash_readable(&mut std::io::BufReader::new(&mut file), &mut hasher)?;
+
+      let file_hash = hasher.finalize_hex();
+      if !checksum.matches(&file_hash) {
+        return Err(DownloadError::HashMismatch {
+          expected: checksum.hex.to_string(),
+          actual: file_hash,
+          file: parallel_file_path.clone(),
+        });
+      }
+    }
+
+    filesystem::file_sync_all(&file)?;
+    Ok(())
+  });
+
+  if let Err(e) = result {
+    filesystem::remove_file(&parallel_file_path)?;
+    return Err(e);
+  }
+
+  filesystem::rename(&parallel_file_path, file_path)?;
+
+  Ok(true)
+}
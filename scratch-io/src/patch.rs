@@ -0,0 +1,98 @@
+use crate::errors::ScratchError;
+use crate::filesystem;
+
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+pub use wharf::{BuildDiff, ChangeMethod, FileChange, PatchProgress};
+
+/// Apply a wharf patch file to a build folder, producing the new build
+///
+/// # Arguments
+///
+/// * `patch_file` - The path to the wharf patch file
+///
+/// * `signature_file` - The path to the signature of the new build. If provided, the written files are verified against it
+///
+/// * `old_build_folder` - The folder with the build the patch was created from
+///
+/// * `staging_folder` - A temporary folder where the patched files are reconstructed. It allows resuming an interrupted patch
+///
+/// * `new_build_folder` - The folder where the new build will be placed
+///
+/// * `progress_callback` - A closure called every time a file starts being patched and every time data is written
+///
+/// # Errors
+///
+/// If the files can't be read, or applying the patch fails
+pub fn apply_patch(
+  patch_file: &Path,
+  signature_file: Option<&Path>,
+  old_build_folder: &Path,
+  staging_folder: &Path,
+  new_build_folder: &Path,
+  progress_callback: impl FnMut(PatchProgress) + Send,
+) -> Result<(), ScratchError> {
+  enter_span!(
+    "apply_patch",
+    patch = patch_file.display(),
+    folder = new_build_folder.display()
+  );
+
+  // Read the patch
+  let patch_file = filesystem::open_file(patch_file, std::fs::OpenOptions::new().read(true))?;
+  let mut patch_reader = std::io::BufReader::new(patch_file);
+  let mut patch = wharf::Patch::read(&mut patch_reader).map_err(ScratchError::Wharf)?;
+
+  // Read the signature, if provided
+  let mut signature_reader = signature_file
+    .map(|f| filesystem::open_file(f, std::fs::OpenOptions::new().read(true)))
+    .transpose()?
+    .map(std::io::BufReader::new);
+  let mut hash_iter = signature_reader
+    .as_mut()
+    .map(wharf::Signature::read)
+    .transpose()
+    .map_err(ScratchError::Wharf)?
+    .map(|s| s.block_hash_iter);
+
+  debug!(
+    "Applying the patch to \"{}\", verifying the files: {}",
+    old_build_folder.display(),
+    hash_iter.is_some()
+  );
+
+  patch
+    .apply_with_progress(
+      old_build_folder,
+      staging_folder,
+      new_build_folder,
+      hash_iter.as_mut(),
+      progress_callback,
+    )
+    .map_err(ScratchError::Wharf)
+    .inspect(|()| debug!("The patch was applied"))
+}
+
+/// Apply a wharf patch file in a new thread, so the caller isn't blocked while the patch is applied
+///
+/// See [`apply_patch`] for the arguments. The result of the patch is returned when joining the thread
+pub fn spawn_apply_patch(
+  patch_file: PathBuf,
+  signature_file: Option<PathBuf>,
+  old_build_folder: PathBuf,
+  staging_folder: PathBuf,
+  new_build_folder: PathBuf,
+  progress_callback: impl FnMut(PatchProgress) + Send + 'static,
+) -> JoinHandle<Result<(), ScratchError>> {
+  std::thread::spawn(move || {
+    apply_patch(
+      &patch_file,
+      signature_file.as_deref(),
+      &old_build_folder,
+      &staging_folder,
+      &new_build_folder,
+      progress_callback,
+    )
+  })
+}
@@ -0,0 +1,182 @@
+use std::io::Read;
+use std::path::Path;
+
+/// The number of bytes read from the start of a file to find its PE headers
+///
+/// The PE headers of real executables start well before this offset
+const PE_HEADER_READ_LIMIT: u64 = 4096;
+/// The offset of `e_lfanew` in the DOS header, which points to the PE signature
+const E_LFANEW_OFFSET: usize = 0x3C;
+/// The size of the PE signature plus the COFF file header
+const COFF_HEADER_END: usize = 4 + 20;
+/// The offset of the `Characteristics` field in the COFF file header
+const CHARACTERISTICS_OFFSET: usize = 4 + 18;
+/// The `IMAGE_FILE_DLL` flag of the COFF `Characteristics` field
+const IMAGE_FILE_DLL: u16 = 0x2000;
+/// The offset of the `Subsystem` field in the optional header, equal in PE32 and PE32+
+const SUBSYSTEM_OFFSET: usize = 68;
+const PE32_MAGIC: u16 = 0x10B;
+const PE32_PLUS_MAGIC: u16 = 0x20B;
+
+/// The subsystem required to run a Windows executable, from its `IMAGE_OPTIONAL_HEADER`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeSubsystem {
+  /// A graphical application, which doesn't open a console window
+  Gui,
+  /// A console application
+  Console,
+  /// Any other subsystem, like drivers or EFI applications, with its raw value
+  Other(u16),
+}
+
+impl PeSubsystem {
+  const fn from_raw(value: u16) -> Self {
+    match value {
+      2 => Self::Gui,
+      3 => Self::Console,
+      v => Self::Other(v),
+    }
+  }
+}
+
+impl std::fmt::Display for PeSubsystem {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Gui => write!(f, "gui"),
+      Self::Console => write!(f, "console"),
+      Self::Other(v) => write!(f, "other({v})"),
+    }
+  }
+}
+
+/// The information of a PE (Windows executable) file relevant to the launch heuristics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PeInfo {
+  pub is_dll: bool,
+  pub subsystem: PeSubsystem,
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(
+    bytes.get(offset..offset + 2)?.try_into().ok()?,
+  ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(
+    bytes.get(offset..offset + 4)?.try_into().ok()?,
+  ))
+}
+
+/// Parse the DOS, COFF and optional headers at the start of a PE file
+///
+/// # Returns
+///
+/// The PE information, or None if the bytes aren't the start of a valid PE file
+fn parse_pe_header(bytes: &[u8]) -> Option<PeInfo> {
+  if !bytes.starts_with(b"MZ") {
+    return None;
+  }
+
+  let pe_offset = usize::try_from(read_u32(bytes, E_LFANEW_OFFSET)?).ok()?;
+  if bytes.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+    return None;
+  }
+
+  let characteristics = read_u16(bytes, pe_offset + CHARACTERISTICS_OFFSET)?;
+
+  let optional_header = pe_offset + COFF_HEADER_END;
+  let magic = read_u16(bytes, optional_header)?;
+  if magic != PE32_MAGIC && magic != PE32_PLUS_MAGIC {
+    return None;
+  }
+
+  let subsystem = read_u16(bytes, optional_header + SUBSYSTEM_OFFSET)?;
+
+  Some(PeInfo {
+    is_dll: characteristics & IMAGE_FILE_DLL != 0,
+    subsystem: PeSubsystem::from_raw(subsystem),
+  })
+}
+
+/// Read the PE headers of a file
+///
+/// # Returns
+///
+/// The PE information, or None if the file couldn't be read or isn't a PE file
+pub(crate) fn read_pe_info(path: &Path) -> Option<PeInfo> {
+  let mut bytes: Vec<u8> = Vec::new();
+
+  let read = std::fs::File::open(path)
+    .and_then(|file| file.take(PE_HEADER_READ_LIMIT).read_to_end(&mut bytes));
+  if let Err(e) = read {
+    debug!(
+      "Couldn't read the PE headers of \"{}\": {e}",
+      path.display()
+    );
+    return None;
+  }
+
+  parse_pe_header(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{PeInfo, PeSubsystem, parse_pe_header};
+
+  // Build the minimal headers of a PE file, with the PE signature at 0x80
+  fn pe_header(magic: u16, characteristics: u16, subsystem: u16) -> Vec<u8> {
+    let pe_offset: usize = 0x80;
+    let mut bytes = vec![0u8; pe_offset + 24 + 96];
+
+    bytes[..2].copy_from_slice(b"MZ");
+    bytes[0x3C..0x40].copy_from_slice(&(pe_offset as u32).to_le_bytes());
+    bytes[pe_offset..pe_offset + 4].copy_from_slice(b"PE\0\0");
+    bytes[pe_offset + 22..pe_offset + 24].copy_from_slice(&characteristics.to_le_bytes());
+    bytes[pe_offset + 24..pe_offset + 26].copy_from_slice(&magic.to_le_bytes());
+    bytes[pe_offset + 24 + 68..pe_offset + 24 + 70].copy_from_slice(&subsystem.to_le_bytes());
+
+    bytes
+  }
+
+  #[test]
+  fn test_parse_pe_subsystem() {
+    assert_eq!(
+      parse_pe_header(&pe_header(0x10B, 0x0102, 2)),
+      Some(PeInfo {
+        is_dll: false,
+        subsystem: PeSubsystem::Gui,
+      })
+    );
+    assert_eq!(
+      parse_pe_header(&pe_header(0x20B, 0x0022, 3)),
+      Some(PeInfo {
+        is_dll: false,
+        subsystem: PeSubsystem::Console,
+      })
+    );
+    assert_eq!(
+      parse_pe_header(&pe_header(0x20B, 0x0022, 10)).map(|i| i.subsystem),
+      Some(PeSubsystem::Other(10))
+    );
+  }
+
+  #[test]
+  fn test_parse_pe_dll() {
+    assert!(parse_pe_header(&pe_header(0x20B, 0x2022, 2)).is_some_and(|i| i.is_dll));
+  }
+
+  #[test]
+  fn test_parse_invalid_pe() {
+    // Not an executable
+    assert_eq!(parse_pe_header(b"#!/bin/sh\necho hi\n"), None);
+    // A DOS executable without PE headers
+    let mut dos = pe_header(0x10B, 0, 2);
+    dos[0x80..0x84].copy_from_slice(b"NE\0\0");
+    assert_eq!(parse_pe_header(&dos), None);
+    // Unknown optional header magic
+    assert_eq!(parse_pe_header(&pe_header(0x107, 0, 2)), None);
+    // Truncated headers
+    assert_eq!(parse_pe_header(&pe_header(0x10B, 0, 2)[..0x90]), None);
+  }
+}
@@ -0,0 +1,135 @@
+use crate::errors::ScratchError;
+use crate::itch_api::ItchClient;
+use crate::itch_api::types::UploadID;
+use crate::{DownloadOptions, DownloadStatus, InstalledUpload, download_upload_with_options};
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// The result of downloading an upload of a [`DownloadQueue`]
+type QueueResult = (UploadID, Result<InstalledUpload, ScratchError>);
+
+/// An upload waiting to be downloaded by a [`DownloadQueue`]
+struct QueuedDownload {
+  upload_id: UploadID,
+  game_folder: PathBuf,
+  options: DownloadOptions,
+}
+
+/// A queue of uploads which are downloaded with a limited number of concurrent downloads
+///
+/// This avoids opening too many connections to the itch.io servers when downloading a lot of
+/// uploads, like the games of a collection
+pub struct DownloadQueue {
+  max_concurrent: NonZeroUsize,
+  downloads: Vec<QueuedDownload>,
+}
+
+impl DownloadQueue {
+  /// Create an empty queue which runs up to `max_concurrent` downloads at the same time
+  #[must_use]
+  pub fn new(max_concurrent: NonZeroUsize) -> Self {
+    Self {
+      max_concurrent,
+      downloads: Vec::new(),
+    }
+  }
+
+  /// Add an upload to the queue
+  ///
+  /// # Arguments
+  ///
+  /// * `upload_id` - The ID of the upload which will be downloaded
+  ///
+  /// * `game_folder` - The folder where the downloaded game files will be placed
+  ///
+  /// * `options` - The download options of this upload, see [`DownloadOptions`]
+  pub fn enqueue(&mut self, upload_id: UploadID, game_folder: PathBuf, options: DownloadOptions) {
+    self.downloads.push(QueuedDownload {
+      upload_id,
+      game_folder,
+      options,
+    });
+  }
+
+  /// The number of uploads in the queue
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.downloads.len()
+  }
+
+  /// Check if the queue doesn't have any upload
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.downloads.is_empty()
+  }
+
+  /// Download all the uploads in the queue, and wait for them to finish
+  ///
+  /// The downloads run in separate threads, and a failed download doesn't stop the rest
+  ///
+  /// # Arguments
+  ///
+  /// * `client` - An itch.io API client
+  ///
+  /// * `progress_callback` - A closure which reports the download progress of each upload.
+  ///   It is called from the download threads
+  ///
+  /// # Returns
+  ///
+  /// A vector of tuples containing each upload ID and its download result, in the same order they were enqueued
+  pub fn run(
+    self,
+    client: &ItchClient,
+    progress_callback: impl Fn(UploadID, DownloadStatus) + Sync,
+  ) -> Vec<QueueResult> {
+    let total: usize = self.downloads.len();
+    let workers: usize = self.max_concurrent.get().min(total);
+
+    // Each worker takes the next download from the front of the queue until it is empty
+    let pending: Mutex<VecDeque<(usize, QueuedDownload)>> =
+      Mutex::new(self.downloads.into_iter().enumerate().collect());
+    let results: Mutex<Vec<Option<QueueResult>>> = Mutex::new((0..total).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+      for _ in 0..workers {
+        scope.spawn(|| {
+          loop {
+            // The lock is released before downloading, so the other workers can keep taking downloads
+            let next = pending
+              .lock()
+              .unwrap_or_else(std::sync::PoisonError::into_inner)
+              .pop_front();
+            let Some((index, download)) = next else {
+              break;
+            };
+
+            let upload_id: UploadID = download.upload_id;
+            let result = download_upload_with_options(
+              client,
+              upload_id,
+              &download.game_folder,
+              &download.options,
+              |_, _| (),
+              |status| progress_callback(upload_id, status),
+            );
+
+            results
+              .lock()
+              .unwrap_or_else(std::sync::PoisonError::into_inner)[index] =
+              Some((upload_id, result));
+          }
+        });
+      }
+    });
+
+    results
+      .into_inner()
+      .unwrap_or_else(std::sync::PoisonError::into_inner)
+      .into_iter()
+      .flatten()
+      .collect()
+  }
+}
@@ -0,0 +1,69 @@
+use crate::{DownloadStatus, FailedEntry};
+
+/// A receiver of the progress of a download, used by [`crate::download_upload_with_reporter`]
+///
+/// It is an alternative to translating every [`DownloadStatus`] manually, so any
+/// progress bar library or UI can be used. The methods that aren't required do nothing
+pub trait DownloadReporter {
+  /// The download started, and the file is `total` bytes long
+  fn starting(&self, total: u64);
+
+  /// `done` bytes of the file have been downloaded
+  fn progress(&self, done: u64);
+
+  /// Something unexpected happened, but the download can continue
+  fn warning(&self, msg: &str);
+
+  /// The download finished, and the archive started being extracted
+  fn extracting(&self);
+
+  /// `done` bytes of the `total` bytes of the archive have been extracted
+  fn extract_progress(&self, _done: u64, _total: u64) {}
+
+  /// Some entries of the archive couldn't be extracted in lenient mode
+  fn extraction_failed_entries(&self, entries: &[FailedEntry]) {
+    for e in entries {
+      self.warning(&format!("Couldn't extract \"{}\": {}", e.entry, e.error));
+    }
+  }
+
+  /// The download was cancelled
+  fn cancelled(&self) {}
+
+  /// The upload was installed after receiving `bytes_transferred` bytes from the network
+  fn finished(&self, _bytes_transferred: u64) {}
+}
+
+/// A [`DownloadReporter`] which ignores all the progress
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopReporter;
+
+impl DownloadReporter for NoopReporter {
+  fn starting(&self, _total: u64) {}
+  fn progress(&self, _done: u64) {}
+  fn warning(&self, _msg: &str) {}
+  fn extracting(&self) {}
+}
+
+/// Send a [`DownloadStatus`] to the matching method of the reporter
+pub(crate) fn report(reporter: &impl DownloadReporter, status: DownloadStatus) {
+  match status {
+    DownloadStatus::Warning(w) => reporter.warning(&w),
+    DownloadStatus::StartingDownload { bytes_to_download } => reporter.starting(bytes_to_download),
+    DownloadStatus::DownloadProgress {
+      downloaded_bytes, ..
+    } => reporter.progress(downloaded_bytes),
+    DownloadStatus::Extract => reporter.extracting(),
+    DownloadStatus::ExtractProgress {
+      extracted_bytes,
+      total_bytes,
+    } => reporter.extract_progress(extracted_bytes, total_bytes),
+    DownloadStatus::ExtractionFailedEntries(entries) => {
+      reporter.extraction_failed_entries(&entries);
+    }
+    DownloadStatus::Cancelled => reporter.cancelled(),
+    DownloadStatus::Finished {
+      bytes_transferred, ..
+    } => reporter.finished(bytes_transferred),
+  }
+}
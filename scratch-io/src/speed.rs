@@ -0,0 +1,257 @@
+use std::cell::Cell;
+use std::io::Read;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// The size of the chunks read by [`TimeoutReader`]
+const TIMEOUT_READER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Computes the speed of a download from its progress reports
+///
+/// The speed is measured between consecutive reports, so it follows the current speed
+/// instead of the average of the whole download
+pub(crate) struct SpeedMeter {
+  started_at: Instant,
+  last_sample: Cell<Option<(Instant, u64)>>,
+  bytes_per_sec: Cell<u64>,
+}
+
+impl SpeedMeter {
+  pub fn new() -> Self {
+    Self::starting_at(Instant::now())
+  }
+
+  fn starting_at(started_at: Instant) -> Self {
+    Self {
+      started_at,
+      last_sample: Cell::new(None),
+      bytes_per_sec: Cell::new(0),
+    }
+  }
+
+  /// Register the number of downloaded bytes at this moment
+  ///
+  /// # Returns
+  ///
+  /// The current speed in bytes per second, and the time since the meter was created
+  pub fn sample(&self, downloaded_bytes: u64) -> (u64, Duration) {
+    self.sample_at(Instant::now(), downloaded_bytes)
+  }
+
+  fn sample_at(&self, now: Instant, downloaded_bytes: u64) -> (u64, Duration) {
+    // The first report can include the bytes of a resumed download, so it is only a reference
+    if let Some((last_time, last_bytes)) = self.last_sample.get() {
+      let elapsed = now.saturating_duration_since(last_time);
+
+      // Reports too close to each other would give a meaningless speed, so keep the last one
+      if !elapsed.is_zero() {
+        let bytes = downloaded_bytes.saturating_sub(last_bytes);
+        let bytes_per_sec = bytes as f64 / elapsed.as_secs_f64();
+        self.bytes_per_sec.set(bytes_per_sec as u64);
+      }
+    }
+
+    self.last_sample.set(Some((now, downloaded_bytes)));
+
+    (
+      self.bytes_per_sec.get(),
+      now.saturating_duration_since(self.started_at),
+    )
+  }
+}
+
+/// Detects a download whose speed stays below a minimum during a whole time window
+///
+/// The speed is averaged over each window, so short pauses of a slow but steady connection
+/// don't stop the download
+pub(crate) struct StallDetector {
+  min_bytes_per_sec: u64,
+  window: Duration,
+  window_start: Instant,
+  window_bytes: u64,
+}
+
+impl StallDetector {
+  pub fn new(min_bytes_per_sec: u64, window: Duration) -> Self {
+    Self::starting_at(Instant::now(), min_bytes_per_sec, window)
+  }
+
+  fn starting_at(started_at: Instant, min_bytes_per_sec: u64, window: Duration) -> Self {
+    Self {
+      min_bytes_per_sec,
+      window,
+      window_start: started_at,
+      window_bytes: 0,
+    }
+  }
+
+  /// Register the bytes of a received chunk
+  ///
+  /// # Returns
+  ///
+  /// True if the speed of the window that has just ended was below the minimum
+  pub fn record(&mut self, bytes: u64) -> bool {
+    self.record_at(Instant::now(), bytes)
+  }
+
+  fn record_at(&mut self, now: Instant, bytes: u64) -> bool {
+    self.window_bytes += bytes;
+
+    let elapsed = now.saturating_duration_since(self.window_start);
+    if elapsed < self.window {
+      return false;
+    }
+
+    let bytes_per_sec = self.window_bytes as f64 / elapsed.as_secs_f64();
+
+    // Start the next window
+    self.window_start = now;
+    self.window_bytes = 0;
+
+    bytes_per_sec < self.min_bytes_per_sec as f64
+  }
+}
+
+/// A reader whose reads fail with [`std::io::ErrorKind::TimedOut`] if no data arrives for `timeout`
+///
+/// A blocking response can't be interrupted, so it is read on another thread. If the reader is
+/// dropped while that thread is waiting, the thread stops when the connection is closed
+pub(crate) struct TimeoutReader {
+  chunks: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+  chunk: Vec<u8>,
+  position: usize,
+  timeout: Duration,
+}
+
+impl TimeoutReader {
+  pub fn new(mut inner: impl Read + Send + 'static, timeout: Duration) -> Self {
+    let (sender, chunks) = mpsc::sync_channel(1);
+
+    std::thread::spawn(move || {
+      loop {
+        let mut chunk = vec![0; TIMEOUT_READER_CHUNK_SIZE];
+        let result = inner.read(&mut chunk).map(|len| {
+          chunk.truncate(len);
+          chunk
+        });
+
+        // Stop at the end of the data, after an error, or if the reader was dropped
+        let finished = result.as_ref().is_ok_and(Vec::is_empty) || result.is_err();
+        if sender.send(result).is_err() || finished {
+          return;
+        }
+      }
+    });
+
+    Self {
+      chunks,
+      chunk: Vec::new(),
+      position: 0,
+      timeout,
+    }
+  }
+}
+
+impl Read for TimeoutReader {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    if self.position == self.chunk.len() {
+      self.chunk = match self.chunks.recv_timeout(self.timeout) {
+        Ok(chunk) => chunk?,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+          return Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("No data was received in {:?}", self.timeout),
+          ));
+        }
+        // The thread only stops after sending the end of the data or an error
+        Err(mpsc::RecvTimeoutError::Disconnected) => Vec::new(),
+      };
+      self.position = 0;
+    }
+
+    let len = buf.len().min(self.chunk.len() - self.position);
+    buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+    self.position += len;
+    Ok(len)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{SpeedMeter, StallDetector, TimeoutReader};
+  use std::io::Read;
+  use std::time::{Duration, Instant};
+
+  #[test]
+  fn test_speed_between_reports() {
+    let start = Instant::now();
+    let meter = SpeedMeter::starting_at(start);
+
+    // A resumed download starts with some bytes already downloaded
+    assert_eq!(
+      meter.sample_at(start + Duration::from_secs(1), 5000),
+      (0, Duration::from_secs(1))
+    );
+    assert_eq!(
+      meter.sample_at(start + Duration::from_secs(2), 6000),
+      (1000, Duration::from_secs(2))
+    );
+    assert_eq!(
+      meter.sample_at(start + Duration::from_millis(2500), 7000),
+      (2000, Duration::from_millis(2500))
+    );
+  }
+
+  #[test]
+  fn test_speed_keeps_last_value_without_elapsed_time() {
+    let start = Instant::now();
+    let meter = SpeedMeter::starting_at(start);
+
+    meter.sample_at(start, 0);
+    meter.sample_at(start + Duration::from_secs(1), 300);
+    assert_eq!(
+      meter.sample_at(start + Duration::from_secs(1), 400),
+      (300, Duration::from_secs(1))
+    );
+  }
+
+  #[test]
+  fn test_stall_detector_waits_for_the_whole_window() {
+    let start = Instant::now();
+    let mut detector = StallDetector::starting_at(start, 1000, Duration::from_secs(10));
+
+    // A slow start isn't a stall until the window ends
+    assert!(!detector.record_at(start + Duration::from_secs(1), 10));
+    // A slow but steady connection above the minimum isn't stopped
+    assert!(!detector.record_at(start + Duration::from_secs(10), 12_000));
+    assert!(!detector.record_at(start + Duration::from_secs(15), 100));
+    assert!(detector.record_at(start + Duration::from_secs(20), 100));
+  }
+
+  #[test]
+  fn test_timeout_reader_reads_everything() {
+    let mut data = Vec::new();
+    TimeoutReader::new(&b"The quick brown fox"[..], Duration::from_secs(5))
+      .read_to_end(&mut data)
+      .unwrap();
+    assert_eq!(data, b"The quick brown fox");
+  }
+
+  #[test]
+  fn test_timeout_reader_fails_without_data() {
+    /// A reader which never sends any data, like an idle connection
+    struct IdleReader;
+
+    impl Read for IdleReader {
+      fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        std::thread::sleep(Duration::from_secs(1));
+        Ok(0)
+      }
+    }
+
+    let error = TimeoutReader::new(IdleReader, Duration::from_millis(50))
+      .read(&mut [0; 16])
+      .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+  }
+}
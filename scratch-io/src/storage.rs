@@ -0,0 +1,391 @@
+use crate::errors::{FilesystemError, FilesystemIOErrorKind as IOErr};
+use crate::{filesystem, game_files};
+
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// How [`GameStorage::open`] opens a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+  /// Open an existing file to read it
+  Read,
+  /// Create the file, or truncate it if it exists, to write it from the start
+  Write,
+  /// Create the file if it doesn't exist, and write after its current contents
+  Append,
+}
+
+/// The metadata of a path in a [`GameStorage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageMetadata {
+  pub is_dir: bool,
+  /// The size of the file in bytes, or 0 for folders
+  pub len: u64,
+}
+
+/// An entry of a folder in a [`GameStorage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+  /// The full path of the entry
+  pub path: PathBuf,
+  pub is_dir: bool,
+}
+
+/// The file operations used to place the game files
+///
+/// The installed folders are created, moved and removed through this trait, so they can be placed
+/// in something other than the local filesystem, like a sandbox or an in-memory test double.
+/// [`LocalStorage`] is the default implementation
+pub trait GameStorage {
+  /// An open file of this storage
+  type File: Read + Write + Seek;
+
+  /// Create a folder and all its missing parents
+  ///
+  /// # Errors
+  ///
+  /// If the folder couldn't be created
+  fn create_dir(&self, path: &Path) -> Result<(), FilesystemError>;
+
+  /// Open a file
+  ///
+  /// # Errors
+  ///
+  /// If the file couldn't be opened
+  fn open(&self, path: &Path, mode: OpenMode) -> Result<Self::File, FilesystemError>;
+
+  /// Move a file or a folder, replacing the destination file if it exists
+  ///
+  /// # Errors
+  ///
+  /// If it couldn't be moved
+  fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError>;
+
+  /// Remove a file
+  ///
+  /// # Errors
+  ///
+  /// If the file couldn't be removed
+  fn remove_file(&self, path: &Path) -> Result<(), FilesystemError>;
+
+  /// Remove an empty folder
+  ///
+  /// # Errors
+  ///
+  /// If the folder couldn't be removed or it isn't empty
+  fn remove_empty_dir(&self, path: &Path) -> Result<(), FilesystemError>;
+
+  /// Remove a folder and all its contents
+  ///
+  /// # Errors
+  ///
+  /// If the folder couldn't be removed
+  fn remove_dir_all(&self, path: &Path) -> Result<(), FilesystemError>;
+
+  /// Read the metadata of a path
+  ///
+  /// # Returns
+  ///
+  /// The metadata, or None if the path doesn't exist
+  ///
+  /// # Errors
+  ///
+  /// If the metadata couldn't be read
+  fn metadata(&self, path: &Path) -> Result<Option<StorageMetadata>, FilesystemError>;
+
+  /// List the entries of a folder
+  ///
+  /// # Errors
+  ///
+  /// If the folder couldn't be read
+  fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>, FilesystemError>;
+
+  /// Copy the contents of a file into another, creating or truncating it
+  ///
+  /// # Errors
+  ///
+  /// If the file couldn't be copied
+  fn copy_file(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+    let mut reader = self.open(from, OpenMode::Read)?;
+    let mut writer = self.open(to, OpenMode::Write)?;
+
+    std::io::copy(&mut reader, &mut writer).map_err(
+      IOErr::CouldntCopyFile {
+        from: from.to_owned(),
+        to: to.to_owned(),
+      }
+      .attach(),
+    )?;
+
+    Ok(())
+  }
+}
+
+/// The [`GameStorage`] of the local filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalStorage;
+
+impl GameStorage for LocalStorage {
+  type File = std::fs::File;
+
+  fn create_dir(&self, path: &Path) -> Result<(), FilesystemError> {
+    filesystem::create_dir(path)
+  }
+
+  fn open(&self, path: &Path, mode: OpenMode) -> Result<Self::File, FilesystemError> {
+    let mut options = std::fs::OpenOptions::new();
+    match mode {
+      OpenMode::Read => options.read(true),
+      OpenMode::Write => options.write(true).create(true).truncate(true),
+      OpenMode::Append => options.append(true).create(true),
+    };
+
+    filesystem::open_file(path, &options)
+  }
+
+  fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+    filesystem::rename(from, to)
+  }
+
+  fn remove_file(&self, path: &Path) -> Result<(), FilesystemError> {
+    filesystem::remove_file(path)
+  }
+
+  fn remove_empty_dir(&self, path: &Path) -> Result<(), FilesystemError> {
+    filesystem::remove_empty_dir(path)
+  }
+
+  // Dangerous paths, like the home folder, are never removed
+  fn remove_dir_all(&self, path: &Path) -> Result<(), FilesystemError> {
+    game_files::remove_folder_safely(path)
+  }
+
+  fn metadata(&self, path: &Path) -> Result<Option<StorageMetadata>, FilesystemError> {
+    if !filesystem::exists(path)? {
+      return Ok(None);
+    }
+
+    let metadata = filesystem::read_path_metadata(path)?;
+    Ok(Some(StorageMetadata {
+      is_dir: metadata.is_dir(),
+      len: if metadata.is_dir() { 0 } else { metadata.len() },
+    }))
+  }
+
+  fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>, FilesystemError> {
+    let mut entries = filesystem::read_dir(path)?;
+    let mut result: Vec<StorageEntry> = Vec::new();
+
+    while let Some(entry) = filesystem::next_entry(&mut entries, path)? {
+      result.push(StorageEntry {
+        is_dir: filesystem::file_type(&entry, path)?.is_dir(),
+        path: entry.path(),
+      });
+    }
+
+    Ok(result)
+  }
+
+  // Keep the permissions of the copied files, like the executable bit
+  fn copy_file(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+    filesystem::copy_file(from, to).map(|_| ())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{GameStorage, OpenMode, StorageEntry, StorageMetadata};
+  use crate::errors::{FilesystemError, FilesystemIOErrorKind as IOErr};
+  use crate::game_files;
+
+  use std::cell::RefCell;
+  use std::collections::BTreeMap;
+  use std::io::ErrorKind;
+  use std::path::{Path, PathBuf};
+
+  /// A storage kept in memory, where folders are None and files are their contents
+  ///
+  /// The writes to the opened files aren't saved, which is enough to test the folder logic
+  #[derive(Default)]
+  struct MemoryStorage {
+    paths: RefCell<BTreeMap<PathBuf, Option<Vec<u8>>>>,
+  }
+
+  impl MemoryStorage {
+    fn with_files(files: &[&str]) -> Self {
+      let storage = Self::default();
+      for file in files {
+        let file = Path::new(file);
+        storage.create_dir(file.parent().unwrap()).unwrap();
+        storage
+          .paths
+          .borrow_mut()
+          .insert(file.to_owned(), Some(Vec::new()));
+      }
+      storage
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+      self
+        .paths
+        .borrow()
+        .iter()
+        .filter(|(_, contents)| contents.is_some())
+        .map(|(path, _)| path.clone())
+        .collect()
+    }
+  }
+
+  fn not_found(kind: IOErr) -> FilesystemError {
+    kind.attach()(ErrorKind::NotFound.into())
+  }
+
+  impl GameStorage for MemoryStorage {
+    type File = std::io::Cursor<Vec<u8>>;
+
+    fn create_dir(&self, path: &Path) -> Result<(), FilesystemError> {
+      let mut paths = self.paths.borrow_mut();
+      for ancestor in path.ancestors() {
+        paths.entry(ancestor.to_owned()).or_insert(None);
+      }
+      Ok(())
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Self::File, FilesystemError> {
+      let mut paths = self.paths.borrow_mut();
+      match mode {
+        OpenMode::Read => match paths.get(path) {
+          Some(Some(contents)) => Ok(std::io::Cursor::new(contents.clone())),
+          _ => Err(not_found(IOErr::CouldntOpenFile(path.to_owned()))),
+        },
+        OpenMode::Write | OpenMode::Append => {
+          paths.insert(path.to_owned(), Some(Vec::new()));
+          Ok(std::io::Cursor::new(Vec::new()))
+        }
+      }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+      let mut paths = self.paths.borrow_mut();
+      let moved: Vec<PathBuf> = paths
+        .keys()
+        .filter(|p| p.starts_with(from))
+        .cloned()
+        .collect();
+      if moved.is_empty() {
+        return Err(not_found(IOErr::CouldntMove {
+          from: from.to_owned(),
+          to: to.to_owned(),
+        }));
+      }
+
+      for path in moved {
+        let contents = paths.remove(&path).unwrap();
+        paths.insert(to.join(path.strip_prefix(from).unwrap()), contents);
+      }
+      Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FilesystemError> {
+      match self.paths.borrow_mut().remove(path) {
+        Some(Some(_)) => Ok(()),
+        _ => Err(not_found(IOErr::CouldntRemoveFile(path.to_owned()))),
+      }
+    }
+
+    fn remove_empty_dir(&self, path: &Path) -> Result<(), FilesystemError> {
+      if !self.read_dir(path)?.is_empty() {
+        return Err(IOErr::CouldntRemoveEmptyDir(path.to_owned()).attach()(
+          ErrorKind::DirectoryNotEmpty.into(),
+        ));
+      }
+      self.paths.borrow_mut().remove(path);
+      Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FilesystemError> {
+      self.paths.borrow_mut().retain(|p, _| !p.starts_with(path));
+      Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Option<StorageMetadata>, FilesystemError> {
+      Ok(
+        self
+          .paths
+          .borrow()
+          .get(path)
+          .map(|contents| StorageMetadata {
+            is_dir: contents.is_none(),
+            len: contents.as_ref().map_or(0, |c| c.len() as u64),
+          }),
+      )
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>, FilesystemError> {
+      let paths = self.paths.borrow();
+      if !matches!(paths.get(path), Some(None)) {
+        return Err(not_found(IOErr::CouldntReadDirectory(path.to_owned())));
+      }
+
+      Ok(
+        paths
+          .iter()
+          .filter(|(p, _)| p.parent() == Some(path))
+          .map(|(p, contents)| StorageEntry {
+            path: p.clone(),
+            is_dir: contents.is_none(),
+          })
+          .collect(),
+      )
+    }
+  }
+
+  #[test]
+  fn test_remove_root_folder_in_memory() {
+    let storage = MemoryStorage::with_files(&[
+      "/games/1/wrapper/inner/game.exe",
+      "/games/1/wrapper/inner/data/level.dat",
+    ]);
+
+    game_files::remove_root_folder(&storage, Path::new("/games/1")).unwrap();
+
+    assert_eq!(
+      storage.files(),
+      vec![
+        PathBuf::from("/games/1/data/level.dat"),
+        PathBuf::from("/games/1/game.exe"),
+      ]
+    );
+    assert!(
+      storage
+        .metadata(Path::new("/games/1/wrapper"))
+        .unwrap()
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn test_remove_root_folder_with_collision_in_memory() {
+    // The wrapper folder contains an entry with its own name
+    let storage = MemoryStorage::with_files(&["/games/1/game/game", "/games/1/game/readme.txt"]);
+    storage
+      .remove_file(Path::new("/games/1/game/readme.txt"))
+      .unwrap();
+
+    game_files::remove_root_folder(&storage, Path::new("/games/1")).unwrap();
+
+    assert_eq!(storage.files(), vec![PathBuf::from("/games/1/game")]);
+  }
+
+  #[test]
+  fn test_move_folder_and_remove_if_empty_in_memory() {
+    let storage = MemoryStorage::with_files(&["/old/1/game.exe"]);
+
+    game_files::move_folder(&storage, Path::new("/old/1"), Path::new("/new/1")).unwrap();
+    assert!(game_files::remove_folder_if_empty(&storage, Path::new("/old")).unwrap());
+    assert!(!game_files::remove_folder_if_empty(&storage, Path::new("/new")).unwrap());
+
+    assert_eq!(storage.files(), vec![PathBuf::from("/new/1/game.exe")]);
+    assert!(storage.metadata(Path::new("/old")).unwrap().is_none());
+  }
+}
@@ -0,0 +1,175 @@
+use crate::checksum::{Checksum, Hasher};
+use crate::errors::{ExtractError, ScratchError};
+use crate::extract::{self, FailedEntry};
+use crate::itch_api::ItchApiUrl;
+use crate::throttle::Throttle;
+use crate::transport::DownloadTransport;
+use crate::{
+  CancellationToken, DOWNLOAD_CANCELLED_ERROR, DownloadContext, DownloadOptions, game_files,
+};
+
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A reader over the download response which hashes, reports and throttles the received data
+struct DownloadReader<'a, R> {
+  inner: R,
+  hasher: Option<Hasher>,
+  downloaded_bytes: u64,
+  progress_callback: &'a dyn Fn(u64),
+  callback_interval: Duration,
+  last_callback: Instant,
+  cancel: &'a CancellationToken,
+  throttle: &'a Throttle,
+}
+
+impl<R: Read> Read for DownloadReader<'_, R> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    // The extractor only sees an IO error, so the cancellation is checked again by the caller
+    if self.cancel.is_cancelled() {
+      return Err(std::io::Error::other(DOWNLOAD_CANCELLED_ERROR));
+    }
+
+    let len = self.inner.read(buf)?;
+
+    if let Some(hasher) = &mut self.hasher {
+      hasher.update(&buf[..len]);
+    }
+
+    self.downloaded_bytes += len as u64;
+    if self.last_callback.elapsed() > self.callback_interval {
+      self.last_callback = Instant::now();
+      (self.progress_callback)(self.downloaded_bytes);
+    }
+
+    // Wait if the download is going faster than the speed limit
+    self.throttle.consume(len as u64, self.cancel);
+
+    Ok(len)
+  }
+}
+
+/// Download an archive and extract it at the same time, without storing the archive on disk
+///
+/// The files are extracted into the `.part` folder next to `extract_folder`, and they are only
+/// moved to `extract_folder` once the whole archive has been downloaded and its hash verified.
+/// If the hash doesn't match, the extracted files are removed
+///
+/// Unlike [`crate::download_file`], an interrupted download can't be resumed, and it has to
+/// start again from the beginning
+///
+/// # Arguments
+///
+/// * `transport` - Something which sends the download requests, usually an itch.io API client
+///
+/// * `url` - A itch.io API address to download the archive from
+///
+/// * `archive_name` - The name of the archive, used to get its format. See [`extract::supports_streaming`]
+///
+/// * `extract_folder` - The folder where the files will be placed
+///
+/// * `checksum` - A hash to check the archive against. If none, don't verify the download
+///
+/// * `options` - The download options. Only `lenient_extraction` is used, the rest come from `ctx`
+///
+/// * `ctx` - The progress callbacks, the cancellation token and the bandwidth limiter of the download
+///
+/// # Returns
+///
+/// The archive entries that couldn't be extracted in lenient mode
+///
+/// # Errors
+///
+/// If the download or the extraction fail, or if the hash doesn't match
+pub(crate) fn download_and_extract<T: DownloadTransport>(
+  transport: &T,
+  url: &ItchApiUrl,
+  archive_name: &Path,
+  extract_folder: &Path,
+  checksum: Option<Checksum<'_>>,
+  options: &DownloadOptions,
+  ctx: &DownloadContext<'_>,
+) -> Result<Vec<FailedEntry>, ScratchError> {
+  let cancel: &CancellationToken = ctx.cancel;
+
+  let res = transport.get(url, None).map_err(ScratchError::Download)?;
+
+  // If the server refused to send the file, don't try to extract its error page
+  if !res.status.is_success() {
+    return Err(ScratchError::Download(format!(
+      "The HTTP server to download the file from returned an error code!
+  It returned code: {}
+  URL: {url}",
+      res.status.as_str()
+    )));
+  }
+
+  if let Some(download_size) = res.content_length {
+    (ctx.file_size_callback)(download_size);
+  }
+
+  debug!(
+    "Downloading \"{}\" and extracting it into \"{}\"",
+    url.redacted(),
+    extract_folder.display()
+  );
+
+  let mut reader = DownloadReader {
+    inner: res.body,
+    hasher: checksum.map(|c| Hasher::new(c.algorithm)),
+    downloaded_bytes: 0,
+    progress_callback: ctx.progress_callback,
+    callback_interval: ctx.callback_interval,
+    last_callback: Instant::now(),
+    cancel,
+    throttle: ctx.throttle,
+  };
+
+  let failed_entries = extract::extract_stream(
+    &mut reader,
+    archive_name,
+    extract_folder,
+    options.lenient_extraction,
+  )
+  .and_then(|failed_entries| {
+    // The archive can have trailing data after its last entry, which has to be hashed too
+    std::io::copy(&mut reader, &mut std::io::sink())
+      .map_err(|e| ExtractError::Other(format!("Couldn't read the end of the archive: {e}")))?;
+    Ok(failed_entries)
+  })
+  .map_err(|e| {
+    if cancel.is_cancelled() {
+      ScratchError::Cancelled
+    } else {
+      ScratchError::Extraction(e)
+    }
+  })?;
+
+  (ctx.progress_callback)(reader.downloaded_bytes);
+
+  let extract_folder_temp = game_files::add_part_extension(extract_folder)?;
+
+  // If the hashes aren't equal, the extracted files can't be trusted, so remove them
+  if let (Some(hasher), Some(checksum)) = (reader.hasher, checksum) {
+    let file_hash = hasher.finalize_hex();
+    trace!(
+      "Downloaded archive hash: {file_hash}, expected hash: {}",
+      checksum.hex
+    );
+
+    if !checksum.matches(&file_hash) {
+      game_files::remove_folder_safely(&extract_folder_temp)?;
+
+      return Err(ScratchError::HashMismatch {
+        expected: checksum.hex.to_string(),
+        actual: file_hash,
+        file: extract_folder_temp,
+      });
+    }
+  }
+
+  extract::commit_extraction(extract_folder)?;
+
+  Ok(failed_entries)
+}
@@ -0,0 +1,112 @@
+use crate::CancellationToken;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The longest time a throttled download sleeps before checking if it was cancelled
+const MAX_THROTTLE_SLEEP: Duration = Duration::from_millis(100);
+
+/// The longest time a download can fall behind its schedule
+///
+/// After being idle, the download can only go faster than the limit until it catches up this time
+const MAX_BURST: Duration = Duration::from_secs(1);
+
+/// A bandwidth limiter shared by every connection of a download
+///
+/// It works as a token bucket refilled at `max_bytes_per_sec`: the download only sleeps while
+/// it is ahead of that schedule, so a limit higher than the real throughput never makes it wait.
+/// The bucket holds at most [`MAX_BURST`] of credit, so a stalled connection or a slow disk
+/// doesn't let the download go unlimited for a long time afterwards
+#[derive(Debug)]
+pub(crate) struct Throttle {
+  max_bytes_per_sec: Option<u64>,
+  start: Instant,
+  /// The time since `start`, in nanoseconds, when the consumed bytes are scheduled to finish
+  schedule_nanos: AtomicU64,
+  consumed_bytes: AtomicU64,
+}
+
+impl Throttle {
+  /// Create a throttle which limits the speed to `max_bytes_per_sec`, or doesn't limit it if None
+  pub(crate) fn new(max_bytes_per_sec: Option<u64>) -> Self {
+    Self {
+      // A limit of 0 bytes per second would never finish, so treat it as no limit
+      max_bytes_per_sec: max_bytes_per_sec.filter(|&m| m > 0),
+      start: Instant::now(),
+      schedule_nanos: AtomicU64::new(0),
+      consumed_bytes: AtomicU64::new(0),
+    }
+  }
+
+  /// Register `bytes` as downloaded, and sleep until the download is back under the limit
+  ///
+  /// The sleep is split in short intervals, so it returns early if `cancel` is cancelled
+  pub(crate) fn consume(&self, bytes: u64, cancel: &CancellationToken) {
+    // The bytes are counted even without a limit, so the transferred data can be reported
+    self.consumed_bytes.fetch_add(bytes, Ordering::Relaxed);
+
+    let Some(max_bytes_per_sec) = self.max_bytes_per_sec else {
+      return;
+    };
+
+    // The time that downloading these bytes should take
+    let cost_nanos =
+      Duration::from_secs_f64(bytes as f64 / max_bytes_per_sec as f64).as_nanos() as u64;
+
+    // The schedule never starts earlier than MAX_BURST ago, which caps the idle credit
+    let earliest_nanos = self.start.elapsed().saturating_sub(MAX_BURST).as_nanos() as u64;
+    let schedule_nanos = |s: u64| s.max(earliest_nanos) + cost_nanos;
+
+    let previous_nanos = self
+      .schedule_nanos
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| {
+        Some(schedule_nanos(s))
+      })
+      .unwrap_or_else(|s| s);
+    let expected_elapsed = Duration::from_nanos(schedule_nanos(previous_nanos));
+
+    loop {
+      let elapsed = self.start.elapsed();
+      if elapsed >= expected_elapsed || cancel.is_cancelled() {
+        return;
+      }
+
+      std::thread::sleep((expected_elapsed - elapsed).min(MAX_THROTTLE_SLEEP));
+    }
+  }
+
+  /// The number of bytes received through this throttle, including the retried and resumed ones
+  pub(crate) fn transferred_bytes(&self) -> u64 {
+    self.consumed_bytes.load(Ordering::Relaxed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Throttle;
+  use crate::CancellationToken;
+
+  use std::time::{Duration, Instant};
+
+  #[test]
+  fn test_idle_credit_is_capped() {
+    // The throttle was idle for 10 seconds, but only one second of credit is kept
+    let throttle = Throttle {
+      start: Instant::now() - Duration::from_secs(10),
+      ..Throttle::new(Some(1000))
+    };
+
+    let start = Instant::now();
+    throttle.consume(1500, &CancellationToken::new());
+    assert!(start.elapsed() >= Duration::from_millis(400));
+  }
+
+  #[test]
+  fn test_unlimited_doesnt_wait() {
+    let throttle = Throttle::new(None);
+    let start = Instant::now();
+    throttle.consume(u64::MAX / 2, &CancellationToken::new());
+    assert!(start.elapsed() < Duration::from_millis(50));
+    assert_eq!(throttle.transferred_bytes(), u64::MAX / 2);
+  }
+}
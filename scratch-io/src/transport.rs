@@ -0,0 +1,478 @@
+use crate::itch_api::{ItchApiUrl, ItchClient, RetryPolicy};
+
+use reqwest::{Method, StatusCode, blocking::Response, header};
+
+/// A range of bytes requested to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+  pub start: u64,
+  /// The last byte of the range (inclusive), or None to request until the end of the file
+  pub end: Option<u64>,
+}
+
+/// The response to a download request
+pub(crate) struct TransportResponse<R> {
+  pub status: StatusCode,
+  pub content_length: Option<u64>,
+  pub body: R,
+}
+
+/// Something that can send the download requests of [`crate::download_file`]
+///
+/// It is implemented by [`ItchClient`], and it allows replacing the network with
+/// a mock to test the resume and verification logic deterministically
+pub(crate) trait DownloadTransport {
+  type Body: std::io::Read + Send + 'static;
+
+  /// Send a GET request to the URL
+  ///
+  /// # Arguments
+  ///
+  /// * `url` - The itch.io API address to download the file from
+  ///
+  /// * `range` - If present, ask the server to only send this range of bytes
+  ///
+  /// # Errors
+  ///
+  /// If the request fails to send
+  fn get(
+    &self,
+    url: &ItchApiUrl,
+    range: Option<ByteRange>,
+  ) -> Result<TransportResponse<Self::Body>, String>;
+
+  /// How the download is retried if the connection is lost while receiving the data
+  fn retry_policy(&self) -> RetryPolicy {
+    RetryPolicy::no_retry()
+  }
+}
+
+impl DownloadTransport for ItchClient {
+  type Body = Response;
+
+  fn get(
+    &self,
+    url: &ItchApiUrl,
+    range: Option<ByteRange>,
+  ) -> Result<TransportResponse<Self::Body>, String> {
+    let res = self
+      .itch_request(url, Method::GET, |b| {
+        // The file must be sent as it is, because the content length and the ranges
+        // would refer to the compressed data
+        let b = b.header(header::ACCEPT_ENCODING, "identity");
+
+        match range {
+          None => b,
+          Some(ByteRange { start, end: None }) => {
+            b.header(header::RANGE, format!("bytes={start}-"))
+          }
+          Some(ByteRange {
+            start,
+            end: Some(end),
+          }) => b.header(header::RANGE, format!("bytes={start}-{end}")),
+        }
+      })
+      .map_err(|e| e.to_string())?;
+
+    Ok(TransportResponse {
+      status: res.status(),
+      content_length: res.content_length(),
+      body: res,
+    })
+  }
+
+  fn retry_policy(&self) -> RetryPolicy {
+    ItchClient::retry_policy(self).clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ByteRange, DownloadTransport, TransportResponse};
+  use crate::errors::ScratchError;
+  use crate::itch_api::{ItchApiUrl, RetryPolicy};
+
+  use md5::{Digest, Md5};
+  use reqwest::StatusCode;
+  use std::io::Cursor;
+  use std::num::NonZeroUsize;
+  use std::path::PathBuf;
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::time::Duration;
+
+  const TEST_DATA: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+  /// A transport which serves `data` from memory
+  struct MockTransport {
+    data: &'static [u8],
+    supports_ranges: bool,
+    /// Stop sending the body after this many bytes, while still reporting the full length
+    truncate_at: Option<usize>,
+  }
+
+  impl DownloadTransport for MockTransport {
+    type Body = Cursor<Vec<u8>>;
+
+    fn get(
+      &self,
+      _url: &ItchApiUrl,
+      range: Option<ByteRange>,
+    ) -> Result<TransportResponse<Self::Body>, String> {
+      let (status, start, end) = match range {
+        Some(r) if self.supports_ranges => (
+          StatusCode::PARTIAL_CONTENT,
+          r.start as usize,
+          r.end.map_or(self.data.len(), |e| e as usize + 1),
+        ),
+        _ => (StatusCode::OK, 0, self.data.len()),
+      };
+
+      let body = &self.data[start..end];
+      let sent = &body[..self.truncate_at.unwrap_or(body.len()).min(body.len())];
+
+      Ok(TransportResponse {
+        status,
+        content_length: Some(body.len() as u64),
+        body: Cursor::new(sent.to_vec()),
+      })
+    }
+  }
+
+  /// A transport whose main server fails with HTTP 503, while its mirror serves the file
+  struct FailingServerTransport {
+    mirror: MockTransport,
+  }
+
+  impl DownloadTransport for FailingServerTransport {
+    type Body = Cursor<Vec<u8>>;
+
+    fn get(
+      &self,
+      url: &ItchApiUrl,
+      range: Option<ByteRange>,
+    ) -> Result<TransportResponse<Self::Body>, String> {
+      if url.as_str().starts_with("https://mirror.itch.zone/") {
+        return self.mirror.get(url, range);
+      }
+
+      Ok(TransportResponse {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        content_length: None,
+        body: Cursor::new(Vec::new()),
+      })
+    }
+  }
+
+  /// A transport which cuts short the first response to a range that doesn't start the file
+  struct FlakyRangeTransport {
+    inner: MockTransport,
+    failed: AtomicBool,
+  }
+
+  impl DownloadTransport for FlakyRangeTransport {
+    type Body = Cursor<Vec<u8>>;
+
+    fn get(
+      &self,
+      url: &ItchApiUrl,
+      range: Option<ByteRange>,
+    ) -> Result<TransportResponse<Self::Body>, String> {
+      let mut res = self.inner.get(url, range)?;
+
+      if range.is_some_and(|r| r.start > 0 && r.end.is_some())
+        && !self.failed.swap(true, Ordering::Relaxed)
+      {
+        res.body.get_mut().truncate(3);
+      }
+
+      Ok(res)
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+      RetryPolicy {
+        max_attempts: 2,
+        ..RetryPolicy::no_retry()
+      }
+    }
+  }
+
+  fn md5_hex(data: &[u8]) -> String {
+    hex::encode(Md5::digest(data))
+  }
+
+  /// Get a new empty folder to download the test files into
+  fn test_folder(name: &str) -> PathBuf {
+    let folder =
+      std::env::temp_dir().join(format!("scratch-io-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+  }
+
+  /// Download [`TEST_DATA`] into `file_path`, starting with `partial` in the .part file
+  fn download(
+    transport: &MockTransport,
+    name: &str,
+    partial: &[u8],
+  ) -> Result<Vec<u8>, ScratchError> {
+    download_with_connections(transport, name, partial, NonZeroUsize::MIN)
+  }
+
+  /// Same as [`download`], but using `connections` parallel connections
+  fn download_with_connections(
+    transport: &MockTransport,
+    name: &str,
+    partial: &[u8],
+    connections: NonZeroUsize,
+  ) -> Result<Vec<u8>, ScratchError> {
+    let folder = test_folder(name);
+    let file_path = folder.join("file");
+    if !partial.is_empty() {
+      std::fs::write(folder.join("file.part"), partial).unwrap();
+    }
+
+    let result = crate::download_file(
+      transport,
+      &ItchApiUrl::other("https://example.com/file".to_string()),
+      &[],
+      &file_path,
+      Some(crate::Checksum::md5(&md5_hex(TEST_DATA))),
+      |_| (),
+      |_| (),
+      Duration::MAX,
+      &crate::CancellationToken::new(),
+      connections,
+      None,
+      None,
+    )
+    .map(|_| std::fs::read(&file_path).unwrap());
+
+    std::fs::remove_dir_all(&folder).unwrap();
+    result
+  }
+
+  #[test]
+  fn new_download() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    assert_eq!(download(&transport, "new", b"").unwrap(), TEST_DATA);
+  }
+
+  #[test]
+  fn resume_with_range() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    assert_eq!(
+      download(&transport, "resume", &TEST_DATA[..10]).unwrap(),
+      TEST_DATA
+    );
+  }
+
+  #[test]
+  fn restart_without_range_support() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: false,
+      truncate_at: None,
+    };
+
+    assert_eq!(
+      download(&transport, "no-ranges", &TEST_DATA[..10]).unwrap(),
+      TEST_DATA
+    );
+  }
+
+  #[test]
+  fn restart_if_partial_file_too_big() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    let mut partial = TEST_DATA.to_vec();
+    partial.extend_from_slice(b"extra bytes");
+
+    assert_eq!(
+      download(&transport, "too-big", &partial).unwrap(),
+      TEST_DATA
+    );
+  }
+
+  #[test]
+  fn truncated_response_fails_verification() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: Some(5),
+    };
+
+    assert!(download(&transport, "truncated", b"").is_err());
+  }
+
+  #[test]
+  fn corrupted_partial_file_fails_verification() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    assert!(matches!(
+      download(&transport, "corrupted", b"The slow"),
+      Err(ScratchError::HashMismatch { .. })
+    ));
+  }
+
+  #[test]
+  fn transferred_bytes_of_resumed_download() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    let folder = test_folder("transferred");
+    let file_path = folder.join("file");
+    std::fs::write(folder.join("file.part"), &TEST_DATA[..10]).unwrap();
+
+    let transferred = crate::download_file(
+      &transport,
+      &ItchApiUrl::other("https://example.com/file".to_string()),
+      &[],
+      &file_path,
+      Some(crate::Checksum::md5(&md5_hex(TEST_DATA))),
+      |_| (),
+      |_| (),
+      Duration::MAX,
+      &crate::CancellationToken::new(),
+      NonZeroUsize::MIN,
+      None,
+      None,
+    );
+
+    std::fs::remove_dir_all(&folder).unwrap();
+    assert_eq!(
+      transferred.unwrap().bytes_transferred,
+      (TEST_DATA.len() - 10) as u64
+    );
+  }
+
+  #[test]
+  fn parallel_download() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: true,
+      truncate_at: None,
+    };
+
+    assert_eq!(
+      download_with_connections(&transport, "parallel", b"", NonZeroUsize::new(4).unwrap())
+        .unwrap(),
+      TEST_DATA
+    );
+  }
+
+  #[test]
+  fn parallel_download_without_range_support() {
+    let transport = MockTransport {
+      data: TEST_DATA,
+      supports_ranges: false,
+      truncate_at: None,
+    };
+
+    assert_eq!(
+      download_with_connections(
+        &transport,
+        "parallel-no-ranges",
+        b"",
+        NonZeroUsize::new(4).unwrap()
+      )
+      .unwrap(),
+      TEST_DATA
+    );
+  }
+
+  #[test]
+  fn parallel_download_retries_failed_range() {
+    let transport = FlakyRangeTransport {
+      inner: MockTransport {
+        data: TEST_DATA,
+        supports_ranges: true,
+        truncate_at: None,
+      },
+      failed: AtomicBool::new(false),
+    };
+
+    let folder = test_folder("parallel-retry");
+    let file_path = folder.join("file");
+
+    let result = crate::download_file(
+      &transport,
+      &ItchApiUrl::other("https://example.com/file".to_string()),
+      &[],
+      &file_path,
+      Some(crate::Checksum::md5(&md5_hex(TEST_DATA))),
+      |_| (),
+      |_| (),
+      Duration::MAX,
+      &crate::CancellationToken::new(),
+      NonZeroUsize::new(4).unwrap(),
+      None,
+      None,
+    );
+    let downloaded = std::fs::read(&file_path);
+
+    std::fs::remove_dir_all(&folder).unwrap();
+    assert!(transport.failed.load(Ordering::Relaxed));
+    assert_eq!(result.unwrap().bytes_transferred, TEST_DATA.len() as u64);
+    assert_eq!(downloaded.unwrap(), TEST_DATA);
+  }
+
+  #[test]
+  fn resume_from_mirror_after_server_error() {
+    let transport = FailingServerTransport {
+      mirror: MockTransport {
+        data: TEST_DATA,
+        supports_ranges: true,
+        truncate_at: None,
+      },
+    };
+
+    let folder = test_folder("mirror");
+    let file_path = folder.join("file");
+    std::fs::write(folder.join("file.part"), &TEST_DATA[..10]).unwrap();
+
+    let result = crate::download_file(
+      &transport,
+      &ItchApiUrl::v2("uploads/1/download"),
+      &[ItchApiUrl::v2("uploads/1/download")
+        .with_base("https://mirror.itch.zone")
+        .unwrap()],
+      &file_path,
+      Some(crate::Checksum::md5(&md5_hex(TEST_DATA))),
+      |_| (),
+      |_| (),
+      Duration::MAX,
+      &crate::CancellationToken::new(),
+      NonZeroUsize::MIN,
+      None,
+      None,
+    );
+    let downloaded = std::fs::read(&file_path);
+
+    std::fs::remove_dir_all(&folder).unwrap();
+    let result = result.unwrap();
+    assert_eq!(result.mirror, Some(0));
+    assert_eq!(result.bytes_transferred, (TEST_DATA.len() - 10) as u64);
+    assert_eq!(downloaded.unwrap(), TEST_DATA);
+  }
+}
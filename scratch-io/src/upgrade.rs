@@ -0,0 +1,415 @@
+use crate::errors::{FilesystemError, ScratchError};
+use crate::itch_api::types::{
+  BuildFileSubtype, BuildFileType, BuildID, UpgradePathBuild, Upload, UploadStorage,
+};
+use crate::itch_api::{ItchApiUrl, ItchClient, endpoints};
+use crate::{
+  BuildDiff, Checksum, DownloadOptions, DownloadStatus, Fingerprint, FingerprintMode,
+  InstalledUpload, PatchProgress, filesystem, folder_fingerprint, game_files,
+};
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The progress of [`upgrade_upload`]
+#[derive(Debug)]
+pub enum UpgradeStatus {
+  /// The installed build is unknown, so the upload is downloaded again fully
+  FullReinstall,
+  /// The patch to the build with the given ID started being downloaded and applied
+  ApplyingPatch {
+    build_id: BuildID,
+    step: usize,
+    total_steps: usize,
+  },
+  /// The progress of applying the patch of the current step
+  PatchProgress(PatchProgress),
+  /// The progress of the full reinstall download
+  Download(DownloadStatus),
+}
+
+/// Add a .old extension to the given folder
+fn add_old_extension(folder: &Path) -> Result<PathBuf, FilesystemError> {
+  let filename = filesystem::get_file_name(folder)?;
+  Ok(folder.with_file_name(format!("{filename}.old")))
+}
+
+/// Replace `folder` with `new_folder`, keeping the old folder until the new one is in place
+fn swap_folders(folder: &Path, new_folder: &Path) -> Result<(), FilesystemError> {
+  let old_folder: PathBuf = add_old_extension(folder)?;
+
+  filesystem::rename(folder, &old_folder)?;
+
+  // If the new folder couldn't be moved, put the old one back
+  if let Err(e) = filesystem::rename(new_folder, folder) {
+    filesystem::rename(&old_folder, folder)?;
+    return Err(e);
+  }
+
+  filesystem::remove_dir_all(&old_folder)?;
+
+  Ok(())
+}
+
+/// Download a raw file of a build, like its wharf patch or signature
+///
+/// The download can be resumed like the uploads. The API doesn't provide the hashes of the
+/// build files, so the file is only verified if the caller knows its hash. The available files
+/// of a build are listed in its [`crate::itch_api::types::BuildFile`]s
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `build_id` - The ID of the build whose file will be downloaded
+///
+/// * `file_type` - The type of the file, like [`BuildFileType::Patch`] or [`BuildFileType::Signature`]
+///
+/// * `sub_type` - The variant of the file, usually [`BuildFileSubtype::Default`]
+///
+/// * `path` - The path where the file will be placed
+///
+/// * `checksum` - A hash to check the file against, with any [`crate::ChecksumAlgorithm`]. If none, don't verify the download
+///
+/// * `options` - The download options. Only the cancellation token and the speed limits are used
+///
+/// # Errors
+///
+/// If the build doesn't have that file, or something goes wrong while downloading it
+pub fn download_build_file(
+  client: &ItchClient,
+  build_id: BuildID,
+  file_type: BuildFileType,
+  sub_type: BuildFileSubtype,
+  path: &Path,
+  checksum: Option<Checksum<'_>>,
+  options: &DownloadOptions,
+) -> Result<(), ScratchError> {
+  crate::download_file(
+    client,
+    &ItchApiUrl::v2(&format!(
+      "builds/{build_id}/download/{}/{}",
+      file_type.as_str(),
+      sub_type.as_str()
+    )),
+    &[],
+    path,
+    checksum,
+    |_| (),
+    |_| (),
+    Duration::MAX,
+    &options.cancel,
+    NonZeroUsize::MIN,
+    options.max_bytes_per_sec,
+    options.min_speed,
+  )
+  .map(|_| ())
+}
+
+/// Apply the patch of a build of the upgrade path into a new folder, and verify it with the build signature
+fn apply_build_patch(
+  upload_folder: &Path,
+  patched_folder: &Path,
+  staging_folder: &Path,
+  patch_path: &Path,
+  signature_path: &Path,
+  progress_callback: impl FnMut(PatchProgress) + Send,
+) -> Result<(), ScratchError> {
+  // Start from an empty folder, in case a previous upgrade was interrupted
+  if filesystem::exists(patched_folder)? {
+    filesystem::remove_dir_all(patched_folder)?;
+  }
+
+  crate::apply_patch(
+    patch_path,
+    None,
+    upload_folder,
+    staging_folder,
+    patched_folder,
+    progress_callback,
+  )?;
+
+  let signature_file =
+    filesystem::open_file(signature_path, std::fs::OpenOptions::new().read(true))?;
+  let mut signature_reader = std::io::BufReader::new(signature_file);
+  let mut signature = wharf::Signature::read(&mut signature_reader).map_err(ScratchError::Wharf)?;
+
+  let issues = signature
+    .verify_files(patched_folder, |_| ())
+    .map_err(ScratchError::Wharf)?;
+  if !issues.are_files_intact() {
+    return Err(ScratchError::Verification(format!(
+      "The patched files don't match the build signature! {} files are broken",
+      issues.files.len()
+    )));
+  }
+
+  Ok(())
+}
+
+/// Download the upload again fully, replacing the installed files only if the download succeeds
+pub(crate) fn reinstall_build(
+  client: &ItchClient,
+  installed_upload: &mut InstalledUpload,
+  target_build_id: BuildID,
+  options: &DownloadOptions,
+  progress_callback: &(impl Fn(UpgradeStatus) + Sync),
+) -> Result<(), ScratchError> {
+  let upload: Upload = endpoints::get_upload_info(client, installed_upload.upload_id)?;
+
+  // The full download always gets the latest build of the upload
+  match upload.storage {
+    UploadStorage::Build { build_id, .. } if build_id == target_build_id => (),
+    _ => {
+      return Err(ScratchError::InvalidInput(format!(
+        "The installed build is unknown, and the target build isn't the latest build of the upload, so it can't be reinstalled!
+  Upload ID: {}
+  Target build ID: {target_build_id}",
+        installed_upload.upload_id
+      )));
+    }
+  }
+
+  progress_callback(UpgradeStatus::FullReinstall);
+
+  let upload_folder: PathBuf = installed_upload.upload_folder();
+  let old_folder: PathBuf = add_old_extension(&upload_folder)?;
+
+  // Keep the installed files until the new ones are in place
+  filesystem::rename(&upload_folder, &old_folder)?;
+
+  let result = crate::download_upload_with_options(
+    client,
+    installed_upload.upload_id,
+    &installed_upload.game_folder,
+    options,
+    |_, _| (),
+    |status| progress_callback(UpgradeStatus::Download(status)),
+  );
+
+  match result {
+    Ok(new_installed_upload) => {
+      filesystem::remove_dir_all(&old_folder)?;
+
+      // Keep the choices and the play history of the user, like reinstall_upload does
+      *installed_upload = InstalledUpload {
+        launch_override: installed_upload.launch_override.take(),
+        last_played_at: installed_upload.last_played_at,
+        total_playtime: installed_upload.total_playtime,
+        ..new_installed_upload
+      };
+      Ok(())
+    }
+    Err(e) => {
+      if !filesystem::exists(&upload_folder)? {
+        filesystem::rename(&old_folder, &upload_folder)?;
+      }
+      Err(e)
+    }
+  }
+}
+
+/// Get the files that change between two builds of an upload, without touching the installed files
+///
+/// The patches of the upgrade path are downloaded into `folder`, analyzed and removed. If the
+/// builds are several patches apart, the diffs of every patch are combined
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `current_build_id` - The ID of the build to compare from
+///
+/// * `target_build_id` - The ID of the build to compare to
+///
+/// * `folder` - A folder where the patches are downloaded temporarily
+///
+/// * `options` - The download options of the patches. Only the cancellation token and the speed limits are used
+///
+/// # Returns
+///
+/// The added, removed and modified files, with an estimate of the bytes that change
+///
+/// # Errors
+///
+/// If the upgrade path couldn't be obtained, or a patch couldn't be downloaded or read
+pub fn diff_builds(
+  client: &ItchClient,
+  current_build_id: BuildID,
+  target_build_id: BuildID,
+  folder: &Path,
+  options: &DownloadOptions,
+) -> Result<BuildDiff, ScratchError> {
+  let upgrade_path: Vec<UpgradePathBuild> =
+    endpoints::get_upgrade_path(client, current_build_id, target_build_id)?
+      .into_iter()
+      .filter(|b| b.build_info.id != current_build_id)
+      .collect();
+
+  filesystem::create_dir(folder)?;
+
+  let mut diff = BuildDiff::default();
+  for build in &upgrade_path {
+    let build_id = build.build_info.id;
+    let patch_path: PathBuf = folder.join(format!("{build_id}-patch.pwr"));
+
+    download_build_file(
+      client,
+      build_id,
+      BuildFileType::Patch,
+      BuildFileSubtype::Default,
+      &patch_path,
+      None,
+      options,
+    )?;
+
+    let result: Result<BuildDiff, ScratchError> = (|| {
+      let patch_file = filesystem::open_file(&patch_path, std::fs::OpenOptions::new().read(true))?;
+      let mut patch_reader = std::io::BufReader::new(patch_file);
+      wharf::Patch::read(&mut patch_reader)
+        .and_then(|mut patch| patch.diff())
+        .map_err(ScratchError::Wharf)
+    })();
+
+    filesystem::remove_file(&patch_path)?;
+    diff = diff.then(result?);
+  }
+
+  Ok(diff)
+}
+
+/// Upgrade an installed upload to another build, applying the wharf patches of the upgrade path
+///
+/// Every patch is applied into a new folder and verified with its build signature before
+/// replacing the installed files, so a failed upgrade leaves the last applied build in place.
+/// If the installed build is unknown, the upload is downloaded again fully instead, which is
+/// only possible if the target build is the latest build of the upload
+///
+/// # Arguments
+///
+/// * `client` - An itch.io API client
+///
+/// * `installed_upload` - The installed upload to upgrade, which will be updated with the new build ID
+///
+/// * `target_build_id` - The ID of the build to upgrade to
+///
+/// * `options` - The download options of the patches and of the full reinstall
+///
+/// * `progress_callback` - A closure which reports the upgrade progress, including the progress of every patch
+///
+/// # Errors
+///
+/// If something goes wrong while downloading or applying the patches. The installed upload is
+/// still updated with the last applied build and its fingerprint, so it must be saved anyway
+pub fn upgrade_upload(
+  client: &ItchClient,
+  installed_upload: &mut InstalledUpload,
+  target_build_id: BuildID,
+  options: &DownloadOptions,
+  progress_callback: impl Fn(UpgradeStatus) + Sync,
+) -> Result<(), ScratchError> {
+  let Some(current_build_id) = installed_upload.installed_build_id else {
+    return reinstall_build(
+      client,
+      installed_upload,
+      target_build_id,
+      options,
+      &progress_callback,
+    );
+  };
+
+  if current_build_id == target_build_id {
+    debug!("The upload is already in the build {target_build_id}");
+    return Ok(());
+  }
+
+  let upgrade_path: Vec<UpgradePathBuild> =
+    endpoints::get_upgrade_path(client, current_build_id, target_build_id)?
+      .into_iter()
+      .filter(|b| b.build_info.id != current_build_id)
+      .collect();
+
+  let upload_id = installed_upload.upload_id;
+  let game_folder: &Path = &installed_upload.game_folder;
+  let upload_folder: PathBuf = installed_upload.upload_folder();
+  let patched_folder: PathBuf = game_files::add_part_extension(&upload_folder)?;
+  let staging_folder: PathBuf = game_folder.join(format!("{upload_id}-staging"));
+
+  let total_steps = upgrade_path.len();
+  let result: Result<(), ScratchError> = (|| {
+    for (step, build) in upgrade_path.iter().enumerate() {
+      let build_id = build.build_info.id;
+
+      if options.cancel.is_cancelled() {
+        return Err(ScratchError::Cancelled);
+      }
+
+      progress_callback(UpgradeStatus::ApplyingPatch {
+        build_id,
+        step: step + 1,
+        total_steps,
+      });
+
+      let patch_path: PathBuf = game_folder.join(format!("{upload_id}-{build_id}-patch.pwr"));
+      let signature_path: PathBuf =
+        game_folder.join(format!("{upload_id}-{build_id}-signature.pws"));
+
+      download_build_file(
+        client,
+        build_id,
+        BuildFileType::Patch,
+        BuildFileSubtype::Default,
+        &patch_path,
+        None,
+        options,
+      )?;
+      download_build_file(
+        client,
+        build_id,
+        BuildFileType::Signature,
+        BuildFileSubtype::Default,
+        &signature_path,
+        None,
+        options,
+      )?;
+
+      let result = apply_build_patch(
+        &upload_folder,
+        &patched_folder,
+        &staging_folder,
+        &patch_path,
+        &signature_path,
+        |p| progress_callback(UpgradeStatus::PatchProgress(p)),
+      );
+
+      // The build files and the staging folder aren't needed anymore
+      filesystem::remove_file(&patch_path)?;
+      filesystem::remove_file(&signature_path)?;
+      if filesystem::exists(&staging_folder)? {
+        filesystem::remove_dir_all(&staging_folder)?;
+      }
+
+      if let Err(e) = result {
+        if filesystem::exists(&patched_folder)? {
+          filesystem::remove_dir_all(&patched_folder)?;
+        }
+        return Err(e);
+      }
+
+      swap_folders(&upload_folder, &patched_folder)?;
+
+      // Record every applied build, so an interrupted upgrade can continue from it
+      installed_upload.installed_build_id = Some(build_id);
+    }
+    Ok(())
+  })();
+
+  // If any patch was applied the files have changed, even if a later one failed, so fingerprint them again
+  if installed_upload.installed_build_id != Some(current_build_id) {
+    let fingerprint: Fingerprint = folder_fingerprint(&upload_folder, FingerprintMode::Fast)?;
+    installed_upload.fingerprint = Some(fingerprint);
+  }
+
+  result
+}
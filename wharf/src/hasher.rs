@@ -88,11 +88,12 @@ impl BlockHasher<'_, '_, '_> {
   }
 }
 
-/// Continuously hash blocks from the buffer pool until no more are available or a mismatch is found.
+/// Continuously hash blocks from the buffer pool until no more are available.
 ///
 /// Must be run on a dedicated hasher thread. Blocks waiting for a filled buffer, hashes it,
 /// then releases it back to the pool for refilling. On a mismatch, signals failure
-/// via the pool and returns.
+/// via the pool and keeps hashing the blocks that were already read, so the
+/// lowest broken block index is always the one reported.
 fn hasher_thread(hasher: &mut InternalHasher, buffer_pool: &BufferPoolSession) {
   loop {
     let Some(buffer) = buffer_pool.get_buffer_to_hash() else {
@@ -104,9 +105,10 @@ fn hasher_thread(hasher: &mut InternalHasher, buffer_pool: &BufferPoolSession) {
     // Leave the block buffer available to be filled by the IO thread again
     buffer_pool.release_hashed_buffer(buffer);
 
+    // Don't return after a mismatch: the blocks that were already read
+    // must still be hashed in case one of them is broken too
     if let BlockHasherStatus::HashMismatch { block_index } = status {
       buffer_pool.set_failed(block_index);
-      return;
     }
   }
 }
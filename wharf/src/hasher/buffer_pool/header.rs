@@ -66,15 +66,26 @@ impl PoolStatus {
   /// This function blocks the current thread until a buffer can be acquired.
   ///
   /// Returns None if the verification has finished.
+  ///
+  /// If the verification has failed, no more slots will be given away to be
+  /// refilled, but the slots that are already waiting to be hashed will still
+  /// be acquired. Because blocks are read in order, those slots could contain
+  /// a broken block before the one that has already failed, and the first
+  /// broken block must always be the one reported.
   pub fn acquire_slot(
     mut guard: MutexGuard<'_, PoolStatus>,
     condvar: &Condvar,
     expected_status: SlotStatus,
   ) -> Option<usize> {
     loop {
-      // If the verification has finished, don't give away more slots!
-      if guard.has_finished() {
-        return None;
+      match guard.status {
+        // If the verification has finished, don't give away more slots!
+        VerificationStatus::Finished => return None,
+        // If it has failed, only give away the slots that still need to be hashed
+        VerificationStatus::Failed { .. } if expected_status != SlotStatus::WaitingForHash => {
+          return None;
+        }
+        VerificationStatus::Failed { .. } | VerificationStatus::Running { .. } => (),
       }
 
       // Check for an available slot
@@ -85,6 +96,12 @@ impl PoolStatus {
         }
       }
 
+      // If the verification has failed and there aren't any slots left to
+      // hash, don't wait because no more slots will be refilled
+      if guard.has_finished() {
+        return None;
+      }
+
       // Sleep until one slot is available
       condvar.wait(&mut guard);
     }
@@ -110,7 +127,18 @@ impl PoolStatus {
     false
   }
 
+  /// Mark the verification as failed
+  ///
+  /// If it had already failed, keep the lowest broken block index, so the
+  /// reported block doesn't depend on the order the hasher threads finish in.
   pub fn set_failed(&mut self, broken_block_index: usize) {
+    let broken_block_index = match self.status {
+      VerificationStatus::Failed {
+        broken_block_index: i,
+      } => i.min(broken_block_index),
+      VerificationStatus::Running { .. } | VerificationStatus::Finished => broken_block_index,
+    };
+
     self.status = VerificationStatus::Failed { broken_block_index }
   }
 }
@@ -1,3 +1,11 @@
+#[macro_use]
+mod logging;
+
+/// The tracing crate, used by the exported logging macros
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+pub use tracing as __tracing;
+
 /// Funcions and structures for reading wharf patches
 mod patch;
 /// Funcions and structures for reading wharf signatures
@@ -17,4 +25,6 @@ mod hasher;
 mod protos;
 
 pub use patch::Patch;
+pub use patch::apply::PatchProgress;
+pub use patch::diff::{BuildDiff, ChangeMethod, FileChange};
 pub use signature::Signature;
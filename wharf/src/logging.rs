@@ -0,0 +1,69 @@
+//! Internal logging macros, shared with the scratch-io crate
+//!
+//! When the `tracing` feature is enabled, these macros forward their
+//! arguments to the [`tracing`](https://docs.rs/tracing) crate. Otherwise,
+//! they expand to nothing, but the format arguments are still type-checked.
+//!
+//! Only plain format strings are supported, so both expansions accept the same input.
+//!
+//! They are exported so scratch-io uses the same macros, but they aren't part of the public API
+
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! debug {
+  ($($arg:tt)*) => {
+    $crate::__tracing::debug!($($arg)*)
+  };
+}
+
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! debug {
+  ($($arg:tt)*) => {{
+    let _ = format_args!($($arg)*);
+  }};
+}
+
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! trace {
+  ($($arg:tt)*) => {
+    $crate::__tracing::trace!($($arg)*)
+  };
+}
+
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! trace {
+  ($($arg:tt)*) => {{
+    let _ = format_args!($($arg)*);
+  }};
+}
+
+/// Enter a span until the end of the current scope
+///
+/// The fields are recorded with their [`std::fmt::Display`] implementation. Without the
+/// `tracing` feature, they are type-checked but never evaluated
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! enter_span {
+  ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+    let _span = $crate::__tracing::debug_span!($name $(, $field = %$value)*).entered();
+  };
+}
+
+#[cfg(not(feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! enter_span {
+  ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+    let _ = || {
+      $(let _ = &$value;)*
+    };
+  };
+}
@@ -1,4 +1,5 @@
 pub mod apply;
+pub mod diff;
 pub mod operations;
 
 use crate::common::{MAGIC_PATCH, Reader, check_magic_bytes, decompress_stream};
@@ -5,8 +5,24 @@ use crate::hasher::BlockHasher;
 use crate::pool::{ContainerPool, StagingPool};
 use crate::signature::BlockHashIter;
 
+use staging::ReconstructEvent;
 use std::path::Path;
 
+/// The progress of a patch application
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatchProgress {
+  /// The index of the file being patched
+  pub file_index: usize,
+  /// The number of files in the new container
+  pub total_files: usize,
+  /// The number of bytes written since the patch started being applied
+  pub bytes_written: u64,
+  /// The size of all the files in the new container
+  ///
+  /// Files that don't need patching aren't written, so `bytes_written` may not reach this value
+  pub total_bytes: u64,
+}
+
 impl Patch<'_> {
   /// Apply the patch operations to produce the new build.
   ///
@@ -40,6 +56,67 @@ impl Patch<'_> {
     hash_iter: Option<&mut BlockHashIter>,
     mut progress_callback: impl FnMut(u64) + Send,
   ) -> Result<(), String> {
+    // Report the bytes written since the last call
+    let mut last_bytes_written: u64 = 0;
+
+    self.apply_with_progress(
+      old_build_folder,
+      staging_folder,
+      new_build_folder,
+      hash_iter,
+      |progress| {
+        if progress.bytes_written > last_bytes_written {
+          progress_callback(progress.bytes_written - last_bytes_written);
+          last_bytes_written = progress.bytes_written;
+        }
+      },
+    )
+  }
+
+  /// Apply the patch operations to produce the new build, reporting detailed progress
+  ///
+  /// This behaves exactly like [`Patch::apply`], but the progress callback receives
+  /// a [`PatchProgress`] struct every time a new file starts being patched and every
+  /// time data is written, so a UI can show which file is being patched
+  ///
+  /// # Arguments
+  ///
+  /// * `old_build_folder` - The path to the old build folder
+  ///
+  /// * `new_build_folder` - The path to the new build folder
+  ///
+  /// * `hash_iter` - Iterator over expected block hashes used to verify the
+  ///   integrity of the written files (optional)
+  ///
+  /// * `progress_callback` - A callback that is called with the patch progress
+  ///
+  /// # Errors
+  ///
+  /// If there is an I/O failure while reading files or metadata, or if hash
+  /// verification of the generated files fails
+  pub fn apply_with_progress(
+    &mut self,
+    old_build_folder: &Path,
+    staging_folder: &Path,
+    new_build_folder: &Path,
+    hash_iter: Option<&mut BlockHashIter>,
+    mut progress_callback: impl FnMut(PatchProgress) + Send,
+  ) -> Result<(), String> {
+    let mut progress = PatchProgress {
+      file_index: 0,
+      total_files: self.container_new.files.len(),
+      bytes_written: 0,
+      total_bytes: self.container_new.size as u64,
+    };
+
+    let progress_callback = |event: ReconstructEvent| {
+      match event {
+        ReconstructEvent::FileStarted(file_index) => progress.file_index = file_index,
+        ReconstructEvent::BytesWritten(bytes) => progress.bytes_written += bytes,
+      }
+      progress_callback(progress);
+    };
+
     // Create the new container folders, files and symlinks,
     // applying all the correct permissions
     let mut dst_pool = ContainerPool::create(&self.container_new, new_build_folder)?;
@@ -71,6 +148,11 @@ impl Patch<'_> {
     // instance to verify that the new game files are intact
     let mut hasher = hash_iter.map(|iter| BlockHasher::new(&self.container_new, iter));
 
+    debug!(
+      "Applying patch with {} files in the new container",
+      self.sync_op_iter.remaining_entries
+    );
+
     // Reconstruct all the modified files into the staging folder
     let status = staging::reconstruct_modified_files(
       &mut src_pool,
@@ -79,9 +161,19 @@ impl Patch<'_> {
       &mut self.sync_op_iter,
       &mut hasher,
       &mut patch_op_buffer,
-      &mut progress_callback,
+      progress_callback,
     )?;
 
+    debug!(
+      "Patched {} of {} files",
+      status
+        .patched_files
+        .iter()
+        .filter(|s| s.has_been_patched())
+        .count(),
+      status.patched_files.len()
+    );
+
     ///////// TODO: do something with the status
     for (file_index, file_status) in status.patched_files.into_iter().enumerate() {
       println!("file {}: {:?}", file_index, file_status);
@@ -84,6 +84,15 @@ impl StagingCheckpoint {
   }
 }
 
+/// An event reported while the files are being reconstructed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReconstructEvent {
+  /// The file with the given index of the new container started being patched
+  FileStarted(usize),
+  /// The number of bytes written since the last event
+  BytesWritten(u64),
+}
+
 // Contains all the individual file patch status
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[must_use]
@@ -190,7 +199,7 @@ fn reconstruct_files_common<F>(
   dst_pool: &mut impl ContainerBackedPool,
   sync_op_iter: &mut SyncEntryIter,
   patch_op_buffer: &mut Vec<u8>,
-  mut progress_callback: impl FnMut(u64) + Send,
+  mut progress_callback: impl FnMut(ReconstructEvent) + Send,
   mut on_file_patched: F,
 ) -> Result<(), String>
 where
@@ -215,6 +224,8 @@ where
     let file_index = header.file_index;
     let new_file_size = dst_pool.get_container_size(file_index)?;
 
+    progress_callback(ReconstructEvent::FileStarted(file_index));
+
     // Patch the file (or skip patching instead if it is not needed)
     let status = patch_file(
       header,
@@ -223,7 +234,7 @@ where
       patch_op_buffer,
       new_file_size,
       checkpoint,
-      &mut progress_callback,
+      |b| progress_callback(ReconstructEvent::BytesWritten(b)),
     )?;
 
     // Return the status and let the caller decide what to do with it
@@ -244,7 +255,7 @@ fn reconstruct_without_verification(
   dst_pool: &mut impl ContainerBackedPool,
   sync_op_iter: &mut SyncEntryIter,
   patch_op_buffer: &mut Vec<u8>,
-  progress_callback: impl FnMut(u64) + Send,
+  progress_callback: impl FnMut(ReconstructEvent) + Send,
 ) -> Result<ReconstructedFilesStatus, String> {
   // Deserialize the last checkpoint stored in the staging folder
   // Get the default (empty) checkpoint if it does not exist
@@ -409,7 +420,7 @@ pub fn reconstruct_with_verification(
   sync_op_iter: &mut SyncEntryIter,
   hasher: &mut BlockHasher,
   patch_op_buffer: &mut Vec<u8>,
-  progress_callback: impl FnMut(u64) + Send,
+  progress_callback: impl FnMut(ReconstructEvent) + Send,
 ) -> Result<ReconstructedFilesStatus, String> {
   // Deserialize the last checkpoint stored in the staging folder
   // Get the default (empty) checkpoint if it does not exist
@@ -501,7 +512,7 @@ pub fn reconstruct_modified_files(
   sync_op_iter: &mut SyncEntryIter,
   hasher: &mut Option<BlockHasher>,
   patch_op_buffer: &mut Vec<u8>,
-  progress_callback: impl FnMut(u64) + Send,
+  progress_callback: impl FnMut(ReconstructEvent) + Send,
 ) -> Result<ReconstructedFilesStatus, String> {
   match hasher {
     None => reconstruct_without_verification(
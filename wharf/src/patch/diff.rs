@@ -0,0 +1,256 @@
+use super::{Patch, RsyncOp, SyncHeaderKind};
+use crate::common::BLOCK_SIZE;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// How the contents of a file of the new container are produced by the patch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeMethod {
+  /// Blocks of the old files are reused, and the rest of the data is sent in the patch
+  Rsync,
+  /// A binary diff is applied to an old file
+  Bsdiff,
+  /// All the data of the file is sent in the patch
+  Rewritten,
+}
+
+/// A file that is added or modified by a patch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileChange {
+  /// The path of the file, relative to the build folder
+  pub path: PathBuf,
+  /// How the file contents are produced
+  pub method: ChangeMethod,
+  /// The size of the file in the new container
+  pub size: u64,
+  /// An estimate of the number of bytes that change, which are the new data sent in the patch
+  pub changed_bytes: u64,
+}
+
+/// The files that change between the old and the new container of a patch
+///
+/// Only the regular files are compared, not the folders or the symlinks
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildDiff {
+  /// The files that only exist in the new container
+  pub added: Vec<FileChange>,
+  /// The files that only exist in the old container
+  pub removed: Vec<PathBuf>,
+  /// The files that exist in both containers, but whose contents change
+  pub modified: Vec<FileChange>,
+}
+
+impl BuildDiff {
+  /// The sum of the estimated changed bytes of all the added and modified files
+  #[must_use]
+  pub fn changed_bytes(&self) -> u64 {
+    self
+      .added
+      .iter()
+      .chain(&self.modified)
+      .map(|f| f.changed_bytes)
+      .sum()
+  }
+
+  /// Combine this diff with the diff of the next patch, as if both patches were a single one
+  ///
+  /// This is used to get the diff between two builds that are several patches apart
+  #[must_use]
+  pub fn then(mut self, next: BuildDiff) -> BuildDiff {
+    for path in next.removed {
+      if let Some(i) = self.added.iter().position(|f| f.path == path) {
+        // A file added and then removed never existed for the old build
+        self.added.remove(i);
+        continue;
+      }
+
+      self.modified.retain(|f| f.path != path);
+      self.removed.push(path);
+    }
+
+    for change in next.added {
+      match self.removed.iter().position(|p| *p == change.path) {
+        // A file removed and then added again is a modified file
+        Some(i) => {
+          self.removed.remove(i);
+          self.modified.push(change);
+        }
+        None => self.added.push(change),
+      }
+    }
+
+    for change in next.modified {
+      let previous = self
+        .added
+        .iter_mut()
+        .chain(self.modified.iter_mut())
+        .find(|f| f.path == change.path);
+
+      match previous {
+        Some(previous) => {
+          previous.method = change.method;
+          previous.size = change.size;
+          previous.changed_bytes += change.changed_bytes;
+        }
+        None => self.modified.push(change),
+      }
+    }
+
+    self
+  }
+}
+
+impl Patch<'_> {
+  /// Compare the old and the new container of the patch, without touching any file
+  ///
+  /// The patch operations are read to find out which files are copied unchanged and how
+  /// much new data the others need. The internal patch iterator is consumed during this call
+  ///
+  /// # Errors
+  ///
+  /// If the patch operations couldn't be decoded
+  pub fn diff(&mut self) -> Result<BuildDiff, String> {
+    let old_paths: HashSet<&str> = self
+      .container_old
+      .files
+      .iter()
+      .map(|f| f.path.as_str())
+      .collect();
+    let new_paths: HashSet<&str> = self
+      .container_new
+      .files
+      .iter()
+      .map(|f| f.path.as_str())
+      .collect();
+
+    let mut diff = BuildDiff {
+      removed: self
+        .container_old
+        .files
+        .iter()
+        .filter(|f| !new_paths.contains(f.path.as_str()))
+        .map(|f| PathBuf::from(&f.path))
+        .collect(),
+      ..Default::default()
+    };
+
+    while let Some(header) = self.sync_op_iter.next_header() {
+      let header = header?;
+      let new_file = &self.container_new.files[header.file_index];
+      let new_size = new_file.size as u64;
+
+      let (method, changed_bytes, unchanged) = match header.kind {
+        SyncHeaderKind::Rsync { op_iter } => {
+          let mut changed_bytes: u64 = 0;
+          let mut reused_blocks = false;
+          let mut ops: usize = 0;
+          let mut literal_copy_of: Option<usize> = None;
+
+          for op in op_iter {
+            ops += 1;
+            match op? {
+              RsyncOp::BlockRange {
+                file_index,
+                block_index,
+                block_span,
+              } => {
+                reused_blocks = true;
+
+                // A single range covering a whole old file of the same size is a plain copy
+                let old_size = self.container_old.files[file_index].size as u64;
+                literal_copy_of = (block_index == 0
+                  && block_span * BLOCK_SIZE as u64 >= new_size
+                  && old_size == new_size)
+                  .then_some(file_index);
+              }
+              RsyncOp::Data(data) => changed_bytes += data.len() as u64,
+            }
+          }
+
+          let unchanged = ops == 1
+            && literal_copy_of.is_some_and(|i| self.container_old.files[i].path == new_file.path);
+          let method = if reused_blocks {
+            ChangeMethod::Rsync
+          } else {
+            ChangeMethod::Rewritten
+          };
+
+          (method, changed_bytes, unchanged)
+        }
+        SyncHeaderKind::Bsdiff { op_iter, .. } => {
+          let mut changed_bytes: u64 = 0;
+
+          // The add bytes are zero where the old data is kept, and the copy bytes are all new
+          for op in op_iter {
+            let op = op?;
+            changed_bytes += op.add.iter().filter(|&&b| b != 0).count() as u64;
+            changed_bytes += op.copy.len() as u64;
+          }
+
+          (ChangeMethod::Bsdiff, changed_bytes, false)
+        }
+      };
+
+      if unchanged {
+        continue;
+      }
+
+      let change = FileChange {
+        path: PathBuf::from(&new_file.path),
+        method,
+        size: new_size,
+        changed_bytes,
+      };
+
+      if old_paths.contains(new_file.path.as_str()) {
+        diff.modified.push(change);
+      } else {
+        diff.added.push(change);
+      }
+    }
+
+    Ok(diff)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{BuildDiff, ChangeMethod, FileChange};
+  use std::path::PathBuf;
+
+  fn change(path: &str, changed_bytes: u64) -> FileChange {
+    FileChange {
+      path: PathBuf::from(path),
+      method: ChangeMethod::Rsync,
+      size: changed_bytes,
+      changed_bytes,
+    }
+  }
+
+  #[test]
+  fn test_combine_consecutive_diffs() {
+    let first = BuildDiff {
+      added: vec![change("new.txt", 10), change("temp.txt", 5)],
+      removed: vec![PathBuf::from("old.txt")],
+      modified: vec![change("game.bin", 100)],
+    };
+    let second = BuildDiff {
+      added: vec![change("old.txt", 20)],
+      removed: vec![PathBuf::from("temp.txt"), PathBuf::from("game.bin")],
+      modified: vec![change("new.txt", 3)],
+    };
+
+    let combined = first.then(second);
+
+    assert_eq!(
+      combined.added,
+      vec![FileChange {
+        size: 3,
+        ..change("new.txt", 13)
+      }]
+    );
+    assert_eq!(combined.removed, vec![PathBuf::from("game.bin")]);
+    assert_eq!(combined.modified, vec![change("old.txt", 20)]);
+  }
+}
@@ -206,6 +206,8 @@ pub fn patch_rsync(
     return Err("After successfully patching a file, the number of written bytes does not equal the expected amount!".to_string());
   }
 
+  trace!("Applied {op_index} rsync operations, writing {written_bytes} bytes");
+
   Ok(PatchFileStatus::Patched { written_bytes })
 }
 
@@ -288,5 +290,7 @@ pub fn patch_bsdiff(
     return Err("After successfully patching a file, the number of written bytes does not equal the expected amount!".to_string());
   }
 
+  trace!("Applied {op_index} bsdiff operations, writing {written_bytes} bytes");
+
   Ok(PatchFileStatus::Patched { written_bytes })
 }
@@ -3,7 +3,9 @@ use crate::hasher::BlockHasher;
 use crate::pool::{ContainerBackedPool, ContainerPool, Pool};
 use crate::protos;
 
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IntegrityIssues {
@@ -28,33 +30,170 @@ impl IntegrityIssues {
   }
 }
 
+/// The files of a container that failed verification, grouped by the reason
+///
+/// The paths are relative to the build folder
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+  /// The files whose contents don't match the signature
+  pub mismatched_files: Vec<PathBuf>,
+  /// The files whose size doesn't match the one in the container
+  pub size_mismatches: Vec<PathBuf>,
+  /// The files that don't exist in the build folder
+  pub missing_files: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+  #[must_use]
+  pub fn are_files_intact(&self) -> bool {
+    self.mismatched_files.is_empty()
+      && self.size_mismatches.is_empty()
+      && self.missing_files.is_empty()
+  }
+
+  /// The number of files that failed verification
+  #[must_use]
+  pub fn broken_file_count(&self) -> usize {
+    self.mismatched_files.len() + self.size_mismatches.len() + self.missing_files.len()
+  }
+}
+
+/// The result of checking the integrity of a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileIntegrity {
+  Intact,
+  Mismatched,
+  SizeMismatch,
+  Missing,
+}
+
+/// The progress of a files verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyProgress {
+  /// The index of the file being verified
+  pub file_index: usize,
+  /// The number of files in the container
+  pub total_files: usize,
+  /// The number of bytes hashed since the verification started
+  pub bytes_hashed: u64,
+  /// The size of all the files in the container
+  ///
+  /// Files with a wrong size aren't hashed, so `bytes_hashed` may not reach this value
+  pub total_bytes: u64,
+}
+
+/// Verifies files of a container one by one, from readers provided by the caller
+///
+/// It is obtained with [`Signature::file_verifier`], and it allows verifying the files
+/// while they are being written (for example, while extracting an archive), without
+/// reading them from the disk again
+pub struct FileVerifier<'sig, 'reader> {
+  container: &'sig protos::Container,
+  hasher: BlockHasher<'sig, 'sig, 'reader>,
+}
+
+impl FileVerifier<'_, '_> {
+  /// Verify a file of the container from a reader
+  ///
+  /// The blocks are hashed and compared against the signature exactly like in
+  /// [`Signature::verify_files`]
+  ///
+  /// # Arguments
+  ///
+  /// * `file_index` - The index of the file in the container
+  ///
+  /// * `file_size` - The size of the data available in `reader`
+  ///
+  /// * `reader` - A reader with the file data, like an already opened [`std::fs::File`]
+  ///
+  /// * `progress_callback` - A callback that is called with the number of
+  ///   bytes read since the last one
+  ///
+  /// # Returns
+  ///
+  /// If the file is intact, returns `true`
+  ///
+  /// # Errors
+  ///
+  /// If the index is out of the container, the signature couldn't be read, or
+  /// there is an I/O failure while reading the file
+  ///
+  /// # Panics
+  ///
+  /// If `file_index` is lower than the one provided in a previous call
+  pub fn verify_next_file(
+    &mut self,
+    file_index: usize,
+    file_size: u64,
+    reader: &mut (impl Read + Send),
+    progress_callback: impl FnMut(u64) + Send,
+  ) -> Result<bool, String> {
+    let container_file_size = self
+      .container
+      .files
+      .get(file_index)
+      .map(|f| f.size as u64)
+      .ok_or_else(|| format!("The container doesn't have a file with the index {file_index}"))?;
+
+    // If the length doesn't match, then this file is broken
+    // The hasher skips its blocks in the next call, so it stays in sync
+    if file_size != container_file_size {
+      return Ok(false);
+    }
+
+    let status = self
+      .hasher
+      .hash_next_file(reader, file_index, progress_callback)?;
+
+    Ok(status.is_intact())
+  }
+}
+
 /// Check if the provided file is intact or broken
 ///
 /// # Returns
 ///
-/// If the file is intact, returns `true`
+/// The integrity of the file, and if it is broken, why
 fn check_file_integrity(
   entry_index: usize,
   src_pool: &mut impl ContainerBackedPool,
   hasher: &mut BlockHasher,
   progress_callback: impl FnMut(u64) + Send,
-) -> Result<bool, String> {
+) -> Result<FileIntegrity, String> {
   // Get the file size
   let container_file_size = src_pool.get_container_size(entry_index)?;
   let file_size = src_pool.get_size(entry_index)?;
 
   // If the length doesn't match, then this file is broken
-  if file_size != Some(container_file_size) {
-    return Ok(false);
+  match file_size {
+    None => return Ok(FileIntegrity::Missing),
+    Some(size) if size != container_file_size => return Ok(FileIntegrity::SizeMismatch),
+    Some(_) => (),
   }
 
   let mut reader = src_pool.get_reader(entry_index)?;
   let status = hasher.hash_next_file(&mut reader, entry_index, progress_callback)?;
 
-  Ok(status.is_intact())
+  Ok(if status.is_intact() {
+    FileIntegrity::Intact
+  } else {
+    FileIntegrity::Mismatched
+  })
 }
 
-impl Signature<'_> {
+impl<'reader> Signature<'reader> {
+  /// Get a [`FileVerifier`] to verify the files of the container from readers
+  ///
+  /// The block hashes of the signature can only be read once, so the files
+  /// must be verified in increasing index order
+  #[must_use]
+  pub fn file_verifier(&mut self) -> FileVerifier<'_, 'reader> {
+    FileVerifier {
+      container: &self.container_new,
+      hasher: BlockHasher::new(&self.container_new, &mut self.block_hash_iter),
+    }
+  }
+
   /// Verify the integrity of all files in the container
   ///
   /// This function iterates over every file in the container and checks if
@@ -86,33 +225,155 @@ impl Signature<'_> {
     build_folder: &Path,
     mut progress_callback: impl FnMut(u64) + Send,
   ) -> Result<IntegrityIssues, String> {
+    // Report the bytes read since the last call, on every chunk
+    let mut last_bytes_hashed: u64 = 0;
+
+    self.verify_files_with_progress(
+      build_folder,
+      |progress| {
+        progress_callback(progress.bytes_hashed - last_bytes_hashed);
+        last_bytes_hashed = progress.bytes_hashed;
+      },
+      Duration::ZERO,
+    )
+  }
+
+  /// Verify the integrity of all files in the container, reporting detailed progress
+  ///
+  /// This behaves exactly like [`Signature::verify_files`], but the progress
+  /// callback receives a [`VerifyProgress`] struct with the current file and
+  /// the total bytes hashed, so the overall progress can be shown
+  ///
+  /// # Arguments
+  ///
+  /// * `build_folder` - The path to the build folder
+  ///
+  /// * `progress_callback` - A callback that is called with the verification progress
+  ///
+  /// * `callback_interval` - The minimum time span between each `progress_callback` call
+  ///
+  /// # Returns
+  ///
+  /// A [`IntegrityIssues`] struct that contains all files that failed verification.
+  ///
+  /// # Errors
+  ///
+  /// If there is an I/O failure while reading files or metadata.
+  pub fn verify_files_with_progress(
+    &mut self,
+    build_folder: &Path,
+    progress_callback: impl FnMut(VerifyProgress) + Send,
+    callback_interval: Duration,
+  ) -> Result<IntegrityIssues, String> {
+    let broken_files: Vec<usize> = self
+      .check_all_files(build_folder, progress_callback, callback_interval)?
+      .into_iter()
+      .map(|(entry_index, _)| entry_index)
+      .collect();
+
+    Ok(IntegrityIssues {
+      files: broken_files.into_boxed_slice(),
+    })
+  }
+
+  /// Verify the integrity of all files in the container, and report why each broken file failed
+  ///
+  /// This behaves exactly like [`Signature::verify_files_with_progress`], but the broken
+  /// files are grouped by the reason they failed verification, and identified by their path
+  ///
+  /// # Arguments
+  ///
+  /// * `build_folder` - The path to the build folder
+  ///
+  /// * `progress_callback` - A callback that is called with the verification progress
+  ///
+  /// * `callback_interval` - The minimum time span between each `progress_callback` call
+  ///
+  /// # Returns
+  ///
+  /// A [`VerifyReport`] struct with the paths of all files that failed verification
+  ///
+  /// # Errors
+  ///
+  /// If there is an I/O failure while reading files or metadata.
+  pub fn verify_files_report(
+    &mut self,
+    build_folder: &Path,
+    progress_callback: impl FnMut(VerifyProgress) + Send,
+    callback_interval: Duration,
+  ) -> Result<VerifyReport, String> {
+    let broken_files = self.check_all_files(build_folder, progress_callback, callback_interval)?;
+
+    let mut report = VerifyReport::default();
+    for (entry_index, integrity) in broken_files {
+      let path = PathBuf::from(&self.container_new.files[entry_index].path);
+
+      match integrity {
+        FileIntegrity::Intact => (),
+        FileIntegrity::Mismatched => report.mismatched_files.push(path),
+        FileIntegrity::SizeMismatch => report.size_mismatches.push(path),
+        FileIntegrity::Missing => report.missing_files.push(path),
+      }
+    }
+
+    Ok(report)
+  }
+
+  /// Check every file in the container, returning the indexes of the broken ones and why
+  fn check_all_files(
+    &mut self,
+    build_folder: &Path,
+    mut progress_callback: impl FnMut(VerifyProgress) + Send,
+    callback_interval: Duration,
+  ) -> Result<Vec<(usize, FileIntegrity)>, String> {
     // This vector holds all the broken file indexes found in the build folder
-    let mut broken_files: Vec<usize> = Vec::new();
+    let mut broken_files: Vec<(usize, FileIntegrity)> = Vec::new();
+
+    let total_bytes = self.container_new.size as u64;
 
     // Create the hasher that will verify the files' integrity
     let mut hasher = BlockHasher::new(&self.container_new, &mut self.block_hash_iter);
 
     // Load a pool from the build folder
     let mut src_pool = ContainerPool::open(&self.container_new, build_folder);
+    let total_files = src_pool.entry_count();
+
+    // Prepare the callback variables
+    let mut bytes_hashed: u64 = 0;
+    let mut last_callback = Instant::now();
 
     // Loop over all the files in the source pool
-    for entry_index in 0..src_pool.entry_count() {
+    for entry_index in 0..total_files {
       // Check if the file is intact
-      let is_intact = check_file_integrity(
-        entry_index,
-        &mut src_pool,
-        &mut hasher,
-        &mut progress_callback,
-      )?;
+      let integrity = check_file_integrity(entry_index, &mut src_pool, &mut hasher, |b| {
+        bytes_hashed += b;
+
+        // Send a callback with the progress
+        if last_callback.elapsed() >= callback_interval {
+          last_callback = Instant::now();
+          progress_callback(VerifyProgress {
+            file_index: entry_index,
+            total_files,
+            bytes_hashed,
+            total_bytes,
+          });
+        }
+      })?;
 
       // If not, add it to the broken files vector
-      if !is_intact {
-        broken_files.push(entry_index);
+      if integrity != FileIntegrity::Intact {
+        broken_files.push((entry_index, integrity));
       }
     }
 
-    Ok(IntegrityIssues {
-      files: broken_files.into_boxed_slice(),
-    })
+    // Always send the final progress, even if the last one was throttled
+    progress_callback(VerifyProgress {
+      file_index: total_files.saturating_sub(1),
+      total_files,
+      bytes_hashed,
+      total_bytes,
+    });
+
+    Ok(broken_files)
   }
 }